@@ -0,0 +1,162 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::io::open_input;
+
+use std::io::Read;
+
+/// Bytes at the start of the file looked at
+const SAMPLE_SIZE: u64 = 8 * 1024;
+
+/// Share of control characters above which content isn't taken as text
+const MAX_CONTROL_RATIO: f64 = 0.1;
+
+/// Formats told apart by their first bytes, with what to do about them. Compressed files, Arrow
+/// files and SQLite databases are opened by name, so they only end up here when piped.
+const SIGNATURES: [(&[u8], &str); 12] = [
+    (
+        b"\x1f\x8b",
+        "it is gzip compressed, which is only read from files. Give the file name instead of piping it",
+    ),
+    (
+        b"\x28\xb5\x2f\xfd",
+        "it is zstd compressed, which is only read from files. Give the file name instead of piping it",
+    ),
+    (
+        b"BZh",
+        "it is bzip2 compressed, which is only read from files. Give the file name instead of piping it",
+    ),
+    (
+        b"\xfd7zXZ\x00",
+        "it is xz compressed, which is only read from files. Give the file name instead of piping it",
+    ),
+    (
+        b"PK\x03\x04",
+        "it is a zip archive, or an Excel workbook not named .xlsx. Extract the CSV or rename the workbook",
+    ),
+    (
+        b"\xd0\xcf\x11\xe0",
+        "it is an Excel 97-2003 workbook. Save it as .xlsx or export the sheet to CSV first",
+    ),
+    (b"PAR1", "it is a Parquet file"),
+    (
+        b"ARROW1",
+        "it is an Arrow file, which is only read from files. Give the file name instead of piping it",
+    ),
+    (
+        b"SQLite format 3\x00",
+        "it is an SQLite database, which is only read from files. Give the file name instead of piping it",
+    ),
+    (b"%PDF", "it is a PDF document"),
+    (b"\x89PNG", "it is a PNG image"),
+    (b"\xff\xd8\xff", "it is a JPEG image"),
+];
+
+/// Fail with a hint if the file looks binary, e.g. compressed or not a CSV file at all, instead
+/// of showing garbage. The file is called name in the message.
+pub fn check_text(filename: &str, name: &str) -> CsvlensResult<()> {
+    let mut sample = vec![];
+    open_input(filename)?
+        .take(SAMPLE_SIZE)
+        .read_to_end(&mut sample)?;
+    match binary_reason(&sample) {
+        Some(reason) => Err(CsvlensError::BinaryContent(
+            name.to_string(),
+            reason.to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Why the content isn't text, if it isn't
+pub fn binary_reason(sample: &[u8]) -> Option<&'static str> {
+    let is_control =
+        |b: &u8| b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | b'\x0c' | b'\x1b');
+    // Some signatures are text, e.g. a header starting with PAR1, so only binary content counts
+    let is_ascii_text = sample.iter().all(|b| b.is_ascii() && !is_control(b));
+    if !is_ascii_text
+        && let Some((_, reason)) = SIGNATURES
+            .iter()
+            .find(|(signature, _)| sample.starts_with(signature))
+    {
+        return Some(reason);
+    }
+    // Text in UTF-16 has a NUL byte for every ASCII character
+    let utf16 = "it looks UTF-16 encoded. Open it with --encoding utf-16";
+    if sample.starts_with(b"\xff\xfe") || sample.starts_with(b"\xfe\xff") {
+        return Some(utf16);
+    }
+    let num_nul = sample.iter().filter(|b| **b == 0).count();
+    if num_nul > 0 && num_nul * 3 >= sample.len() {
+        return Some(utf16);
+    }
+    if num_nul > 0 {
+        return Some(
+            "it has NUL bytes. Is it the right file, or in another encoding (see --encoding)?",
+        );
+    }
+    let num_control = sample.iter().filter(|b| is_control(b)).count();
+    if num_control as f64 > sample.len() as f64 * MAX_CONTROL_RATIO {
+        return Some(
+            "it has mostly binary data. Is it the right file, is it compressed, or is it in another encoding (see --encoding)?",
+        );
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_reason() {
+        assert!(
+            binary_reason(b"\x1f\x8b\x08\x00rest")
+                .unwrap()
+                .contains("gzip")
+        );
+        assert!(binary_reason(b"PAR1\x15\x04").unwrap().contains("Parquet"));
+        let utf16: Vec<u8> = "a,b\n1,2\n".bytes().flat_map(|b| [b, 0]).collect();
+        assert!(
+            binary_reason(&utf16)
+                .unwrap()
+                .ends_with("--encoding utf-16")
+        );
+        assert!(binary_reason(b"a,b\n1,\x002\n").unwrap().contains("NUL"));
+        assert!(
+            binary_reason(b"a,b\n1,\x002\n")
+                .unwrap()
+                .contains("--encoding")
+        );
+        assert!(
+            binary_reason(b"\x01\x02\x03\x04a,b")
+                .unwrap()
+                .contains("binary")
+        );
+        assert!(
+            binary_reason(b"\x01\x02\x03\x04a,b")
+                .unwrap()
+                .contains("--encoding")
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(binary_reason(b""), None);
+        assert_eq!(binary_reason(b"a,b\r\n1,\"x\ty\"\r\n"), None);
+        // Latin-1 isn't valid UTF-8 but is text
+        assert_eq!(binary_reason(b"city\nZ\xfcrich\n"), None);
+        assert_eq!(binary_reason("name\n東京\n".as_bytes()), None);
+        assert_eq!(binary_reason(b"PAR1,PAR2\n1,2\n"), None);
+    }
+
+    #[test]
+    fn test_check_text() {
+        assert!(check_text("tests/data/cities.csv", "cities.csv").is_ok());
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"\x1f\x8b\x08\x00\x00\x00").unwrap();
+        let err = check_text(file.path().to_str().unwrap(), "data.csv").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("data.csv doesn't look like a CSV file: it is gzip compressed")
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use ratatui::style::Color;
+use regex::Regex;
+use std::str::FromStr;
+
+/// Color for the values of a column matching a regex
+#[derive(Clone, Debug)]
+pub struct ColorRule {
+    /// Origin index of the column
+    pub column_index: usize,
+    pub pattern: Regex,
+    pub color: Color,
+}
+
+impl ColorRule {
+    /// Parse a `<column>:<regex>=<color>` specification, e.g. `status:ERROR=red`
+    pub fn parse(spec: &str, headers: &[String]) -> CsvlensResult<ColorRule> {
+        let invalid = || CsvlensError::InvalidColorRule(spec.to_string());
+        let (column_name, rest) = spec.split_once(':').ok_or_else(invalid)?;
+        let (pattern, color) = rest.rsplit_once('=').ok_or_else(invalid)?;
+        let column_index = headers
+            .iter()
+            .position(|h| h == column_name)
+            .ok_or_else(|| CsvlensError::ColumnNameNotFound(column_name.to_string()))?;
+        let pattern = Regex::new(pattern).map_err(|_| invalid())?;
+        let color = Color::from_str(color.trim()).map_err(|_| invalid())?;
+        Ok(ColorRule {
+            column_index,
+            pattern,
+            color,
+        })
+    }
+}
+
+pub fn parse_color_rules(specs: &[String], headers: &[String]) -> CsvlensResult<Vec<ColorRule>> {
+    specs
+        .iter()
+        .map(|spec| ColorRule::parse(spec, headers))
+        .collect()
+}
+
+/// Color of the first rule of the column matching the value
+pub fn find_color(rules: &[ColorRule], column_index: usize, value: &str) -> Option<Color> {
+    rules
+        .iter()
+        .find(|r| r.column_index == column_index && r.pattern.is_match(value))
+        .map(|r| r.color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_rules() {
+        let headers = vec!["id".to_string(), "status".to_string()];
+        let specs = vec![
+            "status:ERROR=red".to_string(),
+            "status:^(WARN|ERR)=yellow".to_string(),
+            "id:a=b=#00ff00".to_string(),
+        ];
+        let rules = parse_color_rules(&specs, &headers).unwrap();
+        assert_eq!(find_color(&rules, 1, "ERROR: disk"), Some(Color::Red));
+        assert_eq!(find_color(&rules, 1, "WARN"), Some(Color::Yellow));
+        assert_eq!(find_color(&rules, 1, "INFO"), None);
+        assert_eq!(find_color(&rules, 0, "ERROR"), None);
+        assert_eq!(rules[2].pattern.as_str(), "a=b");
+        assert_eq!(rules[2].color, Color::Rgb(0, 255, 0));
+    }
+
+    #[test]
+    fn test_invalid_color_rules() {
+        let headers = vec!["status".to_string()];
+        let parse = |spec: &str| ColorRule::parse(spec, &headers);
+        assert!(matches!(
+            parse("status=red"),
+            Err(CsvlensError::InvalidColorRule(_))
+        ));
+        assert!(matches!(
+            parse("status:ERROR=reddish"),
+            Err(CsvlensError::InvalidColorRule(_))
+        ));
+        assert!(matches!(
+            parse("status:(=red"),
+            Err(CsvlensError::InvalidColorRule(_))
+        ));
+        assert!(matches!(
+            parse("level:ERROR=red"),
+            Err(CsvlensError::ColumnNameNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::columns_filter::ColumnsFilter;
+use crate::csv::{CsvConfig, CsvlensRecordIterator};
+
+use regex::Regex;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Counts the cells matching a pattern in each column in the background, to tell which column to
+/// scope a filter to
+pub struct ColumnCounts {
+    internal: Arc<Mutex<ColumnCountsInternalState>>,
+    pattern: Regex,
+}
+
+impl ColumnCounts {
+    /// Columns are those kept by the columns filter if any, indexed as shown like the finder's
+    pub fn new(
+        config: Arc<CsvConfig>,
+        pattern: Regex,
+        columns_filter: Option<Arc<ColumnsFilter>>,
+    ) -> Self {
+        let internal = ColumnCountsInternalState::init(config, pattern.clone(), columns_filter);
+        ColumnCounts { internal, pattern }
+    }
+
+    pub fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    /// Matching cells by column, so far
+    pub fn counts(&self) -> Vec<usize> {
+        self.internal.lock().unwrap().counts.clone()
+    }
+
+    pub fn done(&self) -> bool {
+        self.internal.lock().unwrap().done
+    }
+
+    fn terminate(&self) {
+        self.internal.lock().unwrap().should_terminate = true;
+    }
+
+    #[cfg(test)]
+    pub fn wait_internal(&self) {
+        loop {
+            if self.internal.lock().unwrap().done {
+                break;
+            }
+            thread::sleep(core::time::Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for ColumnCounts {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+struct ColumnCountsInternalState {
+    counts: Vec<usize>,
+    done: bool,
+    should_terminate: bool,
+}
+
+impl ColumnCountsInternalState {
+    fn init(
+        config: Arc<CsvConfig>,
+        pattern: Regex,
+        columns_filter: Option<Arc<ColumnsFilter>>,
+    ) -> Arc<Mutex<ColumnCountsInternalState>> {
+        let m_state = Arc::new(Mutex::new(ColumnCountsInternalState {
+            counts: vec![],
+            done: false,
+            should_terminate: false,
+        }));
+
+        let _m = m_state.clone();
+        thread::spawn(move || {
+            if let Ok(records) = CsvlensRecordIterator::new(config) {
+                for record in records.flatten() {
+                    let fields = record.iter().enumerate().filter(|(i, _)| {
+                        columns_filter
+                            .as_ref()
+                            .is_none_or(|f| f.is_column_filtered(*i))
+                    });
+                    let matches: Vec<usize> = fields
+                        .map(|(_, field)| field)
+                        .enumerate()
+                        .filter(|(_, field)| pattern.is_match(field))
+                        .map(|(local_index, _)| local_index)
+                        .collect();
+                    let mut m = _m.lock().unwrap();
+                    if m.should_terminate {
+                        break;
+                    }
+                    for local_index in matches {
+                        if m.counts.len() <= local_index {
+                            m.counts.resize(local_index + 1, 0);
+                        }
+                        m.counts[local_index] += 1;
+                    }
+                }
+            }
+            _m.lock().unwrap().done = true;
+        });
+
+        m_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+
+    fn counts(pattern: &str, columns: Option<&str>) -> Vec<usize> {
+        let config = Arc::new(CsvConfig::new(
+            "tests/data/cities.csv",
+            None,
+            CsvBaseConfig::new(b',', false),
+        ));
+        let headers: Vec<String> = config
+            .new_reader()
+            .unwrap()
+            .headers()
+            .unwrap()
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let columns_filter =
+            columns.map(|c| Arc::new(ColumnsFilter::new(Regex::new(c).unwrap(), &headers)));
+        let counts = ColumnCounts::new(config, Regex::new(pattern).unwrap(), columns_filter);
+        counts.wait_internal();
+        counts.counts()
+    }
+
+    #[test]
+    fn test_column_counts() {
+        // Counted up to the last column with a match
+        assert_eq!(counts("^41$", None), vec![12, 1, 0, 0, 0, 1]);
+        assert_eq!(counts("^41$", Some("Lon")), vec![0, 1]);
+        assert_eq!(counts("nomatch", None), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,92 @@
+/// Characters ending the prefix of a column name, e.g. in `http_status` or `http.status`
+const SEPARATORS: &[char] = &['_', '.'];
+
+/// Columns grouped by the prefix of their names when several share it, e.g. `http_status` and
+/// `http_latency` in the group `http`
+pub struct ColumnGroups {
+    /// Group of each column, by column index
+    groups: Vec<Option<String>>,
+}
+
+impl ColumnGroups {
+    pub fn new(headers: &[String]) -> Self {
+        let prefixes: Vec<Option<&str>> = headers.iter().map(|h| prefix(h)).collect();
+        let groups = prefixes
+            .iter()
+            .map(|p| {
+                p.filter(|p| prefixes.iter().filter(|q| **q == Some(*p)).count() > 1)
+                    .map(|p| p.to_string())
+            })
+            .collect();
+        ColumnGroups { groups }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.iter().all(|g| g.is_none())
+    }
+
+    pub fn group_of(&self, column_index: usize) -> Option<&str> {
+        self.groups.get(column_index).and_then(|g| g.as_deref())
+    }
+
+    /// Indices of the columns in the group, in order
+    pub fn columns(&self, group: &str) -> Vec<usize> {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.as_deref() == Some(group))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Group of each column, by column index
+    pub fn groups(&self) -> &[Option<String>] {
+        &self.groups
+    }
+}
+
+fn prefix(name: &str) -> Option<&str> {
+    let end = name.find(SEPARATORS)?;
+    (end > 0 && end + 1 < name.len()).then(|| &name[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups() {
+        let headers: Vec<String> = [
+            "http_status",
+            "db.time",
+            "http_latency",
+            "db.rows",
+            "host",
+            "_id",
+            "name_",
+            "user_id",
+        ]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+        let groups = ColumnGroups::new(&headers);
+        assert_eq!(
+            groups.groups(),
+            &[
+                Some("http".to_string()),
+                Some("db".to_string()),
+                Some("http".to_string()),
+                Some("db".to_string()),
+                None,
+                None,
+                None,
+                None,
+            ]
+        );
+        assert_eq!(groups.columns("http"), vec![0, 2]);
+        assert_eq!(groups.group_of(3), Some("db"));
+        assert!(!groups.is_empty());
+
+        assert!(ColumnGroups::new(&["a_b".to_string(), "c_d".to_string()]).is_empty());
+    }
+}
@@ -3,6 +3,9 @@ use regex::Regex;
 #[derive(Debug)]
 pub struct ColumnsFilter {
     pattern: Regex,
+    /// Names of the columns hidden, if the filter hides the columns matching the pattern rather
+    /// than shows them
+    hidden: Option<Vec<String>>,
     indices: Vec<usize>,
     filtered_headers: Vec<String>,
     filtered_flags: Vec<bool>,
@@ -12,11 +15,27 @@ pub struct ColumnsFilter {
 
 impl ColumnsFilter {
     pub fn new(pattern: Regex, headers: &[String]) -> Self {
+        Self::build(pattern, None, headers)
+    }
+
+    /// Filter hiding the columns with the given names
+    pub fn hiding(names: &[String], headers: &[String]) -> Self {
+        let alternatives: Vec<String> = names.iter().map(|n| regex::escape(n)).collect();
+        let pattern = Regex::new(&format!("^(?:{})$", alternatives.join("|"))).unwrap();
+        Self::build(pattern, Some(names.to_vec()), headers)
+    }
+
+    /// The same filter for new headers, e.g. after the file is reloaded
+    pub fn refresh(&self, headers: &[String]) -> Self {
+        Self::build(self.pattern.clone(), self.hidden.clone(), headers)
+    }
+
+    fn build(pattern: Regex, hidden: Option<Vec<String>>, headers: &[String]) -> Self {
         let mut indices = vec![];
         let mut filtered_headers: Vec<String> = vec![];
         let mut filtered_flags: Vec<bool> = vec![];
         for (i, header) in headers.iter().enumerate() {
-            if pattern.is_match(header) {
+            if pattern.is_match(header) != hidden.is_some() {
                 indices.push(i);
                 filtered_headers.push(header.clone());
                 filtered_flags.push(true);
@@ -34,6 +53,7 @@ impl ColumnsFilter {
         }
         Self {
             pattern,
+            hidden,
             indices,
             filtered_headers,
             filtered_flags,
@@ -54,6 +74,10 @@ impl ColumnsFilter {
         self.pattern.to_owned()
     }
 
+    pub fn hidden(&self) -> Option<&Vec<String>> {
+        self.hidden.as_ref()
+    }
+
     pub fn num_filtered(&self) -> usize {
         self.indices.len()
     }
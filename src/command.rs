@@ -0,0 +1,355 @@
+use crate::config;
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::path::Path;
+
+/// The commands that can be typed after `:` or given in a --script
+pub const COMMANDS: &[&str] = &[
+    "goto",
+    "offset",
+    "find",
+    "filter",
+    "columns",
+    "hide",
+    "sort",
+    "sort-natural",
+    "freeze",
+    "export",
+    "write-split",
+    "count",
+    "count-rows",
+    "dump-state",
+    "reload",
+    "quit",
+];
+
+/// The commands that only change what is shown, which a project config may run on open. Files
+/// come with their project configs, e.g. in a cloned repository, so the config can't write files.
+pub const VIEW_COMMANDS: &[&str] = &[
+    "goto",
+    "offset",
+    "find",
+    "filter",
+    "columns",
+    "hide",
+    "sort",
+    "sort-natural",
+    "freeze",
+    "count",
+    "count-rows",
+];
+
+/// Other names for commands, as in vi
+const BUILTIN_ALIASES: &[(&str, &str)] = &[("w", "export"), ("write", "export"), ("q", "quit")];
+
+/// A command with its name resolved, e.g. `so -City` to `sort` and `-City`
+#[derive(Debug, PartialEq)]
+pub struct Command<'a> {
+    pub name: &'static str,
+    pub arg: &'a str,
+}
+
+/// Parse a command line, ex-style: the name may be shortened as long as it stays unambiguous, and
+/// a row number or percentage alone goes there, e.g. `5000` or `50%`.
+pub fn parse(line: &str) -> Result<Command<'_>, String> {
+    let line = line.trim();
+    let line = line.strip_prefix(':').unwrap_or(line).trim_start();
+    let (name, arg) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(name, arg)| (name, arg.trim()));
+    if arg.is_empty() && is_row_number(name) {
+        return Ok(Command {
+            name: "goto",
+            arg: name,
+        });
+    }
+    let name = resolve(name)?;
+    Ok(Command { name, arg })
+}
+
+/// Whether the command line is one of VIEW_COMMANDS
+fn is_view_command(line: &str) -> bool {
+    parse(line).is_ok_and(|c| VIEW_COMMANDS.contains(&c.name))
+}
+
+/// Whether the name is a row number or percentage to go to, e.g. `5000` or `50%`
+fn is_row_number(name: &str) -> bool {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    is_number(name) || name.strip_suffix('%').is_some_and(is_number)
+}
+
+/// The command with the name, or the vi name, in full
+fn resolve_exactly(name: &str) -> Option<&'static str> {
+    COMMANDS.iter().copied().find(|c| *c == name).or_else(|| {
+        BUILTIN_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, command)| *command)
+    })
+}
+
+fn resolve(name: &str) -> Result<&'static str, String> {
+    if let Some(command) = resolve_exactly(name) {
+        return Ok(command);
+    }
+    let matches: Vec<&'static str> = if name.is_empty() {
+        vec![]
+    } else {
+        COMMANDS
+            .iter()
+            .copied()
+            .filter(|c| c.starts_with(name))
+            .collect()
+    };
+    // e.g. `so` is sort rather than sort-natural, which is longer for the same start
+    let shortest = matches.iter().min_by_key(|c| c.len());
+    match shortest {
+        Some(shortest) if matches.iter().all(|c| c.starts_with(shortest)) => Ok(shortest),
+        Some(_) => Err(format!(
+            "Ambiguous command: {name} ({})",
+            matches.join(", ")
+        )),
+        None => Err(format!("Unknown command: {name}")),
+    }
+}
+
+/// Commands defined by the user to run under one name, loaded from a TOML or JSON file like:
+///
+/// ```toml
+/// err = "filter status 5.."
+/// ohio = ["filter ^OH$", "sort LatD desc"]
+/// ```
+#[derive(Debug, Default)]
+pub struct CommandAliases {
+    aliases: Vec<(String, Vec<String>)>,
+}
+
+impl CommandAliases {
+    /// `$CSVLENS_ALIASES`, or `csvlens/aliases.toml` in the platform's config directory
+    pub fn default_path() -> Option<String> {
+        config::config_file("CSVLENS_ALIASES", "aliases.toml")
+    }
+
+    /// Aliases in the given file. A missing file has none.
+    pub fn load(path: &str) -> CsvlensResult<Self> {
+        if !Path::new(path).exists() {
+            return Ok(CommandAliases::default());
+        }
+        Self::from_value(path, &config::load_value(path)?)
+    }
+
+    /// Add the aliases in the `[aliases]` table of a project config (`.csvlens.toml`), unless
+    /// these have the same names. As the config comes with the files rather than the user, they
+    /// may only change the view, and can't be taken for a shortened command, e.g. `filt`.
+    pub fn with_project(mut self, path: &str) -> CsvlensResult<Self> {
+        let Some(value) = config::load_value(path)?.get("aliases").cloned() else {
+            return Ok(self);
+        };
+        let mut project = Self::from_value(path, &value)?;
+        for (name, commands) in &project.aliases {
+            let parse_error = |message| CsvlensError::ConfigParsing(path.to_string(), message);
+            let mut names = COMMANDS
+                .iter()
+                .chain(BUILTIN_ALIASES.iter().map(|(alias, _)| alias));
+            if let Some(command) = names.find(|c| c.starts_with(name.as_str())) {
+                return Err(parse_error(format!(
+                    "{name} could be taken for the {command} command"
+                )));
+            }
+            if let Some(command) = commands.iter().find(|c| !is_view_command(c)) {
+                return Err(parse_error(format!(
+                    "aliases of a project config can only change the view ({}): {command}",
+                    VIEW_COMMANDS.join(", ")
+                )));
+            }
+        }
+        project
+            .aliases
+            .retain(|(name, _)| !self.aliases.iter().any(|(n, _)| n == name));
+        self.aliases.extend(project.aliases);
+        Ok(self)
+    }
+
+    fn from_value(path: &str, value: &serde_json::Value) -> CsvlensResult<Self> {
+        let parse_error = |message: String| CsvlensError::ConfigParsing(path.to_string(), message);
+        let table = value
+            .as_object()
+            .ok_or_else(|| parse_error("expected a table of aliases".to_string()))?;
+        let mut aliases = vec![];
+        for (name, commands) in table {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(parse_error(format!("invalid alias name: {name:?}")));
+            }
+            if resolve_exactly(name).is_some() || is_row_number(name) {
+                return Err(parse_error(format!("{name} is already a command")));
+            }
+            let commands = match commands {
+                serde_json::Value::String(command) => Some(vec![command.clone()]),
+                serde_json::Value::Array(commands) => commands
+                    .iter()
+                    .map(|c| c.as_str().map(String::from))
+                    .collect(),
+                _ => None,
+            }
+            .filter(|commands| !commands.is_empty())
+            .ok_or_else(|| {
+                parse_error(format!(
+                    "expected a command or a list of commands for {name}"
+                ))
+            })?;
+            aliases.push((name.clone(), commands));
+        }
+        Ok(CommandAliases { aliases })
+    }
+
+    /// Whether the line only changes what is shown, as do all the commands if it is an alias
+    pub fn only_changes_view(&self, line: &str) -> bool {
+        match self.expand(line) {
+            Some(commands) => commands.iter().all(|c| is_view_command(c)),
+            None => is_view_command(line),
+        }
+    }
+
+    /// The commands to run for the line if it starts with an alias, with what follows the alias
+    /// added to the last one, e.g. `by price` for `by = "sort"`
+    pub fn expand(&self, line: &str) -> Option<Vec<String>> {
+        let line = line.trim();
+        let line = line.strip_prefix(':').unwrap_or(line).trim_start();
+        let (name, arg) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, arg)| (name, arg.trim()));
+        let (_, commands) = self.aliases.iter().find(|(alias, _)| alias == name)?;
+        let mut commands = commands.clone();
+        if !arg.is_empty() {
+            let last = commands.last_mut().unwrap();
+            *last = format!("{last} {arg}");
+        }
+        Some(commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command<'a>(name: &'static str, arg: &'a str) -> Result<Command<'a>, String> {
+        Ok(Command { name, arg })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("sort -City"), command("sort", "-City"));
+        assert_eq!(parse(":sort  price desc "), command("sort", "price desc"));
+        assert_eq!(parse("so price"), command("sort", "price"));
+        assert_eq!(parse("sort-n price"), command("sort-natural", "price"));
+        assert_eq!(parse("w out.csv"), command("export", "out.csv"));
+        assert_eq!(parse("write out.csv"), command("export", "out.csv"));
+        assert_eq!(
+            parse("wr by=State out_{}.csv"),
+            command("write-split", "by=State out_{}.csv")
+        );
+        assert_eq!(parse("q"), command("quit", ""));
+        assert_eq!(parse("5000"), command("goto", "5000"));
+        assert_eq!(parse(":50%"), command("goto", "50%"));
+        assert_eq!(parse("cou"), command("count", ""));
+        assert_eq!(
+            parse("f x"),
+            Err("Ambiguous command: f (find, filter, freeze)".to_string())
+        );
+        assert_eq!(
+            parse("frobnicate"),
+            Err("Unknown command: frobnicate".to_string())
+        );
+        assert_eq!(parse("5000 rows"), Err("Unknown command: 5000".to_string()));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let aliases = CommandAliases::load("tests/data/aliases.toml").unwrap();
+        assert_eq!(
+            aliases.expand(":ohio"),
+            Some(vec![
+                "filter ^OH$".to_string(),
+                "sort LatD desc".to_string()
+            ])
+        );
+        assert_eq!(
+            aliases.expand("by City desc"),
+            Some(vec!["sort City desc".to_string()])
+        );
+        assert_eq!(aliases.expand("sort City"), None);
+        assert_eq!(aliases.expand("oh"), None);
+
+        // Aliases of a project config are added
+        let aliases = aliases
+            .with_project("tests/data/project/.csvlens.toml")
+            .unwrap();
+        assert_eq!(
+            aliases.expand("errors"),
+            Some(vec!["filter ERROR".to_string()])
+        );
+        assert!(aliases.expand("ohio").is_some());
+        assert!(aliases.only_changes_view("errors"));
+        assert!(aliases.only_changes_view("ohio"));
+        assert!(!aliases.only_changes_view("w out.csv"));
+
+        let aliases = CommandAliases::load("tests/data/missing_aliases.toml").unwrap();
+        assert_eq!(aliases.expand("ohio"), None);
+
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, b"w = \"export out.csv\"\n").unwrap();
+        let result = CommandAliases::load(file.path().to_str().unwrap());
+        assert!(matches!(
+            result,
+            Err(CsvlensError::ConfigParsing(_, message)) if message == "w is already a command"
+        ));
+    }
+
+    #[test]
+    fn test_project_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(config::PROJECT_FILE);
+        let path = path.to_str().unwrap();
+        let project = |user: &str, content: &str| {
+            let user_path = dir.path().join("aliases.toml");
+            std::fs::write(&user_path, user).unwrap();
+            std::fs::write(path, content).unwrap();
+            CommandAliases::load(user_path.to_str().unwrap())
+                .unwrap()
+                .with_project(path)
+        };
+
+        // The user's own aliases win
+        let aliases = project(
+            "errors = \"export /tmp/x\"\n",
+            "[aliases]\nerrors = \"filter ERROR\"\nwarnings = [\"filter WARN\", \"goto 1\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            aliases.expand("errors"),
+            Some(vec!["export /tmp/x".to_string()])
+        );
+        assert!(!aliases.only_changes_view("errors"));
+        assert!(aliases.only_changes_view("warnings"));
+
+        for (content, expected) in [
+            (
+                "[aliases]\nfilt = \"export /tmp/x\"\n",
+                "filt could be taken for the filter command",
+            ),
+            (
+                "[aliases]\nwr = \"goto 1\"\n",
+                "wr could be taken for the write-split command",
+            ),
+            (
+                "[aliases]\nerrors = [\"filter ERROR\", \"w /tmp/x\"]\n",
+                "aliases of a project config can only change the view (goto, offset, find, filter, columns, hide, sort, sort-natural, freeze, count, count-rows): w /tmp/x",
+            ),
+        ] {
+            assert!(
+                matches!(project("", content), Err(CsvlensError::ConfigParsing(_, message)) if message == expected),
+                "{content}"
+            );
+        }
+    }
+}
@@ -8,8 +8,13 @@ pub enum InputMode {
     Filter,
     FilterColumns,
     FreezeColumns,
+    Tag,
+    FilterTag,
+    Command,
     Option,
+    Header,
     Help,
+    Popup,
 }
 
 impl fmt::Display for InputMode {
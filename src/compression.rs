@@ -0,0 +1,273 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use xz2::bufread::XzDecoder;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::process::ChildStdout;
+
+/// Format a file is compressed in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+        }
+    }
+}
+
+/// Compressed formats told apart by their first bytes
+const FORMATS: [(&[u8], Compression); 4] = [
+    (b"\x1f\x8b", Compression::Gzip),
+    (b"\x28\xb5\x2f\xfd", Compression::Zstd),
+    // A block header follows the level, unlike in text starting with BZh
+    (b"BZh", Compression::Bzip2),
+    (b"\xfd7zXZ\x00", Compression::Xz),
+];
+
+/// Magic number of the first block of a bzip2 stream, after `BZh` and the level
+const BZIP2_BLOCK_MAGIC: &[u8] = b"\x31\x41\x59\x26\x53\x59";
+
+/// The format the file is compressed in, if it's a compressed file
+pub fn detect(filename: &str) -> CsvlensResult<Option<Compression>> {
+    // Reading from a pipe, e.g. with process substitution, would take the bytes away
+    if !std::fs::metadata(filename)?.is_file() {
+        return Ok(None);
+    }
+    let mut start = vec![];
+    File::open(filename)?.take(10).read_to_end(&mut start)?;
+    Ok(FORMATS
+        .iter()
+        .find(|(magic, format)| {
+            start.starts_with(magic)
+                && (*format != Compression::Bzip2
+                    || start
+                        .get(3)
+                        .is_some_and(|level| (b'1'..=b'9').contains(level))
+                        && start[4..].starts_with(BZIP2_BLOCK_MAGIC))
+        })
+        .map(|(_, format)| *format))
+}
+
+/// The format a file named like this is compressed in, for files that can't be looked into before
+/// they are read, e.g. downloads
+pub fn detect_extension(filename: &str) -> Option<Compression> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+        "gz" => Some(Compression::Gzip),
+        "zst" => Some(Compression::Zstd),
+        "bz2" => Some(Compression::Bzip2),
+        "xz" => Some(Compression::Xz),
+        _ => None,
+    }
+}
+
+/// Extension of the file once decompressed, e.g. `jsonl` for `events.jsonl.gz`
+pub fn inner_extension(filename: &str) -> Option<String> {
+    detect_extension(filename)?;
+    let (stem, _) = filename.rsplit_once('.')?;
+    let (_, extension) = std::path::Path::new(stem)
+        .file_name()?
+        .to_str()?
+        .rsplit_once('.')?;
+    Some(extension.to_ascii_lowercase())
+}
+
+/// The content of the file decompressed from the format, read as it goes
+pub fn decompress(filename: &str, format: Compression) -> CsvlensResult<Box<dyn Read + Send>> {
+    let file = io::BufReader::new(File::open(filename)?);
+    decoded(file, filename, format)
+}
+
+/// Output of a program decompressed as it goes, e.g. of a download, with `name` telling the input
+/// in errors
+pub fn decompress_output(
+    output: ChildStdout,
+    name: &str,
+    format: Compression,
+) -> CsvlensResult<Box<dyn Read + Send>> {
+    decoded(io::BufReader::new(output), name, format)
+}
+
+/// Input decompressed from the format by csvlens itself, so that no program needs to be installed
+/// for it
+fn decoded<R: io::BufRead + Send + 'static>(
+    input: R,
+    name: &str,
+    format: Compression,
+) -> CsvlensResult<Box<dyn Read + Send>> {
+    let decoder: Box<dyn Read + Send> = match format {
+        Compression::Gzip => Box::new(MultiGzDecoder::new(input)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(input)?),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(input)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(input)),
+    };
+    Ok(Box::new(Decoded {
+        decoder,
+        name: name.to_string(),
+        format,
+    }))
+}
+
+/// Decompressed input, with errors telling what failed to decompress, e.g. a truncated file
+struct Decoded {
+    decoder: Box<dyn Read + Send>,
+    name: String,
+    format: Compression,
+}
+
+impl Read for Decoded {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf).map_err(|e| {
+            io::Error::other(CsvlensError::Decompression(
+                self.name.clone(),
+                self.format.name().to_string(),
+                e.to_string(),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_content(content: &[u8]) -> Option<Compression> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, content).unwrap();
+        detect(file.path().to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect_content(b"\x1f\x8b\x08\x00rest"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            detect_content(b"\x28\xb5\x2f\xfdrest"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(detect_content(b"BZh91AY&SYrest"), Some(Compression::Bzip2));
+        assert_eq!(detect_content(b"\xfd7zXZ\x00rest"), Some(Compression::Xz));
+        // A header that happens to start like bzip2
+        assert_eq!(detect_content(b"BZh,count\n1,2\n"), None);
+        assert_eq!(detect_content(b"a,b\n1,2\n"), None);
+        assert_eq!(detect_content(b""), None);
+    }
+
+    #[test]
+    fn test_detect_extension() {
+        assert_eq!(
+            detect_extension("/exports/cities.csv.gz"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(detect_extension("cities.csv.ZST"), Some(Compression::Zstd));
+        assert_eq!(detect_extension("cities.csv"), None);
+        assert_eq!(detect_extension("cities"), None);
+    }
+
+    #[test]
+    fn test_inner_extension() {
+        assert_eq!(
+            inner_extension("logs/events.JSONL.gz"),
+            Some("jsonl".to_string())
+        );
+        assert_eq!(inner_extension("cities.csv.zst"), Some("csv".to_string()));
+        assert_eq!(inner_extension("v1.2/cities.gz"), None);
+        assert_eq!(inner_extension("events.jsonl"), None);
+    }
+
+    /// The file compressed in the format with the library csvlens decompresses it with
+    fn compressed(filename: &str, format: Compression) -> tempfile::NamedTempFile {
+        let data = std::fs::read(filename).unwrap();
+        let compressed = match format {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                std::io::Write::write_all(&mut encoder, &data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(data.as_slice(), 0).unwrap(),
+            Compression::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+                std::io::Write::write_all(&mut encoder, &data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(vec![], 6);
+                std::io::Write::write_all(&mut encoder, &data).unwrap();
+                encoder.finish().unwrap()
+            }
+        };
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &compressed).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_decompress() {
+        let expected = std::fs::read_to_string("tests/data/cities.csv").unwrap();
+        let mut content = String::new();
+        decompress("tests/data/cities.csv.gz", Compression::Gzip)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, expected);
+
+        // Without the programs of the formats
+        for format in [Compression::Zstd, Compression::Bzip2, Compression::Xz] {
+            let file = compressed("tests/data/cities.csv", format);
+            let filename = file.path().to_str().unwrap();
+            assert_eq!(detect(filename).unwrap(), Some(format));
+            let mut content = String::new();
+            decompress(filename, format)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!(content, expected, "{format:?}");
+        }
+    }
+
+    fn truncated(data: &[u8]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &data[..data.len() / 2]).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_decompress_truncated() {
+        let gzipped = std::fs::read("tests/data/cities.csv.gz").unwrap();
+        let mut inputs = vec![(gzipped, Compression::Gzip)];
+        for format in [Compression::Zstd, Compression::Bzip2, Compression::Xz] {
+            let data = std::fs::read(compressed("tests/data/cities.csv", format).path()).unwrap();
+            inputs.push((data, format));
+        }
+        for (data, format) in inputs {
+            let file = truncated(&data);
+            let filename = file.path().to_str().unwrap();
+            let mut content = vec![];
+            let error = decompress(filename, format)
+                .unwrap()
+                .read_to_end(&mut content)
+                .unwrap_err();
+            assert!(
+                error.to_string().starts_with("Failed to decompress"),
+                "{format:?}: {error}"
+            );
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use serde_json::{Map, Number, Value};
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+/// The path of `csvlens/<name>` in the platform's config directory, unless the environment
+/// variable gives another
+pub fn config_file(env_var: &str, name: &str) -> Option<String> {
+    if let Ok(path) = std::env::var(env_var) {
+        return Some(path);
+    }
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")))
+    }?;
+    let path = config_dir.join("csvlens").join(name);
+    path.to_str().map(|s| s.to_string())
+}
+
+/// Name of the config file kept with the data of a project, e.g. next to exports in a repository
+pub const PROJECT_FILE: &str = ".csvlens.toml";
+
+/// The nearest `.csvlens.toml` in the directory of the file or one above it. None for files that
+/// aren't local, e.g. URLs.
+pub fn project_file(filename: &str) -> Option<String> {
+    let path = std::fs::canonicalize(filename).ok()?;
+    path.parent()?
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|file| file.is_file())
+        .and_then(|file| file.to_str().map(without_verbatim_prefix))
+}
+
+/// The path without the `\\?\` prefix Windows puts on canonical paths, e.g. `C:\data` for
+/// `\\?\C:\data` and `\\server\share` for `\\?\UNC\server\share`, to show it as users write it
+fn without_verbatim_prefix(path: &str) -> String {
+    match path.strip_prefix(r"\\?\UNC\") {
+        Some(share) => format!(r"\\{share}"),
+        None => path.strip_prefix(r"\\?\").unwrap_or(path).to_string(),
+    }
+}
+
+/// Load a structured file as a JSON value. Files ending with `.toml` are parsed as TOML and
+/// everything else as JSON, so that consumers only need to deal with one representation.
+pub fn load_value(path: &str) -> CsvlensResult<Value> {
+    let content = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => CsvlensError::FileNotFound(path.to_string()),
+        _ => e.into(),
+    })?;
+    let parse_error = |message: String| CsvlensError::ConfigParsing(path.to_string(), message);
+    if path.to_lowercase().ends_with(".toml") {
+        let document = content
+            .parse::<DocumentMut>()
+            .map_err(|e| parse_error(e.to_string()))?;
+        Ok(toml_table_to_value(document.as_table()))
+    } else {
+        serde_json::from_str(&content).map_err(|e| parse_error(e.to_string()))
+    }
+}
+
+/// Load a flat table of string values, e.g. `c_017 = "Customer ID"`
+pub fn load_string_map(path: &str) -> CsvlensResult<Vec<(String, String)>> {
+    let value = load_value(path)?;
+    let invalid =
+        || CsvlensError::ConfigParsing(path.to_string(), "expected a table of strings".into());
+    let map = value.as_object().ok_or_else(invalid)?;
+    let mut out = vec![];
+    for (key, v) in map {
+        out.push((key.clone(), v.as_str().ok_or_else(invalid)?.to_string()));
+    }
+    Ok(out)
+}
+
+fn toml_table_to_value(table: &toml_edit::Table) -> Value {
+    let mut map = Map::new();
+    for (key, item) in table.iter() {
+        if let Some(value) = toml_item_to_value(item) {
+            map.insert(key.to_string(), value);
+        }
+    }
+    Value::Object(map)
+}
+
+fn toml_item_to_value(item: &toml_edit::Item) -> Option<Value> {
+    match item {
+        toml_edit::Item::None => None,
+        toml_edit::Item::Value(v) => Some(toml_value_to_value(v)),
+        toml_edit::Item::Table(t) => Some(toml_table_to_value(t)),
+        toml_edit::Item::ArrayOfTables(tables) => Some(Value::Array(
+            tables.iter().map(toml_table_to_value).collect(),
+        )),
+    }
+}
+
+fn toml_value_to_value(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(s) => Value::String(s.value().clone()),
+        toml_edit::Value::Integer(i) => Value::Number(Number::from(*i.value())),
+        toml_edit::Value::Float(f) => Number::from_f64(*f.value())
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        toml_edit::Value::Boolean(b) => Value::Bool(*b.value()),
+        toml_edit::Value::Datetime(d) => Value::String(d.value().to_string()),
+        toml_edit::Value::Array(array) => {
+            Value::Array(array.iter().map(toml_value_to_value).collect())
+        }
+        toml_edit::Value::InlineTable(t) => {
+            let mut map = Map::new();
+            for (key, v) in t.iter() {
+                map.insert(key.to_string(), toml_value_to_value(v));
+            }
+            Value::Object(map)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_and_json_are_equivalent() {
+        let toml_value = load_value("tests/data/validation.toml").unwrap();
+        let json_value = load_value("tests/data/validation.json").unwrap();
+        assert_eq!(toml_value, json_value);
+    }
+
+    #[test]
+    fn test_project_file() {
+        let expected = std::fs::canonicalize("tests/data/project/.csvlens.toml").unwrap();
+        let expected = Some(without_verbatim_prefix(expected.to_str().unwrap()));
+        assert_eq!(
+            project_file("tests/data/project/exports/daily_events.csv"),
+            expected
+        );
+        assert_eq!(project_file("tests/data/cities.csv"), None);
+        assert_eq!(project_file("https://example.com/daily_events.csv"), None);
+    }
+
+    #[test]
+    fn test_without_verbatim_prefix() {
+        assert_eq!(
+            without_verbatim_prefix(r"\\?\C:\data\.csvlens.toml"),
+            r"C:\data\.csvlens.toml"
+        );
+        assert_eq!(
+            without_verbatim_prefix(r"\\?\UNC\fileserver\reports\.csvlens.toml"),
+            r"\\fileserver\reports\.csvlens.toml"
+        );
+        assert_eq!(
+            without_verbatim_prefix("/home/ann/.csvlens.toml"),
+            "/home/ann/.csvlens.toml"
+        );
+    }
+
+    #[test]
+    fn test_load_string_map() {
+        let aliases = load_string_map("tests/data/header_aliases.toml").unwrap();
+        assert_eq!(
+            aliases,
+            vec![
+                ("c_001".to_string(), "Customer ID".to_string()),
+                ("c_017".to_string(), "Signup date".to_string()),
+            ]
+        );
+        let result = load_string_map("tests/data/validation.toml");
+        assert!(matches!(result, Err(CsvlensError::ConfigParsing(_, _))));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let result = load_value("tests/data/simple.csv");
+        assert!(matches!(result, Err(CsvlensError::ConfigParsing(_, _))));
+    }
+}
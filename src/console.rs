@@ -0,0 +1,116 @@
+use crate::osc;
+
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+use crossterm::execute;
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the console acts on escape sequences, e.g. for hyperlinks or the window title, rather
+/// than printing them as text. Legacy Windows consoles (conhost without virtual terminal
+/// processing) don't, but crossterm draws the view there with the console API.
+pub fn supports_escapes() -> bool {
+    #[cfg(windows)]
+    {
+        crossterm::ansi_support::supports_ansi()
+    }
+    #[cfg(not(windows))]
+    {
+        true
+    }
+}
+
+/// Save and restore the window title around the session (XTWINOPS)
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// Whether the view is on screen, for a stop by job control to leave it first
+static ON_SCREEN: AtomicBool = AtomicBool::new(false);
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+static CONTINUED: AtomicBool = AtomicBool::new(false);
+
+/// Put the terminal in the state the view is drawn in: raw mode on the alternate screen, with
+/// pasted text coming at once instead of as key presses, which would run as commands
+pub fn enter_screen(terminal_integration: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut output = io::stderr();
+    execute!(output, EnterAlternateScreen, EnableBracketedPaste)?;
+    if terminal_integration {
+        let host = std::env::var("HOSTNAME").unwrap_or_default();
+        let cwd = std::env::current_dir()?;
+        write!(
+            output,
+            "{PUSH_TITLE}{}",
+            osc::working_directory(&cwd, &host)
+        )?;
+    }
+    catch_job_signals();
+    ON_SCREEN.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Give the terminal back as it was before [`enter_screen`]
+pub fn leave_screen(terminal_integration: bool) -> io::Result<()> {
+    ON_SCREEN.store(false, Ordering::SeqCst);
+    disable_raw_mode()?;
+    execute!(io::stderr(), DisableBracketedPaste, LeaveAlternateScreen)?;
+    if terminal_integration {
+        eprint!("{POP_TITLE}");
+    }
+    Ok(())
+}
+
+/// Stop the process as with Ctrl-Z in a shell, leaving the screen for the shell until it is
+/// continued, e.g. with `fg`. Windows has no job control, so this does nothing there.
+pub fn suspend(terminal_integration: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        leave_screen(terminal_integration)?;
+        signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+        CONTINUED.store(false, Ordering::SeqCst);
+        enter_screen(terminal_integration)?;
+    }
+    #[cfg(not(unix))]
+    let _ = terminal_integration;
+    Ok(())
+}
+
+/// Whether a stop was asked for by SIGTSTP since the last call, e.g. from `kill -TSTP`. Ctrl-Z
+/// itself comes as a key press, as the terminal is in raw mode.
+pub fn take_stop_request() -> bool {
+    STOP_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Whether the process was continued since the last call after a stop it couldn't leave the
+/// screen for, e.g. SIGSTOP. The shell may have reset the terminal in the meantime.
+pub fn take_continued() -> bool {
+    CONTINUED.swap(false, Ordering::SeqCst)
+}
+
+/// SIGTSTP stops the process right away when the view isn't on screen, as if it wasn't caught
+#[cfg(unix)]
+fn catch_job_signals() {
+    use signal_hook::consts::{SIGCONT, SIGTSTP};
+    use signal_hook::low_level::{emulate_default_handler, register};
+    use std::sync::Once;
+
+    static REGISTERED: Once = Once::new();
+    REGISTERED.call_once(|| {
+        // Safety: the actions only use atomics and raise(), which are async-signal-safe
+        unsafe {
+            let _ = register(SIGTSTP, || {
+                if ON_SCREEN.load(Ordering::SeqCst) {
+                    STOP_REQUESTED.store(true, Ordering::SeqCst);
+                } else {
+                    let _ = emulate_default_handler(SIGTSTP);
+                }
+            });
+            let _ = register(SIGCONT, || CONTINUED.store(true, Ordering::SeqCst));
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn catch_job_signals() {}
@@ -1,23 +1,32 @@
 extern crate csv;
 
-use csv::{Position, Reader, ReaderBuilder};
-use std::cmp::max;
+use csv::{Position, Reader, ReaderBuilder, Trim};
+use std::cmp::{max, min};
+use std::fmt;
 use std::fs::File;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time;
 use std::{
-    io::{self, Read},
+    io::{self, Read, Seek, SeekFrom},
     sync::atomic::{AtomicBool, Ordering},
     time::Duration,
 };
 
+use arrow::array::RecordBatch;
+use arrow::csv::reader::Format;
+use arrow::datatypes::Schema;
+use arrow::error::ArrowError;
 use csv::{ByteRecord, StringRecord};
 use csv_core::Reader as CoreReader;
 use csv_core::ReaderBuilder as CoreReaderBuilder;
+use regex::Regex;
 
 use crate::errors::CsvlensResult;
+use crate::io::{InputFile, input_len, open_input};
+use crate::ipc::{ArrowTable, TableReader};
+use crate::number;
 
 fn string_record_to_vec(record: &csv::StringRecord) -> Vec<String> {
     let mut string_vec = Vec::with_capacity(record.len());
@@ -27,9 +36,39 @@ fn string_record_to_vec(record: &csv::StringRecord) -> Vec<String> {
     string_vec
 }
 
+/// Default number of rows per batch read by the arrow CSV reader used for sorting
+pub const DEFAULT_ARROW_BATCH_SIZE: usize = 1024;
+
+/// Default number of records read to infer column types for sorting
+pub const DEFAULT_SCHEMA_INFER_MAX_RECORDS: usize = 1000;
+
+/// Default number of bytes of values read for sorting kept in memory, past which sorted runs of
+/// them are written to temporary files
+pub const DEFAULT_SORT_MEMORY_LIMIT: usize = 1 << 30;
+
+/// Number of rows per batch read and bytes of values kept in memory when sorting with --low-memory
+pub const LOW_MEMORY_ARROW_BATCH_SIZE: usize = 256;
+pub const LOW_MEMORY_SORT_MEMORY_LIMIT: usize = 64 << 20;
+
+/// Default number of fields shown per record, so that malformed files with huge records (e.g. a
+/// single line of a million fields) stay responsive
+pub const DEFAULT_MAX_FIELDS: usize = 10_000;
+
+#[derive(Clone)]
 pub struct CsvBaseConfig {
     delimiter: u8,
     no_headers: bool,
+    arrow_batch_size: usize,
+    schema_infer_max_records: usize,
+    sort_memory_limit: usize,
+    decimal_comma: bool,
+    trim: bool,
+    max_fields: Option<usize>,
+    quote: u8,
+    escape: Option<u8>,
+    quoting: bool,
+    /// Values that stand for none besides empty ones, e.g. `NA`
+    null_values: Vec<String>,
 }
 
 impl CsvBaseConfig {
@@ -37,14 +76,91 @@ impl CsvBaseConfig {
         CsvBaseConfig {
             delimiter,
             no_headers,
+            arrow_batch_size: DEFAULT_ARROW_BATCH_SIZE,
+            schema_infer_max_records: DEFAULT_SCHEMA_INFER_MAX_RECORDS,
+            sort_memory_limit: DEFAULT_SORT_MEMORY_LIMIT,
+            decimal_comma: false,
+            trim: false,
+            max_fields: Some(DEFAULT_MAX_FIELDS),
+            quote: b'"',
+            escape: None,
+            quoting: true,
+            null_values: vec![],
         }
     }
+
+    /// Larger batches speed up sorting at the cost of higher peak memory
+    pub fn with_arrow_batch_size(mut self, batch_size: usize) -> CsvBaseConfig {
+        self.arrow_batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_schema_infer_max_records(mut self, max_records: usize) -> CsvBaseConfig {
+        self.schema_infer_max_records = max_records.max(1);
+        self
+    }
+
+    /// Sort in smaller batches, spilling to temporary files sooner
+    pub fn with_low_memory(self) -> CsvBaseConfig {
+        self.with_arrow_batch_size(LOW_MEMORY_ARROW_BATCH_SIZE)
+            .with_sort_memory_limit(LOW_MEMORY_SORT_MEMORY_LIMIT)
+    }
+
+    /// Sorting spills to temporary files once the values read take more than this many bytes
+    pub fn with_sort_memory_limit(mut self, bytes: usize) -> CsvBaseConfig {
+        self.sort_memory_limit = bytes.max(1);
+        self
+    }
+
+    /// Fields of each record after the first max_fields are left out of the view, or none if None
+    pub fn with_max_fields(mut self, max_fields: Option<usize>) -> CsvBaseConfig {
+        self.max_fields = max_fields.map(|n| n.max(1));
+        self
+    }
+
+    /// Trim leading and trailing whitespace in headers and fields
+    pub fn with_trim(mut self, trim: bool) -> CsvBaseConfig {
+        self.trim = trim;
+        self
+    }
+
+    /// Read numbers with a decimal comma, e.g. `1.234,56`, for sorting, stats and validation
+    pub fn with_decimal_comma(mut self, decimal_comma: bool) -> CsvBaseConfig {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+
+    /// Take these values as empty for sorting, e.g. `NA` or `null`
+    pub fn with_null_values(mut self, null_values: Vec<String>) -> CsvBaseConfig {
+        self.null_values = null_values;
+        self
+    }
+
+    /// Read fields quoted with this character, with quotes in them doubled or escaped by the escape
+    /// character if any, e.g. `'It\'s'`. With quoting off, quotes are read as any other character.
+    pub fn with_quoting(mut self, quote: u8, escape: Option<u8>, quoting: bool) -> CsvBaseConfig {
+        self.quote = quote;
+        self.escape = escape;
+        self.quoting = quoting;
+        self
+    }
 }
 
+/// Options for reading a file, shared by everything that reads it (the viewer, finder, sorter,
+/// validator and profiler) so that they all parse it the same way
 pub struct CsvConfig {
     path: String,
     stream_active: Option<Arc<AtomicBool>>,
     base: CsvBaseConfig,
+    /// Part of the file read, as byte offsets where it starts and ends, e.g. to leave out a footer
+    /// or to read one of several tables in the file
+    data_start: u64,
+    data_end: Option<u64>,
+    /// Part of the file left out in between, as byte offsets where it starts and ends, e.g. a row
+    /// read as a subheader
+    skipped: Option<(u64, u64)>,
+    /// Record batches of an Arrow IPC file, read instead of parsing the file as CSV
+    table: Option<Arc<ArrowTable>>,
 }
 
 impl CsvConfig {
@@ -57,21 +173,268 @@ impl CsvConfig {
             path: path.to_string(),
             stream_active,
             base,
+            data_start: 0,
+            data_end: None,
+            skipped: None,
+            table: None,
+        }
+    }
+
+    /// Same options, but reading the rows of the record batches instead of the file
+    pub fn with_arrow_table(&self, table: Arc<ArrowTable>) -> CsvConfig {
+        CsvConfig {
+            path: self.path.clone(),
+            stream_active: self.stream_active.clone(),
+            base: self.base.clone(),
+            data_start: self.data_start,
+            data_end: self.data_end,
+            skipped: self.skipped,
+            table: Some(table),
         }
     }
 
-    pub fn new_reader(&self) -> CsvlensResult<Reader<File>> {
-        let reader = ReaderBuilder::new()
+    pub fn arrow_table(&self) -> Option<&Arc<ArrowTable>> {
+        self.table.as_ref()
+    }
+
+    /// Same options, but with the first row read as a header or not
+    pub fn with_no_headers(&self, no_headers: bool) -> CsvConfig {
+        CsvConfig {
+            path: self.path.clone(),
+            stream_active: self.stream_active.clone(),
+            base: CsvBaseConfig {
+                no_headers,
+                ..self.base.clone()
+            },
+            data_start: self.data_start,
+            data_end: self.data_end,
+            skipped: None,
+            table: self.table.clone(),
+        }
+    }
+
+    /// Same options, but with another delimiter and the first row read as a header or not
+    pub fn with_dialect(&self, delimiter: u8, no_headers: bool) -> CsvConfig {
+        CsvConfig {
+            path: self.path.clone(),
+            stream_active: self.stream_active.clone(),
+            base: CsvBaseConfig {
+                delimiter,
+                no_headers,
+                ..self.base.clone()
+            },
+            data_start: self.data_start,
+            data_end: self.data_end,
+            skipped: None,
+            table: self.table.clone(),
+        }
+    }
+
+    /// Same options, but with a different number of fields shown per record
+    pub fn with_max_fields(&self, max_fields: Option<usize>) -> CsvConfig {
+        CsvConfig {
+            path: self.path.clone(),
+            stream_active: self.stream_active.clone(),
+            base: CsvBaseConfig {
+                max_fields,
+                ..self.base.clone()
+            },
+            data_start: self.data_start,
+            data_end: self.data_end,
+            skipped: self.skipped,
+            table: self.table.clone(),
+        }
+    }
+
+    /// Same options, but reading only this part of the file. Offsets in the file are relative to
+    /// its start for everything reading it.
+    pub fn with_data_range(&self, data_start: u64, data_end: Option<u64>) -> CsvConfig {
+        CsvConfig {
+            path: self.path.clone(),
+            stream_active: self.stream_active.clone(),
+            base: self.base.clone(),
+            data_start,
+            data_end,
+            skipped: None,
+            table: self.table.clone(),
+        }
+    }
+
+    pub fn data_range(&self) -> (u64, Option<u64>) {
+        (self.data_start, self.data_end)
+    }
+
+    /// Offset in the file at a percentage of the part read, e.g. the middle at 50
+    pub fn offset_at_percent(&self, percent: u64) -> CsvlensResult<u64> {
+        let len = self.data_end()?.saturating_sub(self.data_start);
+        Ok(self.data_start + len * min(percent, 100) / 100)
+    }
+
+    /// Number of bytes read through the file, i.e. without the part left out
+    pub fn data_len(&self) -> CsvlensResult<u64> {
+        let skipped_len = self.skipped.map_or(0, |(start, end)| end - start);
+        Ok(self
+            .data_end()?
+            .saturating_sub(self.data_start)
+            .saturating_sub(skipped_len))
+    }
+
+    fn data_end(&self) -> CsvlensResult<u64> {
+        match (self.data_end, &self.table) {
+            (Some(end), _) => Ok(end),
+            (None, Some(table)) => Ok(table.num_records()),
+            (None, None) => Ok(input_len(self.filename())?),
+        }
+    }
+
+    /// Same options, but leaving out this part of the file, given as offsets in the file. Offsets
+    /// after it are as if it weren't there for everything reading the file.
+    pub fn with_skipped_range(&self, skipped: Option<(u64, u64)>) -> CsvConfig {
+        CsvConfig {
+            path: self.path.clone(),
+            stream_active: self.stream_active.clone(),
+            base: self.base.clone(),
+            data_start: self.data_start,
+            data_end: self.data_end,
+            skipped,
+            table: self.table.clone(),
+        }
+    }
+
+    /// Open the file for reading its records
+    pub fn open(&self) -> CsvlensResult<DataFile> {
+        let mut file = open_input(self.path.as_str())?;
+        if self.data_start > 0 {
+            file.seek(SeekFrom::Start(self.data_start))?;
+        }
+        Ok(DataFile {
+            file,
+            start: self.data_start,
+            pos: 0,
+            end: self.data_end,
+            skip: self
+                .skipped
+                .map(|(start, end)| (start - self.data_start, end - start)),
+        })
+    }
+
+    /// Reader of the records, parsed from the file or read from the record batches
+    pub fn new_reader(&self) -> CsvlensResult<RecordReader> {
+        if let Some(table) = &self.table {
+            return Ok(RecordReader::Table(TableReader::new(
+                table.clone(),
+                self.has_headers(),
+            )));
+        }
+        let reader = self
+            .reader_builder()
+            .has_headers(!self.base.no_headers)
+            .trim(if self.base.trim {
+                Trim::All
+            } else {
+                Trim::None
+            })
+            .from_reader(self.open()?);
+        Ok(RecordReader::Csv(reader))
+    }
+
+    /// Builder of readers parsing records like the file's, e.g. for reading lines of it. Records
+    /// may have any number of fields.
+    pub fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder
             .flexible(true)
             .delimiter(self.base.delimiter)
-            .has_headers(!self.base.no_headers)
-            .from_path(self.path.as_str())?;
-        Ok(reader)
+            .quote(self.base.quote)
+            .escape(self.base.escape)
+            .quoting(self.base.quoting);
+        builder
+    }
+
+    /// Infer the types of columns from the first records
+    pub fn infer_arrow_schema(&self) -> CsvlensResult<Schema> {
+        if let Some(table) = &self.table {
+            return Ok(table.schema().as_ref().clone());
+        }
+        let file = self.open()?;
+        let (schema, _) = self
+            .arrow_format()
+            .infer_schema(file, Some(self.base.schema_infer_max_records))?;
+        Ok(schema)
+    }
+
+    /// Arrow reader of the columns in projection, parsed with the given schema, counting the bytes
+    /// it reads. The batches of an Arrow IPC file are read as they are, with their columns cast to
+    /// the types in the schema, and count their rows as bytes.
+    pub fn new_arrow_reader(
+        &self,
+        schema: Arc<Schema>,
+        projection: Vec<usize>,
+        bytes_read: Arc<AtomicU64>,
+    ) -> CsvlensResult<RecordBatches<'_>> {
+        if let Some(table) = &self.table {
+            let batches = table.batches(schema, projection).inspect(move |batch| {
+                if let Ok(batch) = batch {
+                    bytes_read.fetch_add(batch.num_rows() as u64, Ordering::Relaxed);
+                }
+            });
+            return Ok(Box::new(batches));
+        }
+        let file = CountingReader {
+            inner: self.open()?,
+            bytes_read,
+        };
+        let reader = arrow::csv::ReaderBuilder::new(schema)
+            .with_format(self.arrow_format())
+            .with_batch_size(self.base.arrow_batch_size)
+            .with_projection(projection)
+            .build(file)?;
+        Ok(Box::new(reader))
+    }
+
+    fn arrow_format(&self) -> Format {
+        // Rows with missing fields are allowed, as they are by the flexible csv reader
+        let format = Format::default()
+            .with_delimiter(self.base.delimiter)
+            .with_header(!self.base.no_headers)
+            .with_truncated_rows(true);
+        // Quoting can't be turned off for arrow, so quotes are a byte that never comes up instead:
+        // files with NUL bytes are refused as binary
+        let format = format.with_quote(if self.base.quoting {
+            self.base.quote
+        } else {
+            b'\0'
+        });
+        let format = match self.base.escape {
+            Some(escape) => format.with_escape(escape),
+            None => format,
+        };
+        match self.null_regex() {
+            Some(null_regex) => format.with_null_regex(null_regex),
+            None => format,
+        }
+    }
+
+    /// Regex of the values arrow reads as null: empty ones, and the null values if any
+    fn null_regex(&self) -> Option<Regex> {
+        if self.base.null_values.is_empty() {
+            return None;
+        }
+        let values: Vec<String> = self
+            .base
+            .null_values
+            .iter()
+            .map(|v| regex::escape(v))
+            .collect();
+        Regex::new(&format!("^(?:|{})$", values.join("|"))).ok()
     }
 
     pub fn new_core_reader(&self) -> CoreReader {
         CoreReaderBuilder::new()
             .delimiter(self.base.delimiter)
+            .quote(self.base.quote)
+            .escape(self.base.escape)
+            .quoting(self.base.quoting)
             .build()
     }
 
@@ -91,6 +454,46 @@ impl CsvConfig {
         !self.base.no_headers
     }
 
+    pub fn max_fields(&self) -> Option<usize> {
+        self.base.max_fields
+    }
+
+    pub fn trim(&self) -> bool {
+        self.base.trim
+    }
+
+    /// The character fields are quoted with, or None if quoting is off
+    pub fn quote(&self) -> Option<u8> {
+        self.base.quoting.then_some(self.base.quote)
+    }
+
+    pub fn decimal_comma(&self) -> bool {
+        self.base.decimal_comma
+    }
+
+    pub fn sort_memory_limit(&self) -> usize {
+        self.base.sort_memory_limit
+    }
+
+    /// Whether the value is empty, or one of the values standing for none
+    pub fn is_null(&self, value: &str) -> bool {
+        value.is_empty() || self.base.null_values.iter().any(|v| v == value)
+    }
+
+    /// The field of the record, empty if it is missing or stands for none
+    pub fn field<'r>(&self, record: &'r StringRecord, index: usize) -> &'r str {
+        record.get(index).filter(|v| !self.is_null(v)).unwrap_or("")
+    }
+
+    /// Parse a value as a number the way it's written in the file
+    pub fn parse_number(&self, value: &str) -> Option<f64> {
+        number::parse_number(value, self.base.decimal_comma)
+    }
+
+    pub fn parse_percentage(&self, value: &str) -> Option<f64> {
+        number::parse_percentage(value, self.base.decimal_comma)
+    }
+
     /// Convert position to a 0-based record index
     pub fn position_to_record_index(&self, position: u64) -> u64 {
         if self.base.no_headers {
@@ -118,11 +521,204 @@ impl CsvConfig {
     }
 }
 
+/// File of a [CsvConfig], seen as only the part of it that is read
+pub struct DataFile {
+    file: InputFile,
+    /// Offset in the file where this starts
+    start: u64,
+    /// Position relative to start
+    pos: u64,
+    /// Offset in the file where this ends
+    end: Option<u64>,
+    /// Part left out, as the position relative to start where it is and its length
+    skip: Option<(u64, u64)>,
+}
+
+impl DataFile {
+    /// Offset in the file of a position relative to start
+    fn file_offset(&self, pos: u64) -> u64 {
+        match self.skip {
+            Some((at, len)) if pos >= at => self.start.saturating_add(pos).saturating_add(len),
+            _ => self.start.saturating_add(pos),
+        }
+    }
+
+    /// Position relative to start of an offset in the file, taking the part left out as if it
+    /// were at its start
+    fn position_of(&self, file_offset: u64) -> u64 {
+        let pos = file_offset.saturating_sub(self.start);
+        match self.skip {
+            Some((at, len)) if pos >= at + len => pos - len,
+            Some((at, _)) => min(pos, at),
+            None => pos,
+        }
+    }
+}
+
+impl Read for DataFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut max_len = buf.len() as u64;
+        if let Some(end) = self.end {
+            max_len = min(max_len, end.saturating_sub(self.file_offset(self.pos)));
+        }
+        if let Some((at, _)) = self.skip
+            && self.pos < at
+        {
+            max_len = min(max_len, at - self.pos);
+        }
+        let n = self.file.read(&mut buf[..max_len as usize])?;
+        self.pos += n as u64;
+        // Jump over the part left out on reaching it
+        if let Some((at, len)) = self.skip
+            && n > 0
+            && self.pos == at
+        {
+            self.file.seek(SeekFrom::Current(len as i64))?;
+        }
+        Ok(n)
+    }
+}
+
+impl Seek for DataFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let file_pos = match (pos, self.end) {
+            (SeekFrom::Start(offset), _) => SeekFrom::Start(self.file_offset(offset)),
+            (SeekFrom::End(offset), Some(end)) => {
+                SeekFrom::Start(end.saturating_add_signed(offset))
+            }
+            (SeekFrom::End(offset), None) => SeekFrom::End(offset),
+            (SeekFrom::Current(offset), _) => {
+                SeekFrom::Start(self.file_offset(self.pos.saturating_add_signed(offset)))
+            }
+        };
+        let file_pos = self.file.seek(file_pos)?;
+        self.pos = self.position_of(file_pos);
+        Ok(self.pos)
+    }
+}
+
+/// Counts the bytes read through it, for readers that don't tell their position like arrow's
+pub struct CountingReader<R> {
+    inner: R,
+    bytes_read: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Record batches read for sorting
+pub type RecordBatches<'a> = Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>> + 'a>;
+
+/// Reader of the records of a [CsvConfig], parsing them from the file or reading them from the
+/// record batches of an Arrow IPC file
+pub enum RecordReader {
+    Csv(Reader<DataFile>),
+    Table(TableReader),
+}
+
+impl RecordReader {
+    pub fn headers(&mut self) -> CsvlensResult<&StringRecord> {
+        match self {
+            RecordReader::Csv(reader) => Ok(reader.headers()?),
+            RecordReader::Table(reader) => Ok(reader.headers()),
+        }
+    }
+
+    pub fn byte_headers(&mut self) -> CsvlensResult<&ByteRecord> {
+        match self {
+            RecordReader::Csv(reader) => Ok(reader.byte_headers()?),
+            RecordReader::Table(reader) => Ok(reader.byte_headers()),
+        }
+    }
+
+    pub fn read_record(&mut self, record: &mut StringRecord) -> CsvlensResult<bool> {
+        match self {
+            RecordReader::Csv(reader) => Ok(reader.read_record(record)?),
+            RecordReader::Table(reader) => reader.read_record(record),
+        }
+    }
+
+    pub fn read_byte_record(&mut self, record: &mut ByteRecord) -> CsvlensResult<bool> {
+        match self {
+            RecordReader::Csv(reader) => Ok(reader.read_byte_record(record)?),
+            RecordReader::Table(reader) => {
+                let mut string_record = StringRecord::new();
+                let has_record = reader.read_record(&mut string_record)?;
+                *record = string_record.into_byte_record();
+                Ok(has_record)
+            }
+        }
+    }
+
+    pub fn position(&self) -> &Position {
+        match self {
+            RecordReader::Csv(reader) => reader.position(),
+            RecordReader::Table(reader) => reader.position(),
+        }
+    }
+
+    pub fn seek(&mut self, position: Position) -> CsvlensResult<()> {
+        match self {
+            RecordReader::Csv(reader) => reader.seek(position)?,
+            RecordReader::Table(reader) => reader.seek(&position),
+        }
+        Ok(())
+    }
+
+    /// Offset in the file of a byte position of the reader, as record_index_at_offset takes it
+    fn file_offset(&self, byte: u64) -> u64 {
+        match self {
+            RecordReader::Csv(reader) => reader.get_ref().file_offset(byte),
+            // Positions in record batches are record numbers, the same whatever the config
+            RecordReader::Table(_) => byte,
+        }
+    }
+}
+
 pub struct CsvLensReader {
     config: Arc<CsvConfig>,
-    reader: Reader<File>,
+    reader: RecordReader,
     pub headers: Vec<String>,
     internal: Arc<Mutex<ReaderInternalState>>,
+    is_clone: bool,
+    /// Whether any record read had more fields than shown, shared with clones
+    fields_capped: Arc<AtomicBool>,
+}
+
+/// Stable identity of a record, independent of any filter or sort applied to the view. Held as
+/// the 0-based index of the record in the file, and shown to users as its 1-based record number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RowId(usize);
+
+impl RowId {
+    pub fn from_index(index: usize) -> Self {
+        RowId(index)
+    }
+
+    pub fn from_record_num(record_num: usize) -> Self {
+        RowId(record_num.saturating_sub(1))
+    }
+
+    /// 0-based index of the record in the file
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// 1-based record number, as shown in the line number column
+    pub fn record_num(self) -> usize {
+        self.0.saturating_add(1)
+    }
+}
+
+impl fmt::Display for RowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.record_num())
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -132,6 +728,18 @@ pub struct Row {
 }
 
 impl Row {
+    pub fn id(&self) -> RowId {
+        RowId::from_record_num(self.record_num)
+    }
+
+    /// Approximate number of bytes used by the fields
+    pub fn memory_usage(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|field| std::mem::size_of::<String>() + field.capacity())
+            .sum()
+    }
+
     pub fn subset(&self, indices: &[usize]) -> Row {
         let mut subfields = vec![];
         for i in indices {
@@ -164,7 +772,10 @@ struct GetRowIndex {
 
 impl Drop for CsvLensReader {
     fn drop(&mut self) {
-        self.terminate();
+        // Indexing is shared with clones and only stops with the original reader
+        if !self.is_clone {
+            self.terminate();
+        }
     }
 }
 
@@ -181,7 +792,14 @@ impl CsvLensReader {
         } else {
             reader.headers()?.clone()
         };
-        let headers = string_record_to_vec(&headers_record);
+        let mut headers = string_record_to_vec(&headers_record);
+        let fields_capped = Arc::new(AtomicBool::new(false));
+        if let Some(max_fields) = config.max_fields()
+            && headers.len() > max_fields
+        {
+            headers.truncate(max_fields);
+            fields_capped.store(true, Ordering::Relaxed);
+        }
 
         let (m_internal, _handle) = ReaderInternalState::init_internal(config.clone());
 
@@ -192,10 +810,32 @@ impl CsvLensReader {
             reader,
             headers,
             internal: m_internal,
+            is_clone: false,
+            fields_capped,
         };
         Ok(reader)
     }
 
+    /// A reader with its own file handle sharing the row positions indexed by this one, e.g. to
+    /// read rows on another thread
+    pub fn try_clone(&self) -> CsvlensResult<Self> {
+        Ok(Self {
+            config: self.config.clone(),
+            reader: self.config.new_reader()?,
+            headers: self.headers.clone(),
+            internal: self.internal.clone(),
+            is_clone: true,
+            fields_capped: self.fields_capped.clone(),
+        })
+    }
+
+    /// Number of fields shown per record, if any record read so far had more
+    pub fn fields_cap(&self) -> Option<usize> {
+        self.config
+            .max_fields()
+            .filter(|_| self.fields_capped.load(Ordering::Relaxed))
+    }
+
     pub fn get_rows(
         &mut self,
         rows_from: u64,
@@ -221,7 +861,7 @@ impl CsvLensReader {
                 order_index: x.0,
             })
             .collect::<Vec<_>>();
-        get_row_indices.sort_by(|a, b| a.record_index.cmp(&b.record_index));
+        get_row_indices.sort_by_key(|a| a.record_index);
         self._get_rows_impl_sorted(&get_row_indices)
     }
 
@@ -232,13 +872,21 @@ impl CsvLensReader {
         // stats for debugging and testing
         let mut stats = GetRowsStats::new();
 
-        let pos = Position::new();
-        self.reader.seek(pos)?;
-
         let tic = time::Instant::now();
-        let pos_table = self.get_pos_table();
+        let (pos_table, pos_table_len) = match (indices.first(), indices.last()) {
+            (Some(first), Some(last)) => {
+                self.get_pos_table_range(first.record_index, last.record_index)
+            }
+            _ => (vec![], 0),
+        };
         stats.pos_table_elapsed = Some(tic.elapsed());
-        stats.pos_table_entry = pos_table.len();
+        stats.pos_table_entry = pos_table_len;
+
+        let reader = match &mut self.reader {
+            RecordReader::Csv(reader) => reader,
+            RecordReader::Table(_) => return self.get_table_rows(indices),
+        };
+        reader.seek(Position::new())?;
 
         let mut pos_iter = pos_table.iter();
         let mut indices_iter = indices.iter();
@@ -249,8 +897,6 @@ impl CsvLensReader {
         let mut next_pos = pos_iter.next();
         let mut next_wanted = indices_iter.next();
 
-        let num_fields = self.headers.len();
-
         let mut should_stop = false;
         loop {
             if next_wanted.is_none() {
@@ -268,13 +914,13 @@ impl CsvLensReader {
                 next_pos = pos_iter.next();
             }
             if let Some(pos) = seek_pos {
-                self.reader.seek(pos.clone())?;
+                reader.seek(pos.clone())?;
                 stats.log_seek();
             }
 
             // note that records() excludes header by default, but here the first entry is header
             // because of the seek() above.
-            let mut records = self.reader.records();
+            let mut records = reader.records();
 
             // parse records and collect those that are wanted
             loop {
@@ -292,17 +938,8 @@ impl CsvLensReader {
                     }
                     if self.config.position_to_record_index(record_position) == wanted.record_index
                     {
-                        let string_record = r?;
-                        let mut fields = Vec::with_capacity(num_fields);
-                        for field in string_record.iter() {
-                            fields.push(String::from(field));
-                        }
-                        let row = Row {
-                            record_num: self.config.position_to_record_num(record_position)
-                                as usize,
-                            fields,
-                        };
-                        res[wanted.order_index] = row;
+                        res[wanted.order_index] =
+                            Self::to_row(&self.config, &self.fields_capped, record_position, &r?);
                         res_max_index.replace(
                             res_max_index
                                 .map_or(wanted.order_index, |x| max(x, wanted.order_index)),
@@ -343,6 +980,52 @@ impl CsvLensReader {
         Ok((res, stats))
     }
 
+    /// Rows of the record batches of an Arrow IPC file, which are read right at their positions
+    fn get_table_rows(
+        &mut self,
+        indices: &[GetRowIndex],
+    ) -> CsvlensResult<(Vec<Row>, GetRowsStats)> {
+        let mut stats = GetRowsStats::new();
+        let mut res = vec![Row::empty(); indices.len()];
+        let mut res_max_index: Option<usize> = None;
+        let mut record = StringRecord::new();
+        for wanted in indices {
+            let mut position = Position::new();
+            position.set_byte(wanted.record_index + u64::from(self.config.has_headers()));
+            self.reader.seek(position)?;
+            stats.log_seek();
+            let record_position = self.reader.position().record();
+            if !self.reader.read_record(&mut record)? {
+                break;
+            }
+            stats.log_parsed_record();
+            res[wanted.order_index] =
+                Self::to_row(&self.config, &self.fields_capped, record_position, &record);
+            res_max_index =
+                Some(res_max_index.map_or(wanted.order_index, |x| max(x, wanted.order_index)));
+        }
+        res.truncate(res_max_index.map_or(0, |x| x + 1));
+        Ok((res, stats))
+    }
+
+    /// Row of the record at a position, with as many fields as are shown
+    fn to_row(
+        config: &CsvConfig,
+        fields_capped: &AtomicBool,
+        record_position: u64,
+        record: &StringRecord,
+    ) -> Row {
+        let max_fields = config.max_fields().unwrap_or(usize::MAX);
+        if record.len() > max_fields {
+            fields_capped.store(true, Ordering::Relaxed);
+        }
+        let fields = record.iter().take(max_fields).map(String::from).collect();
+        Row {
+            record_num: config.position_to_record_num(record_position) as usize,
+            fields,
+        }
+    }
+
     pub fn get_approx_line_numbers(&self) -> usize {
         self.internal
             .lock()
@@ -355,8 +1038,147 @@ impl CsvLensReader {
         self.internal.lock().unwrap().total_line_number
     }
 
-    pub fn get_pos_table(&self) -> Vec<Position> {
-        self.internal.lock().unwrap().pos_table.clone()
+    /// The part of the position table needed to read the records from first to last (0-based
+    /// record indices), along with the size of the whole table. This is the closest position at
+    /// or before first, followed by all positions up to and including the first one after last.
+    /// Only this part is copied, so that reading a screen costs the same near the end of a huge
+    /// file or stream as near the start.
+    fn get_pos_table_range(&self, first: u64, last: u64) -> (Vec<Position>, usize) {
+        let m_guard = self.internal.lock().unwrap();
+        let pos_table = &m_guard.pos_table;
+        let num_before = |index: u64| {
+            pos_table
+                .partition_point(|pos| self.config.position_to_record_index(pos.record()) <= index)
+        };
+        let start = num_before(first).saturating_sub(1);
+        let end = min(num_before(last) + 1, pos_table.len());
+        (pos_table[start..end].to_vec(), pos_table.len())
+    }
+
+    /// 0-based index of the first record starting at or after an offset in the file, if any.
+    /// Reading starts from the closest indexed position before the offset.
+    pub fn record_index_at_offset(&mut self, file_offset: u64) -> CsvlensResult<Option<u64>> {
+        Ok(self
+            .record_at_offset(file_offset)?
+            .map(|(record_index, _)| record_index))
+    }
+
+    /// Offsets in the file where the records at the 0-based indices start, None for those past
+    /// the end. Along with record_indices_at_offsets, this finds the same records once the file
+    /// is read with another config, e.g. with the header row read as a record.
+    pub fn record_offsets(&mut self, indices: &[u64]) -> CsvlensResult<Vec<Option<u64>>> {
+        let mut sorted: Vec<(usize, u64)> = indices.iter().copied().enumerate().collect();
+        sorted.sort_by_key(|(_, index)| *index);
+        let mut offsets = vec![None; indices.len()];
+        let mut record = csv::ByteRecord::new();
+        // Index of the record read next, if reading on gets to the next one wanted
+        let mut next_index: Option<u64> = None;
+        for (order, index) in sorted {
+            let (pos_table, _) = self.get_pos_table_range(index, index);
+            let closest = pos_table
+                .into_iter()
+                .next()
+                .filter(|pos| self.position_index(pos) <= index);
+            let reads_on = next_index.is_some_and(|next| {
+                next <= index
+                    && closest
+                        .as_ref()
+                        .is_none_or(|pos| self.position_index(pos) <= next)
+            });
+            if !reads_on {
+                self.reader.seek(closest.unwrap_or_else(Position::new))?;
+            }
+            next_index = None;
+            loop {
+                let position = self.reader.position().clone();
+                if !self.reader.read_byte_record(&mut record)? {
+                    break;
+                }
+                if self.config.has_headers() && position.record() == 0 {
+                    continue;
+                }
+                let record_index = self.config.position_to_record_index(position.record());
+                if record_index == index {
+                    offsets[order] = Some(self.reader.file_offset(position.byte()));
+                    next_index = Some(index + 1);
+                    break;
+                }
+            }
+        }
+        Ok(offsets)
+    }
+
+    /// 0-based indices of the records starting right at the offsets in the file, None for offsets
+    /// where no record starts
+    pub fn record_indices_at_offsets(
+        &mut self,
+        offsets: &[u64],
+    ) -> CsvlensResult<Vec<Option<u64>>> {
+        offsets
+            .iter()
+            .map(|offset| {
+                Ok(self
+                    .record_at_offset(*offset)?
+                    .filter(|(_, start)| start == offset)
+                    .map(|(record_index, _)| record_index))
+            })
+            .collect()
+    }
+
+    /// Record index of the position, with the header row as the first record to read
+    fn position_index(&self, position: &Position) -> u64 {
+        match position.record() {
+            0 => 0,
+            record => self.config.position_to_record_index(record),
+        }
+    }
+
+    /// 0-based index and offset in the file of the first record starting at or after an offset
+    fn record_at_offset(&mut self, file_offset: u64) -> CsvlensResult<Option<(u64, u64)>> {
+        let (target, seek_pos) = match &self.reader {
+            RecordReader::Csv(reader) => {
+                let target = reader.get_ref().position_of(file_offset);
+                let m_guard = self.internal.lock().unwrap();
+                let pos_table = &m_guard.pos_table;
+                let n = pos_table.partition_point(|pos| pos.byte() <= target);
+                (target, n.checked_sub(1).map(|i| pos_table[i].clone()))
+            }
+            // Offsets in record batches are record numbers, which are gone to right away
+            RecordReader::Table(_) => {
+                let mut position = Position::new();
+                position.set_byte(file_offset);
+                (file_offset, Some(position))
+            }
+        };
+        self.reader.seek(seek_pos.unwrap_or_else(Position::new))?;
+
+        let mut record = csv::ByteRecord::new();
+        loop {
+            let position = self.reader.position().clone();
+            let is_header = self.config.has_headers() && position.record() == 0;
+            let has_record = self.reader.read_byte_record(&mut record)?;
+            if !has_record {
+                return Ok(None);
+            }
+            if !is_header && position.byte() >= target {
+                return Ok(Some((
+                    self.config.position_to_record_index(position.record()),
+                    self.reader.file_offset(position.byte()),
+                )));
+            }
+        }
+    }
+
+    /// Time taken to index the whole file, once done
+    pub fn indexing_elapsed(&self) -> Option<time::Duration> {
+        self.internal.lock().unwrap().elapsed
+    }
+
+    /// Approximate number of bytes used by the table of row positions, or by the record batches
+    /// the rows are read from
+    pub fn memory_usage(&self) -> usize {
+        self.internal.lock().unwrap().pos_table.capacity() * std::mem::size_of::<Position>()
+            + self.config.arrow_table().map_or(0, |t| t.memory_usage())
     }
 
     fn terminate(&self) {
@@ -374,7 +1196,7 @@ impl CsvLensReader {
         }
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "harness"))]
     pub fn wait_internal(&self) {
         loop {
             if self.internal.lock().unwrap().done {
@@ -394,7 +1216,7 @@ pub struct GetRowsStats {
 }
 
 impl GetRowsStats {
-    fn new() -> GetRowsStats {
+    pub fn new() -> GetRowsStats {
         GetRowsStats {
             num_seek: 0,
             num_parsed_record: 0,
@@ -567,7 +1389,8 @@ impl Iterator for StreamingCsvReader {
 
 pub enum CsvlensRecordIterator {
     Streaming(Box<StreamingCsvReader>),
-    Standard(csv::StringRecordsIntoIter<File>),
+    Standard(csv::StringRecordsIntoIter<DataFile>),
+    Table(Box<TableReader>),
 }
 
 impl CsvlensRecordIterator {
@@ -575,8 +1398,10 @@ impl CsvlensRecordIterator {
         Ok(if config.is_streaming() {
             CsvlensRecordIterator::Streaming(Box::new(StreamingCsvReader::new(config)?))
         } else {
-            let reader = config.new_reader()?;
-            CsvlensRecordIterator::Standard(reader.into_records())
+            match config.new_reader()? {
+                RecordReader::Csv(reader) => CsvlensRecordIterator::Standard(reader.into_records()),
+                RecordReader::Table(reader) => CsvlensRecordIterator::Table(Box::new(reader)),
+            }
         })
     }
 
@@ -584,6 +1409,7 @@ impl CsvlensRecordIterator {
         match self {
             CsvlensRecordIterator::Streaming(iter) => iter.reader_position(),
             CsvlensRecordIterator::Standard(iter) => iter.reader().position(),
+            CsvlensRecordIterator::Table(iter) => iter.as_ref().position(),
         }
     }
 }
@@ -598,6 +1424,7 @@ impl Iterator for CsvlensRecordIterator {
                 Ok(record) => Ok(record),
                 Err(e) => Err(e.into()),
             }),
+            CsvlensRecordIterator::Table(iter) => iter.next(),
         }
     }
 }
@@ -607,6 +1434,8 @@ struct ReaderInternalState {
     current_line_number: Arc<AtomicUsize>,
     pos_table: Vec<Position>,
     done: bool,
+    /// Time taken to scan the whole file
+    elapsed: Option<time::Duration>,
     should_terminate: bool,
     #[cfg(test)]
     started_scanning: bool,
@@ -622,6 +1451,7 @@ impl ReaderInternalState {
             current_line_number: current_line_number.clone(),
             pos_table: vec![],
             done: false,
+            elapsed: None,
             should_terminate: false,
             #[cfg(test)]
             started_scanning: false,
@@ -631,6 +1461,22 @@ impl ReaderInternalState {
 
         let _m = m_state.clone();
         let handle = thread::spawn(move || {
+            let start = time::Instant::now();
+            // The rows of record batches are counted without reading them, and read right at their
+            // positions without a table of them
+            if let Some(table) = config.arrow_table() {
+                let n_lines = table.num_rows() + usize::from(config.no_headers());
+                current_line_number.store(n_lines, Ordering::Relaxed);
+                let mut m = _m.lock().unwrap();
+                #[cfg(test)]
+                {
+                    m.started_scanning = true;
+                }
+                m.total_line_number = Some(n_lines);
+                m.done = true;
+                m.elapsed = Some(start.elapsed());
+                return;
+            }
             let pos_table_update_every = if config.is_streaming() {
                 // When streaming, filesize cannot be determined. Use a larger default of 64KB (16K
                 // entries for 1GB file, pos table size: 384 KB)
@@ -643,11 +1489,10 @@ impl ReaderInternalState {
                     64 * 1024
                 }
             } else {
-                let filesize = File::open(config.filename())
-                    .unwrap()
-                    .metadata()
-                    .unwrap()
-                    .len();
+                // The file may be gone by now, e.g. a temporary copy of it once the app has quit
+                let Ok(filesize) = input_len(config.filename()) else {
+                    return;
+                };
                 let pos_table_num_entries = 10000;
                 let minimum_interval = 500; // handle small csv (don't keep pos every byte)
                 max(minimum_interval, filesize / pos_table_num_entries)
@@ -657,7 +1502,9 @@ impl ReaderInternalState {
             let mut n_lines = 0;
             let mut n_bytes: u64 = 0;
             let mut last_updated_at = 0;
-            let mut iter = CsvlensRecordIterator::new(config).unwrap();
+            let Ok(mut iter) = CsvlensRecordIterator::new(config) else {
+                return;
+            };
 
             #[cfg(test)]
             {
@@ -686,6 +1533,7 @@ impl ReaderInternalState {
             let mut m = _m.lock().unwrap();
             m.total_line_number = Some(n_lines);
             m.done = true;
+            m.elapsed = Some(start.elapsed());
         });
 
         (m_state, handle)
@@ -792,6 +1640,35 @@ mod tests {
         assert_eq!(rows, expected);
     }
 
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_record_index_at_offset(#[case] is_streaming: bool) {
+        let stream_active = if is_streaming {
+            Some(Arc::new(AtomicBool::new(true)))
+        } else {
+            None
+        };
+        let config = Arc::new(CsvConfig::new(
+            "tests/data/simple.csv",
+            stream_active.clone(),
+            CsvBaseConfig::new(b',', false),
+        ));
+        let mut r = CsvLensReader::new(config.clone()).unwrap();
+        wait_till_ready(&r, &stream_active);
+        // A1 starts after the header at 4, A2 at 10 and A1000 at 9778
+        assert_eq!(r.record_index_at_offset(0).unwrap(), Some(0));
+        assert_eq!(r.record_index_at_offset(4).unwrap(), Some(0));
+        assert_eq!(r.record_index_at_offset(5).unwrap(), Some(1));
+        assert_eq!(r.record_index_at_offset(9778).unwrap(), Some(999));
+        assert_eq!(r.record_index_at_offset(9779).unwrap(), Some(1000));
+        let len = config.offset_at_percent(100).unwrap();
+        assert_eq!(r.record_index_at_offset(len).unwrap(), None);
+        // Rows can still be read afterwards
+        let rows = r.get_rows(1, 1).unwrap().0;
+        assert_eq!(rows, vec![Row::new(2, vec!["A2", "B2"])]);
+    }
+
     #[rstest]
     #[case(false)]
     #[case(true)]
@@ -911,6 +1788,46 @@ mod tests {
         assert_eq!(stats, expected);
     }
 
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_simple_get_rows_backward(#[case] is_streaming: bool) {
+        let stream_active = if is_streaming {
+            Some(Arc::new(AtomicBool::new(true)))
+        } else {
+            None
+        };
+        let config = Arc::new(CsvConfig::new(
+            "tests/data/simple.csv",
+            stream_active.clone(),
+            CsvBaseConfig::new(b',', false),
+        ));
+        let mut r = CsvLensReader::new(config).unwrap();
+        wait_till_ready(&r, &stream_active);
+        r.get_rows(4990, 5).unwrap();
+
+        // Going back seeks to the closest indexed position instead of reading from the start
+        let (rows, mut stats) = r.get_rows(1234, 1).unwrap();
+        assert_eq!(rows, vec![Row::new(1235, vec!["A1235", "B1235"])]);
+        stats.pos_table_elapsed.take();
+        let expected = GetRowsStats {
+            num_seek: 1,
+            num_parsed_record: 8,
+            pos_table_elapsed: None,
+            pos_table_entry: 115,
+        };
+        assert_eq!(stats, expected);
+
+        // Only the part of the index around the wanted records is used
+        let (pos_table, len) = r.get_pos_table_range(1234, 1235);
+        assert_eq!(len, 115);
+        assert_eq!(pos_table.len(), 2);
+        assert!(r.config.position_to_record_index(pos_table[0].record()) <= 1234);
+        assert!(r.config.position_to_record_index(pos_table[1].record()) > 1235);
+        let (pos_table, _) = r.get_pos_table_range(0, 1);
+        assert_eq!(pos_table.len(), 1);
+    }
+
     #[rstest]
     #[case(false)]
     #[case(true)]
@@ -1111,4 +2028,13 @@ mod tests {
         // Finally wait till internal thread is done
         reader.wait_internal();
     }
+
+    #[test]
+    fn test_row_id() {
+        let row = Row::new(3, vec!["a"]);
+        assert_eq!(row.id(), RowId::from_index(2));
+        assert_eq!(row.id().record_num(), 3);
+        assert_eq!(row.id().to_string(), "3");
+        assert!(RowId::from_index(1) < row.id());
+    }
 }
@@ -0,0 +1,124 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+/// Layouts of dates that arrow doesn't read as dates, tried in order when sorting. Month first
+/// comes before day first, so that `03/12/2023` is read as March 12 unless another value like
+/// `13/12/2023` rules it out.
+pub const DATE_FORMATS: [&str; 22] = [
+    "%m/%d/%Y",
+    "%d/%m/%Y",
+    "%Y/%m/%d",
+    "%d.%m.%Y",
+    "%m-%d-%Y",
+    "%d-%m-%Y",
+    "%b %d %Y",
+    "%b %d, %Y",
+    "%d %b %Y",
+    "%B %d %Y",
+    "%B %d, %Y",
+    "%d %B %Y",
+    "%d-%b-%Y",
+    "%Y-%m-%d",
+    "%m/%d/%Y %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%d/%m/%Y %H:%M",
+    "%d/%m/%Y %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%a %b %d %H:%M:%S %Y",
+];
+
+/// Parse a date, with or without a time, written with a strftime pattern. Dates without a time
+/// are at midnight.
+pub fn parse_date(value: &str, format: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, format)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, format)
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })
+}
+
+/// Parse `<column>=<strftime pattern>` specifications into patterns by column name
+pub fn parse_date_formats(
+    specs: &[String],
+    headers: &[String],
+) -> CsvlensResult<HashMap<String, String>> {
+    let mut formats = HashMap::new();
+    for spec in specs {
+        let Some((column_name, pattern)) = spec.split_once('=') else {
+            return Err(CsvlensError::InvalidDateFormat(spec.clone()));
+        };
+        if pattern.is_empty() || StrftimeItems::new(pattern).any(|i| i == Item::Error) {
+            return Err(CsvlensError::InvalidDateFormat(spec.clone()));
+        }
+        if !headers.iter().any(|h| h == column_name) {
+            return Err(CsvlensError::ColumnNameNotFound(column_name.to_string()));
+        }
+        formats.insert(column_name.to_string(), pattern.to_string());
+    }
+    Ok(formats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date() {
+        let at = |y, m, d, h| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+        assert_eq!(
+            parse_date("12/03/2023", "%m/%d/%Y"),
+            Some(at(2023, 12, 3, 0))
+        );
+        assert_eq!(
+            parse_date("12/03/2023", "%d/%m/%Y"),
+            Some(at(2023, 3, 12, 0))
+        );
+        assert_eq!(
+            parse_date(" Jan 5 2021", "%b %d %Y"),
+            Some(at(2021, 1, 5, 0))
+        );
+        assert_eq!(
+            parse_date("5 January 2021", "%d %B %Y"),
+            Some(at(2021, 1, 5, 0))
+        );
+        assert_eq!(
+            parse_date("12/03/2023 14:00", "%m/%d/%Y %H:%M"),
+            Some(at(2023, 12, 3, 14))
+        );
+        assert_eq!(parse_date("13/03/2023", "%m/%d/%Y"), None);
+        assert_eq!(parse_date("12/03/2023 14:00", "%m/%d/%Y"), None);
+        assert_eq!(parse_date("20230105", "%m/%d/%Y"), None);
+    }
+
+    #[test]
+    fn test_parse_date_formats() {
+        let headers = vec!["id".to_string(), "due".to_string()];
+        let formats = parse_date_formats(&["due=%d/%m/%Y".to_string()], &headers).unwrap();
+        assert_eq!(formats.get("due").map(|f| f.as_str()), Some("%d/%m/%Y"));
+        assert!(matches!(
+            parse_date_formats(&["due".to_string()], &headers),
+            Err(CsvlensError::InvalidDateFormat(_))
+        ));
+        assert!(matches!(
+            parse_date_formats(&["due=%Q".to_string()], &headers),
+            Err(CsvlensError::InvalidDateFormat(_))
+        ));
+        assert!(matches!(
+            parse_date_formats(&["nope=%Y".to_string()], &headers),
+            Err(CsvlensError::ColumnNameNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Decoder applied to the values of a column before they are displayed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decoder {
+    Base64,
+    Hex,
+    Url,
+}
+
+impl Decoder {
+    pub fn from_name(name: &str) -> CsvlensResult<Decoder> {
+        match name.to_lowercase().as_str() {
+            "base64" => Ok(Decoder::Base64),
+            "hex" => Ok(Decoder::Hex),
+            "url" => Ok(Decoder::Url),
+            _ => Err(CsvlensError::InvalidDecoder(name.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Decoder::Base64 => "base64",
+            Decoder::Hex => "hex",
+            Decoder::Url => "url",
+        }
+    }
+
+    /// The next decoder when cycling through them interactively, None meaning no decoding
+    pub fn cycle(decoder: Option<Decoder>) -> Option<Decoder> {
+        match decoder {
+            None => Some(Decoder::Base64),
+            Some(Decoder::Base64) => Some(Decoder::Hex),
+            Some(Decoder::Hex) => Some(Decoder::Url),
+            Some(Decoder::Url) => None,
+        }
+    }
+
+    /// Decode a value for display. Values that fail to decode are shown as is.
+    pub fn decode(&self, value: &str) -> String {
+        let trimmed = value.trim();
+        let bytes = match self {
+            Decoder::Base64 => STANDARD
+                .decode(trimmed)
+                .or_else(|_| URL_SAFE_NO_PAD.decode(trimmed.trim_end_matches('=')))
+                .ok(),
+            Decoder::Hex => hex::decode(trimmed.trim_start_matches("0x")).ok(),
+            Decoder::Url => {
+                // '+' is a space in form encoded values
+                let value = value.replace('+', " ");
+                Some(percent_encoding::percent_decode_str(&value).collect())
+            }
+        };
+        match bytes {
+            Some(bytes) => bytes_to_display_string(bytes),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// Show bytes as text if they are valid UTF-8, otherwise escape them
+fn bytes_to_display_string(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            let mut out = String::new();
+            for b in e.as_bytes() {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    out.push(*b as char);
+                } else {
+                    let _ = write!(out, "\\x{b:02x}");
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Parse `<column>=<decoder>` specifications into decoders by column index
+pub fn parse_decoders(
+    specs: &[String],
+    headers: &[String],
+) -> CsvlensResult<HashMap<usize, Decoder>> {
+    let mut decoders = HashMap::new();
+    for spec in specs {
+        let Some((column_name, decoder_name)) = spec.rsplit_once('=') else {
+            return Err(CsvlensError::InvalidDecoder(spec.clone()));
+        };
+        let column_index = headers
+            .iter()
+            .position(|h| h == column_name)
+            .ok_or_else(|| CsvlensError::ColumnNameNotFound(column_name.to_string()))?;
+        decoders.insert(column_index, Decoder::from_name(decoder_name)?);
+    }
+    Ok(decoders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Decoder::Base64.decode("aGVsbG8gd29ybGQ="), "hello world");
+        assert_eq!(Decoder::Base64.decode("aGVsbG8gd29ybGQ"), "hello world");
+        assert_eq!(Decoder::Base64.decode("not base64!"), "not base64!");
+        assert_eq!(Decoder::Hex.decode("68656c6c6f"), "hello");
+        assert_eq!(Decoder::Hex.decode("0x00ff41"), "\\x00\\xffA");
+        assert_eq!(Decoder::Url.decode("a%20b+c%2Fd"), "a b c/d");
+    }
+
+    #[test]
+    fn test_parse_decoders() {
+        let headers = vec!["id".to_string(), "payload".to_string()];
+        let decoders = parse_decoders(&["payload=base64".to_string()], &headers).unwrap();
+        assert_eq!(decoders.get(&1), Some(&Decoder::Base64));
+        assert!(matches!(
+            parse_decoders(&["payload=rot13".to_string()], &headers),
+            Err(CsvlensError::InvalidDecoder(_))
+        ));
+        assert!(matches!(
+            parse_decoders(&["nope=hex".to_string()], &headers),
+            Err(CsvlensError::ColumnNameNotFound(_))
+        ));
+    }
+}
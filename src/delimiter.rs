@@ -1,9 +1,11 @@
 use crate::errors::{CsvlensError, CsvlensResult};
+use crate::io::open_input;
 
 /// Delimiter behaviour as specified in the command line
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum Delimiter {
     /// Use the default delimiter (auto detect)
+    #[default]
     Default,
 
     /// Comma delimiter
@@ -59,11 +61,23 @@ impl Delimiter {
     }
 }
 
+/// Parse a character of the option given in the command line, e.g. the quote character
+pub fn parse_char(option: &'static str, arg: &str) -> CsvlensResult<u8> {
+    let mut chars = arg.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii() => Ok(c as u8),
+        _ => Err(CsvlensError::InvalidCharacter(option, arg.to_string())),
+    }
+}
+
 /// Sniff the delimiter from the file
 pub fn sniff_delimiter(filename: &str) -> Option<u8> {
     let mut sniffer = csv_nose::Sniffer::new();
     sniffer.sample_size(csv_nose::SampleSize::Records(200));
-    if let Ok(metadata) = sniffer.sniff_path(filename) {
+    let Ok(file) = open_input(filename) else {
+        return None;
+    };
+    if let Ok(metadata) = sniffer.sniff_reader(file) {
         return Some(metadata.dialect.delimiter);
     }
     None
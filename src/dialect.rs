@@ -0,0 +1,167 @@
+use crate::io::open_input;
+
+/// Delimiters tried when the file could be read with more than one
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Number of records looked at to tell whether a delimiter fits the file
+const SAMPLE_RECORDS: usize = 50;
+
+/// Number of rows shown in the preview of a dialect
+const PREVIEW_ROWS: usize = 3;
+
+/// How the file is split into fields and rows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dialect {
+    pub delimiter: u8,
+    pub no_headers: bool,
+}
+
+impl Dialect {
+    pub fn description(&self) -> String {
+        let delimiter = match self.delimiter {
+            b',' => "Comma".to_string(),
+            b';' => "Semicolon".to_string(),
+            b'\t' => "Tab".to_string(),
+            b'|' => "Pipe".to_string(),
+            d => format!("'{}'", d as char),
+        };
+        let header = if self.no_headers {
+            "no header row"
+        } else {
+            "first row is the header"
+        };
+        format!("{delimiter} separated, {header}")
+    }
+
+    /// The first rows of the file read this way
+    pub fn preview(&self, filename: &str) -> Vec<Vec<String>> {
+        sample_records(filename, self.delimiter, PREVIEW_ROWS)
+    }
+}
+
+/// Ways to read the file if the guess can't be told for sure, the guess first. Empty if there is
+/// no doubt, e.g. when the delimiter was given and the header row detected.
+///
+/// A delimiter is doubtful if others split the first records into the same number of fields too.
+/// The header row is doubtful if it couldn't be told apart from the records.
+pub fn ambiguous_dialects(
+    filename: &str,
+    guess: Dialect,
+    delimiter_known: bool,
+    header_known: bool,
+) -> Vec<Dialect> {
+    let mut delimiters = vec![guess.delimiter];
+    if !delimiter_known {
+        delimiters.extend(
+            CANDIDATE_DELIMITERS
+                .into_iter()
+                .filter(|d| *d != guess.delimiter && fits(filename, *d)),
+        );
+    }
+    let headers = if header_known {
+        vec![guess.no_headers]
+    } else {
+        vec![guess.no_headers, !guess.no_headers]
+    };
+
+    let dialects: Vec<Dialect> = delimiters
+        .iter()
+        .flat_map(|delimiter| {
+            headers.iter().map(|no_headers| Dialect {
+                delimiter: *delimiter,
+                no_headers: *no_headers,
+            })
+        })
+        .collect();
+    if dialects.len() > 1 { dialects } else { vec![] }
+}
+
+/// Whether the delimiter splits each of the first records into the same number of fields, more
+/// than one
+fn fits(filename: &str, delimiter: u8) -> bool {
+    let records = sample_records(filename, delimiter, SAMPLE_RECORDS);
+    records.len() > 1 && records[0].len() > 1 && records.iter().all(|r| r.len() == records[0].len())
+}
+
+fn sample_records(filename: &str, delimiter: u8, n: usize) -> Vec<Vec<String>> {
+    let Ok(file) = open_input(filename) else {
+        return vec![];
+    };
+    ::csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(file)
+        .records()
+        .take(n)
+        .map_while(Result::ok)
+        .map(|r| r.iter().map(|s| s.to_string()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dialects(content: &str, delimiter_known: bool, header_known: bool) -> Vec<(char, bool)> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, content.as_bytes()).unwrap();
+        let guess = Dialect {
+            delimiter: b',',
+            no_headers: false,
+        };
+        ambiguous_dialects(
+            file.path().to_str().unwrap(),
+            guess,
+            delimiter_known,
+            header_known,
+        )
+        .into_iter()
+        .map(|d| (d.delimiter as char, d.no_headers))
+        .collect()
+    }
+
+    #[test]
+    fn test_ambiguous_delimiter() {
+        let content = "1;2,5;3\n4;5,5;6\n7;8,5;9\n";
+        assert_eq!(
+            dialects(content, false, true),
+            vec![(',', false), (';', false)]
+        );
+        assert_eq!(dialects(content, true, true), vec![]);
+    }
+
+    #[test]
+    fn test_ambiguous_header() {
+        let content = "a,b\nfoo,x\nlonger,yy\n";
+        assert_eq!(
+            dialects(content, false, false),
+            vec![(',', false), (',', true)]
+        );
+        assert_eq!(dialects(content, false, true), vec![]);
+    }
+
+    #[test]
+    fn test_unambiguous() {
+        assert_eq!(dialects("a,b;c\n1,2\n3,4\n", false, true), vec![]);
+        assert_eq!(dialects("a\n1\n", false, true), vec![]);
+        assert_eq!(dialects("", false, true), vec![]);
+    }
+
+    #[test]
+    fn test_description() {
+        let dialect = Dialect {
+            delimiter: b';',
+            no_headers: true,
+        };
+        assert_eq!(dialect.description(), "Semicolon separated, no header row");
+        let dialect = Dialect {
+            delimiter: b':',
+            no_headers: false,
+        };
+        assert_eq!(
+            dialect.description(),
+            "':' separated, first row is the header"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::binary;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::io::open_input;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use std::io::{BufWriter, Read, Write};
+use tempfile::NamedTempFile;
+
+/// Bytes at the start of the file the encoding is guessed from
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// Bytes decoded at a time when transcoding
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The encoding of the label given with --encoding, e.g. `latin1`, `cp1252`, `utf-16` or
+/// `shift_jis`, or None for `auto` to guess it from the file
+pub fn from_label(label: &str) -> CsvlensResult<Option<&'static Encoding>> {
+    if label.eq_ignore_ascii_case("auto") {
+        return Ok(None);
+    }
+    Encoding::for_label(label.as_bytes())
+        .map(Some)
+        .ok_or_else(|| CsvlensError::UnknownEncoding(label.to_string()))
+}
+
+/// Guess the encoding of text from its start: by its byte order mark if any, then UTF-16 told by
+/// its NUL bytes, UTF-8 if it is valid, and otherwise the legacy encoding the text looks most
+/// like, e.g. windows-1252 for Latin-1 or Shift_JIS
+pub fn detect(sample: &[u8], is_complete: bool) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return encoding;
+    }
+    // Each ASCII character in UTF-16 has a NUL byte, after it in little endian and before it in
+    // big endian
+    let nul_bytes = |start: usize| sample.iter().skip(start).step_by(2).filter(|b| **b == 0);
+    let (even, odd) = (nul_bytes(0).count(), nul_bytes(1).count());
+    let half = sample.len() / 2;
+    if half > 0 && even == 0 && odd * 3 >= half {
+        return UTF_16LE;
+    }
+    if half > 0 && odd == 0 && even * 3 >= half {
+        return UTF_16BE;
+    }
+    // A character may be cut off at the end of the sample
+    match std::str::from_utf8(sample) {
+        Ok(_) => return UTF_8,
+        Err(e) if e.error_len().is_none() && !is_complete => return UTF_8,
+        Err(_) => {}
+    }
+    // Binary files aren't text in any encoding, and are told apart as such when opened
+    if binary::binary_reason(sample).is_some() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(sample, is_complete);
+    detector.guess(None, true)
+}
+
+/// The encoding of the file, guessed from its start unless given
+pub fn file_encoding(
+    filename: &str,
+    encoding: Option<&'static Encoding>,
+) -> CsvlensResult<&'static Encoding> {
+    if let Some(encoding) = encoding {
+        return Ok(encoding);
+    }
+    let mut sample = vec![];
+    let n = open_input(filename)?
+        .take(SAMPLE_SIZE)
+        .read_to_end(&mut sample)?;
+    Ok(detect(&sample, (n as u64) < SAMPLE_SIZE))
+}
+
+/// Copy of the file in UTF-8, for reading text in another encoding. Characters that can't be
+/// decoded become U+FFFD, and a byte order mark is left out.
+pub fn to_utf8(filename: &str, encoding: &'static Encoding) -> CsvlensResult<NamedTempFile> {
+    let mut file = open_input(filename)?;
+    let out = NamedTempFile::new()?;
+    let mut writer = BufWriter::new(out.as_file());
+    let mut decoder = encoding.new_decoder();
+    let mut input = vec![0; CHUNK_SIZE];
+    let mut output = String::with_capacity(CHUNK_SIZE * 3);
+    loop {
+        let n = file.read(&mut input)?;
+        let last = n == 0;
+        let mut read = 0;
+        loop {
+            let (result, consumed, _) =
+                decoder.decode_to_string(&input[read..n], &mut output, last);
+            read += consumed;
+            writer.write_all(output.as_bytes())?;
+            output.clear();
+            if result == encoding_rs::CoderResult::InputEmpty {
+                break;
+            }
+        }
+        if last {
+            break;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_label() {
+        assert_eq!(from_label("auto").unwrap(), None);
+        assert_eq!(
+            from_label("latin1").unwrap(),
+            Some(encoding_rs::WINDOWS_1252)
+        );
+        assert_eq!(
+            from_label("Shift_JIS").unwrap(),
+            Some(encoding_rs::SHIFT_JIS)
+        );
+        assert!(matches!(
+            from_label("klingon"),
+            Err(CsvlensError::UnknownEncoding(label)) if label == "klingon"
+        ));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("city,pop\nZürich,421878\n".as_bytes(), true), UTF_8);
+        // Cut off in the middle of ü
+        assert_eq!(detect(&"Zürich".as_bytes()[..2], false), UTF_8);
+
+        let utf16le: Vec<u8> = "a,b\n1,2\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(detect(&utf16le, true), UTF_16LE);
+        let utf16be: Vec<u8> = "a,b\n1,2\n"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        assert_eq!(detect(&utf16be, true), UTF_16BE);
+        assert_eq!(detect(b"\xff\xfea\x00", true), UTF_16LE);
+
+        let latin1 = std::fs::read("tests/data/latin1.csv").unwrap();
+        assert_eq!(detect(&latin1, true), encoding_rs::WINDOWS_1252);
+        let shift_jis = std::fs::read("tests/data/shift_jis.csv").unwrap();
+        assert_eq!(detect(&shift_jis, true), encoding_rs::SHIFT_JIS);
+        assert_eq!(detect(b"%PDF-1.7\n\xe2\xe3\xcf\xd3\n\x01\x02", true), UTF_8);
+    }
+
+    #[test]
+    fn test_to_utf8() {
+        let file = to_utf8("tests/data/latin1.csv", encoding_rs::WINDOWS_1252).unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert!(content.contains("Málaga"));
+
+        let file = to_utf8("tests/data/utf16.csv", UTF_16LE).unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert!(content.starts_with("city,country\n"));
+    }
+}
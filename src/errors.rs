@@ -23,6 +23,12 @@ pub enum CsvlensError {
     #[error(transparent)]
     DelimiterParsing(#[from] std::char::TryFromCharError),
 
+    #[error("--{0} should be exactly one ASCII character, got '{1}'")]
+    InvalidCharacter(&'static str, String),
+
+    #[error("Unknown encoding: {0} (e.g. utf-8, utf-16, latin1, windows-1252 or shift_jis)")]
+    UnknownEncoding(String),
+
     #[error(transparent)]
     Csv(#[from] csv::Error),
 
@@ -35,6 +41,119 @@ pub enum CsvlensError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    #[error("Failed to parse {0}: {1}")]
+    ConfigParsing(String, String),
+
+    #[error("Invalid CSVLENS_OPTS: {0}")]
+    InvalidDefaultOptions(String),
+
+    #[error(
+        "Invalid theme: {0} (expected auto, dark, light, high-contrast, deuteranopia or protanopia)"
+    )]
+    InvalidTheme(String),
+
+    #[error(
+        "Invalid theme color: {0} (expected <role>=<color>, with role one of match, current-match, error, pattern, marked or selection)"
+    )]
+    InvalidThemeColor(String),
+
+    #[error("Invalid color rule: {0} (expected <column>:<regex>=<color>)")]
+    InvalidColorRule(String),
+
+    #[error("Invalid decoder: {0} (expected <column>=base64, hex or url)")]
+    InvalidDecoder(String),
+
+    #[error("Invalid date format: {0} (expected <column>=<strftime pattern>, e.g. due=%d/%m/%Y)")]
+    InvalidDateFormat(String),
+
+    #[error(
+        "Invalid display format: {0} (expected a template like {{:.2}}%, date:<strftime pattern>, bytes or duration:<s, ms, us or ns>)"
+    )]
+    InvalidDisplayFormat(String),
+
+    #[error("Invalid memory limit: {0} (expected e.g. 512M or 2G)")]
+    InvalidMemoryLimit(String),
+
+    #[error("Invalid range: {0}")]
+    InvalidRange(String),
+
+    #[error("Invalid --skip-footer: {0} (expected a number of rows or auto)")]
+    InvalidSkipFooter(String),
+
+    #[error("Can't append to {0}: its columns are {1}, not the ones exported")]
+    ExportColumnsMismatch(String, String),
+
+    #[error("More than {0} values to split by, e.g. a column of ids")]
+    TooManySplitFiles(usize),
+
+    #[error("The filter changed before all of its rows were found")]
+    FilterChanged,
+
+    #[error("Invalid regex: {0}")]
+    InvalidRegex(String),
+
+    #[error("{0} line {1}: {2}")]
+    InvalidScript(String, usize, String),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Invalid validation spec: {0}")]
+    InvalidValidationSpec(String),
+
+    #[error("{0} doesn't look like a CSV file: {1}")]
+    BinaryContent(String, String),
+
+    #[error("Failed to read the clipboard: {0}")]
+    Clipboard(String),
+
+    #[error("Failed to decompress {0} with {1}: {2}")]
+    Decompression(String, String, String),
+
+    #[error("Stopped reading the input before its end: {0}")]
+    InputStopped(String),
+
+    #[error("Invalid JSON object on line {0}: {1}")]
+    JsonLines(usize, String),
+
+    #[error("Failed to read {0}: {1}")]
+    Workbook(String, String),
+
+    #[error("No sheet named {0}, the sheets are: {1}")]
+    SheetNotFound(String, String),
+
+    #[error("--sheet is for Excel workbooks, {0} isn't one")]
+    NotAWorkbook(String),
+
+    #[error("Failed to query {0}: {1}")]
+    Database(String, String),
+
+    #[error("No table named {0}, the tables are: {1}")]
+    TableNotFound(String, String),
+
+    #[error("--table and --query are for SQLite databases, {0} isn't one")]
+    NotADatabase(String),
+
+    #[error("Failed to read Arrow file {0}: {1}")]
+    ArrowIpc(String, String),
+
+    #[error("Failed to download {0}: {1}")]
+    Http(String, String),
+
+    #[error("S3 error: {0}")]
+    S3(String),
+
+    #[error("GCS error: {0}")]
+    Gcs(String),
+
+    /// No record matched a query of --count-only or --exists. Holds what to print, if anything.
+    #[error("{0}")]
+    NoMatches(String),
+
+    /// Error of --count-only, --exists or --dump-state, which exit with another code than the view
+    #[error(transparent)]
+    Headless(Box<CsvlensError>),
+
     #[error("Draw error: {0}")]
     DrawError(String),
 }
@@ -0,0 +1,716 @@
+use crate::csv::CsvLensReader;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::find::FinderResults;
+use crate::sort::{SortOrder, Sorter};
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use ::csv::{QuoteStyle, ReaderBuilder, StringRecord, Terminator, Writer, WriterBuilder};
+use unicode_width::UnicodeWidthStr;
+
+/// Which rows the export command writes and how, regardless of how the file was read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportOptions {
+    pub delimiter: u8,
+    pub quoting: Quoting,
+    pub crlf: bool,
+    pub headers: bool,
+    /// Add the rows to the end of the file if there is one already
+    pub append: bool,
+    /// Write the rows the filter leaves out instead of the ones it shows
+    pub unmatched: bool,
+}
+
+/// Which fields are quoted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quoting {
+    /// Only those with a delimiter, quote or line break
+    Necessary,
+    All,
+    /// All but numbers
+    NonNumeric,
+    Never,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            delimiter: b',',
+            quoting: Quoting::Necessary,
+            crlf: false,
+            headers: true,
+            append: false,
+            unmatched: false,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Split the options given after the file name off the argument of the export command, e.g.
+    /// `out.csv delimiter=tab quote=all crlf no-headers`. Words are taken as options from the end
+    /// for as long as they are ones, so that file names can have spaces.
+    pub fn split_options(arg: &str) -> Result<(&str, ExportOptions), String> {
+        let mut options = ExportOptions::default();
+        let mut path = arg.trim_end();
+        let mut words = vec![];
+        while let Some((rest, last)) = path.rsplit_once(char::is_whitespace) {
+            if !is_option(last) {
+                break;
+            }
+            words.push(last);
+            path = rest.trim_end();
+        }
+        // Given in order, so that a later option wins
+        for option in words.iter().rev() {
+            options.apply(option)?;
+        }
+        Ok((path, options))
+    }
+
+    fn apply(&mut self, option: &str) -> Result<(), String> {
+        match option.split_once('=') {
+            Some(("delimiter", value)) => {
+                self.delimiter = match value {
+                    "tab" | "\\t" => b'\t',
+                    "space" => b' ',
+                    "comma" => b',',
+                    "semicolon" => b';',
+                    "pipe" => b'|',
+                    v if v.len() == 1 => v.as_bytes()[0],
+                    _ => return Err(format!("Invalid delimiter: {value}")),
+                }
+            }
+            Some(("quote", value)) => {
+                self.quoting = match value {
+                    "necessary" => Quoting::Necessary,
+                    "all" => Quoting::All,
+                    "non-numeric" => Quoting::NonNumeric,
+                    "never" => Quoting::Never,
+                    _ => return Err(format!("Invalid quoting: {value}")),
+                }
+            }
+            _ => match option {
+                "crlf" => self.crlf = true,
+                "lf" => self.crlf = false,
+                "headers" => self.headers = true,
+                "no-headers" => self.headers = false,
+                "append" => self.append = true,
+                "unmatched" => self.unmatched = true,
+                _ => return Err(format!("Unknown export option: {option}")),
+            },
+        }
+        Ok(())
+    }
+
+    /// A writer to the file, after the rows in it if appending, and whether to write the header
+    /// row first. Rows are only appended to a file with the same columns, told by its header row
+    /// if there is one or else by the number of fields.
+    pub fn writer(
+        &self,
+        path: &str,
+        headers: &[String],
+        has_headers: bool,
+    ) -> CsvlensResult<(Writer<File>, bool)> {
+        let with_headers = has_headers && self.headers;
+        let appending = self.append && std::fs::metadata(path).is_ok_and(|m| m.len() > 0);
+        if !appending {
+            return Ok((self.writer_builder().from_path(path)?, with_headers));
+        }
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .from_path(path)?;
+        let mut first = StringRecord::new();
+        reader.read_record(&mut first)?;
+        let same_columns = if with_headers {
+            first.iter().eq(headers.iter().map(String::as_str))
+        } else {
+            first.len() == headers.len()
+        };
+        if !same_columns {
+            return Err(CsvlensError::ExportColumnsMismatch(
+                path.to_string(),
+                first.iter().collect::<Vec<_>>().join(", "),
+            ));
+        }
+        let mut file = OpenOptions::new().read(true).append(true).open(path)?;
+        // The last row might not end with a line break
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(if self.crlf { b"\r\n" } else { b"\n" })?;
+        }
+        Ok((self.writer_builder().from_writer(file), false))
+    }
+
+    fn writer_builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .quote_style(match self.quoting {
+                Quoting::Necessary => QuoteStyle::Necessary,
+                Quoting::All => QuoteStyle::Always,
+                Quoting::NonNumeric => QuoteStyle::NonNumeric,
+                Quoting::Never => QuoteStyle::Never,
+            });
+        if self.crlf {
+            builder.terminator(Terminator::CRLF);
+        }
+        builder
+    }
+}
+
+/// Number of rows read from the file at a time when exporting
+pub const EXPORT_CHUNK_SIZE: usize = 10_000;
+
+/// Which rows of the view are exported, in the order shown. They are only all known once the
+/// file, and the filter if any, are read to the end, so they are taken a chunk at a time by the
+/// exporter rather than gathered beforehand.
+pub enum ExportRows {
+    /// All the rows, in the order of the sort if any
+    All(Option<(Arc<Sorter>, SortOrder)>),
+    /// The rows the filter shows
+    Found(FinderResults),
+    /// The rows the filter leaves out, in the order of the sort if any
+    Unmatched(FinderResults, Option<(Arc<Sorter>, SortOrder)>),
+}
+
+impl ExportRows {
+    /// Number of rows exported out of the records in the file, once the filter is done if any
+    pub fn total(&self, num_records: u64) -> CsvlensResult<Option<u64>> {
+        let finder = match self {
+            ExportRows::All(_) => return Ok(Some(num_records)),
+            ExportRows::Found(finder) | ExportRows::Unmatched(finder, _) => finder,
+        };
+        if finder.interrupted() {
+            return Err(CsvlensError::FilterChanged);
+        }
+        let count = finder.final_count().map(|count| count as u64);
+        Ok(match self {
+            ExportRows::Unmatched(..) => count.map(|count| num_records.saturating_sub(count)),
+            _ => count,
+        })
+    }
+
+    /// Indices of the rows exported from the position in the order of the view on, up to the
+    /// chunk size but possibly fewer. None past the end.
+    fn chunk(&self, position: u64, num_records: u64) -> Option<Vec<u64>> {
+        let in_order = |order: &Option<(Arc<Sorter>, SortOrder)>| {
+            let end = (position + EXPORT_CHUNK_SIZE as u64).min(num_records);
+            match order {
+                Some((sorter, order)) => sorter
+                    .get_sorted_indices(position, end - position, *order)
+                    .unwrap_or_default(),
+                None => (position..end).collect(),
+            }
+        };
+        match self {
+            ExportRows::All(order) => (position < num_records).then(|| in_order(order)),
+            ExportRows::Found(finder) => {
+                let indices = finder.get_subset_found(position as usize, EXPORT_CHUNK_SIZE);
+                (!indices.is_empty()).then_some(indices)
+            }
+            ExportRows::Unmatched(finder, order) => (position < num_records).then(|| {
+                let indices = in_order(order);
+                let found = finder.contains(&indices);
+                indices
+                    .into_iter()
+                    .zip(found)
+                    .filter(|(_, found)| !found)
+                    .map(|(index, _)| index)
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Where the exporter writes the rows, with the fields of the columns exported
+pub trait ExportWriter: Send {
+    fn write_row(&mut self, fields: &[String]) -> CsvlensResult<()>;
+
+    /// Write out what is left once all the rows are written, or the export is cancelled
+    fn finish(&mut self) -> CsvlensResult<()>;
+
+    /// Number of files written to so far, if more than the one named
+    fn num_files(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ExportWriter for Writer<File> {
+    fn write_row(&mut self, fields: &[String]) -> CsvlensResult<()> {
+        Ok(self.write_record(fields)?)
+    }
+
+    fn finish(&mut self) -> CsvlensResult<()> {
+        Ok(self.flush()?)
+    }
+}
+
+/// Writes the rows to one file per value of a column, named after the template with the value in
+/// place of {}
+pub struct SplitWriter {
+    column_index: usize,
+    template: String,
+    options: ExportOptions,
+    headers: Vec<String>,
+    has_headers: bool,
+    writers: HashMap<String, Writer<File>>,
+}
+
+impl SplitWriter {
+    pub fn new(
+        column_index: usize,
+        template: &str,
+        options: &ExportOptions,
+        headers: Vec<String>,
+        has_headers: bool,
+    ) -> Self {
+        SplitWriter {
+            column_index,
+            template: template.to_string(),
+            options: *options,
+            headers,
+            has_headers,
+            writers: HashMap::new(),
+        }
+    }
+}
+
+impl ExportWriter for SplitWriter {
+    fn write_row(&mut self, fields: &[String]) -> CsvlensResult<()> {
+        let path = split_path(&self.template, &fields[self.column_index]);
+        if !self.writers.contains_key(&path) {
+            if self.writers.len() == MAX_SPLIT_FILES {
+                return Err(CsvlensError::TooManySplitFiles(MAX_SPLIT_FILES));
+            }
+            let (mut writer, write_headers) =
+                self.options
+                    .writer(&path, &self.headers, self.has_headers)?;
+            if write_headers {
+                writer.write_record(&self.headers)?;
+            }
+            self.writers.insert(path.clone(), writer);
+        }
+        let writer = self.writers.get_mut(&path).unwrap();
+        Ok(writer.write_record(fields)?)
+    }
+
+    fn finish(&mut self) -> CsvlensResult<()> {
+        for writer in self.writers.values_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn num_files(&self) -> Option<usize> {
+        Some(self.writers.len())
+    }
+}
+
+/// The value as shown of a field in the column with the index
+pub type FormatValue = Box<dyn Fn(usize, &str) -> String + Send>;
+
+/// Writes the rows as a plain text table, with the values padded to line up in columns. The
+/// values are kept in a temporary file until the widths of the columns are known.
+pub struct TextTableWriter {
+    file: File,
+    spool: Writer<File>,
+    widths: Vec<usize>,
+    format: FormatValue,
+}
+
+impl TextTableWriter {
+    pub fn new(path: &str, headers: Option<&[String]>, format: FormatValue) -> CsvlensResult<Self> {
+        let mut writer = TextTableWriter {
+            file: File::create(path)?,
+            spool: WriterBuilder::new()
+                .flexible(true)
+                .from_writer(tempfile::tempfile()?),
+            widths: vec![],
+            format,
+        };
+        if let Some(headers) = headers {
+            writer.spool_line(headers)?;
+        }
+        Ok(writer)
+    }
+
+    fn spool_line(&mut self, values: &[String]) -> CsvlensResult<()> {
+        if self.widths.len() < values.len() {
+            self.widths.resize(values.len(), 0);
+        }
+        for (width, value) in self.widths.iter_mut().zip(values) {
+            *width = (*width).max(value.width());
+        }
+        Ok(self.spool.write_record(values)?)
+    }
+}
+
+impl ExportWriter for TextTableWriter {
+    fn write_row(&mut self, fields: &[String]) -> CsvlensResult<()> {
+        let values: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(column, field)| (self.format)(column, field))
+            .collect();
+        self.spool_line(&values)
+    }
+
+    fn finish(&mut self) -> CsvlensResult<()> {
+        self.spool.flush()?;
+        let mut spooled = self.spool.get_ref().try_clone()?;
+        spooled.seek(SeekFrom::Start(0))?;
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(spooled);
+        let mut writer = BufWriter::new(&self.file);
+        for record in reader.records() {
+            let padded: Vec<String> = record?
+                .iter()
+                .zip(&self.widths)
+                .map(|(value, width)| format!("{value}{}", " ".repeat(width - value.width())))
+                .collect();
+            writeln!(writer, "{}", padded.join("  ").trim_end())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes rows to a file on a background thread, a chunk at a time, so that exporting millions of
+/// rows neither freezes the interface nor holds them all in memory
+pub struct Exporter {
+    internal: Arc<Mutex<ExporterInternalState>>,
+}
+
+/// How far an export got
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportProgress {
+    pub path: String,
+    pub rows_written: usize,
+    /// Known once the file, and the filter if any, are read to the end
+    pub total: Option<usize>,
+    /// Number of files written to, when split by the values of a column
+    pub num_files: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportStatus {
+    Running,
+    Finished,
+    Cancelled,
+    Failed(String),
+}
+
+impl Exporter {
+    /// Write the rows, with only the given columns if any, after what the writer already wrote
+    pub fn new(
+        reader: CsvLensReader,
+        columns: Option<Vec<usize>>,
+        rows: ExportRows,
+        writer: Box<dyn ExportWriter>,
+        path: &str,
+    ) -> Self {
+        let internal = ExporterInternalState::init(reader, columns, rows, writer, path);
+        Exporter { internal }
+    }
+
+    pub fn progress(&self) -> ExportProgress {
+        self.internal.lock().unwrap().progress.clone()
+    }
+
+    pub fn status(&self) -> ExportStatus {
+        self.internal.lock().unwrap().status.clone()
+    }
+
+    /// Stop writing after the chunk being written, without waiting for it. The rows written so
+    /// far stay in the file.
+    pub fn cancel(&self) {
+        self.internal.lock().unwrap().should_terminate = true;
+    }
+
+    pub fn wait(&self) {
+        while self.status() == ExportStatus::Running {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Make writing every chunk take at least this long, to simulate a slow export
+    #[cfg(test)]
+    pub fn set_delay(&self, delay: Duration) {
+        self.internal.lock().unwrap().delay = delay;
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+struct ExporterInternalState {
+    progress: ExportProgress,
+    status: ExportStatus,
+    should_terminate: bool,
+    #[cfg(test)]
+    delay: Duration,
+}
+
+impl ExporterInternalState {
+    fn init(
+        reader: CsvLensReader,
+        columns: Option<Vec<usize>>,
+        rows: ExportRows,
+        writer: Box<dyn ExportWriter>,
+        path: &str,
+    ) -> Arc<Mutex<ExporterInternalState>> {
+        let m_state = Arc::new(Mutex::new(ExporterInternalState {
+            progress: ExportProgress {
+                path: path.to_string(),
+                rows_written: 0,
+                total: None,
+                num_files: None,
+            },
+            status: ExportStatus::Running,
+            should_terminate: false,
+            #[cfg(test)]
+            delay: Duration::ZERO,
+        }));
+
+        let _m = m_state.clone();
+        thread::spawn(move || {
+            let status = Self::run(&_m, reader, columns, rows, writer)
+                .unwrap_or_else(|e| ExportStatus::Failed(e.to_string()));
+            _m.lock().unwrap().status = status;
+        });
+
+        m_state
+    }
+
+    fn run(
+        m: &Mutex<ExporterInternalState>,
+        mut reader: CsvLensReader,
+        columns: Option<Vec<usize>>,
+        rows: ExportRows,
+        mut writer: Box<dyn ExportWriter>,
+    ) -> CsvlensResult<ExportStatus> {
+        // The rows to write are known once the file and the filter are read to the end
+        let num_records = loop {
+            if m.lock().unwrap().should_terminate {
+                return Ok(ExportStatus::Cancelled);
+            }
+            if let Some(num_records) = reader.get_total_line_numbers() {
+                let num_records = num_records as u64;
+                if let Some(total) = rows.total(num_records)? {
+                    m.lock().unwrap().progress.total = Some(total as usize);
+                    break num_records;
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        let mut position = 0;
+        while let Some(indices) = rows.chunk(position, num_records) {
+            #[cfg(test)]
+            {
+                let delay = m.lock().unwrap().delay;
+                thread::sleep(delay);
+            }
+            if m.lock().unwrap().should_terminate {
+                writer.finish()?;
+                return Ok(ExportStatus::Cancelled);
+            }
+            let (chunk_rows, _) = reader.get_rows_for_indices(&indices)?;
+            for row in chunk_rows {
+                match &columns {
+                    Some(columns) => writer.write_row(&row.subset(columns).fields)?,
+                    None => writer.write_row(&row.fields)?,
+                }
+            }
+            let mut m_guard = m.lock().unwrap();
+            m_guard.progress.rows_written += indices.len();
+            m_guard.progress.num_files = writer.num_files();
+            drop(m_guard);
+            position += EXPORT_CHUNK_SIZE as u64;
+        }
+        writer.finish()?;
+        Ok(ExportStatus::Finished)
+    }
+}
+
+/// Most files written by one split, to not run out of file handles on a column of ids
+pub const MAX_SPLIT_FILES: usize = 1000;
+
+/// The file rows with the value go to when splitting, the value in place of `{}` in the template.
+/// Characters that can't be in a file name are replaced, and an empty value is `_`.
+pub fn split_path(template: &str, value: &str) -> String {
+    let name: String = value
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = match name.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => name,
+    };
+    template.replace("{}", &name)
+}
+
+fn is_option(word: &str) -> bool {
+    word.starts_with("delimiter=")
+        || word.starts_with("quote=")
+        || matches!(
+            word,
+            "crlf" | "lf" | "headers" | "no-headers" | "append" | "unmatched"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_options() {
+        assert_eq!(
+            ExportOptions::split_options("out.csv").unwrap(),
+            ("out.csv", ExportOptions::default())
+        );
+        let (path, options) =
+            ExportOptions::split_options("my out.csv delimiter=; quote=all crlf no-headers")
+                .unwrap();
+        assert_eq!(path, "my out.csv");
+        assert_eq!(
+            options,
+            ExportOptions {
+                delimiter: b';',
+                quoting: Quoting::All,
+                crlf: true,
+                headers: false,
+                append: false,
+                unmatched: false,
+            }
+        );
+        let (_, options) = ExportOptions::split_options("out.tsv delimiter=tab append").unwrap();
+        assert_eq!(options.delimiter, b'\t');
+        assert!(options.append);
+        assert_eq!(
+            ExportOptions::split_options("out.csv quote=some"),
+            Err("Invalid quoting: some".to_string())
+        );
+        assert_eq!(
+            ExportOptions::split_options("out.csv delimiter=ab"),
+            Err("Invalid delimiter: ab".to_string())
+        );
+    }
+
+    fn exporter(path: &str, num_rows: usize) -> Exporter {
+        let input = format!("{path}.in");
+        let content: String = std::iter::once("a,b\n".to_string())
+            .chain((0..num_rows).map(|i| format!("{i},{}\n", i * 2)))
+            .collect();
+        std::fs::write(&input, content).unwrap();
+        let config = Arc::new(crate::csv::CsvConfig::new(
+            &input,
+            None,
+            crate::csv::CsvBaseConfig::new(b',', false),
+        ));
+        let reader = CsvLensReader::new(config).unwrap();
+        reader.wait_internal();
+        let writer = ExportOptions::default()
+            .writer_builder()
+            .from_path(path)
+            .unwrap();
+        Exporter::new(
+            reader,
+            Some(vec![1]),
+            ExportRows::All(None),
+            Box::new(writer),
+            path,
+        )
+    }
+
+    #[test]
+    fn test_exporter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        let path = path.to_str().unwrap();
+        let exporter = exporter(path, 3);
+        exporter.wait();
+        assert_eq!(exporter.status(), ExportStatus::Finished);
+        assert_eq!(
+            exporter.progress(),
+            ExportProgress {
+                path: path.to_string(),
+                rows_written: 3,
+                total: Some(3),
+                num_files: None,
+            }
+        );
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "0\n2\n4\n");
+    }
+
+    #[test]
+    fn test_exporter_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        let path = path.to_str().unwrap();
+        let exporter = exporter(path, EXPORT_CHUNK_SIZE * 3);
+        exporter.set_delay(Duration::from_millis(100));
+        exporter.cancel();
+        exporter.wait();
+        assert_eq!(exporter.status(), ExportStatus::Cancelled);
+        let rows_written = exporter.progress().rows_written;
+        assert!(rows_written < EXPORT_CHUNK_SIZE * 3);
+        // What was written before stays
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap().lines().count(),
+            rows_written
+        );
+    }
+
+    #[test]
+    fn test_export_rows() {
+        let row_ids = [
+            crate::csv::RowId::from_index(1),
+            crate::csv::RowId::from_index(3),
+        ];
+        let finder = crate::find::Finder::new_for_tag("t", &row_ids, None, SortOrder::Ascending);
+
+        let found = ExportRows::Found(finder.results());
+        assert_eq!(found.total(5).unwrap(), Some(2));
+        assert_eq!(found.chunk(0, 5), Some(vec![1, 3]));
+        assert_eq!(found.chunk(EXPORT_CHUNK_SIZE as u64, 5), None);
+
+        let unmatched = ExportRows::Unmatched(finder.results(), None);
+        assert_eq!(unmatched.total(5).unwrap(), Some(3));
+        assert_eq!(unmatched.chunk(0, 5), Some(vec![0, 2, 4]));
+        assert_eq!(unmatched.chunk(EXPORT_CHUNK_SIZE as u64, 5), None);
+
+        let all = ExportRows::All(None);
+        assert_eq!(all.total(5).unwrap(), Some(5));
+        assert_eq!(all.chunk(0, 5), Some(vec![0, 1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(split_path("out_{}.csv", "North"), "out_North.csv");
+        assert_eq!(split_path("out_{}.csv", "a/b: c"), "out_a_b_ c.csv");
+        assert_eq!(split_path("out_{}.csv", " "), "out__.csv");
+        assert_eq!(split_path("out_{}.csv", ".."), "out__.csv");
+    }
+}
@@ -0,0 +1,427 @@
+use crate::csv::CsvConfig;
+
+use std::cmp::Ordering;
+
+use ::csv::StringRecord;
+
+/// Functions an expression can call, each on one value
+const FUNCTIONS: [&str; 5] = ["len", "abs", "lower", "upper", "trim"];
+
+/// A simple expression over the columns of a row, e.g. `len(name)` or `price * qty`, to sort by
+/// something that isn't in the file as is. Columns are referred to by name, in backquotes if the
+/// name isn't a plain word.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    text: String,
+    node: Node,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Column(usize),
+    Number(f64),
+    Text(String),
+    Negate(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(&'static str, Box<Node>),
+}
+
+/// The value of an expression for a row. Arithmetic on anything but numbers, or dividing by
+/// zero, is empty.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnknownColumn(String),
+    Invalid(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Column(String),
+    Number(f64),
+    Text(String),
+    Symbol(char),
+}
+
+impl Expression {
+    pub fn parse(text: &str, headers: &[String]) -> Result<Expression, ParseError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            headers,
+        };
+        let node = parser.sum()?;
+        if let Some(token) = tokens.get(parser.position) {
+            return Err(ParseError::Invalid(format!(
+                "unexpected {}",
+                describe(token)
+            )));
+        }
+        Ok(Expression {
+            text: text.trim().to_string(),
+            node,
+        })
+    }
+
+    /// The value of a column as is
+    pub fn column(index: usize, name: &str) -> Expression {
+        Expression {
+            text: name.to_string(),
+            node: Node::Column(index),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The value for a row, with numbers in text read as numbers
+    pub fn eval(&self, record: &StringRecord, config: &CsvConfig) -> Value {
+        match eval(&self.node, record, config) {
+            Value::Text(text) if text.trim().is_empty() => Value::Empty,
+            Value::Text(text) => match config.parse_number(&text) {
+                Some(n) => Value::Number(n),
+                None => Value::Text(text),
+            },
+            value => value,
+        }
+    }
+}
+
+impl Value {
+    /// Order of values to sort by: empty values first, then numbers, then text compared with
+    /// the given comparison
+    pub fn cmp_by(&self, other: &Value, cmp: fn(&str, &str) -> Ordering) -> Ordering {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+            (Value::Text(a), Value::Text(b)) => cmp(a, b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Empty => 0,
+            Value::Number(_) => 1,
+            Value::Text(_) => 2,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Value::Empty
+    }
+
+    fn as_number(&self, config: &CsvConfig) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Text(text) => config.parse_number(text),
+            Value::Empty => None,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Value::Number(n) => n.to_string(),
+            Value::Text(text) => text.clone(),
+            Value::Empty => String::new(),
+        }
+    }
+}
+
+fn eval(node: &Node, record: &StringRecord, config: &CsvConfig) -> Value {
+    match node {
+        Node::Column(index) => {
+            let value = config.field(record, *index);
+            if value.trim().is_empty() {
+                Value::Empty
+            } else {
+                Value::Text(value.to_string())
+            }
+        }
+        Node::Number(n) => Value::Number(*n),
+        Node::Text(text) => Value::Text(text.clone()),
+        Node::Negate(node) => match eval(node, record, config).as_number(config) {
+            Some(n) => Value::Number(-n),
+            None => Value::Empty,
+        },
+        Node::Binary(op, left, right) => {
+            let left = eval(left, record, config).as_number(config);
+            let right = eval(right, record, config).as_number(config);
+            let (Some(a), Some(b)) = (left, right) else {
+                return Value::Empty;
+            };
+            let result = match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' if b != 0.0 => a / b,
+                '%' if b != 0.0 => a % b,
+                _ => return Value::Empty,
+            };
+            Value::Number(result)
+        }
+        Node::Call(function, node) => {
+            let value = eval(node, record, config);
+            match *function {
+                "len" => Value::Number(value.to_text().chars().count() as f64),
+                "abs" => match value.as_number(config) {
+                    Some(n) => Value::Number(n.abs()),
+                    None => Value::Empty,
+                },
+                "lower" => Value::Text(value.to_text().to_lowercase()),
+                "upper" => Value::Text(value.to_text().to_uppercase()),
+                _ => Value::Text(value.to_text().trim().to_string()),
+            }
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '+' | '-' | '*' | '/' | '%' | '(' | ')' => tokens.push(Token::Symbol(c)),
+            '`' | '\'' | '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => quoted.push(next),
+                        None => return Err(ParseError::Invalid(format!("missing closing {c}"))),
+                    }
+                }
+                tokens.push(if c == '`' {
+                    Token::Column(quoted)
+                } else {
+                    Token::Text(quoted)
+                });
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(next) = chars.next_if(|n| n.is_ascii_digit() || *n == '.') {
+                    number.push(next);
+                }
+                let n = number
+                    .parse()
+                    .map_err(|_| ParseError::Invalid(format!("invalid number {number}")))?;
+                tokens.push(Token::Number(n));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.next_if(|n| n.is_alphanumeric() || *n == '_') {
+                    word.push(next);
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => return Err(ParseError::Invalid(format!("unexpected {c}"))),
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => word.clone(),
+        Token::Column(name) => format!("`{name}`"),
+        Token::Number(n) => n.to_string(),
+        Token::Text(text) => format!("'{text}'"),
+        Token::Symbol(c) => c.to_string(),
+    }
+}
+
+/// Recursive descent over the tokens, with the usual precedence of arithmetic
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    headers: &'a [String],
+}
+
+impl Parser<'_> {
+    fn next_symbol_in(&mut self, symbols: &[char]) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(c)) if symbols.contains(c) => {
+                self.position += 1;
+                Some(*c)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ParseError> {
+        self.next_symbol_in(&[symbol])
+            .map(|_| ())
+            .ok_or_else(|| ParseError::Invalid(format!("expected {symbol}")))
+    }
+
+    fn sum(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.product()?;
+        while let Some(op) = self.next_symbol_in(&['+', '-']) {
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+        Ok(node)
+    }
+
+    fn product(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.unary()?;
+        while let Some(op) = self.next_symbol_in(&['*', '/', '%']) {
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        if self.next_symbol_in(&['-']).is_some() {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        let Some(token) = self.tokens.get(self.position) else {
+            return Err(ParseError::Invalid("unexpected end".to_string()));
+        };
+        self.position += 1;
+        match token {
+            Token::Number(n) => Ok(Node::Number(*n)),
+            Token::Text(text) => Ok(Node::Text(text.clone())),
+            Token::Column(name) => self.column(name),
+            Token::Symbol('(') => {
+                let node = self.sum()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Token::Word(word) if self.tokens.get(self.position) == Some(&Token::Symbol('(')) => {
+                let Some(function) = FUNCTIONS.iter().find(|f| **f == word.as_str()) else {
+                    return Err(ParseError::Invalid(format!("unknown function {word}")));
+                };
+                self.position += 1;
+                let node = self.sum()?;
+                self.expect(')')?;
+                Ok(Node::Call(function, Box::new(node)))
+            }
+            Token::Word(word) => self.column(word),
+            token => Err(ParseError::Invalid(format!(
+                "unexpected {}",
+                describe(token)
+            ))),
+        }
+    }
+
+    fn column(&self, name: &str) -> Result<Node, ParseError> {
+        self.headers
+            .iter()
+            .position(|h| h == name)
+            .map(Node::Column)
+            .ok_or_else(|| ParseError::UnknownColumn(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+
+    fn eval_all(text: &str) -> Result<Vec<Value>, ParseError> {
+        let headers: Vec<String> = ["name", "price", "qty", "unit price"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let expression = Expression::parse(text, &headers)?;
+        let config = CsvConfig::new("", None, CsvBaseConfig::new(b',', false));
+        let records = [
+            vec!["Lamp", "12.5", "2", "6.25"],
+            vec!["Armchair", "", "1", "x"],
+        ];
+        Ok(records
+            .iter()
+            .map(|r| expression.eval(&StringRecord::from(r.clone()), &config))
+            .collect())
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(
+            eval_all("len(name)").unwrap(),
+            vec![Value::Number(4.0), Value::Number(8.0)]
+        );
+        assert_eq!(
+            eval_all("price * qty").unwrap(),
+            vec![Value::Number(25.0), Value::Empty]
+        );
+        assert_eq!(
+            eval_all("-(qty + 1) * 2 - qty / 2").unwrap(),
+            vec![Value::Number(-7.0), Value::Number(-4.5)]
+        );
+        assert_eq!(
+            eval_all("`unit price`").unwrap(),
+            vec![Value::Number(6.25), Value::Text("x".to_string())]
+        );
+        assert_eq!(
+            eval_all("upper(name)").unwrap(),
+            vec![
+                Value::Text("LAMP".to_string()),
+                Value::Text("ARMCHAIR".to_string())
+            ]
+        );
+        assert_eq!(
+            eval_all("qty / (qty - qty)").unwrap(),
+            vec![Value::Empty, Value::Empty]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            eval_all("len(title)"),
+            Err(ParseError::UnknownColumn("title".to_string()))
+        );
+        assert_eq!(
+            eval_all("size(name)"),
+            Err(ParseError::Invalid("unknown function size".to_string()))
+        );
+        assert_eq!(
+            eval_all("price *"),
+            Err(ParseError::Invalid("unexpected end".to_string()))
+        );
+        assert_eq!(
+            eval_all("len(name"),
+            Err(ParseError::Invalid("expected )".to_string()))
+        );
+        assert_eq!(
+            eval_all("price qty"),
+            Err(ParseError::Invalid("unexpected qty".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_cmp() {
+        let mut values = vec![
+            Value::Text("b".to_string()),
+            Value::Number(2.0),
+            Value::Empty,
+            Value::Text("a".to_string()),
+            Value::Number(-1.0),
+        ];
+        values.sort_by(|a, b| a.cmp_by(b, str::cmp));
+        assert_eq!(
+            values,
+            vec![
+                Value::Empty,
+                Value::Number(-1.0),
+                Value::Number(2.0),
+                Value::Text("a".to_string()),
+                Value::Text("b".to_string()),
+            ]
+        );
+    }
+}
@@ -97,7 +97,9 @@ pub struct Finder {
     internal: Arc<Mutex<FinderInternalState>>,
     pub cursor: Option<FinderCursor>,
     row_hint: usize,
-    target: Regex,
+    target: Option<Regex>,
+    inverted: bool,
+    tag: Option<String>,
     column_index: Option<usize>,
     starting_row_index: usize,
     sorter: Option<Arc<sort::Sorter>>,
@@ -213,9 +215,13 @@ impl PartialEq for FoundRow {
 impl Eq for FoundRow {}
 
 impl Finder {
+    /// Finder of the rows matching the target, or if inverted, of those where nothing matches it.
+    /// Only the column is searched if given.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: Arc<csv::CsvConfig>,
         target: Regex,
+        inverted: bool,
         column_index: Option<usize>,
         starting_row_index: usize,
         sorter: Option<Arc<sort::Sorter>>,
@@ -225,6 +231,7 @@ impl Finder {
         let internal = FinderInternalState::init(
             config,
             target.clone(),
+            inverted,
             column_index,
             starting_row_index,
             sorter.clone(),
@@ -235,7 +242,9 @@ impl Finder {
             internal,
             cursor: None,
             row_hint: starting_row_index,
-            target,
+            target: Some(target),
+            inverted,
+            tag: None,
             column_index,
             starting_row_index,
             sorter: sorter.clone(),
@@ -244,6 +253,28 @@ impl Finder {
         Ok(finder)
     }
 
+    /// Finder of the rows with a tag, given in file order
+    pub fn new_for_tag(
+        tag: &str,
+        row_ids: &[csv::RowId],
+        sorter: Option<Arc<sort::Sorter>>,
+        sort_order: SortOrder,
+    ) -> Self {
+        let internal = FinderInternalState::init_with_rows(row_ids, &sorter, sort_order);
+        Finder {
+            internal,
+            cursor: None,
+            row_hint: 0,
+            target: None,
+            inverted: false,
+            tag: Some(tag.to_string()),
+            column_index: None,
+            starting_row_index: 0,
+            sorter,
+            sort_order,
+        }
+    }
+
     pub fn count(&self) -> usize {
         (self.internal.lock().unwrap()).count
     }
@@ -281,10 +312,20 @@ impl Finder {
         }
     }
 
-    pub fn target(&self) -> Regex {
+    /// Regex to match cells with, or None if finding rows by tag
+    pub fn target(&self) -> Option<Regex> {
         self.target.clone()
     }
 
+    /// Whether the rows found are those not matching the target
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
     pub fn column_index(&self) -> Option<usize> {
         self.column_index
     }
@@ -297,6 +338,21 @@ impl Finder {
         &self.sorter
     }
 
+    /// Whether the given record has been found so far
+    pub fn contains_row(&self, row_id: csv::RowId) -> bool {
+        self.results().contains(&[row_id.index() as u64])[0]
+    }
+
+    /// The rows found, to be read from another thread while the finder goes on, or after it is
+    /// dropped
+    pub fn results(&self) -> FinderResults {
+        FinderResults {
+            internal: self.internal.clone(),
+            sorter: self.sorter.clone(),
+            sort_order: self.sort_order,
+        }
+    }
+
     pub fn reset_cursor(&mut self) {
         self.cursor = None;
     }
@@ -413,6 +469,19 @@ impl Finder {
         m_guard.elapsed()
     }
 
+    /// Approximate number of bytes used by the matches found so far
+    pub fn memory_usage(&self) -> usize {
+        let m_guard = self.internal.lock().unwrap();
+        m_guard
+            .founds
+            .iter()
+            .map(|f| {
+                std::mem::size_of::<FoundRow>()
+                    + f.column_indices.capacity() * std::mem::size_of::<usize>()
+            })
+            .sum()
+    }
+
     pub fn get_subset_found(&self, offset: usize, num_rows: usize) -> Vec<u64> {
         let m_guard = self.internal.lock().unwrap();
         let founds = &m_guard.founds;
@@ -437,6 +506,65 @@ impl Finder {
     }
 }
 
+/// Rows found by a finder, shared with it
+pub struct FinderResults {
+    internal: Arc<Mutex<FinderInternalState>>,
+    sorter: Option<Arc<sort::Sorter>>,
+    sort_order: SortOrder,
+}
+
+impl FinderResults {
+    /// Number of rows found, once the finder read the file to the end
+    pub fn final_count(&self) -> Option<usize> {
+        let m_guard = self.internal.lock().unwrap();
+        m_guard.done.then_some(m_guard.count)
+    }
+
+    /// Whether the finder was stopped before reading the file to the end, e.g. by filtering by
+    /// something else, so that only some of the rows were found
+    pub fn interrupted(&self) -> bool {
+        self.internal.lock().unwrap().interrupted
+    }
+
+    /// Indices of the rows found, in the order found
+    pub fn get_subset_found(&self, offset: usize, num_rows: usize) -> Vec<u64> {
+        let m_guard = self.internal.lock().unwrap();
+        let founds = &m_guard.founds;
+        let start = min(offset, founds.len());
+        let end = min(start.saturating_add(num_rows), founds.len());
+        founds[start..end]
+            .iter()
+            .map(|x| x.row_index() as u64)
+            .collect()
+    }
+
+    /// Whether each of the records has been found so far
+    pub fn contains(&self, row_indices: &[u64]) -> Vec<bool> {
+        let row_orders: Vec<usize> = row_indices
+            .iter()
+            .map(|&row_index| match &self.sorter {
+                Some(s) => s
+                    .get_record_order(row_index, self.sort_order)
+                    .unwrap_or(u64::MAX) as usize,
+                _ => row_index as usize,
+            })
+            .collect();
+        let m_guard = self.internal.lock().unwrap();
+        let founds = &m_guard.founds;
+        row_indices
+            .iter()
+            .zip(row_orders)
+            .map(|(&row_index, row_order)| {
+                let start = founds.partition_point(|r| r.row_order() < row_order);
+                founds[start..]
+                    .iter()
+                    .take_while(|r| r.row_order() == row_order)
+                    .any(|r| r.row_index() == row_index as usize)
+            })
+            .collect()
+    }
+}
+
 impl Drop for Finder {
     fn drop(&mut self) {
         self.terminate();
@@ -449,6 +577,8 @@ struct FinderInternalState {
     founds: SortedVec<FoundRow>,
     founds_index_after_starting_row: Option<usize>,
     done: bool,
+    /// Whether it stopped before reading the file to the end, so that the rows found are only some
+    interrupted: bool,
     should_terminate: bool,
     start: Instant,
     first_match_elapsed: Option<Duration>,
@@ -456,9 +586,11 @@ struct FinderInternalState {
 }
 
 impl FinderInternalState {
+    #[allow(clippy::too_many_arguments)]
     pub fn init(
         config: Arc<csv::CsvConfig>,
         target: Regex,
+        inverted: bool,
         target_local_column_index: Option<usize>,
         starting_row_index: usize,
         sorter: Option<Arc<sort::Sorter>>,
@@ -471,6 +603,7 @@ impl FinderInternalState {
             founds: SortedVec::new(),
             founds_index_after_starting_row: None,
             done: false,
+            interrupted: false,
             should_terminate: false,
             start: Instant::now(),
             first_match_elapsed: None,
@@ -485,9 +618,9 @@ impl FinderInternalState {
         let _handle = thread::spawn(move || {
             let mut bg_reader = config.new_reader().unwrap();
 
-            // search header
+            // search header, unless looking for rows that don't match
             let mut column_indices = vec![];
-            if let Ok(header) = bg_reader.headers() {
+            if !inverted && let Ok(header) = bg_reader.headers() {
                 let mut local_column_index = 0;
                 for (column_index, field) in header.iter().enumerate() {
                     if let Some(columns_filter) = &columns_filter
@@ -512,6 +645,7 @@ impl FinderInternalState {
 
             for (row_index, r) in records.enumerate() {
                 let mut column_indices = vec![];
+                let is_valid = r.is_ok();
                 if let Ok(valid_record) = r {
                     let mut local_column_index = 0;
                     for (column_index, field) in valid_record.iter().enumerate() {
@@ -532,7 +666,13 @@ impl FinderInternalState {
                         local_column_index += 1;
                     }
                 }
-                if !column_indices.is_empty() {
+                // Rows found by not matching have no cells to highlight
+                let is_found = if inverted {
+                    is_valid && column_indices.is_empty()
+                } else {
+                    !column_indices.is_empty()
+                };
+                if is_found {
                     let row_order = match &sorter {
                         Some(s) => s
                             .get_record_order(row_index as u64, sort_order)
@@ -556,8 +696,9 @@ impl FinderInternalState {
                     }
                     (*m).found_one(found);
                 }
-                let m = _m.lock().unwrap();
+                let mut m = _m.lock().unwrap();
                 if m.should_terminate {
+                    m.interrupted = true;
                     break;
                 }
             }
@@ -581,6 +722,45 @@ impl FinderInternalState {
         m_state
     }
 
+    fn init_with_rows(
+        row_ids: &[csv::RowId],
+        sorter: &Option<Arc<sort::Sorter>>,
+        sort_order: SortOrder,
+    ) -> Arc<Mutex<FinderInternalState>> {
+        let mut internal = FinderInternalState {
+            count: 0,
+            found_header: None,
+            founds: SortedVec::new(),
+            founds_index_after_starting_row: None,
+            done: true,
+            interrupted: false,
+            should_terminate: false,
+            start: Instant::now(),
+            first_match_elapsed: None,
+            elapsed: None,
+        };
+        for row_id in row_ids {
+            let row_index = row_id.index();
+            let row_order = match sorter {
+                Some(s) => s
+                    .get_record_order(row_index as u64, sort_order)
+                    .unwrap_or(u64::MAX) as usize,
+                _ => row_index,
+            };
+            // No cells are highlighted since the whole row is found
+            internal.found_one(FoundRow {
+                row_index,
+                row_order,
+                column_indices: vec![],
+            });
+        }
+        if internal.count > 0 {
+            internal.founds_index_after_starting_row = Some(0);
+        }
+        internal.elapsed = Some(internal.start.elapsed());
+        Arc::new(Mutex::new(internal))
+    }
+
     fn found_one(&mut self, found: FoundRow) {
         if self.first_match_elapsed.is_none() {
             self.first_match_elapsed = Some(self.start.elapsed());
@@ -0,0 +1,213 @@
+use crate::csv::CsvConfig;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::io::{input_len, open_input};
+
+use std::io::{Read, Seek, SeekFrom};
+
+/// Most rows taken as a footer when detecting it
+const MAX_DETECTED_ROWS: usize = 20;
+
+/// Bytes read from the end of the file at a time while looking for the footer
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Rows after the records that aren't records themselves, e.g. totals or notes appended by
+/// spreadsheet exports
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FooterSpec {
+    /// This many rows at the end of the file
+    Rows(usize),
+    /// Trailing rows with a different number of fields than the header
+    Auto,
+}
+
+/// Where the footer of a file starts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Footer {
+    pub start: u64,
+    pub num_rows: usize,
+}
+
+impl FooterSpec {
+    pub fn parse(s: &str) -> CsvlensResult<FooterSpec> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(FooterSpec::Auto);
+        }
+        s.trim()
+            .parse::<usize>()
+            .map(FooterSpec::Rows)
+            .map_err(|_| CsvlensError::InvalidSkipFooter(s.to_string()))
+    }
+
+    /// Find the footer in the file. Rows are taken to be lines, as footers rarely have quoted
+    /// line breaks. None if there is no footer to skip.
+    pub fn find(&self, config: &CsvConfig) -> CsvlensResult<Option<Footer>> {
+        let mut reader = config.new_reader()?;
+        let num_fields = reader.byte_headers()?.len();
+        // The header is never part of the footer, nor is the first record without one
+        let data_start = if config.has_headers() {
+            reader.position().byte()
+        } else {
+            0
+        };
+
+        let max_rows = match self {
+            FooterSpec::Rows(0) => return Ok(None),
+            FooterSpec::Rows(n) => *n,
+            FooterSpec::Auto => MAX_DETECTED_ROWS,
+        };
+        let lines = last_lines(config.filename(), data_start, max_rows)?;
+
+        let mut footer = None;
+        for (num_rows, (start, line)) in lines.iter().enumerate() {
+            if *self == FooterSpec::Auto && !is_footer_line(line, num_fields, config) {
+                break;
+            }
+            footer = Some(Footer {
+                start: *start,
+                num_rows: num_rows + 1,
+            });
+        }
+        Ok(footer)
+    }
+}
+
+/// Whether a line doesn't fit in with the records
+fn is_footer_line(line: &[u8], num_fields: usize, config: &CsvConfig) -> bool {
+    // An odd number of quotes means the line is the end of a record with quoted line breaks
+    if let Some(quote) = config.quote()
+        && line.iter().filter(|b| **b == quote).count() % 2 == 1
+    {
+        return false;
+    }
+    let mut reader = config.reader_builder().has_headers(false).from_reader(line);
+    let mut record = ::csv::ByteRecord::new();
+    match reader.read_byte_record(&mut record) {
+        Ok(true) => record.len() != num_fields,
+        _ => false,
+    }
+}
+
+/// Up to n non-blank lines at the end of the file after data_start, last first, with the offsets
+/// they start at
+fn last_lines(filename: &str, data_start: u64, n: usize) -> CsvlensResult<Vec<(u64, Vec<u8>)>> {
+    let mut file = open_input(filename)?;
+    let mut tail: Vec<u8> = vec![];
+    let mut tail_start = input_len(filename)?;
+    // Read backwards until enough lines are seen
+    loop {
+        let lines = split_last_lines(&tail, tail_start, data_start, n);
+        if lines.len() == n || tail_start <= data_start {
+            return Ok(lines);
+        }
+        let chunk_start = tail_start.saturating_sub(CHUNK_SIZE).max(data_start);
+        let mut chunk = vec![0u8; (tail_start - chunk_start) as usize];
+        file.seek(SeekFrom::Start(chunk_start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        tail_start = chunk_start;
+    }
+}
+
+fn split_last_lines(
+    tail: &[u8],
+    tail_start: u64,
+    data_start: u64,
+    n: usize,
+) -> Vec<(u64, Vec<u8>)> {
+    let mut lines = vec![];
+    let mut line_end = tail.len();
+    while lines.len() < n {
+        let line_start = tail[..line_end]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        // The first line may be cut off unless it starts at data_start
+        if line_start == 0 && tail_start > data_start {
+            break;
+        }
+        let line = &tail[line_start..line_end];
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            lines.push((tail_start + line_start as u64, line.to_vec()));
+        }
+        if line_start == 0 {
+            break;
+        }
+        line_end = line_start - 1;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+
+    fn find_footer(content: &str, spec: &str) -> Option<(String, usize)> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, content.as_bytes()).unwrap();
+        let config = CsvConfig::new(
+            file.path().to_str().unwrap(),
+            None,
+            CsvBaseConfig::new(b',', false),
+        );
+        let footer = FooterSpec::parse(spec).unwrap().find(&config).unwrap()?;
+        Some((
+            content[footer.start as usize..].to_string(),
+            footer.num_rows,
+        ))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(FooterSpec::parse("2").unwrap(), FooterSpec::Rows(2));
+        assert_eq!(FooterSpec::parse("auto").unwrap(), FooterSpec::Auto);
+        assert!(matches!(
+            FooterSpec::parse("-1"),
+            Err(CsvlensError::InvalidSkipFooter(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_rows() {
+        let content = "a,b\n1,2\n3,4\nTotal,6\n\n";
+        assert_eq!(
+            find_footer(content, "1"),
+            Some(("Total,6\n\n".to_string(), 1))
+        );
+        assert_eq!(
+            find_footer(content, "2"),
+            Some(("3,4\nTotal,6\n\n".to_string(), 2))
+        );
+        assert_eq!(
+            find_footer(content, "9"),
+            Some(("1,2\n3,4\nTotal,6\n\n".to_string(), 3))
+        );
+        assert_eq!(find_footer(content, "0"), None);
+        assert_eq!(find_footer("a,b\n", "1"), None);
+    }
+
+    #[test]
+    fn test_find_auto() {
+        let content = "a,b,c\n1,2,3\n\"x\ny\",5,6\nTotals: 11\nExported 2024-01-01\n";
+        assert_eq!(
+            find_footer(content, "auto"),
+            Some(("Totals: 11\nExported 2024-01-01\n".to_string(), 2))
+        );
+        // The end of a record with a line break is not a footer
+        assert_eq!(find_footer("a,b,c\n1,\"2\n3\",4\n", "auto"), None);
+        assert_eq!(find_footer("a,b,c\n1,2,3\n", "auto"), None);
+    }
+
+    #[test]
+    fn test_find_across_chunks() {
+        let mut content = "a,b\n".to_string();
+        for i in 0..20000 {
+            content.push_str(&format!("{i},{i}\n"));
+        }
+        content.push_str("Total\n");
+        let (footer, num_rows) = find_footer(&content, "3").unwrap();
+        assert_eq!(footer, "19998,19998\n19999,19999\nTotal\n");
+        assert_eq!(num_rows, 3);
+    }
+}
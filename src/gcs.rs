@@ -0,0 +1,407 @@
+use crate::compression;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::http::Download;
+use crate::remote::RemoteObject;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Endpoint of the JSON API of Cloud Storage
+const ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// Host of an emulator to use instead, e.g. fake-gcs-server, which takes no credentials. The
+/// same variable as the Cloud Storage client libraries.
+const EMULATOR_VAR: &str = "STORAGE_EMULATOR_HOST";
+
+/// How long an access token is used before getting another one. They expire after an hour.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(45 * 60);
+
+/// Objects opened so far by their URL, shared by everything reading them so that a chunk read
+/// by one is there for the others
+static OBJECTS: LazyLock<Mutex<HashMap<String, Arc<RemoteObject>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Access token of the Application Default Credentials and when it was got
+static TOKEN: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Object given as `gs://bucket/object`
+#[derive(Clone, Debug, PartialEq)]
+pub struct GcsLocation {
+    pub bucket: String,
+    pub object: String,
+}
+
+impl GcsLocation {
+    /// Location of a `gs://` URL, or None for anything else (e.g. a local path)
+    pub fn parse(url: &str) -> Option<GcsLocation> {
+        let (bucket, object) = url.strip_prefix("gs://")?.split_once('/')?;
+        if bucket.is_empty() || object.is_empty() {
+            return None;
+        }
+        Some(GcsLocation {
+            bucket: bucket.to_string(),
+            object: object.to_string(),
+        })
+    }
+
+    /// Whether the object is compressed by its name, e.g. `events.csv.gz`, which is downloaded
+    /// and decompressed as it comes rather than read with range requests
+    pub fn is_compressed(&self) -> bool {
+        compression::detect_extension(&self.object).is_some()
+    }
+}
+
+impl fmt::Display for GcsLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gs://{}/{}", self.bucket, self.object)
+    }
+}
+
+/// The object at the location, read with range requests of the JSON API as the Application
+/// Default Credentials, looked up the first time and failing early if it can't be read
+pub fn open(location: &GcsLocation) -> CsvlensResult<Arc<RemoteObject>> {
+    let url = location.to_string();
+    if let Some(object) = OBJECTS.lock().unwrap().get(&url) {
+        return Ok(object.clone());
+    }
+    let object = Arc::new(Api::from_env().open(location)?);
+    OBJECTS.lock().unwrap().insert(url, object.clone());
+    Ok(object)
+}
+
+/// Download of the whole object, decompressed as it comes if compressed
+pub fn download(location: &GcsLocation) -> CsvlensResult<Download> {
+    Api::from_env().download(location)
+}
+
+/// Where requests are sent, and whether with credentials
+struct Api {
+    endpoint: String,
+    authorized: bool,
+}
+
+/// Version of an object when it was looked up
+struct Generation {
+    size: u64,
+    generation: String,
+}
+
+impl Api {
+    fn from_env() -> Api {
+        match std::env::var(EMULATOR_VAR) {
+            Ok(host) if host.contains("://") => Api {
+                endpoint: host.trim_end_matches('/').to_string(),
+                authorized: false,
+            },
+            Ok(host) => Api {
+                endpoint: format!("http://{}", host.trim_end_matches('/')),
+                authorized: false,
+            },
+            Err(_) => Api {
+                endpoint: ENDPOINT.to_string(),
+                authorized: true,
+            },
+        }
+    }
+
+    fn open(self, location: &GcsLocation) -> CsvlensResult<RemoteObject> {
+        let Generation { size, generation } = self.generation(location)?;
+        // Requests are for the version looked up, and fail if it is replaced meanwhile instead
+        // of mixing parts of different versions
+        let url = format!(
+            "{}?alt=media&generation={generation}",
+            self.object_url(location)
+        );
+        let location = location.clone();
+        let fetch = move |start: u64, end: u64| {
+            self.get(&url, Some((start, end)))
+                .map_err(|e| io::Error::other(format!("{location}: {e}")))
+        };
+        Ok(RemoteObject::new(size, Box::new(fetch)))
+    }
+
+    fn download(self, location: &GcsLocation) -> CsvlensResult<Download> {
+        let Generation { generation, .. } = self.generation(location)?;
+        let url = format!(
+            "{}?alt=media&generation={generation}",
+            self.object_url(location)
+        );
+        let name = location.to_string();
+        let curl = self
+            .curl(&url, None, "--fail")
+            .map_err(|message| CsvlensError::Gcs(format!("{name}: {message}")))?;
+        Download::start(curl, &location.object, move |stderr| {
+            CsvlensError::Gcs(format!("{name}: {}", curl_message(stderr)))
+        })
+    }
+
+    fn generation(&self, location: &GcsLocation) -> CsvlensResult<Generation> {
+        let url = format!("{}?fields=size,generation", self.object_url(location));
+        let error = |message: String| CsvlensError::Gcs(format!("{location}: {message}"));
+        let body = self.get(&url, None).map_err(error)?;
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&body).map_err(|e| error(e.to_string()))?;
+        // Both are strings, since they can be larger than JSON numbers are read as
+        let field = |name: &str| {
+            metadata[name]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| error(format!("no {name} in the object's metadata")))
+        };
+        Ok(Generation {
+            size: field("size")?
+                .parse()
+                .map_err(|_| error("bad size".into()))?,
+            generation: field("generation")?,
+        })
+    }
+
+    fn object_url(&self, location: &GcsLocation) -> String {
+        format!(
+            "{}/storage/v1/b/{}/o/{}",
+            self.endpoint,
+            encode(&location.bucket),
+            encode(&location.object)
+        )
+    }
+
+    /// Response to a GET of the URL, of the bytes from start up to end if given, or the message
+    /// of the error
+    fn get(&self, url: &str, range: Option<(u64, u64)>) -> Result<Vec<u8>, String> {
+        let curl = self.curl(url, range, "--fail-with-body")?;
+        let output = curl.wait_with_output().map_err(|e| e.to_string())?;
+        if output.status.success() {
+            return Ok(output.stdout);
+        }
+        // e.g. {"error": {"code": 404, "message": "No such object: lake/events.csv"}}
+        let api_message = serde_json::from_slice::<serde_json::Value>(&output.stdout)
+            .ok()
+            .and_then(|body| body["error"]["message"].as_str().map(String::from));
+        Err(api_message.unwrap_or_else(|| {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            curl_message(stderr.lines().next().unwrap_or_default()).to_string()
+        }))
+    }
+
+    /// curl getting the URL, with the token and the range on stdin rather than in the arguments,
+    /// which other users can see
+    fn curl(&self, url: &str, range: Option<(u64, u64)>, fail: &str) -> Result<Child, String> {
+        let token = if self.authorized {
+            Some(access_token()?)
+        } else {
+            None
+        };
+        let mut curl = Command::new("curl")
+            .args(["--silent", "--show-error", "--location", fail])
+            .args(["--config", "-"])
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{e}. Is curl installed?"))?;
+        let mut config = curl.stdin.take().unwrap();
+        if let Some(token) = token {
+            writeln!(config, "header = \"Authorization: Bearer {token}\"")
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some((start, end)) = range {
+            writeln!(config, "range = \"{start}-{}\"", end - 1).map_err(|e| e.to_string())?;
+        }
+        drop(config);
+        Ok(curl)
+    }
+}
+
+/// Access token of the Application Default Credentials, as printed by the Google Cloud CLI, which
+/// knows all of their kinds: a user's (`gcloud auth application-default login`), service account
+/// keys in GOOGLE_APPLICATION_CREDENTIALS, workload identity and the metadata server of Google
+/// Cloud machines. Reused until shortly before it expires.
+fn access_token() -> Result<String, String> {
+    let mut token = TOKEN.lock().unwrap();
+    if let Some((value, got)) = token.as_ref()
+        && got.elapsed() < TOKEN_LIFETIME
+    {
+        return Ok(value.clone());
+    }
+    let output = Command::new("gcloud")
+        .args(["auth", "application-default", "print-access-token"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            format!("{e}. Is the Google Cloud CLI installed? Credentials are got with it")
+        })?;
+    if !output.status.success() {
+        // e.g. "ERROR: (gcloud.auth.application-default.print-access-token) Your default
+        // credentials were not found..."
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().next().unwrap_or_default();
+        let message = message.strip_prefix("ERROR: ").unwrap_or(message);
+        let message = match message.strip_prefix('(') {
+            Some(rest) => rest.split_once(") ").map_or(message, |(_, m)| m),
+            None => message,
+        };
+        return Err(format!("no Application Default Credentials: {message}"));
+    }
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    *token = Some((value.clone(), Instant::now()));
+    Ok(value)
+}
+
+/// Message of an error of curl, e.g. "The requested URL returned error: 404" of
+/// "curl: (22) The requested URL returned error: 404"
+fn curl_message(stderr: &str) -> &str {
+    match stderr.strip_prefix("curl: (") {
+        Some(rest) => rest.split_once(") ").map_or(rest, |(_, m)| m),
+        None => stderr,
+    }
+}
+
+/// Name percent-encoded as a path segment, slashes included
+fn encode(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse() {
+        let location = GcsLocation::parse("gs://lake/raw/2024/events.csv.gz").unwrap();
+        assert_eq!(location.bucket, "lake");
+        assert_eq!(location.object, "raw/2024/events.csv.gz");
+        assert_eq!(location.to_string(), "gs://lake/raw/2024/events.csv.gz");
+        assert!(location.is_compressed());
+        assert!(
+            !GcsLocation::parse("gs://lake/events.csv")
+                .unwrap()
+                .is_compressed()
+        );
+        assert_eq!(GcsLocation::parse("gs://lake/"), None);
+        assert_eq!(GcsLocation::parse("gs://lake"), None);
+        assert_eq!(GcsLocation::parse("s3://lake/events.csv"), None);
+        assert_eq!(GcsLocation::parse("events.csv"), None);
+    }
+
+    #[test]
+    fn test_object_url() {
+        let api = Api {
+            endpoint: ENDPOINT.to_string(),
+            authorized: true,
+        };
+        let location = GcsLocation::parse("gs://lake/raw/2024/new events+ü.csv").unwrap();
+        assert_eq!(
+            api.object_url(&location),
+            "https://storage.googleapis.com/storage/v1/b/lake/o/raw%2F2024%2Fnew%20events%2B%C3%BC.csv"
+        );
+    }
+
+    /// Serve as many requests as given like the JSON API for the object `lake/data.csv`, returning
+    /// the API of the server and the request lines and ranges asked for
+    fn serve(data: &'static [u8], requests: usize) -> (Api, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api = Api {
+            endpoint: format!("http://{}", listener.local_addr().unwrap()),
+            authorized: false,
+        };
+        let handle = std::thread::spawn(move || {
+            let mut seen = vec![];
+            for _ in 0..requests {
+                let (mut stream, _) = listener.accept().unwrap();
+                let headers: Vec<String> = BufReader::new(&stream)
+                    .lines()
+                    .map_while(Result::ok)
+                    .take_while(|line| !line.is_empty())
+                    .collect();
+                let range = headers.iter().find_map(|h| h.strip_prefix("Range: bytes="));
+                let (status, body) = if !headers[0].contains("/o/data.csv?") {
+                    let error = r#"{"error": {"code": 404, "message": "No such object: lake/missing.csv"}}"#;
+                    ("404 Not Found", error.as_bytes().to_vec())
+                } else if headers[0].contains("fields=") {
+                    let metadata = format!(r#"{{"size": "{}", "generation": "17"}}"#, data.len());
+                    ("200 OK", metadata.into_bytes())
+                } else if let Some((start, end)) = range.and_then(|r| r.split_once('-')) {
+                    let (start, end): (usize, usize) =
+                        (start.parse().unwrap(), end.parse().unwrap());
+                    ("206 Partial Content", data[start..=end].to_vec())
+                } else {
+                    ("200 OK", data.to_vec())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+                seen.push(headers[0].clone());
+                seen.extend(range.map(String::from));
+            }
+            seen
+        });
+        (api, handle)
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_open() {
+        let (api, handle) = serve(b"a,b\n1,2\n", 2);
+        let object = Arc::new(
+            api.open(&GcsLocation::parse("gs://lake/data.csv").unwrap())
+                .unwrap(),
+        );
+        assert_eq!(object.len(), 8);
+        let mut content = String::new();
+        object.reader().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "a,b\n1,2\n");
+        assert_eq!(
+            handle.join().unwrap(),
+            vec![
+                "GET /storage/v1/b/lake/o/data.csv?fields=size,generation HTTP/1.1",
+                "GET /storage/v1/b/lake/o/data.csv?alt=media&generation=17 HTTP/1.1",
+                "0-7",
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_download() {
+        let (api, handle) = serve(b"a,b\n1,2\n", 2);
+        let mut content = String::new();
+        api.download(&GcsLocation::parse("gs://lake/data.csv").unwrap())
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "a,b\n1,2\n");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_open_error() {
+        let (api, handle) = serve(b"", 1);
+        let result = api.open(&GcsLocation::parse("gs://lake/missing.csv").unwrap());
+        assert!(matches!(
+            result,
+            Err(CsvlensError::Gcs(message))
+                if message == "gs://lake/missing.csv: No such object: lake/missing.csv"
+        ));
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+use regex::Regex;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+const MAP_WIDTH: usize = 40;
+const MAP_HEIGHT: usize = 12;
+
+static WKT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)^\s*(?:SRID=\d+;\s*)?(POINT|LINESTRING|POLYGON|MULTIPOINT|MULTILINESTRING|MULTIPOLYGON|GEOMETRYCOLLECTION)\s*(?:ZM|Z|M)?\s*(\(.*\))\s*$").unwrap()
+});
+
+static LAT_LON_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*\(?\s*(-?\d+(?:\.\d+)?)\s*[,;\s]\s*(-?\d+(?:\.\d+)?)\s*\)?\s*$").unwrap()
+});
+
+/// Coordinates parsed from a GeoJSON, WKT or "lat, lon" value
+#[derive(Debug, PartialEq)]
+pub struct Geometry {
+    pub kind: String,
+    /// (lon, lat) pairs, in the axis order of GeoJSON and WKT
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Geometry {
+    pub fn parse(value: &str) -> Option<Geometry> {
+        let value = value.trim();
+        let geometry = if value.starts_with('{') {
+            Geometry::from_geojson(value)
+        } else if let Some(caps) = WKT_REGEX.captures(value) {
+            Some(Geometry {
+                kind: caps[1].to_uppercase(),
+                points: parse_wkt_points(&caps[2]),
+            })
+        } else if let Some(caps) = LAT_LON_REGEX.captures(value) {
+            let lat = caps[1].parse::<f64>().ok()?;
+            let lon = caps[2].parse::<f64>().ok()?;
+            if lat.abs() > 90.0 || lon.abs() > 180.0 {
+                return None;
+            }
+            Some(Geometry {
+                kind: "Coordinates".to_string(),
+                points: vec![(lon, lat)],
+            })
+        } else {
+            None
+        };
+        geometry.filter(|g| !g.points.is_empty())
+    }
+
+    fn from_geojson(value: &str) -> Option<Geometry> {
+        let json: Value = serde_json::from_str(value).ok()?;
+        let kind = json.get("type")?.as_str()?.to_string();
+        let mut points = vec![];
+        collect_geojson_points(&json, &mut points);
+        Some(Geometry { kind, points })
+    }
+
+    /// (min_lon, min_lat, max_lon, max_lat)
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        self.points.iter().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        )
+    }
+
+    /// Center of the bounding box as (lat, lon)
+    pub fn center(&self) -> (f64, f64) {
+        let (x0, y0, x1, y1) = self.bounds();
+        ((y0 + y1) / 2.0, (x0 + x1) / 2.0)
+    }
+
+    pub fn maps_url(&self) -> String {
+        let (lat, lon) = self.center();
+        format!(
+            "https://www.openstreetmap.org/?mlat={lat:.6}&mlon={lon:.6}#map=12/{lat:.6}/{lon:.6}"
+        )
+    }
+
+    /// Lines describing the geometry followed by a rough map of its points
+    pub fn describe(&self) -> Vec<String> {
+        let (x0, y0, x1, y1) = self.bounds();
+        let (lat, lon) = self.center();
+        let mut lines = vec![
+            format!("Type: {}", self.kind),
+            format!("Points: {}", self.points.len()),
+            format!("Center: {lat:.6}, {lon:.6}"),
+        ];
+        if self.points.len() > 1 {
+            lines.push(format!(
+                "Bounds: lat {y0:.6} to {y1:.6}, lon {x0:.6} to {x1:.6}"
+            ));
+        }
+        lines.push("".to_string());
+        lines.extend(self.render_map());
+        lines
+    }
+
+    /// Plot the points in their bounding box, or on the whole world for a single location
+    fn render_map(&self) -> Vec<String> {
+        let (mut x0, mut y0, mut x1, mut y1) = self.bounds();
+        let is_world = x1 - x0 < 1e-9 && y1 - y0 < 1e-9;
+        if is_world {
+            (x0, y0, x1, y1) = (-180.0, -90.0, 180.0, 90.0);
+        }
+        let to_cell = |x: f64, y: f64| {
+            let col = ((x - x0) / (x1 - x0).max(1e-9) * (MAP_WIDTH - 1) as f64).round();
+            let row = ((y1 - y) / (y1 - y0).max(1e-9) * (MAP_HEIGHT - 1) as f64).round();
+            (
+                (col.max(0.0) as usize).min(MAP_WIDTH - 1),
+                (row.max(0.0) as usize).min(MAP_HEIGHT - 1),
+            )
+        };
+
+        let mut grid = vec![vec![' '; MAP_WIDTH]; MAP_HEIGHT];
+        if is_world {
+            // Equator and prime meridian as reference lines
+            let (meridian, equator) = to_cell(0.0, 0.0);
+            for c in grid[equator].iter_mut() {
+                *c = '-';
+            }
+            for row in grid.iter_mut() {
+                row[meridian] = if row[meridian] == '-' { '+' } else { '|' };
+            }
+        }
+        for &(x, y) in &self.points {
+            let (col, row) = to_cell(x, y);
+            grid[row][col] = '*';
+        }
+
+        let border = format!("+{}+", "-".repeat(MAP_WIDTH));
+        let mut lines = vec![border.clone()];
+        lines.extend(
+            grid.into_iter()
+                .map(|row| format!("|{}|", row.into_iter().collect::<String>())),
+        );
+        lines.push(border);
+        lines
+    }
+}
+
+fn collect_geojson_points(value: &Value, points: &mut Vec<(f64, f64)>) {
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("Feature") => {
+            if let Some(geometry) = value.get("geometry") {
+                collect_geojson_points(geometry, points);
+            }
+        }
+        Some("FeatureCollection") => {
+            for feature in value
+                .get("features")
+                .and_then(|f| f.as_array())
+                .into_iter()
+                .flatten()
+            {
+                collect_geojson_points(feature, points);
+            }
+        }
+        Some("GeometryCollection") => {
+            for geometry in value
+                .get("geometries")
+                .and_then(|g| g.as_array())
+                .into_iter()
+                .flatten()
+            {
+                collect_geojson_points(geometry, points);
+            }
+        }
+        Some(_) => {
+            if let Some(coordinates) = value.get("coordinates") {
+                collect_coordinates(coordinates, points);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Coordinates are nested arrays of positions, a position being an array of numbers
+fn collect_coordinates(value: &Value, points: &mut Vec<(f64, f64)>) {
+    let Some(array) = value.as_array() else {
+        return;
+    };
+    match (
+        array.first().and_then(|v| v.as_f64()),
+        array.get(1).and_then(|v| v.as_f64()),
+    ) {
+        (Some(x), Some(y)) => points.push((x, y)),
+        _ => {
+            for v in array {
+                collect_coordinates(v, points);
+            }
+        }
+    }
+}
+
+fn parse_wkt_points(body: &str) -> Vec<(f64, f64)> {
+    // Nesting doesn't matter for the points, and type names in collections are skipped
+    body.split(['(', ')', ','])
+        .filter_map(|position| {
+            let mut numbers = position.split_whitespace().map(|n| n.parse::<f64>());
+            match (numbers.next(), numbers.next()) {
+                (Some(Ok(x)), Some(Ok(y))) => Some((x, y)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lat_lon() {
+        let g = Geometry::parse("40.7128, -74.0060").unwrap();
+        assert_eq!(g.kind, "Coordinates");
+        assert_eq!(g.points, vec![(-74.006, 40.7128)]);
+        assert_eq!(g.center(), (40.7128, -74.006));
+        assert!(Geometry::parse("120.5, 10").is_none());
+        assert!(Geometry::parse("hello").is_none());
+    }
+
+    #[test]
+    fn test_parse_wkt() {
+        let g = Geometry::parse("POINT (30 10)").unwrap();
+        assert_eq!(g.kind, "POINT");
+        assert_eq!(g.points, vec![(30.0, 10.0)]);
+
+        let g =
+            Geometry::parse("SRID=4326;polygon z ((30 10 1, 40 40 1, 20 40 1, 30 10 1))").unwrap();
+        assert_eq!(g.kind, "POLYGON");
+        assert_eq!(g.points.len(), 4);
+        assert_eq!(g.bounds(), (20.0, 10.0, 40.0, 40.0));
+
+        let g =
+            Geometry::parse("GEOMETRYCOLLECTION (POINT (4 6), LINESTRING (4 6, 7 10))").unwrap();
+        assert_eq!(g.points, vec![(4.0, 6.0), (4.0, 6.0), (7.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_parse_geojson() {
+        let g = Geometry::parse(
+            r#"{"type": "Feature", "geometry": {"type": "LineString", "coordinates": [[102.0, 0.0], [103.0, 1.0]]}}"#,
+        )
+        .unwrap();
+        assert_eq!(g.kind, "Feature");
+        assert_eq!(g.points, vec![(102.0, 0.0), (103.0, 1.0)]);
+        assert!(Geometry::parse(r#"{"type": "Point"}"#).is_none());
+        assert!(Geometry::parse(r#"{"a": 1}"#).is_none());
+    }
+
+    #[test]
+    fn test_describe() {
+        let g = Geometry::parse("0, 0").unwrap();
+        let lines = g.describe();
+        assert_eq!(lines[0], "Type: Coordinates");
+        assert_eq!(lines[2], "Center: 0.000000, 0.000000");
+        // The point sits where the equator and prime meridian cross
+        let map = &lines[4..];
+        assert_eq!(map.len(), MAP_HEIGHT + 2);
+        assert_eq!(map[0], format!("+{}+", "-".repeat(MAP_WIDTH)));
+        assert!(map.iter().any(|l| l.contains("-*-")));
+        assert_eq!(
+            g.maps_url(),
+            "https://www.openstreetmap.org/?mlat=0.000000&mlon=0.000000#map=12/0.000000/0.000000"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use crate::app::App;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::runner::{self, CsvlensOptions};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::layout::Position;
+
+/// Renders csvlens to a plain-text screen of a given size and feeds it scripted key presses, for
+/// integration and snapshot tests. Requires the `harness` feature.
+///
+/// Background work like reading, sorting and finding is waited on after each key press, so the
+/// screen is the same on every run.
+///
+/// ```ignore
+/// use csvlens::CsvlensOptions;
+/// use csvlens::harness::Harness;
+///
+/// let options = CsvlensOptions {
+///     filename: Some("cities.csv".to_string()),
+///     no_presets: true,
+///     no_wizard: true,
+///     ..Default::default()
+/// };
+/// let mut harness = Harness::new(options, 80, 12).unwrap();
+/// harness.keys("&Salem<Enter>").unwrap();
+/// assert!(harness.screen().contains("Salem"));
+/// ```
+pub struct Harness {
+    app: App,
+    terminal: Terminal<TestBackend>,
+    output: Option<Option<String>>,
+}
+
+impl Harness {
+    /// The view csvlens opens for the options, on a terminal of the given size. Presets in the
+    /// user's config and the wizard for ambiguous files apply unless `no_presets` and `no_wizard`
+    /// are set.
+    pub fn new(options: CsvlensOptions, width: u16, height: u16) -> CsvlensResult<Harness> {
+        let app = runner::new_app(options)?;
+        let Ok(terminal) = Terminal::new(TestBackend::new(width, height));
+        let mut harness = Harness {
+            app,
+            terminal,
+            output: None,
+        };
+        harness.settle()?;
+        Ok(harness)
+    }
+
+    /// Presses the keys, in a notation like vim's: characters are typed as is and named keys go
+    /// in angle brackets, e.g. `/Salem<Enter>`, `<C-j>`, `<S-Down>` or `<lt>` for `<`. Keys
+    /// pressed after csvlens exited are ignored.
+    pub fn keys(&mut self, keys: &str) -> CsvlensResult<()> {
+        for key in parse_keys(keys)? {
+            self.press(key)?;
+        }
+        Ok(())
+    }
+
+    pub fn press(&mut self, key: KeyEvent) -> CsvlensResult<()> {
+        if self.exited() {
+            return Ok(());
+        }
+        if let Some(output) = self.app.handle_key(key)? {
+            self.output = Some(output);
+            return Ok(());
+        }
+        // Drawing can update the state stepping depends on, as in the main loop
+        self.app.draw(&mut self.terminal)?;
+        self.settle()
+    }
+
+    /// Pastes the text, as a terminal with bracketed paste sends it
+    pub fn paste(&mut self, text: &str) -> CsvlensResult<()> {
+        if self.exited() {
+            return Ok(());
+        }
+        if let Some(output) = self.app.handle_paste(text)? {
+            self.output = Some(output);
+            return Ok(());
+        }
+        self.app.draw(&mut self.terminal)?;
+        self.settle()
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> CsvlensResult<()> {
+        self.terminal.backend_mut().resize(width, height);
+        self.settle()
+    }
+
+    /// Lines of the last frame, padded with spaces to the width of the terminal
+    pub fn lines(&self) -> Vec<String> {
+        let buffer = self.terminal.backend().buffer();
+        let area = buffer.area();
+        (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buffer[Position::new(x, y)].symbol())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The last frame as text
+    pub fn screen(&self) -> String {
+        self.lines().join("\n")
+    }
+
+    pub fn exited(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// What csvlens printed on exit, e.g. the selected cell
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_ref().and_then(|o| o.as_deref())
+    }
+
+    fn settle(&mut self) -> CsvlensResult<()> {
+        self.app.wait_for_background()?;
+        self.app.draw(&mut self.terminal)
+    }
+}
+
+fn parse_keys(keys: &str) -> CsvlensResult<Vec<KeyEvent>> {
+    let mut events = vec![];
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| CsvlensError::InvalidKey(rest.to_string()))?;
+            events.push(parse_named_key(&rest[..=end])?);
+            rest = &rest[end + 1..];
+        } else {
+            events.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(events)
+}
+
+/// A key like `<Enter>` or `<C-Left>`
+fn parse_named_key(key: &str) -> CsvlensResult<KeyEvent> {
+    let invalid = || CsvlensError::InvalidKey(key.to_string());
+    let mut name = &key[1..key.len() - 1];
+    let mut modifiers = KeyModifiers::NONE;
+    loop {
+        if let Some(rest) = name.strip_prefix("C-") {
+            modifiers |= KeyModifiers::CONTROL;
+            name = rest;
+        } else if let Some(rest) = name.strip_prefix("S-") {
+            modifiers |= KeyModifiers::SHIFT;
+            name = rest;
+        } else {
+            break;
+        }
+    }
+    let code = match name {
+        "Enter" | "CR" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "BS" | "Backspace" => KeyCode::Backspace,
+        "Space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return Err(invalid()),
+            }
+        }
+    };
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(filename: &str, width: u16, height: u16) -> Harness {
+        let options = CsvlensOptions {
+            filename: Some(filename.to_string()),
+            no_presets: true,
+            no_wizard: true,
+            ..Default::default()
+        };
+        Harness::new(options, width, height).unwrap()
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(
+            parse_keys("/a<Enter><C-j><S-Down><lt>").unwrap(),
+            vec![
+                key(KeyCode::Char('/'), KeyModifiers::NONE),
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Enter, KeyModifiers::NONE),
+                key(KeyCode::Char('j'), KeyModifiers::CONTROL),
+                key(KeyCode::Down, KeyModifiers::SHIFT),
+                key(KeyCode::Char('<'), KeyModifiers::NONE),
+            ]
+        );
+        assert!(matches!(
+            parse_keys("<Nope>"),
+            Err(CsvlensError::InvalidKey(k)) if k == "<Nope>"
+        ));
+        assert!(matches!(
+            parse_keys("a<Enter"),
+            Err(CsvlensError::InvalidKey(k)) if k == "<Enter"
+        ));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut harness = open("tests/data/cities.csv", 80, 10);
+        harness.keys("&^OH$<Enter>").unwrap();
+        let expected = vec![
+            "────────────────────────────────────────────────────────────────────────────────",
+            "       LatD    LatM    LatS    NS    LonD    LonM    LonS    EW    City         ",
+            "────┬───────────────────────────────────────────────────────────────────────────",
+            "1   │  41      5       59      N     80      39      0       W     Youngsto…    ",
+            "50  │  41      39      0       N     83      32      24      W     Toledo       ",
+            "62  │  40      21      36      N     80      37      12      W     Steubenv…    ",
+            "65  │  39      55      11      N     83      48      35      W     Springfi…    ",
+            "92  │  41      27      0       N     82      42      35      W     Sandusky     ",
+            "────┴───────────────────────────────────────────────────────────────────────────",
+            "tests/data/cities.csv [Row 1/128, Col 1/10] [Filter \"^OH$\": 1/6]                ",
+        ];
+        assert_eq!(harness.lines(), expected);
+
+        harness.resize(40, 6).unwrap();
+        let expected = vec![
+            "────────────────────────────────────────",
+            "       LatD    LatM    LatS    NS    …  ",
+            "────┬───────────────────────────────────",
+            "1   │  41      5       59      N     …  ",
+            "────┴───────────────────────────────────",
+            "tests/data/cities.csv [Row 1/128, Col 1/",
+        ];
+        assert_eq!(harness.lines(), expected);
+    }
+
+    #[test]
+    fn test_exit() {
+        let options = CsvlensOptions {
+            filename: Some("tests/data/simple.csv".to_string()),
+            echo_column: Some("b".to_string()),
+            no_presets: true,
+            no_wizard: true,
+            ..Default::default()
+        };
+        let mut harness = Harness::new(options, 40, 10).unwrap();
+        harness.keys("j<Enter>").unwrap();
+        assert!(harness.exited());
+        assert_eq!(harness.output(), Some("B2"));
+        // Ignored after exiting
+        harness.keys("q").unwrap();
+        assert_eq!(harness.output(), Some("B2"));
+
+        let mut harness = open("tests/data/simple.csv", 40, 6);
+        harness.keys("q").unwrap();
+        assert!(harness.exited());
+        assert_eq!(harness.output(), None);
+    }
+}
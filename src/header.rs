@@ -0,0 +1,113 @@
+use crate::io::open_input;
+
+use std::io::Read;
+
+/// Number of records looked at to decide whether there is a header row
+const SAMPLE_RECORDS: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ValueKind {
+    Empty,
+    Number,
+    /// Text of this many characters
+    Text(usize),
+}
+
+impl ValueKind {
+    fn of(value: &str) -> ValueKind {
+        let value = value.trim();
+        if value.is_empty() {
+            ValueKind::Empty
+        } else if value.parse::<f64>().is_ok() {
+            ValueKind::Number
+        } else {
+            ValueKind::Text(value.chars().count())
+        }
+    }
+}
+
+/// Sniff whether the first row of the file is a header
+pub fn sniff_header(filename: &str, delimiter: u8) -> Option<bool> {
+    let file = open_input(filename).ok()?;
+    has_header(file, delimiter)
+}
+
+/// Guess whether the first row is a header by comparing it with the rest, like Python's
+/// csv.Sniffer: each column whose values are all numbers, or all text of the same length, votes
+/// for a header if the first row doesn't fit in. None if there is nothing to tell them apart.
+fn has_header<R: Read>(source: R, delimiter: u8) -> Option<bool> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(source);
+    let mut records = reader.records().take(SAMPLE_RECORDS + 1);
+    let first = records.next()?.ok()?;
+    let rest: Vec<::csv::StringRecord> = records.map_while(Result::ok).collect();
+
+    let mut votes = 0i64;
+    for (i, first_value) in first.iter().enumerate() {
+        let mut kinds = rest
+            .iter()
+            .filter_map(|r| r.get(i))
+            .map(ValueKind::of)
+            .filter(|k| *k != ValueKind::Empty);
+        let Some(column_kind) = kinds.next() else {
+            continue;
+        };
+        if !kinds.all(|k| k == column_kind) {
+            // Text of varying lengths could be a header or not
+            continue;
+        }
+        if ValueKind::of(first_value) == column_kind {
+            votes -= 1;
+        } else {
+            votes += 1;
+        }
+    }
+    match votes {
+        0 => None,
+        v => Some(v > 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_header_str(content: &str) -> Option<bool> {
+        has_header(content.as_bytes(), b',')
+    }
+
+    #[test]
+    fn test_has_header() {
+        assert_eq!(
+            has_header_str("id,price\n1,9.5\n2,10\n3,7.25\n"),
+            Some(true)
+        );
+        assert_eq!(has_header_str("1,9.5\n2,10\n3,7.25\n"), Some(false));
+        // Fixed length codes below a longer title
+        assert_eq!(
+            has_header_str("country,name\nJP,Tokyo\nFR,Paris\n"),
+            Some(true)
+        );
+        assert_eq!(
+            has_header_str("JP,Tokyo\nFR,Paris\nDE,Berlin\n"),
+            Some(false)
+        );
+        assert_eq!(has_header_str("a,b\nfoo,x\nlonger,yy\n"), None);
+        assert_eq!(has_header_str("id,name\n"), None);
+        assert_eq!(has_header_str(""), None);
+    }
+
+    #[test]
+    fn test_sniff_header_files() {
+        assert_eq!(
+            sniff_header("tests/data/no_headers_readings.csv", b','),
+            Some(false)
+        );
+        // Codes like A1 and A10 are too alike to tell
+        assert_eq!(sniff_header("tests/data/no_headers.csv", b','), None);
+        assert_eq!(sniff_header("tests/data/cities.csv", b','), Some(true));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::columns_filter::ColumnsFilter;
+use crate::csv::{CsvConfig, CsvlensRecordIterator};
+use crate::errors::CsvlensResult;
+
+use regex::Regex;
+use std::sync::Arc;
+
+/// Number of records with a field matching target in the columns kept by the columns filter, or
+/// of all records without a target. Counting stops at limit, if any.
+pub fn count_matches(
+    config: Arc<CsvConfig>,
+    target: Option<&Regex>,
+    columns_filter: Option<&ColumnsFilter>,
+    limit: Option<usize>,
+) -> CsvlensResult<usize> {
+    let mut count = 0;
+    for record in CsvlensRecordIterator::new(config)? {
+        if limit.is_some_and(|n| count >= n) {
+            break;
+        }
+        let record = record?;
+        let matches = match target {
+            Some(target) => record.iter().enumerate().any(|(i, field)| {
+                columns_filter.is_none_or(|f| f.is_column_filtered(i)) && target.is_match(field)
+            }),
+            None => true,
+        };
+        if matches {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+
+    fn count(target: Option<&str>, columns: Option<&str>, limit: Option<usize>) -> usize {
+        let config = Arc::new(CsvConfig::new(
+            "tests/data/cities.csv",
+            None,
+            CsvBaseConfig::new(b',', false),
+        ));
+        let headers: Vec<String> = config
+            .new_reader()
+            .unwrap()
+            .headers()
+            .unwrap()
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let columns_filter = columns.map(|c| ColumnsFilter::new(Regex::new(c).unwrap(), &headers));
+        let target = target.map(|t| Regex::new(t).unwrap());
+        count_matches(config, target.as_ref(), columns_filter.as_ref(), limit).unwrap()
+    }
+
+    #[test]
+    fn test_count_matches() {
+        assert_eq!(count(None, None, None), 128);
+        assert_eq!(count(Some("^OH$"), None, None), 6);
+        assert_eq!(count(Some("^OH$"), None, Some(1)), 1);
+        assert_eq!(count(Some("^OH$"), Some("City"), None), 0);
+        assert_eq!(count(Some("nomatch"), None, None), 0);
+    }
+}
@@ -25,6 +25,7 @@ Ctrl + →                : Scroll right to last column
 G (or End)              : Go to bottom
 g (or Home)             : Go to top
 <n>G                    : Go to line n
+<n>%                    : Go to n percent of the way through the file
 
 # Search
 
@@ -41,21 +42,61 @@ TAB                     : Toggle between row, column or cell selection modes
 <                       : Decrease selected column's width
 Shift + ↓ (or J)        : Sort rows by the selected column (auto by type: numeric for numbers and lexicographic for text)
 Ctrl + J                : Sort rows by the selected column (natural; e.g. \"file2\" < \"file10\")
+s                       : Choose a column to sort by in the header (←/→ to move, Enter to sort, Esc when done)
 # (in Cell mode)        : Find and highlight rows like the selected cell
+# (in Column mode)      : Also sort by the selected column where rows are equal (again to reverse, then remove)
 @ (in Cell mode)        : Filter rows like the selected cell
-y                       : Copy the selected row or cell to clipboard
+! (in Cell mode)        : Filter rows where the column doesn't have the value of the selected cell
+y                       : Copy the selected cell, or the marked rows (or else the selected row) to clipboard
 Enter (in Cell mode)    : Print the selected cell to stdout and exit
 
 # Other options
 
 -S                      : Toggle line wrapping
 -W                      : Toggle line wrapping by words
+-D                      : Cycle the decoder of the selected column (base64, hex, url, none)
+-U                      : Cycle humanizing the selected column (bytes, durations in ms, in ns, none)
+-I                      : Toggle abbreviating long values like hashes in the selected column (i shows them in full)
+-G                      : Collapse or expand the group of the selected column (with --group-columns)
+-K                      : Toggle ignoring case when sorting text (e.g. \"apple\" < \"Banana\")
+-C                      : Toggle the schema sidebar (distinct values per column, stats of selected column)
+-P                      : Toggle highlighting of emails, IP addresses and UUIDs
+-A                      : Toggle screen reader mode (announce the selected cell, no borders)
+-M                      : Show memory used by row index, sort, find, validation and profile results
+-R                      : Toggle review mode, showing how many rows of the view are tagged done
+-H                      : Toggle whether the first row is read as the header
+-T                      : List the tables in the file (with --split-tables) to switch between
+-F                      : Show all fields of records with more than --max-fields
+-O                      : Group the sorted rows by the column sorted by (z collapses a group, Z all)
 f<n>                    : Freeze this number of columns from the left
 r                       : Reset to default view (clear all filters and custom column widths)
 H (or ?)                : Display this help
 m                       : Mark / unmark the selected row visually
 M                       : Clear all row marks
 Ctrl + e                : Print the marked rows (with header) to stdout and exit
+t<tag>                  : Tag / untag the marked rows, or the selected row if none is marked
+T<tag>                  : Filter rows with this tag
+c                       : Compare the selected row with the rows selected next; c again swaps the two rows
+C                       : Stop comparing rows
+V                       : Start / end a block of cells at the selected cell; the status bar shows the sum, mean, min and max of its numbers
+z                       : Collapse / expand the group of the selected row (with -O)
+Z                       : Collapse / expand all groups
+x (in review mode)      : Tag the selected row done and go to the next row that is not done
+v                       : List cells failing validation (with --validate); Enter jumps to the row
+i                       : Show details of the selected cell (coordinates are shown on a map)
+o                       : Open the URL or file path in the selected cell (underlined)
+:<command>              : Run a command (goto, filter, columns, hide, sort, freeze, count, count-rows, export, write-split, reload, quit)
+:<n> / :w <file> / :q    : Go to row n, export, quit (names can be shortened, e.g. :so City desc)
+:count <regex>           : Show matches per column; Enter filters in the selected column
+Ctrl + r (in a prompt)  : Insert the value of the selected cell, e.g. after / or &
+Ctrl + a / Ctrl + e     : Move to the start / end of a prompt
+Alt + b / Alt + f       : Move back / forward a word in a prompt
+Ctrl + w / Alt + d      : Delete the word before / after the cursor in a prompt
+Ctrl + u / Ctrl + k     : Delete the prompt / up to its end
+Ctrl + z (or Ctrl + _)  : Undo the last edit in a prompt
+:dump-state [file]      : Save the view state as JSON (to csvlens-state.json by default)
+Esc                     : Stop waiting on a stalled read (shown as Waiting on I/O), or cancel an export
+Ctrl + z                : Suspend to the shell (fg to come back)
 q                       : Exit";
 
 pub struct HelpPage {}
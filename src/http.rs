@@ -0,0 +1,330 @@
+use crate::compression;
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// User name and password for HTTP basic auth, if the server asks for it
+const USER_VAR: &str = "CSVLENS_HTTP_USER";
+const PASSWORD_VAR: &str = "CSVLENS_HTTP_PASSWORD";
+
+/// Whether the file name is an `http://` or `https://` URL
+pub fn is_url(filename: &str) -> bool {
+    let scheme = filename.get(..8).unwrap_or(filename).to_ascii_lowercase();
+    scheme.starts_with("http://") || scheme.starts_with("https://")
+}
+
+/// Download the URL with curl, read as it goes. Responses compressed for the transfer are
+/// decompressed by curl, and files compressed on the server (e.g. `.csv.gz`) like local files.
+/// Fails early if nothing can be downloaded, e.g. for a 404.
+pub fn download(url: &str) -> CsvlensResult<Download> {
+    let credentials = std::env::var(USER_VAR).ok().map(|user| {
+        let password = std::env::var(PASSWORD_VAR).unwrap_or_default();
+        (user, password)
+    });
+    fetch(url, credentials)
+}
+
+fn fetch(url: &str, credentials: Option<(String, String)>) -> CsvlensResult<Download> {
+    let error = |message: String| CsvlensError::Http(url.to_string(), message);
+    let mut curl = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--compressed",
+        ])
+        // Options on stdin rather than in the arguments, which other users can see
+        .args(["--config", "-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| error(format!("{e}. Is curl installed?")))?;
+    let mut config = curl.stdin.take().unwrap();
+    if let Some((user, password)) = credentials {
+        writeln!(
+            config,
+            "user = \"{}\"",
+            quote(&format!("{user}:{password}"))
+        )?;
+    }
+    drop(config);
+
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let url = url.to_string();
+    Download::start(curl, path, move |stderr| {
+        // e.g. "curl: (22) The requested URL returned error: 404"
+        let message = match stderr.strip_prefix("curl: (") {
+            Some(rest) => rest.split_once(") ").map_or(rest, |(_, m)| m),
+            None => stderr,
+        };
+        CsvlensError::Http(url.clone(), message.to_string())
+    })
+}
+
+/// Text in double quotes of a curl config file
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Output of a program downloading a file, e.g. the body of a URL. Downloading is stopped once no
+/// longer read.
+pub struct Download {
+    program: Child,
+    /// Output of the program, decompressed if need be
+    stdout: Box<dyn Read + Send>,
+    /// Read before the body was handed on, to know that the download started
+    first: Vec<u8>,
+    first_pos: usize,
+    received: Arc<AtomicU64>,
+    /// What the program wrote to stderr, read as it goes so that the program never waits on a
+    /// full pipe
+    stderr: Option<JoinHandle<String>>,
+    /// Error for the first line the program wrote to stderr when it fails
+    error: Box<dyn Fn(&str) -> CsvlensError + Send>,
+}
+
+impl Download {
+    /// Read the output of the program, decompressed if the name of the file ends like a compressed
+    /// one, e.g. `.csv.gz`. Waits for the first bytes, so that an error is given with the first
+    /// line the program wrote to stderr if it fails to start the download. Failing later, e.g.
+    /// when the connection drops, is an error at the end of the output.
+    pub fn start(
+        mut program: Child,
+        name: &str,
+        error: impl Fn(&str) -> CsvlensError + Send + 'static,
+    ) -> CsvlensResult<Download> {
+        let program_stdout = program.stdout.take().unwrap();
+        let stderr = program.stderr.take().map(|mut pipe| {
+            thread::spawn(move || {
+                let mut stderr = String::new();
+                let _ = pipe.read_to_string(&mut stderr);
+                stderr
+            })
+        });
+        let stdout: Box<dyn Read + Send> = match compression::detect_extension(name) {
+            Some(format) => compression::decompress_output(program_stdout, name, format)?,
+            None => Box::new(program_stdout),
+        };
+        let mut download = Download {
+            program,
+            stdout,
+            first: vec![0; 64 * 1024],
+            first_pos: 0,
+            received: Arc::new(AtomicU64::new(0)),
+            stderr,
+            error: Box::new(error),
+        };
+
+        let n = download.stdout.read(&mut download.first)?;
+        download.first.truncate(n);
+        if n == 0 {
+            download.check_status()?;
+        }
+        Ok(download)
+    }
+
+    /// Once the program has written all of its output, whether it downloaded all of it
+    fn check_status(&mut self) -> CsvlensResult<()> {
+        if self.program.wait()?.success() {
+            return Ok(());
+        }
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        Err((self.error)(stderr.lines().next().unwrap_or_default()))
+    }
+
+    /// Number of bytes read so far, updated as the download goes
+    pub fn received(&self) -> Arc<AtomicU64> {
+        self.received.clone()
+    }
+}
+
+impl Read for Download {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = if self.first_pos < self.first.len() {
+            let n = (self.first.len() - self.first_pos).min(buf.len());
+            buf[..n].copy_from_slice(&self.first[self.first_pos..self.first_pos + n]);
+            self.first_pos += n;
+            n
+        } else {
+            // A download cut short fails to decompress too, which is told by the program's error
+            match self.stdout.read(buf) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.check_status().map_err(io::Error::other)?;
+                    return Err(e);
+                }
+            }
+        };
+        if n == 0 && !buf.is_empty() {
+            self.check_status().map_err(io::Error::other)?;
+        }
+        self.received.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        let _ = self.program.kill();
+        let _ = self.program.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// Serve one request with the response, returning the URL and the request's headers
+    fn serve(status: &str, body: &'static [u8]) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        serve_partial(status, body, body.len())
+    }
+
+    /// Serve one request with a response claiming to be as long as content_length, which it is
+    /// cut short of if longer than body
+    fn serve_partial(
+        status: &str,
+        body: &'static [u8],
+        content_length: usize,
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let status = status.to_string();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let headers: Vec<String> = BufReader::new(&stream)
+                .lines()
+                .map_while(Result::ok)
+                .take_while(|line| !line.is_empty())
+                .collect();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {content_length}\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+            headers
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/report.csv"));
+        assert!(is_url("HTTP://example.com/report.csv"));
+        assert!(!is_url("s3://bucket/report.csv"));
+        assert!(!is_url("http.csv"));
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_download() {
+        let (url, handle) = serve("200 OK", b"a,b\n1,2\n");
+        let mut download = download(&format!("{url}/report.csv?v=1")).unwrap();
+        let received = download.received();
+        let mut content = String::new();
+        download.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "a,b\n1,2\n");
+        assert_eq!(received.load(Ordering::Relaxed), 8);
+        let headers = handle.join().unwrap();
+        assert_eq!(headers[0], "GET /report.csv?v=1 HTTP/1.1");
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_basic_auth() {
+        let (url, handle) = serve("200 OK", b"a\n");
+        let credentials = Some(("ann".to_string(), "s3cret".to_string()));
+        fetch(&url, credentials).unwrap();
+        let headers = handle.join().unwrap();
+        // base64 of ann:s3cret
+        assert!(headers.contains(&"Authorization: Basic YW5uOnMzY3JldA==".to_string()));
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_download_compressed() {
+        let gzipped = std::fs::read("tests/data/cities.csv.gz").unwrap().leak();
+        let (url, handle) = serve("200 OK", gzipped);
+        let mut content = String::new();
+        download(&format!("{url}/cities.csv.gz"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(
+            content,
+            std::fs::read_to_string("tests/data/cities.csv").unwrap()
+        );
+        handle.join().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_download_interrupted() {
+        let (url, handle) = serve_partial("200 OK", b"a,b\n1,2\n", 1000);
+        let mut content = String::new();
+        let result = download(&format!("{url}/report.csv"))
+            .unwrap()
+            .read_to_string(&mut content);
+        // The rows received before are kept
+        assert_eq!(content, "a,b\n1,2\n");
+        let message = result.unwrap_err().to_string();
+        assert!(message.starts_with("Failed to download"), "{message}");
+        handle.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_program_with_long_stderr() {
+        // More messages than a pipe holds, which the program would block on if they weren't read
+        let program = Command::new("sh")
+            .args(["-c", "echo a,b; yes failed | head -c 1000000 >&2; exit 1"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut content = String::new();
+        let result = Download::start(program, "report.csv", |stderr| {
+            CsvlensError::Http("report.csv".to_string(), stderr.to_string())
+        })
+        .unwrap()
+        .read_to_string(&mut content);
+        assert_eq!(content, "a,b\n");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Failed to download report.csv: failed"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs curl, run with --include-ignored"]
+    fn test_download_error() {
+        let (url, handle) = serve("404 Not Found", b"");
+        let result = download(&format!("{url}/missing.csv"));
+        assert!(matches!(
+            result,
+            Err(CsvlensError::Http(_, message))
+                if message == "The requested URL returned error: 404"
+        ));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r#"ann:p"a\ss"#), r#"ann:p\"a\\ss"#);
+    }
+}
@@ -4,8 +4,8 @@ use crate::history::BufferHistoryContainer;
 use crate::util::events::{CsvlensEvent, CsvlensEvents};
 use crate::watch::FileWatcher;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
-use tui_input::Input;
-use tui_input::backend::crossterm::EventHandler;
+use tui_input::backend::crossterm::to_input_request;
+use tui_input::{Input, InputRequest};
 
 pub enum Control {
     ScrollUp,
@@ -23,6 +23,7 @@ pub enum Control {
     ScrollLeftMost,
     ScrollRightMost,
     ScrollTo(usize),
+    ScrollToPercent(u64),
     ScrollToNextFound,
     ScrollToPrevFound,
     IncreaseWidth,
@@ -32,6 +33,7 @@ pub enum Control {
     Filter(String),
     FilterColumns(String),
     FilterLikeCell,
+    FilterUnlikeCell,
     FreezeColumns(usize),
     Quit,
     BufferContent(Input),
@@ -43,13 +45,45 @@ pub enum Control {
     ToggleLineWrap(WrapMode),
     ToggleMark,
     ResetMarks,
+    Compare,
+    StopCompare,
+    ToggleBlock,
+    ToggleRowGroups,
+    ToggleGroup,
+    ToggleAllGroups,
+    FocusHeader,
+    LeaveHeader,
+    Tag(String),
+    FilterTag(String),
+    Command(String),
+    ToggleReviewMode,
+    ToggleHeaders,
+    ShowTables,
+    LoadAllFields,
+    ReviewDone,
+    AbortRead,
+    PasteCell,
     ToggleSort,
     ToggleNaturalSort,
+    ToggleSortIgnoreCase,
     Reset,
     Help,
+    ShowValidationErrors,
+    CycleDecoder,
+    CycleHumanized,
+    ToggleAbbreviate,
+    ToggleColumnGroup,
+    OpenCell,
+    TogglePatterns,
+    ToggleScreenReader,
+    ShowCellDetails,
+    ToggleSchemaSidebar,
+    ShowMemoryUsage,
     UnknownOption(String),
     UserError(String),
     FileChanged,
+    Suspend,
+    Resize,
     Nothing,
 }
 
@@ -69,6 +103,7 @@ pub struct InputHandler {
     mode: InputMode,
     buffer_state: BufferState,
     buffer_history_container: BufferHistoryContainer,
+    undo_stack: Vec<Input>,
 }
 
 impl InputHandler {
@@ -78,18 +113,21 @@ impl InputHandler {
             mode: InputMode::Default,
             buffer_state: BufferState::Inactive,
             buffer_history_container: BufferHistoryContainer::new(),
+            undo_stack: vec![],
         }
     }
 
     pub fn next(&mut self) -> Control {
         match self.events.next().unwrap() {
             CsvlensEvent::Input(key) => self.handle_key(key),
+            CsvlensEvent::Paste(text) => self.insert_text(&text),
             CsvlensEvent::FileChanged => Control::FileChanged,
+            CsvlensEvent::Resize => Control::Resize,
             CsvlensEvent::Tick => Control::Nothing,
         }
     }
 
-    fn handle_key(&mut self, mut key: KeyEvent) -> Control {
+    pub fn handle_key(&mut self, mut key: KeyEvent) -> Control {
         /*
         The shift key modifier is not consistent across platforms.
 
@@ -118,8 +156,19 @@ impl InputHandler {
         } else {
             key.modifiers.remove(KeyModifiers::SHIFT);
         }
+        // Ctrl-Z undoes an edit while typing, and suspends as in a shell otherwise
+        if key.code == KeyCode::Char('z')
+            && key.modifiers == KeyModifiers::CONTROL
+            && !self.is_input_buffering()
+        {
+            return Control::Suspend;
+        }
         if self.is_help_mode() {
             self.handler_help(key)
+        } else if self.mode == InputMode::Popup {
+            self.handler_popup(key)
+        } else if self.mode == InputMode::Header {
+            self.handler_header(key)
         } else if self.is_input_buffering() {
             self.handler_buffering(key)
         } else {
@@ -163,6 +212,10 @@ impl InputHandler {
                     self.init_buffer(InputMode::Option);
                     Control::empty_buffer()
                 }
+                KeyCode::Char(':') => {
+                    self.init_buffer(InputMode::Command);
+                    Control::empty_buffer()
+                }
                 KeyCode::Char('f') => {
                     self.init_buffer(InputMode::FreezeColumns);
                     Control::empty_buffer()
@@ -175,8 +228,24 @@ impl InputHandler {
                 KeyCode::Char('?') => Control::Help,
                 KeyCode::Char('#') => Control::FindLikeCell,
                 KeyCode::Char('@') => Control::FilterLikeCell,
+                KeyCode::Char('!') => Control::FilterUnlikeCell,
                 KeyCode::Char('y') => Control::CopySelection,
                 KeyCode::Char('m') => Control::ToggleMark,
+                KeyCode::Char('c') => Control::Compare,
+                KeyCode::Char('z') => Control::ToggleGroup,
+                KeyCode::Char('s') => {
+                    self.mode = InputMode::Header;
+                    Control::FocusHeader
+                }
+                KeyCode::Char('v') => Control::ShowValidationErrors,
+                KeyCode::Char('o') => Control::OpenCell,
+                KeyCode::Char('i') => Control::ShowCellDetails,
+                KeyCode::Char('x') => Control::ReviewDone,
+                KeyCode::Esc => Control::AbortRead,
+                KeyCode::Char('t') => {
+                    self.init_buffer(InputMode::Tag);
+                    Control::empty_buffer()
+                }
                 _ => Control::Nothing,
             },
             KeyModifiers::SHIFT => match key_event.code {
@@ -185,6 +254,13 @@ impl InputHandler {
                 KeyCode::Char('H') => Control::Help,
                 KeyCode::Char('J') | KeyCode::Down => Control::ToggleSort,
                 KeyCode::Char('M') => Control::ResetMarks,
+                KeyCode::Char('C') => Control::StopCompare,
+                KeyCode::Char('V') => Control::ToggleBlock,
+                KeyCode::Char('Z') => Control::ToggleAllGroups,
+                KeyCode::Char('T') => {
+                    self.init_buffer(InputMode::FilterTag);
+                    Control::empty_buffer()
+                }
                 _ => Control::Nothing,
             },
             KeyModifiers::CONTROL => match key_event.code {
@@ -231,12 +307,22 @@ impl InputHandler {
                 self.reset_buffer();
                 res
             }
+            KeyCode::Char('%') if self.mode == InputMode::GotoLine => {
+                self.buffer_history_container.set(self.mode, input.value());
+                let res = match input.value().parse::<u64>() {
+                    Ok(percent) if percent <= 100 => Control::ScrollToPercent(percent),
+                    _ => Control::BufferReset,
+                };
+                self.reset_buffer();
+                res
+            }
             KeyCode::Up => {
                 let mode = match self.mode {
                     InputMode::Filter => InputMode::Find,
                     _ => self.mode,
                 };
                 if let Some(buf) = self.buffer_history_container.prev(mode) {
+                    self.undo_stack.push(input.clone());
                     self.buffer_state = BufferState::Active(Input::new(buf.clone()));
                     Control::BufferContent(Input::new(buf))
                 } else {
@@ -248,6 +334,7 @@ impl InputHandler {
                     InputMode::Filter => InputMode::Find,
                     _ => self.mode,
                 };
+                self.undo_stack.push(input.clone());
                 if let Some(buf) = self.buffer_history_container.next(mode) {
                     self.buffer_state = BufferState::Active(Input::new(buf.clone()));
                     Control::BufferContent(Input::new(buf))
@@ -266,6 +353,12 @@ impl InputHandler {
                     control = Control::Filter(input.value().to_string());
                 } else if self.mode == InputMode::FilterColumns {
                     control = Control::FilterColumns(input.value().to_string());
+                } else if self.mode == InputMode::Tag {
+                    control = Control::Tag(input.value().to_string());
+                } else if self.mode == InputMode::FilterTag {
+                    control = Control::FilterTag(input.value().to_string());
+                } else if self.mode == InputMode::Command {
+                    control = Control::Command(input.value().to_string());
                 } else {
                     control = Control::BufferReset;
                 }
@@ -279,27 +372,69 @@ impl InputHandler {
                 self.reset_buffer();
                 control
             }
+            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => {
+                Control::PasteCell
+            }
+            // Ctrl-_ (the readline undo) comes through as Ctrl-7 on most terminals
+            KeyCode::Char('z' | '_' | '7') if key_event.modifiers == KeyModifiers::CONTROL => {
+                match self.undo_stack.pop() {
+                    Some(previous) => {
+                        self.buffer_state = BufferState::Active(previous);
+                        self.buffer_changed()
+                    }
+                    None => Control::Nothing,
+                }
+            }
             _ => {
-                if input.handle_event(&Event::Key(key_event)).is_some() {
-                    // Parse immediately for FreezeColumns since it should just be a number
-                    let control = if self.mode == InputMode::FreezeColumns {
-                        let control = if let Ok(n) = input.value().parse::<usize>() {
-                            Control::FreezeColumns(n)
-                        } else {
-                            Control::UserError(format!("Invalid number: {}", input.value()))
-                        };
-                        self.reset_buffer();
-                        control
-                    } else {
-                        Control::BufferContent(input.clone())
-                    };
-                    return control;
+                let request = readline_request(&key_event)
+                    .or_else(|| to_input_request(&Event::Key(key_event)));
+                let before = input.clone();
+                match request.and_then(|request| input.handle(request)) {
+                    Some(changed) => {
+                        if changed.value {
+                            self.undo_stack.push(before);
+                        }
+                        self.buffer_changed()
+                    }
+                    None => Control::Nothing,
                 }
-                Control::Nothing
             }
         }
     }
 
+    /// Insert text at the cursor of the prompt being typed in, e.g. when pasted. Line breaks are
+    /// taken as spaces, so that a pasted line break doesn't submit the prompt halfway.
+    pub fn insert_text(&mut self, text: &str) -> Control {
+        let BufferState::Active(input) = &mut self.buffer_state else {
+            return Control::Nothing;
+        };
+        if self.mode == InputMode::Option || text.is_empty() {
+            return Control::Nothing;
+        }
+        self.undo_stack.push(input.clone());
+        for c in text.lines().collect::<Vec<_>>().join(" ").chars() {
+            input.handle(InputRequest::InsertChar(c));
+        }
+        self.buffer_changed()
+    }
+
+    fn buffer_changed(&mut self) -> Control {
+        let BufferState::Active(input) = &self.buffer_state else {
+            return Control::Nothing;
+        };
+        // Parse immediately for FreezeColumns since it should just be a number
+        if self.mode == InputMode::FreezeColumns {
+            let control = if let Ok(n) = input.value().parse::<usize>() {
+                Control::FreezeColumns(n)
+            } else {
+                Control::UserError(format!("Invalid number: {}", input.value()))
+            };
+            self.reset_buffer();
+            return control;
+        }
+        Control::BufferContent(input.clone())
+    }
+
     fn handler_buffering_option_mode(&mut self, key_event: KeyEvent) -> Control {
         match key_event.code {
             KeyCode::Esc | KeyCode::Backspace | KeyCode::Enter => {
@@ -314,6 +449,62 @@ impl InputHandler {
                 self.reset_buffer();
                 Control::ToggleLineWrap(WrapMode::Words)
             }
+            KeyCode::Char('D') => {
+                self.reset_buffer();
+                Control::CycleDecoder
+            }
+            KeyCode::Char('U') => {
+                self.reset_buffer();
+                Control::CycleHumanized
+            }
+            KeyCode::Char('I') => {
+                self.reset_buffer();
+                Control::ToggleAbbreviate
+            }
+            KeyCode::Char('G') => {
+                self.reset_buffer();
+                Control::ToggleColumnGroup
+            }
+            KeyCode::Char('K') => {
+                self.reset_buffer();
+                Control::ToggleSortIgnoreCase
+            }
+            KeyCode::Char('P') => {
+                self.reset_buffer();
+                Control::TogglePatterns
+            }
+            KeyCode::Char('A') => {
+                self.reset_buffer();
+                Control::ToggleScreenReader
+            }
+            KeyCode::Char('C') => {
+                self.reset_buffer();
+                Control::ToggleSchemaSidebar
+            }
+            KeyCode::Char('M') => {
+                self.reset_buffer();
+                Control::ShowMemoryUsage
+            }
+            KeyCode::Char('R') => {
+                self.reset_buffer();
+                Control::ToggleReviewMode
+            }
+            KeyCode::Char('H') => {
+                self.reset_buffer();
+                Control::ToggleHeaders
+            }
+            KeyCode::Char('T') => {
+                self.reset_buffer();
+                Control::ShowTables
+            }
+            KeyCode::Char('F') => {
+                self.reset_buffer();
+                Control::LoadAllFields
+            }
+            KeyCode::Char('O') => {
+                self.reset_buffer();
+                Control::ToggleRowGroups
+            }
             KeyCode::Char(x) => {
                 self.reset_buffer();
                 Control::UnknownOption(x.to_string())
@@ -331,17 +522,50 @@ impl InputHandler {
         }
     }
 
+    /// Choosing a column to sort by from the header, with s
+    fn handler_header(&mut self, key_event: KeyEvent) -> Control {
+        match (key_event.modifiers, key_event.code) {
+            (KeyModifiers::NONE, KeyCode::Char('h') | KeyCode::Left) => Control::ScrollLeft,
+            (KeyModifiers::NONE, KeyCode::Char('l') | KeyCode::Right) => Control::ScrollRight,
+            (KeyModifiers::CONTROL, KeyCode::Left) => Control::ScrollLeftMost,
+            (KeyModifiers::CONTROL, KeyCode::Right) => Control::ScrollRightMost,
+            (KeyModifiers::NONE, KeyCode::Enter) => Control::ToggleSort,
+            (KeyModifiers::CONTROL, KeyCode::Char('j')) => Control::ToggleNaturalSort,
+            (KeyModifiers::NONE, KeyCode::Char('q' | 's') | KeyCode::Esc) => {
+                self.mode = InputMode::Default;
+                Control::LeaveHeader
+            }
+            _ => Control::Nothing,
+        }
+    }
+
+    fn handler_popup(&mut self, key_event: KeyEvent) -> Control {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Esc => Control::Quit,
+            KeyCode::Char('j') | KeyCode::Down => Control::ScrollDown,
+            KeyCode::Char('k') | KeyCode::Up => Control::ScrollUp,
+            KeyCode::Char('g') | KeyCode::Home => Control::ScrollTop,
+            KeyCode::Char('G') | KeyCode::End => Control::ScrollBottom,
+            KeyCode::PageDown => Control::ScrollPageDown,
+            KeyCode::PageUp => Control::ScrollPageUp,
+            KeyCode::Enter => Control::Select,
+            _ => Control::Nothing,
+        }
+    }
+
     fn is_input_buffering(&self) -> bool {
         matches!(self.buffer_state, BufferState::Active(_))
     }
 
     fn init_buffer(&mut self, mode: InputMode) {
         self.buffer_state = BufferState::Active(Input::default());
+        self.undo_stack.clear();
         self.mode = mode;
     }
 
     fn reset_buffer(&mut self) {
         self.buffer_state = BufferState::Inactive;
+        self.undo_stack.clear();
         self.buffer_history_container.reset_cursors();
         self.mode = InputMode::Default;
     }
@@ -358,7 +582,29 @@ impl InputHandler {
         self.mode = InputMode::Default;
     }
 
+    pub fn enter_popup_mode(&mut self) {
+        self.mode = InputMode::Popup;
+    }
+
+    pub fn exit_popup_mode(&mut self) {
+        self.mode = InputMode::Default;
+    }
+
     fn is_help_mode(&mut self) -> bool {
         self.mode == InputMode::Help
     }
 }
+
+/// Readline keys that tui-input doesn't map itself. Terminals report the Alt key as ALT rather
+/// than META, and Alt-d deletes the next word as in readline.
+fn readline_request(key_event: &KeyEvent) -> Option<InputRequest> {
+    match (key_event.code, key_event.modifiers) {
+        (KeyCode::Char('b'), KeyModifiers::ALT) => Some(InputRequest::GoToPrevWord),
+        (KeyCode::Char('f'), KeyModifiers::ALT) => Some(InputRequest::GoToNextWord),
+        (KeyCode::Char('d'), KeyModifiers::ALT | KeyModifiers::META) => {
+            Some(InputRequest::DeleteNextWord)
+        }
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => Some(InputRequest::DeleteNextChar),
+        _ => None,
+    }
+}
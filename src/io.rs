@@ -1,16 +1,37 @@
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
 
+use crate::compression;
 use crate::csv::{CsvBaseConfig, CsvConfig, CsvlensRecordIterator};
 use crate::errors::{CsvlensError, CsvlensResult};
+use crate::gcs::{self, GcsLocation};
+use crate::http::{self, Download};
+use crate::remote::ObjectReader;
+#[cfg(feature = "s3")]
+use crate::s3;
+use crate::s3::S3Location;
+
+/// Extensions of the formats converted from the whole file rather than read as it comes, which
+/// compressed files named like `events.jsonl.gz` are decompressed in full for
+const WHOLE_FILE_EXTENSIONS: &[&str] = &[
+    "jsonl", "ndjson", "xlsx", "xlsm", "sqlite", "sqlite3", "db", "arrow", "arrows", "feather",
+    "ipc",
+];
 
 pub struct SeekableFile {
     filename: Option<String>,
     inner_file: Option<NamedTempFile>,
     stream_active: Option<Arc<AtomicBool>>,
+    /// Bytes received of a URL being downloaded
+    downloaded: Option<Arc<AtomicU64>>,
+    /// Why streaming the input stopped before its end, e.g. a network error
+    stream_error: Arc<Mutex<Option<String>>>,
+    /// What the input is, e.g. "compressed files", for messages about what it needs
+    kind: &'static str,
 }
 
 impl SeekableFile {
@@ -18,56 +39,74 @@ impl SeekableFile {
         maybe_filename: &Option<String>,
         no_streaming_stdin: bool,
     ) -> CsvlensResult<SeekableFile> {
-        let inner_file = NamedTempFile::new()?;
+        // Decompressed into a file with the same extension, which some formats are told by
+        let whole_file_extension = maybe_filename
+            .as_deref()
+            .and_then(compression::inner_extension)
+            .filter(|e| WHOLE_FILE_EXTENSIONS.contains(&e.as_str()));
+        let inner_file = match &whole_file_extension {
+            Some(extension) => tempfile::Builder::new()
+                .suffix(&format!(".{extension}"))
+                .tempfile()?,
+            None => NamedTempFile::new()?,
+        };
         let inner_file_res;
+        let kind;
         let mut stream_active = None;
+        let mut downloaded = None;
+        let stream_error = Arc::new(Mutex::new(None));
 
-        let mut stream_to_inner_file = || {
+        let mut stream_to_inner_file = |source: Box<dyn Read + Send>| {
             let inner_path = inner_file.path().to_owned();
-
-            // Thread to stream stdin to inner file
-            let stream_active_flag = Arc::new(AtomicBool::new(true));
-            let _stream_active_flag = stream_active_flag.clone();
-            let _inner_path = inner_path.clone();
-            std::thread::spawn(move || {
-                let mut stdin = std::io::stdin();
-                Self::chunked_copy_to_path(&mut stdin, _inner_path).unwrap();
-                _stream_active_flag.store(false, Ordering::Relaxed);
-            });
-            stream_active = Some(stream_active_flag);
-
-            // Thread to wait for the headers to be available. This is needed because once App is
-            // started, it will immediately read the headers from the file. For slowly streaming
-            // inputs, the headers might not be available yet.
-            let _stream_active = stream_active.clone();
-            let handle = std::thread::spawn(move || {
-                // The delimiter here can be just an approximation since we just need to make sure
-                // the header row as a whole is ready. Set no_headers: true to yield the header row
-                // as a record.
-                let base_config = CsvBaseConfig::new(b',', true);
-                let path = inner_path.to_str().unwrap();
-                let config = CsvConfig::new(path, _stream_active, base_config);
-                let mut record_iterator = CsvlensRecordIterator::new(Arc::new(config)).unwrap();
-                record_iterator.next();
-            });
-            handle.join().unwrap();
+            stream_active = Some(Self::stream_to_path(source, inner_path, &stream_error));
         };
 
-        let copy_to_inner_file = || {
+        let copy_to_inner_file = |mut source: Box<dyn Read + Send>| {
             let inner_path = inner_file.path().to_owned();
-            let mut stdin = std::io::stdin();
-            Self::chunked_copy_to_path(&mut stdin, inner_path).unwrap();
+            Self::chunked_copy_to_path(&mut source, inner_path).map(|_| ())
         };
 
-        let mut prepare_inner_file = || {
+        let mut prepare_inner_file = |source: Box<dyn Read + Send>| {
             if no_streaming_stdin {
-                copy_to_inner_file()
+                copy_to_inner_file(source)
             } else {
-                stream_to_inner_file()
+                stream_to_inner_file(source);
+                Ok(())
             }
         };
 
-        if let Some(filename) = maybe_filename {
+        if let Some(location) = maybe_filename.as_deref().and_then(S3Location::parse) {
+            // Objects are read where they are with range requests, like a file, once it is known
+            // they can be
+            Self::open_s3(&location)?;
+            inner_file_res = None;
+            kind = "S3 objects";
+        } else if let Some(location) = maybe_filename.as_deref().and_then(GcsLocation::parse)
+            && !location.is_compressed()
+        {
+            gcs::open(&location)?;
+            inner_file_res = None;
+            kind = "GCS objects";
+        } else if let Some(download) = Self::download(maybe_filename.as_deref())? {
+            // Streamed like stdin too, with the bytes received shown meanwhile
+            downloaded = Some(download.received());
+            prepare_inner_file(Box::new(download))?;
+            inner_file_res = Some(inner_file);
+            kind = "downloads";
+        } else if let Some(filename) = maybe_filename
+            && let Some(format) = compression::detect(filename).unwrap_or(None)
+        {
+            // Decompressed like stdin is read, so rows show up as they are decompressed, unless
+            // the format needs all of it
+            let source = compression::decompress(filename, format)?;
+            if whole_file_extension.is_some() {
+                copy_to_inner_file(source)?;
+            } else {
+                prepare_inner_file(source)?;
+            }
+            inner_file_res = Some(inner_file);
+            kind = "compressed files";
+        } else if let Some(filename) = maybe_filename {
             let mut f = File::open(filename).map_err(|e| match e.kind() {
                 std::io::ErrorKind::NotFound => CsvlensError::FileNotFound(filename.clone()),
                 _ => e.into(),
@@ -75,21 +114,52 @@ impl SeekableFile {
             // If not seekable, it most likely is due to process substitution using
             // pipe - write out to a temp file to make it seekable
             if f.seek(SeekFrom::Start(0)).is_err() {
-                prepare_inner_file();
+                prepare_inner_file(Box::new(std::io::stdin()))?;
                 inner_file_res = Some(inner_file);
+                kind = "piped input";
             } else {
                 inner_file_res = None;
+                kind = "files";
             }
         } else {
             // Handle input from stdin
-            prepare_inner_file();
+            prepare_inner_file(Box::new(std::io::stdin()))?;
             inner_file_res = Some(inner_file);
+            kind = "piped input";
         }
 
         Ok(SeekableFile {
             filename: maybe_filename.clone(),
             inner_file: inner_file_res,
             stream_active,
+            downloaded,
+            stream_error,
+            kind,
+        })
+    }
+
+    /// Input read from the source as it comes like stdin, e.g. the rows of a database as CSV
+    pub fn from_source(
+        mut source: Box<dyn Read + Send>,
+        no_streaming: bool,
+        kind: &'static str,
+    ) -> CsvlensResult<SeekableFile> {
+        let inner_file = NamedTempFile::new()?;
+        let stream_error = Arc::new(Mutex::new(None));
+        let stream_active = if no_streaming {
+            Self::chunked_copy_to_path(&mut source, inner_file.path())?;
+            None
+        } else {
+            let inner_path = inner_file.path().to_owned();
+            Some(Self::stream_to_path(source, inner_path, &stream_error))
+        };
+        Ok(SeekableFile {
+            filename: None,
+            inner_file: Some(inner_file),
+            stream_active,
+            downloaded: None,
+            stream_error,
+            kind,
         })
     }
 
@@ -106,6 +176,91 @@ impl SeekableFile {
         &self.stream_active
     }
 
+    /// What the input is, e.g. "piped input", for messages about options it can't be used with
+    /// while streamed
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// Why streaming the input stopped before its end, once it has
+    pub fn stream_error(&self) -> Option<String> {
+        self.stream_error.lock().unwrap().clone()
+    }
+
+    /// Bytes received so far while a URL is still being downloaded
+    pub fn download_progress(&self) -> Option<u64> {
+        let downloading = self
+            .stream_active
+            .as_ref()
+            .is_some_and(|active| active.load(Ordering::Relaxed));
+        let downloaded = self.downloaded.as_ref()?;
+        downloading.then(|| downloaded.load(Ordering::Relaxed))
+    }
+
+    /// Download of a URL or a compressed Google Cloud Storage object, if the file name is one
+    fn download(maybe_filename: Option<&str>) -> CsvlensResult<Option<Download>> {
+        let Some(filename) = maybe_filename else {
+            return Ok(None);
+        };
+        if http::is_url(filename) {
+            Ok(Some(http::download(filename)?))
+        } else if let Some(location) = GcsLocation::parse(filename) {
+            Ok(Some(gcs::download(&location)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    #[cfg(feature = "s3")]
+    fn open_s3(location: &S3Location) -> CsvlensResult<()> {
+        s3::open(location).map(|_| ())
+    }
+
+    #[cfg(not(feature = "s3"))]
+    fn open_s3(location: &S3Location) -> CsvlensResult<()> {
+        Err(CsvlensError::S3(format!(
+            "{location}: csvlens was built without S3 support (the s3 feature)"
+        )))
+    }
+
+    /// Copy the source to the file on a thread, returning once the headers are there with the flag
+    /// of whether it still is
+    fn stream_to_path(
+        mut source: Box<dyn Read + Send>,
+        inner_path: PathBuf,
+        stream_error: &Arc<Mutex<Option<String>>>,
+    ) -> Arc<AtomicBool> {
+        // Thread to stream the input to inner file
+        let stream_active_flag = Arc::new(AtomicBool::new(true));
+        let _stream_active_flag = stream_active_flag.clone();
+        let _inner_path = inner_path.clone();
+        let _stream_error = stream_error.clone();
+        std::thread::spawn(move || {
+            // The rows received so far stay viewable, with the error shown
+            if let Err(e) = Self::chunked_copy_to_path(&mut source, _inner_path) {
+                _stream_error.lock().unwrap().replace(e.to_string());
+            }
+            _stream_active_flag.store(false, Ordering::Relaxed);
+        });
+        let stream_active = Some(stream_active_flag.clone());
+
+        // Thread to wait for the headers to be available. This is needed because once App is
+        // started, it will immediately read the headers from the file. For slowly streaming
+        // inputs, the headers might not be available yet.
+        let handle = std::thread::spawn(move || {
+            // The delimiter here can be just an approximation since we just need to make sure
+            // the header row as a whole is ready. Set no_headers: true to yield the header row
+            // as a record.
+            let base_config = CsvBaseConfig::new(b',', true);
+            let path = inner_path.to_str().unwrap();
+            let config = CsvConfig::new(path, stream_active, base_config);
+            let mut record_iterator = CsvlensRecordIterator::new(Arc::new(config)).unwrap();
+            record_iterator.next();
+        });
+        handle.join().unwrap();
+        stream_active_flag
+    }
+
     fn chunked_copy<R: Read, W: Write>(source: &mut R, dest: &mut W) -> CsvlensResult<usize> {
         let mut total_copied = 0;
         let mut buffer = vec![0; 1_000_000];
@@ -114,8 +269,9 @@ impl SeekableFile {
             if n == 0 {
                 break;
             }
-            let n_written = dest.write(&buffer[..n])?;
-            total_copied += n_written;
+            // A single write can be partial, which would silently drop data from the spool
+            dest.write_all(&buffer[..n])?;
+            total_copied += n;
         }
         Ok(total_copied)
     }
@@ -133,3 +289,82 @@ impl SeekableFile {
         SeekableFile::chunked_copy(source, &mut file)
     }
 }
+
+/// Input read by its name: a file, or an S3 or GCS object read with range requests as parts of it
+/// are needed
+pub enum InputFile {
+    File(File),
+    Object(ObjectReader),
+}
+
+impl Read for InputFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            InputFile::File(f) => f.read(buf),
+            InputFile::Object(o) => o.read(buf),
+        }
+    }
+}
+
+impl Seek for InputFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            InputFile::File(f) => f.seek(pos),
+            InputFile::Object(o) => o.seek(pos),
+        }
+    }
+}
+
+/// Open the input of the name for reading, an `s3://` or `gs://` URL as the object
+pub fn open_input(filename: &str) -> std::io::Result<InputFile> {
+    #[cfg(feature = "s3")]
+    if let Some(location) = S3Location::parse(filename) {
+        let object = s3::open(&location).map_err(std::io::Error::other)?;
+        return Ok(InputFile::Object(object.reader()));
+    }
+    if let Some(location) = GcsLocation::parse(filename) {
+        let object = gcs::open(&location).map_err(std::io::Error::other)?;
+        return Ok(InputFile::Object(object.reader()));
+    }
+    File::open(filename).map(InputFile::File)
+}
+
+/// Size in bytes of the input of the name
+pub fn input_len(filename: &str) -> std::io::Result<u64> {
+    #[cfg(feature = "s3")]
+    if let Some(location) = S3Location::parse(filename) {
+        let object = s3::open(&location).map_err(std::io::Error::other)?;
+        return Ok(object.len());
+    }
+    if let Some(location) = GcsLocation::parse(filename) {
+        let object = gcs::open(&location).map_err(std::io::Error::other)?;
+        return Ok(object.len());
+    }
+    Ok(std::fs::metadata(filename)?.len())
+}
+
+/// Text in the system clipboard, e.g. a table copied from a spreadsheet or a web page
+pub fn clipboard_text() -> CsvlensResult<String> {
+    #[cfg(feature = "clipboard")]
+    {
+        let text = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(|e| CsvlensError::Clipboard(e.to_string()))?;
+        if text.trim().is_empty() {
+            return Err(CsvlensError::Clipboard("no text to view".to_string()));
+        }
+        Ok(text)
+    }
+    #[cfg(not(feature = "clipboard"))]
+    Err(CsvlensError::Clipboard(
+        "csvlens was built without clipboard support".to_string(),
+    ))
+}
+
+/// Temporary file with the given text, to view content that isn't saved anywhere
+pub fn scratch_file(text: &str) -> CsvlensResult<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
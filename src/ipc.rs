@@ -0,0 +1,442 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::fmt::Write;
+use std::fs::File;
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, RecordBatch};
+use arrow::buffer::Buffer;
+use arrow::compute::cast;
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::StreamDecoder;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use csv::{ByteRecord, Position, StringRecord};
+
+/// At the start and end of files in the Arrow IPC file format, which Feather V2 files are too
+const MAGIC: &[u8] = b"ARROW1";
+
+const EXTENSIONS: &[&str] = &["arrow", "arrows", "feather", "ipc"];
+
+/// Values are shown as arrow's CSV writer writes them: nulls empty, dates and timestamps in ISO
+/// 8601 and binary values in hex
+const FORMAT_OPTIONS: FormatOptions<'static> = FormatOptions::new().with_display_error(true);
+
+/// Whether the file is in the Arrow IPC file or streaming format
+pub fn is_arrow(filename: &str) -> bool {
+    let mut start = vec![];
+    let has_magic = File::open(filename)
+        .is_ok_and(|f| f.take(MAGIC.len() as u64).read_to_end(&mut start).is_ok())
+        && start == MAGIC;
+    has_magic
+        || Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// The record batches of an Arrow IPC file, read into memory. Rows are read from them as records
+/// of a file with a header, where the position of a record is its number with the header at 0,
+/// in place of a byte offset.
+pub struct ArrowTable {
+    path: String,
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+    /// Index of the first row of each batch
+    batch_starts: Vec<usize>,
+    num_rows: usize,
+    /// Rows of the file read, as a half-open range of 0-based row indices
+    rows: (usize, Option<usize>),
+}
+
+impl ArrowTable {
+    /// Read all record batches of the file, in the file or the streaming format
+    pub fn read(filename: &str) -> CsvlensResult<ArrowTable> {
+        let error = |e: ArrowError| CsvlensError::ArrowIpc(filename.to_string(), e.to_string());
+        let data = Buffer::from(std::fs::read(filename)?);
+        // The file format holds a stream after the magic number and its padding to 8 bytes,
+        // followed by a footer repeating what the stream has, the length of the footer and the
+        // magic number again
+        let mut stream = if data.starts_with(MAGIC) {
+            let footer_end = data.len().saturating_sub(10);
+            let footer_len = data
+                .get(footer_end..footer_end + 4)
+                .filter(|_| data.len() >= 18 && data.ends_with(MAGIC))
+                .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+                .filter(|len| len + 8 <= footer_end)
+                .ok_or_else(|| error(ArrowError::IpcError("the file is truncated".to_string())))?;
+            data.slice_with_length(8, footer_end - footer_len - 8)
+        } else {
+            data
+        };
+        // Messages are decoded from the data read rather than read into buffers of the lengths
+        // they declare, so that a corrupted length is found to be past the end of the file
+        // instead of being allocated. Arrow panics on offsets past the end of a message, which is
+        // caught without the hook, e.g. leaving the screen when the file is reloaded.
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let decoded = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut decoder = StreamDecoder::new();
+            let mut batches = vec![];
+            while !stream.is_empty() {
+                if let Some(batch) = decoder.decode(&mut stream)? {
+                    batches.push(batch);
+                }
+            }
+            decoder.finish()?;
+            let schema = decoder
+                .schema()
+                .ok_or_else(|| ArrowError::IpcError("the file is empty".to_string()))?;
+            Ok((schema, batches))
+        }));
+        panic::set_hook(hook);
+        let (schema, batches) = decoded
+            .unwrap_or_else(|_| Err(ArrowError::IpcError("the file is corrupted".to_string())))
+            .map_err(error)?;
+        // Columns of types that can't be shown are found now rather than while scrolling
+        for column in batches.first().iter().flat_map(|b| b.columns()) {
+            ArrayFormatter::try_new(column.as_ref(), &FORMAT_OPTIONS).map_err(error)?;
+        }
+        Ok(ArrowTable::new(filename, schema, batches, (0, None)))
+    }
+
+    fn new(
+        path: &str,
+        schema: SchemaRef,
+        batches: Vec<RecordBatch>,
+        rows: (usize, Option<usize>),
+    ) -> ArrowTable {
+        let mut batch_starts = Vec::with_capacity(batches.len());
+        let mut num_rows = 0;
+        for batch in &batches {
+            batch_starts.push(num_rows);
+            num_rows += batch.num_rows();
+        }
+        ArrowTable {
+            path: path.to_string(),
+            schema,
+            batches,
+            batch_starts,
+            num_rows,
+            rows,
+        }
+    }
+
+    /// Only the rows from start to end, given as 0-based row indices
+    pub fn slice(&self, start: usize, end: Option<usize>) -> ArrowTable {
+        let end = end.map_or(self.num_rows, |e| e.min(self.num_rows));
+        let start = start.min(end);
+        let mut batches = vec![];
+        for (batch, batch_start) in self.batches.iter().zip(&self.batch_starts) {
+            let batch_end = batch_start + batch.num_rows();
+            if batch_end <= start || *batch_start >= end {
+                continue;
+            }
+            let offset = start.saturating_sub(*batch_start);
+            let len = batch_end.min(end) - batch_start - offset;
+            batches.push(batch.slice(offset, len));
+        }
+        let rows = (self.rows.0 + start, Some(self.rows.0 + end));
+        ArrowTable::new(&self.path, self.schema.clone(), batches, rows)
+    }
+
+    /// The file read again as it is now, with the same rows
+    pub fn reload(&self) -> CsvlensResult<ArrowTable> {
+        let table = ArrowTable::read(&self.path)?;
+        Ok(match self.rows {
+            (0, None) => table,
+            (start, end) => table.slice(start, end),
+        })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Number of records, i.e. positions, including the header
+    pub fn num_records(&self) -> u64 {
+        self.num_rows as u64 + 1
+    }
+
+    pub fn headers(&self) -> StringRecord {
+        self.schema.fields().iter().map(|f| f.name()).collect()
+    }
+
+    /// Read the values of a row into the record, or return false past the last row
+    pub fn read_row(&self, index: usize, record: &mut StringRecord) -> CsvlensResult<bool> {
+        if index >= self.num_rows {
+            return Ok(false);
+        }
+        let batch_index = self.batch_starts.partition_point(|start| *start <= index) - 1;
+        let batch = &self.batches[batch_index];
+        let row = index - self.batch_starts[batch_index];
+        record.clear();
+        let mut value = String::new();
+        for column in batch.columns() {
+            value.clear();
+            let formatter = ArrayFormatter::try_new(column.as_ref(), &FORMAT_OPTIONS)?;
+            write!(value, "{}", formatter.value(row)).ok();
+            record.push_field(&value);
+        }
+        Ok(true)
+    }
+
+    /// The columns in projection of each batch, cast to their types in the schema, e.g. numbers
+    /// to be sorted as floats
+    pub fn batches(
+        &self,
+        schema: Arc<Schema>,
+        projection: Vec<usize>,
+    ) -> impl Iterator<Item = Result<RecordBatch, ArrowError>> + use<'_> {
+        self.batches.iter().map(move |batch| {
+            let fields: Vec<_> = projection
+                .iter()
+                .map(|i| schema.field(*i).clone())
+                .collect();
+            let columns = projection
+                .iter()
+                .zip(&fields)
+                .map(|(i, field)| match batch.column(*i) {
+                    c if c.data_type() == field.data_type() => Ok(c.clone()),
+                    c => cast(c, field.data_type()),
+                })
+                .collect::<Result<Vec<ArrayRef>, _>>()?;
+            RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        })
+    }
+
+    /// Approximate number of bytes used by the batches
+    pub fn memory_usage(&self) -> usize {
+        self.batches.iter().map(|b| b.get_array_memory_size()).sum()
+    }
+}
+
+/// Reader of the rows of a table as records, like a csv reader of a file with a header
+pub struct TableReader {
+    table: Arc<ArrowTable>,
+    has_headers: bool,
+    headers: StringRecord,
+    byte_headers: ByteRecord,
+    /// Position of the next record, 0 being the header
+    next: u64,
+    /// Whether the header is read as a record from the start, as after seeking there
+    read_header: bool,
+    position: Position,
+}
+
+impl TableReader {
+    pub fn new(table: Arc<ArrowTable>, has_headers: bool) -> TableReader {
+        let headers = table.headers();
+        TableReader {
+            table,
+            has_headers,
+            byte_headers: headers.as_byte_record().clone(),
+            headers,
+            next: 0,
+            read_header: !has_headers,
+            position: Position::new(),
+        }
+    }
+
+    pub fn headers(&self) -> &StringRecord {
+        &self.headers
+    }
+
+    pub fn byte_headers(&self) -> &ByteRecord {
+        &self.byte_headers
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Go to a position, of which only the byte offset is used, being the record number
+    pub fn seek(&mut self, position: &Position) {
+        self.next = position.byte().min(self.table.num_records());
+        self.read_header = true;
+        self.set_position();
+    }
+
+    pub fn read_record(&mut self, record: &mut StringRecord) -> CsvlensResult<bool> {
+        if self.next == 0 {
+            self.next = 1;
+            if self.read_header || !self.has_headers {
+                record.clone_from(&self.headers);
+                self.set_position();
+                return Ok(true);
+            }
+        }
+        let has_row = self.table.read_row(self.next as usize - 1, record)?;
+        if has_row {
+            self.next += 1;
+        }
+        self.set_position();
+        Ok(has_row)
+    }
+
+    fn set_position(&mut self) {
+        let mut position = Position::new();
+        position
+            .set_byte(self.next)
+            .set_line(self.next + 1)
+            .set_record(self.next);
+        self.position = position;
+    }
+}
+
+impl Iterator for TableReader {
+    type Item = CsvlensResult<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = StringRecord::new();
+        match self.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow::datatypes::DataType;
+    use tempfile::NamedTempFile;
+
+    fn rows(filename: &str) -> Vec<Vec<String>> {
+        let table = Arc::new(ArrowTable::read(filename).unwrap());
+        let mut rows = vec![table.headers().iter().map(String::from).collect()];
+        for record in TableReader::new(table, true) {
+            rows.push(record.unwrap().iter().map(String::from).collect());
+        }
+        rows
+    }
+
+    #[test]
+    fn test_is_arrow() {
+        assert!(is_arrow("tests/data/places.feather"));
+        assert!(is_arrow("tests/data/places.arrows"));
+        assert!(!is_arrow("tests/data/cities.csv"));
+        assert!(!is_arrow("tests/data/shop.sqlite"));
+    }
+
+    #[test]
+    fn test_read() {
+        let expected = [
+            "id,city,population,area,capital,founded,continent,updated,flag",
+            "1,London,8982000,1572.0,true,1970-01-01,Europe,2023-11-14T22:13:20,0102",
+            "2,Tōkyō,,2194.07,true,1969-12-31,Asia,2023-11-14T22:14:20,",
+            "3,Sydney,5312000,12368.0,false,1971-01-01,Oceania,2023-11-14T22:15:20,",
+            "4,Osaka; Japan,2691000,225.2,false,1972-01-01,Asia,2023-11-14T22:16:20,ff",
+            "5,Paris,2161000,105.4,true,2022-01-08,Europe,2023-11-14T22:17:20,00",
+        ];
+        let expected: Vec<Vec<String>> = expected
+            .iter()
+            .map(|line| line.split(',').map(|v| v.replace(';', ",")).collect())
+            .collect();
+        // The file format and the streaming format hold the same batches
+        assert_eq!(rows("tests/data/places.feather"), expected);
+        assert_eq!(rows("tests/data/places.arrows"), expected);
+    }
+
+    #[test]
+    fn test_slice() {
+        let table = ArrowTable::read("tests/data/places.feather").unwrap();
+        let sliced = Arc::new(table.slice(1, Some(3)));
+        assert_eq!(sliced.num_rows(), 2);
+        let cities: Vec<String> = TableReader::new(sliced.clone(), true)
+            .map(|r| r.unwrap()[1].to_string())
+            .collect();
+        assert_eq!(cities, vec!["Tōkyō", "Sydney"]);
+        assert_eq!(sliced.reload().unwrap().num_rows(), 2);
+    }
+
+    #[test]
+    fn test_seek() {
+        let table = Arc::new(ArrowTable::read("tests/data/places.feather").unwrap());
+        let mut reader = TableReader::new(table, true);
+        let mut record = StringRecord::new();
+        let mut position = Position::new();
+        position.set_byte(4);
+        reader.seek(&position);
+        assert!(reader.read_record(&mut record).unwrap());
+        assert_eq!(&record[1], "Osaka, Japan");
+        assert_eq!(reader.position().byte(), 5);
+        // The header is read as a record after seeking to the start
+        reader.seek(&Position::new());
+        assert!(reader.read_record(&mut record).unwrap());
+        assert_eq!(&record[0], "id");
+    }
+
+    #[test]
+    fn test_batches() {
+        let table = ArrowTable::read("tests/data/places.feather").unwrap();
+        let fields: Vec<_> = table
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| match f.name().as_str() {
+                "id" => f.as_ref().clone().with_data_type(DataType::Float64),
+                _ => f.as_ref().clone(),
+            })
+            .collect();
+        let batches: Vec<RecordBatch> = table
+            .batches(Arc::new(Schema::new(fields)), vec![0, 2])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(batches[0].num_columns(), 2);
+        assert_eq!(batches[0].column(0).data_type(), &DataType::Float64);
+        assert_eq!(batches[0].schema().field(1).name(), "population");
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        let mut file = NamedTempFile::new().unwrap();
+        let data = std::fs::read("tests/data/places.feather").unwrap();
+        std::io::Write::write_all(&mut file, &data[..600]).unwrap();
+        let filename = file.path().to_str().unwrap();
+        assert!(matches!(
+            ArrowTable::read(filename),
+            Err(CsvlensError::ArrowIpc(_, message)) if message.contains("truncated")
+        ));
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let data = std::fs::read("tests/data/places.feather").unwrap();
+        let file = NamedTempFile::new().unwrap();
+        let filename = file.path().to_str().unwrap();
+        // Cut anywhere
+        for len in 0..data.len() {
+            std::fs::write(filename, &data[..len]).unwrap();
+            assert!(
+                matches!(ArrowTable::read(filename), Err(CsvlensError::ArrowIpc(..))),
+                "cut at {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_corrupted() {
+        let data = std::fs::read("tests/data/places.arrows").unwrap();
+        let file = NamedTempFile::new().unwrap();
+        let filename = file.path().to_str().unwrap();
+        // Lengths, offsets and indices are checked rather than trusted
+        for pos in 0..data.len() {
+            let mut corrupted = data.clone();
+            corrupted[pos] = 0xff;
+            std::fs::write(filename, &corrupted).unwrap();
+            if let Ok(table) = ArrowTable::read(filename) {
+                TableReader::new(Arc::new(table), true).for_each(drop);
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+/// A JSON object with its keys in the order they are in the line, which a serde_json Map doesn't
+/// keep
+struct Object(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ObjectVisitor;
+
+        impl<'de> Visitor<'de> for ObjectVisitor {
+            type Value = Object;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+                let mut entries = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Object(entries))
+            }
+        }
+
+        deserializer.deserialize_map(ObjectVisitor)
+    }
+}
+
+/// Whether the file is JSON Lines, i.e. its first line is a JSON object
+pub fn is_json_lines(filename: &str) -> bool {
+    let Ok(file) = File::open(filename) else {
+        return false;
+    };
+    let first_line = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .find(|line| !line.trim().is_empty());
+    first_line.is_some_and(|line| {
+        line.trim_start().starts_with('{') && serde_json::from_str::<Object>(&line).is_ok()
+    })
+}
+
+/// A CSV file with a column for each top-level key of the objects, in the order the keys are
+/// first seen, and a row for each object. Values that are neither strings, numbers nor booleans
+/// are kept as JSON text, and keys an object doesn't have are empty like nulls.
+pub fn to_csv(filename: &str) -> CsvlensResult<NamedTempFile> {
+    // All the keys are needed before the header can be written
+    let mut keys: Vec<String> = vec![];
+    let mut key_indices: HashMap<String, usize> = HashMap::new();
+    for_each_object(filename, |object| {
+        for (key, _) in object.0 {
+            if !key_indices.contains_key(&key) {
+                key_indices.insert(key.clone(), keys.len());
+                keys.push(key);
+            }
+        }
+        Ok(())
+    })?;
+
+    let file = NamedTempFile::new()?;
+    let mut writer = ::csv::Writer::from_path(file.path())?;
+    writer.write_record(&keys)?;
+    let mut record = vec![String::new(); keys.len()];
+    for_each_object(filename, |object| {
+        record.iter_mut().for_each(String::clear);
+        for (key, value) in object.0 {
+            record[key_indices[&key]] = match value {
+                Value::Null => String::new(),
+                Value::String(s) => s,
+                value => value.to_string(),
+            };
+        }
+        writer.write_record(&record)?;
+        Ok(())
+    })?;
+    writer.flush()?;
+    Ok(file)
+}
+
+fn for_each_object(
+    filename: &str,
+    mut f: impl FnMut(Object) -> CsvlensResult<()>,
+) -> CsvlensResult<()> {
+    let reader = BufReader::new(File::open(filename)?);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object = serde_json::from_str::<Object>(&line)
+            .map_err(|e| CsvlensError::JsonLines(i + 1, e.to_string()))?;
+        f(object)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_json_lines() {
+        assert!(is_json_lines("tests/data/events.jsonl"));
+        assert!(!is_json_lines("tests/data/cities.csv"));
+        assert!(!is_json_lines("tests/data/empty.csv"));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let file = to_csv("tests/data/events.jsonl").unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content.lines().collect::<Vec<_>>(),
+            vec![
+                "time,level,message,user,tags",
+                "10:00,info,\"Started, listening on :8080\",,",
+                "10:01,warn,Slow request,42,",
+                "10:02,error,Request failed,7,\"[\"\"db\"\",\"\"timeout\"\"]\"",
+                "10:03,info,,,",
+                "10:04,debug,\"{\"\"depth\"\":2}\",,",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let mut file = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"{\"a\": 1}\n{\"a\": \n").unwrap();
+        assert!(matches!(
+            to_csv(file.path().to_str().unwrap()),
+            Err(CsvlensError::JsonLines(2, _))
+        ));
+    }
+}
@@ -38,27 +38,80 @@
 //! }
 //! ```
 mod app;
+mod binary;
+mod color_rule;
+mod column_counts;
+mod column_groups;
 mod columns_filter;
+mod command;
 mod common;
+mod compression;
+mod config;
+mod console;
 mod csv;
+mod date;
+mod decode;
 mod delimiter;
+mod dialect;
+mod encoding;
 pub mod errors;
+mod export;
+mod expr;
 mod find;
+mod footer;
+mod format;
+mod gcs;
+mod geo;
+#[cfg(any(test, feature = "harness"))]
+pub mod harness;
+mod header;
+mod headless;
 mod help;
 mod history;
+mod http;
 mod input;
 mod io;
+mod ipc;
+mod jsonl;
+mod links;
+mod loader;
+mod locale;
+mod memory;
+mod messages;
+mod metadata;
+mod number;
+mod osc;
+mod outline;
+mod popup;
+mod prefetch;
+mod presets;
+mod profile;
+mod recognize;
+mod remote;
+mod resume;
 mod runner;
+mod s3;
+mod sidebar;
+mod sketch;
+mod slice;
 mod sort;
+mod sqlite;
+mod subheader;
+mod tables;
+mod tags;
 mod theme;
+mod timings;
 mod ui;
 mod util;
+mod validate;
 mod view;
 mod watch;
 mod wrap;
+mod xlsx;
 
 pub use app::WrapMode;
 pub use runner::CsvlensOptions;
+pub use runner::default_args;
 pub use runner::run_csvlens;
 pub use runner::run_csvlens_with_options;
 
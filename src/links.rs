@@ -0,0 +1,141 @@
+use regex::Regex;
+use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+
+static URL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?i)(https?|ftp|file)://[^\s]+$").unwrap());
+
+/// Absolute or relative paths, with forward or back slashes, e.g. `~/data.csv`, `..\logs\a.log`,
+/// `C:\logs\a.log` or the UNC path of a share, `\\server\share\a.log`
+static PATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(~?/|~\\|\.\.?[/\\]|[A-Za-z]:[/\\]|\\\\[^\\\s]+\\)[^\t\r\n]*$").unwrap()
+});
+
+/// Something in a cell that can be opened externally
+#[derive(Debug, PartialEq)]
+pub enum Link {
+    Url(String),
+    Path(String),
+}
+
+impl Link {
+    pub fn detect(value: &str) -> Option<Link> {
+        let value = value.trim();
+        if URL_REGEX.is_match(value) {
+            Some(Link::Url(value.to_string()))
+        } else if PATH_REGEX.is_match(value) {
+            Some(Link::Path(value.to_string()))
+        } else {
+            None
+        }
+    }
+
+    fn target(&self) -> String {
+        match self {
+            Link::Url(url) => url.clone(),
+            Link::Path(path) => match (path.get(..2), home_dir()) {
+                (Some("~/" | "~\\"), Some(home)) => format!("{home}{}", &path[1..]),
+                _ => path.clone(),
+            },
+        }
+    }
+
+    /// Open the link with the given command, or the platform default. `{}` in the command is
+    /// replaced with the link, otherwise it is passed as the last argument.
+    pub fn open(&self, open_command: Option<&str>) -> std::io::Result<()> {
+        let target = self.target();
+        if let Link::Path(_) = self
+            && !std::path::Path::new(&target).exists()
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{target} does not exist"),
+            ));
+        }
+        let mut args: Vec<String> = match open_command {
+            Some(command) => command.split_whitespace().map(|s| s.to_string()).collect(),
+            None => default_open_command(),
+        };
+        if args.iter().any(|a| a.contains("{}")) {
+            args = args.iter().map(|a| a.replace("{}", &target)).collect();
+        } else {
+            args.push(target);
+        }
+        let Some((program, args)) = args.split_first() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "empty open command",
+            ));
+        };
+        // Don't wait for the program so that the UI stays responsive
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(|_| ())
+    }
+}
+
+/// The home directory `~` stands for, which Windows gives in USERPROFILE rather than HOME
+fn home_dir() -> Option<String> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var(var).ok()
+}
+
+fn default_open_command() -> Vec<String> {
+    if cfg!(target_os = "macos") {
+        vec!["open".to_string()]
+    } else if cfg!(windows) {
+        ["cmd", "/C", "start", ""]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    } else {
+        vec!["xdg-open".to_string()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Link::detect("https://example.com/a?b=c"),
+            Some(Link::Url("https://example.com/a?b=c".to_string()))
+        );
+        assert_eq!(
+            Link::detect(" ./artifacts/build.log "),
+            Some(Link::Path("./artifacts/build.log".to_string()))
+        );
+        assert_eq!(
+            Link::detect("~/data.csv"),
+            Some(Link::Path("~/data.csv".to_string()))
+        );
+        assert_eq!(
+            Link::detect(r"C:\logs\app.log"),
+            Some(Link::Path(r"C:\logs\app.log".to_string()))
+        );
+        assert_eq!(
+            Link::detect(r"\\fileserver\reports\q3.xlsx"),
+            Some(Link::Path(r"\\fileserver\reports\q3.xlsx".to_string()))
+        );
+        assert_eq!(
+            Link::detect(r"..\logs\app.log"),
+            Some(Link::Path(r"..\logs\app.log".to_string()))
+        );
+        assert_eq!(Link::detect(r"\\"), None);
+        assert_eq!(Link::detect(r"\N"), None);
+        assert_eq!(Link::detect("see https://example.com"), None);
+        assert_eq!(Link::detect("1/2"), None);
+    }
+
+    #[test]
+    fn test_open_missing_path() {
+        let link = Link::Path("./no/such/file".to_string());
+        assert!(link.open(Some("true")).is_err());
+    }
+}
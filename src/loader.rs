@@ -0,0 +1,193 @@
+use crate::csv::{CsvLensReader, GetRowsStats, Row};
+use crate::errors::CsvlensResult;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type LoadResult = CsvlensResult<(Vec<Row>, GetRowsStats)>;
+
+/// Reads the rows to show on a background thread, so that a read stalling on slow storage (e.g. a
+/// network filesystem) leaves the interface responsive instead of freezing it. Reads that don't
+/// finish within the given timeout are left pending, to be polled for or abandoned.
+pub struct RowsLoader {
+    last_id: AtomicU64,
+    /// Reads to do as (id, record indices), of which the thread only does the latest
+    requests: Sender<(u64, Vec<u64>)>,
+    results: Receiver<(u64, LoadResult)>,
+    shared: Arc<LoaderShared>,
+}
+
+impl RowsLoader {
+    pub fn new(reader: CsvLensReader) -> Self {
+        let (requests, requests_receiver) = mpsc::channel();
+        let (results_sender, results) = mpsc::channel();
+        let shared = Arc::new(LoaderShared {
+            pending: AtomicU64::new(0),
+            reading: AtomicBool::new(false),
+            #[cfg(test)]
+            delay: std::sync::Mutex::new(Duration::ZERO),
+        });
+        LoaderShared::spawn(shared.clone(), reader, requests_receiver, results_sender);
+        RowsLoader {
+            last_id: AtomicU64::new(0),
+            requests,
+            results,
+            shared,
+        }
+    }
+
+    /// Read the rows at the given record indices, waiting up to timeout for them. Returns None if
+    /// the read is still pending. A new read replaces any pending one, and doesn't wait at all if
+    /// an earlier read is still stuck.
+    pub fn load(&self, indices: Vec<u64>, timeout: Duration) -> Option<LoadResult> {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.shared.pending.store(id, Ordering::Relaxed);
+        let stuck = self.shared.reading.load(Ordering::Relaxed);
+        // The thread only stops once the loader is dropped
+        self.requests.send((id, indices)).ok()?;
+        if stuck {
+            return None;
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let (result_id, result) = self.results.recv_timeout(remaining).ok()?;
+            if result_id == id {
+                self.shared.pending.store(0, Ordering::Relaxed);
+                return Some(result);
+            }
+        }
+    }
+
+    /// Result of the pending read if it has finished
+    pub fn poll(&self) -> Option<LoadResult> {
+        let id = self.shared.pending.load(Ordering::Relaxed);
+        if id == 0 {
+            return None;
+        }
+        // Results of reads replaced since are dropped
+        while let Ok((result_id, result)) = self.results.try_recv() {
+            if result_id == id {
+                self.shared.pending.store(0, Ordering::Relaxed);
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    /// Give up on the pending read. A read already started can't be interrupted, but its result is
+    /// discarded.
+    pub fn abort(&self) -> bool {
+        self.shared.pending.swap(0, Ordering::Relaxed) != 0
+    }
+
+    /// Make every read take at least this long, to simulate slow storage
+    #[cfg(test)]
+    pub fn set_delay(&self, delay: Duration) {
+        *self.shared.delay.lock().unwrap() = delay;
+    }
+}
+
+struct LoaderShared {
+    /// Id of the read whose result is waited for, 0 if none
+    pending: AtomicU64,
+    /// Whether a read is in progress
+    reading: AtomicBool,
+    #[cfg(test)]
+    delay: std::sync::Mutex<Duration>,
+}
+
+impl LoaderShared {
+    fn spawn(
+        shared: Arc<LoaderShared>,
+        mut reader: CsvLensReader,
+        requests: Receiver<(u64, Vec<u64>)>,
+        results: Sender<(u64, LoadResult)>,
+    ) {
+        thread::spawn(move || {
+            // Until the loader is dropped
+            while let Ok(mut request) = requests.recv() {
+                while let Ok(newer) = requests.try_recv() {
+                    request = newer;
+                }
+                let (id, indices) = request;
+                if shared.pending.load(Ordering::Relaxed) != id {
+                    continue;
+                }
+                shared.reading.store(true, Ordering::Relaxed);
+                #[cfg(test)]
+                {
+                    let delay = *shared.delay.lock().unwrap();
+                    thread::sleep(delay);
+                }
+                let result = reader.get_rows_for_indices(&indices);
+                shared.reading.store(false, Ordering::Relaxed);
+                // Results nobody waits for anymore are dropped
+                if shared.pending.load(Ordering::Relaxed) == id
+                    && results.send((id, result)).is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv;
+
+    fn loader() -> (RowsLoader, CsvLensReader) {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/simple.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let reader = CsvLensReader::new(config).unwrap();
+        reader.wait_internal();
+        (RowsLoader::new(reader.try_clone().unwrap()), reader)
+    }
+
+    #[test]
+    fn test_load() {
+        let (loader, mut reader) = loader();
+        let (rows, _) = loader
+            .load(vec![3, 1], Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows, reader.get_rows_for_indices(&[3, 1]).unwrap().0);
+        assert!(loader.poll().is_none());
+        assert!(!loader.abort());
+    }
+
+    #[test]
+    fn test_stalled_load() {
+        let (loader, mut reader) = loader();
+        loader.set_delay(Duration::from_millis(300));
+        assert!(loader.load(vec![0], Duration::ZERO).is_none());
+        assert!(loader.poll().is_none());
+        // Let the first read start
+        thread::sleep(Duration::from_millis(50));
+
+        // Replaced by a newer read without waiting for the stuck one
+        assert!(loader.load(vec![5], Duration::from_secs(5)).is_none());
+        let result = loop {
+            if let Some(result) = loader.poll() {
+                break result;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(result.unwrap().0, reader.get_rows(5, 1).unwrap().0);
+        assert!(!loader.abort());
+
+        assert!(loader.load(vec![7], Duration::ZERO).is_none());
+        assert!(loader.abort());
+        assert!(!loader.abort());
+        thread::sleep(Duration::from_millis(500));
+        assert!(loader.poll().is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::OnceLock;
+
+/// How numbers are written, e.g. 1,234.5 in English, 1.234,5 in German and 1234.5 in the C locale
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumberFormat {
+    /// Between groups of three digits, if they are grouped
+    pub group_separator: Option<char>,
+    pub decimal_separator: char,
+}
+
+impl Default for NumberFormat {
+    /// The C locale's, without grouping
+    fn default() -> Self {
+        NumberFormat {
+            group_separator: None,
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// The format of a POSIX locale name, e.g. `de_DE.UTF-8` or `fr_CH@euro`. C and POSIX keep
+    /// their ungrouped digits, and other unknown locales are written like English.
+    pub fn for_locale(name: &str) -> NumberFormat {
+        let name = name.split(['.', '@']).next().unwrap_or_default();
+        if matches!(name, "" | "C" | "POSIX") {
+            return NumberFormat::default();
+        }
+        let (language, territory) = name.split_once(['_', '-']).unwrap_or((name, ""));
+        let (group_separator, decimal_separator) = match (language, territory) {
+            ("de" | "it", "CH" | "LI") => ('’', '.'),
+            ("es", "MX" | "US") => (',', '.'),
+            ("pt", "BR") => ('.', ','),
+            (
+                "da" | "de" | "el" | "es" | "hr" | "id" | "is" | "it" | "nl" | "ro" | "sl" | "sr"
+                | "tr" | "vi",
+                _,
+            ) => ('.', ','),
+            (
+                "be" | "bg" | "cs" | "et" | "fi" | "fr" | "hu" | "kk" | "lt" | "lv" | "nb" | "nn"
+                | "no" | "pl" | "pt" | "ru" | "sk" | "sv" | "uk",
+                _,
+            ) => ('\u{a0}', ','),
+            _ => (',', '.'),
+        };
+        NumberFormat {
+            group_separator: Some(group_separator),
+            decimal_separator,
+        }
+    }
+
+    /// The format of the locale set for numbers, by the first of LC_ALL, LC_NUMERIC and LANG set,
+    /// or the C locale's if none is
+    pub fn from_env() -> NumberFormat {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .map_or_else(NumberFormat::default, |name| Self::for_locale(&name))
+    }
+
+    pub fn format_count(&self, n: usize) -> String {
+        self.group(&n.to_string())
+    }
+
+    pub fn format_decimal(&self, x: f64, decimals: usize) -> String {
+        let text = format!("{:.decimals$}", x.abs());
+        let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+        let mut out = if x.is_sign_negative() && x != 0.0 {
+            "-".to_string()
+        } else {
+            String::new()
+        };
+        out += &self.group(integer);
+        if !fraction.is_empty() {
+            out.push(self.decimal_separator);
+            out += fraction;
+        }
+        out
+    }
+
+    /// Digits with the separator between groups of three
+    fn group(&self, digits: &str) -> String {
+        let Some(separator) = self.group_separator else {
+            return digits.to_string();
+        };
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(separator);
+            }
+            out.push(c);
+        }
+        out
+    }
+}
+
+static NUMBER_FORMAT: OnceLock<NumberFormat> = OnceLock::new();
+
+/// Write numbers in the format from now on, e.g. the one of the user's locale read by the runner.
+/// Only the first call has an effect, and numbers are written like in the C locale until then.
+pub fn set_number_format(format: NumberFormat) {
+    let _ = NUMBER_FORMAT.set(format);
+}
+
+fn number_format() -> NumberFormat {
+    NUMBER_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Count in the user's locale, e.g. 1,234,567 or 1.234.567
+pub fn format_count(n: usize) -> String {
+    number_format().format_count(n)
+}
+
+/// Number with the given number of decimals in the user's locale, e.g. 1.5 or 1,5
+pub fn format_decimal(x: f64, decimals: usize) -> String {
+    number_format().format_decimal(x, decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_locale() {
+        let format = NumberFormat::for_locale("de_DE.UTF-8");
+        assert_eq!(format.format_count(1234567), "1.234.567");
+        assert_eq!(format.format_decimal(1234.56, 1), "1.234,6");
+
+        let format = NumberFormat::for_locale("fr_FR@euro");
+        assert_eq!(format.format_count(1234567), "1\u{a0}234\u{a0}567");
+        assert_eq!(format.format_decimal(-0.25, 2), "-0,25");
+
+        assert_eq!(
+            NumberFormat::for_locale("de_CH").format_count(1234),
+            "1’234"
+        );
+        assert_eq!(
+            NumberFormat::for_locale("pt_BR").format_decimal(1.5, 1),
+            "1,5"
+        );
+        let english = NumberFormat::for_locale("en_US.UTF-8");
+        assert_eq!(english.format_count(1234567), "1,234,567");
+        assert_eq!(NumberFormat::for_locale("ja_JP"), english);
+
+        // Digits stay ungrouped in the C locale, as when no locale is set
+        assert_eq!(NumberFormat::for_locale("C"), NumberFormat::default());
+        assert_eq!(NumberFormat::for_locale("C.UTF-8"), NumberFormat::default());
+        assert_eq!(NumberFormat::for_locale("POSIX"), NumberFormat::default());
+        assert_eq!(NumberFormat::for_locale(""), NumberFormat::default());
+    }
+
+    #[test]
+    fn test_format() {
+        let english = NumberFormat::for_locale("en_GB");
+        assert_eq!(english.format_count(999), "999");
+        assert_eq!(english.format_count(1000), "1,000");
+        assert_eq!(english.format_count(123_456_789), "123,456,789");
+        assert_eq!(english.format_decimal(1536.0 / 1024.0, 1), "1.5");
+        assert_eq!(english.format_decimal(1234.5, 2), "1,234.50");
+        assert_eq!(english.format_decimal(0.0, 0), "0");
+
+        let c = NumberFormat::default();
+        assert_eq!(c.format_count(123_456_789), "123456789");
+        assert_eq!(c.format_decimal(-1234.5, 1), "-1234.5");
+    }
+}
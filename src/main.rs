@@ -1,8 +1,22 @@
-use csvlens::run_csvlens;
+use csvlens::errors::CsvlensError;
+use csvlens::{default_args, run_csvlens};
 
 fn main() {
-    let args_itr = std::env::args_os().skip(1);
-    match run_csvlens(args_itr) {
+    // The options of CSVLENS_OPTS come first, so that the command line overrides them
+    let result = default_args()
+        .and_then(|defaults| run_csvlens(defaults.into_iter().chain(std::env::args_os().skip(1))));
+    match result {
+        // Exit codes of queries are like grep's for scripts: 1 if nothing matched, 2 on errors
+        Err(CsvlensError::NoMatches(output)) => {
+            if !output.is_empty() {
+                println!("{output}");
+            }
+            std::process::exit(1);
+        }
+        Err(CsvlensError::Headless(e)) => {
+            println!("{e:#}");
+            std::process::exit(2);
+        }
         Err(e) => {
             println!("{e:#}");
             std::process::exit(1);
@@ -0,0 +1,41 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+/// Parse a size such as 512M, 2G or 1.5GB into bytes. Units are powers of 1024.
+pub fn parse_size(s: &str) -> CsvlensResult<u64> {
+    let invalid = || CsvlensError::InvalidMemoryLimit(s.to_string());
+    let trimmed = s.trim().to_uppercase();
+    let without_suffix = trimmed
+        .trim_end_matches("IB")
+        .trim_end_matches('B')
+        .trim_end();
+    let (number, multiplier) = match without_suffix.chars().last() {
+        Some('K') => (&without_suffix[..without_suffix.len() - 1], 1u64 << 10),
+        Some('M') => (&without_suffix[..without_suffix.len() - 1], 1 << 20),
+        Some('G') => (&without_suffix[..without_suffix.len() - 1], 1 << 30),
+        Some('T') => (&without_suffix[..without_suffix.len() - 1], 1 << 40),
+        _ => (without_suffix, 1),
+    };
+    let number = number.trim().parse::<f64>().map_err(|_| invalid())?;
+    if !number.is_finite() || number <= 0.0 {
+        return Err(invalid());
+    }
+    Ok((number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("512M").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_size("1.5gb").unwrap(), 3 * 512 * 1024 * 1024);
+        assert_eq!(parse_size("2 GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert!(matches!(
+            parse_size("lots"),
+            Err(CsvlensError::InvalidMemoryLimit(_))
+        ));
+        assert!(parse_size("0").is_err());
+    }
+}
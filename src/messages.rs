@@ -0,0 +1,194 @@
+use crate::format::humanize_bytes;
+use crate::locale::format_count;
+
+use std::fmt;
+
+/// Parts of the status bar, worded here rather than where they are drawn so that the text can be
+/// translated in one place. Numbers are written as in the user's locale.
+pub enum Status<'a> {
+    /// Selected row, and the selected column with its name if any, or else the first one shown
+    Position {
+        row: &'a str,
+        column: usize,
+        total_columns: usize,
+        column_name: Option<&'a str>,
+    },
+    /// Table shown of several in the file
+    Table {
+        current: usize,
+        total: usize,
+    },
+    /// Records with more fields than shown
+    FieldsCap(usize),
+    /// Seconds waited on a stalled read
+    IoWait(u64),
+    Invalid {
+        count: usize,
+        done: bool,
+    },
+    Marked {
+        count: usize,
+        hidden: usize,
+    },
+    Tags(&'a str),
+    SortHeader,
+    Reviewed {
+        done: usize,
+        total: usize,
+    },
+    Echo(&'a str),
+    IgnoreCase,
+    /// Seconds since the file was reloaded last
+    LastReload(u64),
+    FileReplaced,
+    /// Bytes of the file downloaded so far
+    Downloading(u64),
+    /// Rows exported so far, out of the total once the rows are all read
+    Exporting {
+        rows_written: usize,
+        total: Option<usize>,
+        path: &'a str,
+    },
+}
+
+impl fmt::Display for Status<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Position {
+                row,
+                column,
+                total_columns,
+                column_name: Some(name),
+            } => write!(
+                f,
+                "[Row {row}, Col {}/{}: {name}]",
+                format_count(*column),
+                format_count(*total_columns)
+            ),
+            Status::Position {
+                row,
+                column,
+                total_columns,
+                column_name: None,
+            } => write!(
+                f,
+                "[Row {row}, Col {}/{}]",
+                format_count(*column),
+                format_count(*total_columns)
+            ),
+            Status::Table { current, total } => write!(f, "[Table {current}/{total}]"),
+            Status::FieldsCap(n) => write!(f, "[First {} fields, -F for all]", format_count(*n)),
+            Status::IoWait(seconds) => write!(f, "[Waiting on I/O {seconds}s, Esc to abort]"),
+            Status::Invalid { count, done } => {
+                let suffix = if *done { "" } else { "+" };
+                write!(f, "[Invalid: {}{suffix}]", format_count(*count))
+            }
+            Status::Marked { count, hidden: 0 } => write!(f, "[Marked: {}]", format_count(*count)),
+            Status::Marked { count, hidden } => write!(
+                f,
+                "[Marked: {}, {} hidden]",
+                format_count(*count),
+                format_count(*hidden)
+            ),
+            Status::Tags(tags) => write!(f, "[Tags: {tags}]"),
+            Status::SortHeader => write!(f, "[Sort: ←→ column, ↵ sort, Esc done]"),
+            Status::Reviewed { done, total } => write!(
+                f,
+                "[{}/{} reviewed]",
+                format_count(*done),
+                format_count(*total)
+            ),
+            Status::Echo(column_name) => write!(f, "[Echo {column_name} ↵]"),
+            Status::IgnoreCase => write!(f, "[ignore-case]"),
+            Status::LastReload(seconds) => write!(f, "[Last reload: {seconds}s ago]"),
+            Status::FileReplaced => {
+                write!(f, "[File truncated or replaced, :reload to read it again]")
+            }
+            Status::Downloading(bytes) => {
+                write!(f, "[Downloading, {} so far]", humanize_bytes(*bytes))
+            }
+            Status::Exporting {
+                rows_written,
+                total: Some(total),
+                path,
+            } => write!(
+                f,
+                "[Exporting {}/{} rows to {path}, Esc to cancel]",
+                format_count(*rows_written),
+                format_count(*total)
+            ),
+            Status::Exporting {
+                total: None, path, ..
+            } => write!(
+                f,
+                "[Exporting to {path} once the rows are all read, Esc to cancel]"
+            ),
+        }
+    }
+}
+
+/// Number of the row shown out of the total, e.g. 12/345. While the file is still being indexed,
+/// the total is only at least the number of rows scanned so far.
+pub fn row_position(
+    row_num: Option<usize>,
+    total: Option<(usize, bool)>,
+    streaming: bool,
+) -> String {
+    let row = row_num.map_or("-".to_owned(), format_count);
+    match total {
+        Some((total, true)) => format!(
+            "{row} of ≥{} ({}…)",
+            format_count(total),
+            if streaming { "streaming" } else { "scanning" }
+        ),
+        Some((total, false)) => format!("{row}/{}", format_count(total)),
+        None => format!("{row}/?"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_position() {
+        assert_eq!(row_position(Some(12), Some((345, false)), false), "12/345");
+        assert_eq!(
+            row_position(Some(1234), Some((5_000_000, true)), false),
+            "1234 of ≥5000000 (scanning…)"
+        );
+        assert_eq!(
+            row_position(None, Some((0, true)), false),
+            "- of ≥0 (scanning…)"
+        );
+        assert_eq!(
+            row_position(Some(3), Some((120, true)), true),
+            "3 of ≥120 (streaming…)"
+        );
+        assert_eq!(
+            row_position(Some(1500), Some((20_000, false)), false),
+            "1500/20000"
+        );
+        assert_eq!(row_position(Some(1), None, false), "1/?");
+    }
+
+    #[test]
+    fn test_status() {
+        let position = Status::Position {
+            row: "5/1,000",
+            column: 2,
+            total_columns: 10,
+            column_name: Some("City"),
+        };
+        assert_eq!(position.to_string(), "[Row 5/1,000, Col 2/10: City]");
+        let marked = Status::Marked {
+            count: 1200,
+            hidden: 3,
+        };
+        assert_eq!(marked.to_string(), "[Marked: 1200, 3 hidden]");
+        assert_eq!(
+            Status::Downloading(1536).to_string(),
+            "[Downloading, 1.5 KiB so far]"
+        );
+    }
+}
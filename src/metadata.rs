@@ -0,0 +1,412 @@
+use crate::config;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::number;
+
+use arrow::datatypes::{DataType, TimeUnit};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Type of a column as declared by sidecar metadata
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Date,
+    DateTime,
+    Any,
+}
+
+impl FieldType {
+    /// Parse a type name used by frictionless table schemas or CSVW datatypes
+    pub fn from_name(name: &str) -> FieldType {
+        match name {
+            "string" | "normalizedString" | "token" | "anyURI" => FieldType::String,
+            "integer" | "int" | "long" | "short" | "byte" | "nonNegativeInteger"
+            | "positiveInteger" | "nonPositiveInteger" | "negativeInteger" | "unsignedLong"
+            | "unsignedInt" | "unsignedShort" | "unsignedByte" => FieldType::Integer,
+            "number" | "decimal" | "double" | "float" => FieldType::Number,
+            "boolean" => FieldType::Boolean,
+            "date" => FieldType::Date,
+            "datetime" | "dateTime" => FieldType::DateTime,
+            _ => FieldType::Any,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, FieldType::Integer | FieldType::Number)
+    }
+
+    /// Arrow type to use instead of inference when sorting
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            FieldType::String => Some(DataType::Utf8),
+            // Same as inferred integer columns, which are sorted as floats
+            FieldType::Integer | FieldType::Number => Some(DataType::Float64),
+            FieldType::Boolean => Some(DataType::Boolean),
+            FieldType::Date => Some(DataType::Date32),
+            FieldType::DateTime => Some(DataType::Timestamp(TimeUnit::Millisecond, None)),
+            FieldType::Any => None,
+        }
+    }
+
+    /// Whether a non-empty value is a valid literal of this type. Numbers may have a decimal comma
+    /// if decimal_comma is set.
+    pub fn accepts(&self, value: &str, decimal_comma: bool) -> bool {
+        let value = value.trim();
+        match self {
+            FieldType::Integer => value.parse::<i64>().is_ok(),
+            FieldType::Number => number::parse_number(value, decimal_comma).is_some(),
+            FieldType::Boolean => parse_bool(value).is_some(),
+            FieldType::Date => chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            FieldType::DateTime => {
+                chrono::DateTime::parse_from_rfc3339(value).is_ok()
+                    || chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").is_ok()
+            }
+            FieldType::String | FieldType::Any => true,
+        }
+    }
+}
+
+/// Column description from sidecar metadata
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldMetadata {
+    pub name: String,
+    pub title: Option<String>,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub pattern: Option<String>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub enum_values: Option<Vec<String>>,
+}
+
+/// Column types, titles and constraints of a CSV file, read from a frictionless
+/// `datapackage.json` or a CSVW metadata file sitting next to it
+#[derive(Clone, Debug)]
+pub struct TableMetadata {
+    pub source: String,
+    pub fields: Vec<FieldMetadata>,
+}
+
+impl TableMetadata {
+    /// Look for sidecar metadata describing `filename`. CSVW metadata (`<file>-metadata.json` or
+    /// `csv-metadata.json`) takes precedence over `datapackage.json`. Files that describe other
+    /// tables are passed over, while ones that can't be read or are malformed are errors.
+    pub fn discover(filename: &str) -> CsvlensResult<Option<TableMetadata>> {
+        let path = Path::new(filename);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let Some(file_name) = path.file_name().and_then(|f| f.to_str()) else {
+            return Ok(None);
+        };
+
+        let csvw_candidates = [
+            PathBuf::from(format!("{filename}-metadata.json")),
+            dir.join("csv-metadata.json"),
+        ];
+        for candidate in csvw_candidates {
+            if candidate.is_file() {
+                let source = candidate.to_string_lossy().to_string();
+                let value = config::load_value(&source)?;
+                if let Some(fields) = parse_csvw(&value, file_name, &source)? {
+                    return Ok(Some(TableMetadata { source, fields }));
+                }
+            }
+        }
+
+        let datapackage = dir.join("datapackage.json");
+        if datapackage.is_file() {
+            let source = datapackage.to_string_lossy().to_string();
+            let value = config::load_value(&source)?;
+            if let Some(fields) = parse_datapackage(&value, file_name, dir, &source)? {
+                return Ok(Some(TableMetadata { source, fields }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldMetadata> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+fn invalid(source: &str, message: &str) -> CsvlensError {
+    CsvlensError::ConfigParsing(source.to_string(), message.to_string())
+}
+
+fn refers_to(url: &str, file_name: &str) -> bool {
+    Path::new(url).file_name().and_then(|f| f.to_str()) == Some(file_name)
+}
+
+fn parse_datapackage(
+    value: &Value,
+    file_name: &str,
+    dir: &Path,
+    source: &str,
+) -> CsvlensResult<Option<Vec<FieldMetadata>>> {
+    // A package without resources is about something else, not a broken description of this file
+    let Some(resources) = value.get("resources").and_then(|r| r.as_array()) else {
+        return Ok(None);
+    };
+    let resource = resources.iter().find(|r| match r.get("path") {
+        Some(Value::String(p)) => refers_to(p, file_name),
+        Some(Value::Array(paths)) => paths
+            .iter()
+            .any(|p| p.as_str().is_some_and(|p| refers_to(p, file_name))),
+        _ => false,
+    });
+    let Some(resource) = resource else {
+        return Ok(None);
+    };
+    let schema = match resource.get("schema") {
+        // Schema can also be a path to a separate file
+        Some(Value::String(schema_path)) => {
+            config::load_value(&dir.join(schema_path).to_string_lossy())?
+        }
+        Some(schema) => schema.clone(),
+        None => return Ok(None),
+    };
+    let Some(fields) = schema.get("fields").and_then(|f| f.as_array()) else {
+        return Err(invalid(source, "schema is missing fields"));
+    };
+    let mut out = vec![];
+    for field in fields {
+        let name = field
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or_else(|| invalid(source, "field is missing name"))?;
+        let constraints = field.get("constraints");
+        let constraint = |key: &str| constraints.and_then(|c| c.get(key));
+        out.push(FieldMetadata {
+            name: name.to_string(),
+            title: field
+                .get("title")
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_string()),
+            field_type: field
+                .get("type")
+                .and_then(|t| t.as_str())
+                .map_or(FieldType::Any, FieldType::from_name),
+            required: constraint("required")
+                .and_then(|r| r.as_bool())
+                .unwrap_or(false),
+            pattern: constraint("pattern")
+                .and_then(|p| p.as_str())
+                .map(|p| p.to_string()),
+            minimum: constraint("minimum").and_then(as_number),
+            maximum: constraint("maximum").and_then(as_number),
+            enum_values: constraint("enum").and_then(as_strings),
+        });
+    }
+    Ok(Some(out))
+}
+
+fn parse_csvw(
+    value: &Value,
+    file_name: &str,
+    source: &str,
+) -> CsvlensResult<Option<Vec<FieldMetadata>>> {
+    // Either a single table description or a group of tables
+    let table = match value.get("tables").and_then(|t| t.as_array()) {
+        Some(tables) => tables.iter().find(|t| {
+            t.get("url")
+                .and_then(|u| u.as_str())
+                .is_some_and(|u| refers_to(u, file_name))
+        }),
+        None => value.get("url").and_then(|u| u.as_str()).and_then(|u| {
+            if refers_to(u, file_name) {
+                Some(value)
+            } else {
+                None
+            }
+        }),
+    };
+    let Some(table) = table else {
+        return Ok(None);
+    };
+    let Some(columns) = table
+        .get("tableSchema")
+        .and_then(|s| s.get("columns"))
+        .and_then(|c| c.as_array())
+    else {
+        return Err(invalid(source, "tableSchema is missing columns"));
+    };
+    let mut out = vec![];
+    for column in columns {
+        if column.get("virtual").and_then(|v| v.as_bool()) == Some(true) {
+            continue;
+        }
+        let name = column
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or_else(|| invalid(source, "column is missing name"))?;
+        let title = match column.get("titles") {
+            Some(Value::String(t)) => Some(t.clone()),
+            Some(Value::Array(titles)) => titles
+                .first()
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_string()),
+            // Language map, e.g. {"en": "Name"}
+            Some(Value::Object(titles)) => titles.values().next().and_then(|t| match t {
+                Value::String(t) => Some(t.clone()),
+                Value::Array(t) => t.first().and_then(|t| t.as_str()).map(|t| t.to_string()),
+                _ => None,
+            }),
+            _ => None,
+        };
+        // Datatype is either a name or a description with a base type and facets
+        let datatype = column.get("datatype");
+        let (field_type, facets) = match datatype {
+            Some(Value::String(name)) => (FieldType::from_name(name), None),
+            Some(Value::Object(d)) => (
+                d.get("base")
+                    .and_then(|b| b.as_str())
+                    .map_or(FieldType::String, FieldType::from_name),
+                datatype,
+            ),
+            _ => (FieldType::Any, None),
+        };
+        let facet = |key: &str| facets.and_then(|f| f.get(key));
+        let pattern = if matches!(field_type, FieldType::String | FieldType::Any) {
+            facet("format")
+                .and_then(|f| f.as_str())
+                .map(|f| format!("^(?:{f})$"))
+        } else {
+            None
+        };
+        out.push(FieldMetadata {
+            name: name.to_string(),
+            title,
+            field_type,
+            required: column
+                .get("required")
+                .and_then(|r| r.as_bool())
+                .unwrap_or(false),
+            pattern,
+            minimum: facet("minimum").and_then(as_number),
+            maximum: facet("maximum").and_then(as_number),
+            enum_values: None,
+        });
+    }
+    Ok(Some(out))
+}
+
+/// Parse a boolean literal: true/false, yes/no or 1/0, in any case
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+fn as_strings(value: &Value) -> Option<Vec<String>> {
+    value.as_array().map(|values| {
+        values
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datapackage() {
+        let metadata = TableMetadata::discover("tests/data/metadata.csv")
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.source, "tests/data/datapackage.json");
+        let score = metadata.field("score").unwrap();
+        assert_eq!(score.field_type, FieldType::Number);
+        assert_eq!(score.title, Some("Score".to_string()));
+        assert_eq!(score.minimum, Some(0.0));
+        let code = metadata.field("code").unwrap();
+        assert_eq!(code.field_type, FieldType::String);
+        assert_eq!(
+            code.enum_values,
+            Some(vec![
+                "007".to_string(),
+                "1".to_string(),
+                "10".to_string(),
+                "9".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_csvw() {
+        let metadata = TableMetadata::discover("tests/data/metadata_csvw.csv")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            metadata.source,
+            "tests/data/metadata_csvw.csv-metadata.json"
+        );
+        let id = metadata.field("id").unwrap();
+        assert_eq!(id.field_type, FieldType::Integer);
+        assert!(id.required);
+        let name = metadata.field("name").unwrap();
+        assert_eq!(name.title, Some("Full name".to_string()));
+        assert_eq!(name.pattern, Some("^(?:[A-Z].*)$".to_string()));
+        let score = metadata.field("score").unwrap();
+        assert_eq!(score.field_type, FieldType::Number);
+        assert_eq!(score.maximum, Some(100.0));
+    }
+
+    #[test]
+    fn test_other_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let filename = dir.path().join("a.csv");
+        std::fs::write(&filename, "x\n1\n").unwrap();
+        let filename = filename.to_str().unwrap();
+        let datapackage = dir.path().join("datapackage.json");
+
+        // Packages without resources, or without this file among them, are not about it
+        std::fs::write(&datapackage, r#"{"name": "unrelated"}"#).unwrap();
+        assert!(TableMetadata::discover(filename).unwrap().is_none());
+        std::fs::write(
+            &datapackage,
+            r#"{"resources": [{"path": "b.csv", "schema": {}}]}"#,
+        )
+        .unwrap();
+        assert!(TableMetadata::discover(filename).unwrap().is_none());
+
+        // Broken descriptions of it are errors
+        std::fs::write(&datapackage, "{").unwrap();
+        assert!(TableMetadata::discover(filename).is_err());
+        std::fs::write(
+            &datapackage,
+            r#"{"resources": [{"path": "a.csv", "schema": {}}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            TableMetadata::discover(filename),
+            Err(CsvlensError::ConfigParsing(_, message)) if message == "schema is missing fields"
+        ));
+    }
+
+    #[test]
+    fn test_no_metadata() {
+        assert!(
+            TableMetadata::discover("tests/data/simple.csv")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
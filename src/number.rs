@@ -0,0 +1,95 @@
+/// Characters separating groups of thousands in numbers with a decimal comma
+const THOUSANDS_SEPARATORS: [char; 5] = ['.', ' ', '\'', '\u{a0}', '\u{202f}'];
+
+/// Parse a number, written with a decimal comma (e.g. `1.234,56`) if decimal_comma is set
+pub fn parse_number(value: &str, decimal_comma: bool) -> Option<f64> {
+    if decimal_comma {
+        parse_decimal_comma(value)
+    } else {
+        value.trim().parse::<f64>().ok()
+    }
+}
+
+/// Parse a percentage like `12.5%` or `-3 %`, as the number before the percent sign
+pub fn parse_percentage(value: &str, decimal_comma: bool) -> Option<f64> {
+    let number = value.trim().strip_suffix('%')?;
+    parse_number(number, decimal_comma)
+}
+
+/// Parse a number like `-1.234.567,89` or `0,5` as written in much of Europe. Thousands may be
+/// separated by dots, spaces or apostrophes in groups of three digits, so that `1.5` is not taken
+/// as a number.
+fn parse_decimal_comma(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (sign, value) = match value.strip_prefix(['-', '+']) {
+        Some(rest) => (&value[..1], rest),
+        None => ("", value),
+    };
+    let (integer, fraction) = match value.split_once(',') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (value, None),
+    };
+
+    let groups: Vec<&str> = integer.split(THOUSANDS_SEPARATORS).collect();
+    if groups.len() > 1
+        && (groups[0].is_empty() || groups[0].len() > 3 || groups[1..].iter().any(|g| g.len() != 3))
+    {
+        return None;
+    }
+    let integer = groups.concat();
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(&integer) {
+        return None;
+    }
+    match fraction {
+        Some(fraction) if fraction.is_empty() || !is_digits(fraction) => None,
+        Some(fraction) => format!("{sign}{integer}.{fraction}").parse().ok(),
+        None if integer.is_empty() => None,
+        None => format!("{sign}{integer}").parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal_comma() {
+        let parse = |s| parse_number(s, true);
+        assert_eq!(parse("1.234,56"), Some(1234.56));
+        assert_eq!(parse(" -0,5 "), Some(-0.5));
+        assert_eq!(parse(",25"), Some(0.25));
+        assert_eq!(parse("1 234 567"), Some(1234567.0));
+        assert_eq!(parse("12'000,1"), Some(12000.1));
+        assert_eq!(parse("1.234"), Some(1234.0));
+        assert_eq!(parse("42"), Some(42.0));
+        for s in [
+            "1.5",
+            "1,2,3",
+            "12.34.567",
+            "1.234,",
+            ".123",
+            "",
+            "-",
+            "1e5",
+            "abc",
+        ] {
+            assert_eq!(parse(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(parse_percentage("12.5%", false), Some(12.5));
+        assert_eq!(parse_percentage(" -3 % ", false), Some(-3.0));
+        assert_eq!(parse_percentage("12,5%", true), Some(12.5));
+        assert_eq!(parse_percentage("12.5", false), None);
+        assert_eq!(parse_percentage("%", false), None);
+    }
+
+    #[test]
+    fn test_parse_decimal_point() {
+        assert_eq!(parse_number(" 1.5", false), Some(1.5));
+        assert_eq!(parse_number("1,5", false), None);
+    }
+}
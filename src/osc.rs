@@ -0,0 +1,95 @@
+use ratatui::buffer::{Buffer, Cell};
+use ratatui::layout::{Position, Rect};
+use std::path::Path;
+
+/// Cells showing a URL, turned into a terminal hyperlink after drawing
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hyperlink {
+    pub url: String,
+    pub area: Rect,
+}
+
+/// OSC 7 sequence reporting the working directory, so that new tabs or panes of the terminal
+/// open in the same directory
+pub fn working_directory(cwd: &Path, host: &str) -> String {
+    let mut path = String::new();
+    for b in cwd.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            path.push(b as char);
+        } else {
+            path.push_str(&format!("%{b:02X}"));
+        }
+    }
+    format!("\x1b]7;file://{host}{path}\x1b\\")
+}
+
+/// A cell and its x and y position
+pub type PositionedCell = (u16, u16, Cell);
+
+/// Copies of the drawn cells of the hyperlinks with OSC 8 sequences around each line of them.
+/// The backend prints symbols as is, so drawing these over the same cells only adds the links.
+pub fn hyperlink_cells(buf: &Buffer, links: &[Hyperlink]) -> Vec<PositionedCell> {
+    let mut cells = vec![];
+    for link in links {
+        // Control characters would end the sequence early
+        if link.url.chars().any(char::is_control) {
+            continue;
+        }
+        let area = link.area.intersection(buf.area);
+        for y in area.top()..area.bottom() {
+            let is_drawn = |x: &u16| !buf[Position::new(*x, y)].symbol().trim().is_empty();
+            let first = (area.left()..area.right()).find(is_drawn);
+            let last = (area.left()..area.right()).rfind(is_drawn);
+            let (Some(first), Some(last)) = (first, last) else {
+                continue;
+            };
+            for x in first..=last {
+                let mut cell = buf[Position::new(x, y)].clone();
+                let mut symbol = cell.symbol().to_string();
+                if x == first {
+                    symbol = format!("\x1b]8;;{}\x1b\\{symbol}", link.url);
+                }
+                if x == last {
+                    symbol.push_str("\x1b]8;;\x1b\\");
+                }
+                cell.set_symbol(&symbol);
+                cells.push((x, y, cell));
+            }
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_working_directory() {
+        assert_eq!(
+            working_directory(Path::new("/home/me/my data"), "box"),
+            "\x1b]7;file://box/home/me/my%20data\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_hyperlink_cells() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 2));
+        buf.set_string(2, 0, "ab", ratatui::style::Style::default());
+        let link = |url: &str| Hyperlink {
+            url: url.to_string(),
+            area: Rect::new(0, 0, 6, 2),
+        };
+        let cells = hyperlink_cells(&buf, &[link("https://a.b")]);
+        let symbols: Vec<(u16, u16, &str)> =
+            cells.iter().map(|(x, y, c)| (*x, *y, c.symbol())).collect();
+        assert_eq!(
+            symbols,
+            vec![
+                (2, 0, "\x1b]8;;https://a.b\x1b\\a"),
+                (3, 0, "b\x1b]8;;\x1b\\"),
+            ]
+        );
+        assert!(hyperlink_cells(&buf, &[link("https://a.b/\x07")]).is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+use crate::csv::{CsvConfig, CsvlensRecordIterator};
+use crate::sort::{SortOrder, Sorter};
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Rows of the same value of the column sorted by, one after another in the sorted view
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    pub value: String,
+    /// Position of the first row of the group in the sorted view
+    pub start: u64,
+    pub len: u64,
+}
+
+/// What to show above the first row of a group
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupLabel {
+    pub value: String,
+    pub len: u64,
+    pub collapsed: bool,
+}
+
+/// Splits the rows of a sorted view into groups of equal values of the column sorted by, found in
+/// the background, of which some are collapsed to their first row. Rows are indexed as shown,
+/// i.e. without the rows hidden in collapsed groups.
+pub struct Outline {
+    sorter: Arc<Sorter>,
+    sort_order: SortOrder,
+    column_name: String,
+    /// Indices of the collapsed groups, in the order of the view
+    collapsed: BTreeSet<usize>,
+    internal: Arc<Mutex<OutlineInternalState>>,
+}
+
+impl Outline {
+    pub fn new(
+        config: Arc<CsvConfig>,
+        sorter: Arc<Sorter>,
+        sort_order: SortOrder,
+        column_name: String,
+    ) -> Self {
+        let internal = OutlineInternalState::init(config, sorter.clone(), sort_order);
+        Outline {
+            sorter,
+            sort_order,
+            column_name,
+            collapsed: BTreeSet::new(),
+            internal,
+        }
+    }
+
+    pub fn sorter(&self) -> &Arc<Sorter> {
+        &self.sorter
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    pub fn column_name(&self) -> &str {
+        &self.column_name
+    }
+
+    pub fn done(&self) -> bool {
+        self.internal.lock().unwrap().done
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.with_groups(|groups| groups.len())
+    }
+
+    pub fn num_collapsed(&self) -> usize {
+        self.collapsed.len()
+    }
+
+    /// Number of rows shown
+    pub fn num_visible(&self) -> u64 {
+        self.with_groups(|groups| self.num_visible_of(groups))
+    }
+
+    /// Positions in the sorted view of the rows shown from a row on
+    pub fn visible_positions(&self, rows_from: u64, num_rows: u64) -> Vec<u64> {
+        self.with_groups(|groups| self.visible_positions_of(groups, rows_from, num_rows))
+    }
+
+    /// Labels of the rows shown from a row on, for those that start a group
+    pub fn labels(&self, rows_from: u64, num_rows: u64) -> Vec<Option<GroupLabel>> {
+        self.with_groups(|groups| {
+            self.visible_positions_of(groups, rows_from, num_rows)
+                .into_iter()
+                .map(|position| {
+                    let index = Self::group_index_of(groups, position);
+                    let group = &groups[index];
+                    (group.start == position).then(|| GroupLabel {
+                        value: group.value.clone(),
+                        len: group.len,
+                        collapsed: self.collapsed.contains(&index),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Row shown for a position in the sorted view, the first row of its group if collapsed
+    pub fn visible_index(&self, position: u64) -> u64 {
+        self.with_groups(|groups| {
+            let mut num_hidden = 0;
+            for i in &self.collapsed {
+                let group = &groups[*i];
+                if group.start >= position {
+                    break;
+                }
+                num_hidden += (group.len - 1).min(position - group.start);
+            }
+            position - num_hidden
+        })
+    }
+
+    /// Collapse the group of a row shown, or expand it. The value of the group and whether it is
+    /// now collapsed.
+    pub fn toggle(&mut self, visible_index: u64) -> Option<(String, bool)> {
+        let internal = self.internal.clone();
+        let m = internal.lock().unwrap();
+        let groups = m.groups.as_ref()?;
+        if visible_index >= self.num_visible_of(groups) {
+            return None;
+        }
+        let position = self.position_of(groups, visible_index);
+        let index = Self::group_index_of(groups, position);
+        let collapsed = self.collapsed.insert(index);
+        if !collapsed {
+            self.collapsed.remove(&index);
+        }
+        Some((groups[index].value.clone(), collapsed))
+    }
+
+    /// Collapse all groups, or expand them all if all are collapsed already. Whether they are
+    /// now collapsed.
+    pub fn toggle_all(&mut self) -> bool {
+        let num_groups = self.num_groups();
+        if self.collapsed.len() == num_groups {
+            self.collapsed.clear();
+            false
+        } else {
+            self.collapsed = (0..num_groups).collect();
+            true
+        }
+    }
+
+    fn with_groups<T: Default>(&self, f: impl FnOnce(&Vec<Group>) -> T) -> T {
+        let m = self.internal.lock().unwrap();
+        m.groups.as_ref().map(f).unwrap_or_default()
+    }
+
+    fn num_visible_of(&self, groups: &[Group]) -> u64 {
+        let num_rows = groups.last().map_or(0, |g| g.start + g.len);
+        let num_hidden: u64 = self
+            .collapsed
+            .iter()
+            .map(|i| groups[*i].len.saturating_sub(1))
+            .sum();
+        num_rows - num_hidden
+    }
+
+    fn visible_positions_of(&self, groups: &[Group], rows_from: u64, num_rows: u64) -> Vec<u64> {
+        let rows_to = rows_from
+            .saturating_add(num_rows)
+            .min(self.num_visible_of(groups));
+        (rows_from..rows_to)
+            .map(|i| self.position_of(groups, i))
+            .collect()
+    }
+
+    fn position_of(&self, groups: &[Group], visible_index: u64) -> u64 {
+        let mut num_hidden = 0;
+        for i in &self.collapsed {
+            let group = &groups[*i];
+            if visible_index + num_hidden <= group.start {
+                break;
+            }
+            num_hidden += group.len - 1;
+        }
+        visible_index + num_hidden
+    }
+
+    fn group_index_of(groups: &[Group], position: u64) -> usize {
+        groups
+            .partition_point(|g| g.start <= position)
+            .saturating_sub(1)
+    }
+
+    fn terminate(&self) {
+        self.internal.lock().unwrap().should_terminate = true;
+    }
+
+    #[cfg(test)]
+    pub fn wait_internal(&self) {
+        loop {
+            if self.internal.lock().unwrap().done {
+                break;
+            }
+            thread::sleep(core::time::Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Outline {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+struct OutlineInternalState {
+    groups: Option<Vec<Group>>,
+    done: bool,
+    should_terminate: bool,
+}
+
+impl OutlineInternalState {
+    fn init(
+        config: Arc<CsvConfig>,
+        sorter: Arc<Sorter>,
+        sort_order: SortOrder,
+    ) -> Arc<Mutex<OutlineInternalState>> {
+        let m_state = Arc::new(Mutex::new(OutlineInternalState {
+            groups: None,
+            done: false,
+            should_terminate: false,
+        }));
+
+        let _m = m_state.clone();
+        thread::spawn(move || {
+            let column_index = sorter.column_index;
+            let mut values = vec![];
+            if let Ok(records) = CsvlensRecordIterator::new(config) {
+                for record in records {
+                    if _m.lock().unwrap().should_terminate {
+                        break;
+                    }
+                    // Rows that can't be read still take their place
+                    let value = record
+                        .ok()
+                        .and_then(|r| r.get(column_index).map(str::to_owned))
+                        .unwrap_or_default();
+                    values.push(value);
+                }
+            }
+            let mut groups: Vec<Group> = vec![];
+            let record_indices = sorter
+                .get_sorted_indices(0, values.len() as u64, sort_order)
+                .unwrap_or_default();
+            for (position, record_index) in record_indices.into_iter().enumerate() {
+                let value = values
+                    .get(record_index as usize)
+                    .cloned()
+                    .unwrap_or_default();
+                match groups.last_mut() {
+                    Some(group) if group.value == value => group.len += 1,
+                    _ => groups.push(Group {
+                        value,
+                        start: position as u64,
+                        len: 1,
+                    }),
+                }
+            }
+            let mut m = _m.lock().unwrap();
+            if !m.should_terminate {
+                m.groups = Some(groups);
+            }
+            m.done = true;
+        });
+
+        m_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+    use crate::sort::{ColumnHint, SortType};
+
+    fn outline() -> Outline {
+        let config = Arc::new(CsvConfig::new(
+            "tests/data/cities.csv",
+            None,
+            CsvBaseConfig::new(b',', false),
+        ));
+        let sorter = Arc::new(Sorter::new(
+            config.clone(),
+            9,
+            "State".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        ));
+        sorter.wait_internal();
+        let outline = Outline::new(config, sorter, SortOrder::Ascending, "State".to_string());
+        outline.wait_internal();
+        outline
+    }
+
+    #[test]
+    fn test_groups() {
+        let outline = outline();
+        assert!(outline.done());
+        assert_eq!(outline.num_groups(), 46);
+        assert_eq!(outline.num_visible(), 128);
+        let label = |value: &str, len| {
+            Some(GroupLabel {
+                value: value.to_string(),
+                len,
+                collapsed: false,
+            })
+        };
+        assert_eq!(
+            outline.labels(0, 5),
+            vec![
+                label("AL", 2),
+                None,
+                label("AZ", 1),
+                label("BC", 1),
+                label("CA", 12)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collapse() {
+        let mut outline = outline();
+        // The CA group is at positions 4 to 15
+        assert_eq!(outline.toggle(6), Some(("CA".to_string(), true)));
+        assert_eq!(outline.num_visible(), 117);
+        assert_eq!(outline.visible_positions(3, 3), vec![3, 4, 16]);
+        assert_eq!(outline.visible_index(4), 4);
+        assert_eq!(outline.visible_index(10), 4);
+        assert_eq!(outline.visible_index(16), 5);
+        assert_eq!(
+            outline.labels(4, 1),
+            vec![Some(GroupLabel {
+                value: "CA".to_string(),
+                len: 12,
+                collapsed: true,
+            })]
+        );
+
+        assert_eq!(outline.toggle(4), Some(("CA".to_string(), false)));
+        assert_eq!(outline.num_visible(), 128);
+
+        assert!(outline.toggle_all());
+        assert_eq!(outline.num_visible(), 46);
+        assert_eq!(outline.visible_positions(3, 2), vec![4, 16]);
+        assert!(!outline.toggle_all());
+        assert_eq!(outline.num_visible(), 128);
+    }
+}
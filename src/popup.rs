@@ -0,0 +1,204 @@
+use crate::csv::RowId;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, StatefulWidget, Widget},
+};
+
+/// Width of a popup over an area, leaving some of the table visible around it
+pub fn popup_width(area_width: u16) -> u16 {
+    (area_width.saturating_mul(4) / 5).max(area_width.min(20))
+}
+
+/// What happens when an entry is selected with Enter
+#[derive(Debug, Clone, PartialEq)]
+pub enum PopupAction {
+    JumpToRecord(RowId),
+    Open(String),
+    SwitchTable(usize),
+    ChooseDialect(usize),
+    /// Filter by the pattern in the column, by its index among the shown columns
+    FilterColumn(usize, String),
+}
+
+/// An entry in a list popup, optionally with an action to run when selected
+#[derive(Debug, Clone)]
+pub struct PopupItem {
+    pub label: String,
+    pub action: Option<PopupAction>,
+}
+
+impl PopupItem {
+    pub fn text(label: &str) -> Self {
+        PopupItem {
+            label: label.to_string(),
+            action: None,
+        }
+    }
+}
+
+/// A scrollable list rendered as an overlay on top of the table
+pub struct ListPopup {}
+
+impl ListPopup {
+    pub fn new() -> Self {
+        ListPopup {}
+    }
+}
+
+pub struct ListPopupState {
+    active: bool,
+    title: String,
+    show_count: bool,
+    items: Vec<PopupItem>,
+    selected: usize,
+    offset: usize,
+    num_visible: usize,
+}
+
+impl ListPopupState {
+    pub fn new() -> Self {
+        ListPopupState {
+            active: false,
+            title: "".to_string(),
+            show_count: true,
+            items: vec![],
+            selected: 0,
+            offset: 0,
+            num_visible: 1,
+        }
+    }
+
+    pub fn activate(&mut self, title: &str, items: Vec<PopupItem>) {
+        self.active = true;
+        self.title = title.to_string();
+        self.show_count = true;
+        self.items = items;
+        self.selected = 0;
+        self.offset = 0;
+    }
+
+    /// Show lines of details about a single value rather than a list of entries
+    pub fn activate_details(&mut self, title: &str, items: Vec<PopupItem>) {
+        self.activate(title, items);
+        self.show_count = false;
+    }
+
+    /// Replace the title and items of the active popup, keeping the selected position
+    pub fn update(&mut self, title: &str, items: Vec<PopupItem>) {
+        self.title = title.to_string();
+        self.items = items;
+        self.select(self.selected);
+    }
+
+    pub fn deactivate(&mut self) {
+        self.active = false;
+        self.items.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn selected_item(&self) -> Option<&PopupItem> {
+        self.items.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        self.select(self.selected.saturating_add(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.select(self.selected.saturating_sub(1));
+    }
+
+    pub fn select_first(&mut self) {
+        self.select(0);
+    }
+
+    pub fn select_last(&mut self) {
+        self.select(self.items.len().saturating_sub(1));
+    }
+
+    pub fn page_down(&mut self) {
+        self.select(self.selected.saturating_add(self.num_visible));
+    }
+
+    pub fn page_up(&mut self) {
+        self.select(self.selected.saturating_sub(self.num_visible));
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+        self.adjust_offset();
+    }
+
+    fn adjust_offset(&mut self) {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + self.num_visible {
+            self.offset = self.selected + 1 - self.num_visible;
+        }
+    }
+}
+
+impl StatefulWidget for ListPopup {
+    type State = ListPopupState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        // Leave some of the table visible around the popup
+        let width = popup_width(area.width);
+        let height = (area.height.saturating_mul(3) / 5).max(area.height.min(5));
+        let popup_area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        Clear.render(popup_area, buf);
+
+        let title = if state.show_count {
+            format!(" {} ({}) ", state.title, state.items.len())
+        } else {
+            format!(" {} ", state.title)
+        };
+        let hint = match state.items.iter().find_map(|item| item.action.as_ref()) {
+            Some(PopupAction::JumpToRecord(_)) => " ↵ jump · q close ",
+            Some(PopupAction::Open(_)) => " ↵ open · q close ",
+            Some(PopupAction::SwitchTable(_)) => " ↵ switch · q close ",
+            Some(PopupAction::ChooseDialect(_)) => " ↵ choose · q keep the first ",
+            Some(PopupAction::FilterColumn(_, _)) => " ↵ filter in column · q close ",
+            None => " q close ",
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_bottom(Line::from(hint).right_aligned());
+        let inner = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        state.num_visible = (inner.height as usize).max(1);
+        state.adjust_offset();
+
+        for (i, item) in state
+            .items
+            .iter()
+            .enumerate()
+            .skip(state.offset)
+            .take(state.num_visible)
+        {
+            let y = inner.y + (i - state.offset) as u16;
+            // Only entries with an action look selectable
+            let style = if i == state.selected && item.action.is_some() {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            buf.set_style(Rect::new(inner.x, y, inner.width, 1), style);
+            buf.set_stringn(inner.x, y, &item.label, inner.width as usize, style);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::csv::{CsvLensReader, Row};
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// Number of screens of rows to read ahead of the current one
+const PREFETCH_SCREENS: u64 = 3;
+
+/// Reads the records following the current screen on a background thread while scrolling down,
+/// so that the next screens are already in memory when they are shown
+pub struct Prefetcher {
+    internal: Arc<PrefetcherShared>,
+}
+
+impl Prefetcher {
+    pub fn new(reader: CsvLensReader) -> Self {
+        let internal = PrefetcherShared::init(reader);
+        Prefetcher { internal }
+    }
+
+    /// Rows for the given range of record indices if all of them have been prefetched
+    pub fn get_rows(&self, rows_from: u64, num_rows: u64) -> Option<Vec<Row>> {
+        let m_guard = self.internal.state.lock().unwrap();
+        (rows_from..rows_from + num_rows)
+            .map(|i| m_guard.rows.get(&i).cloned())
+            .collect()
+    }
+
+    /// Read the screens following the one starting at rows_from, and forget rows far from it
+    pub fn prefetch(&self, rows_from: u64, num_rows: u64) {
+        let mut m_guard = self.internal.state.lock().unwrap();
+        let window_start = rows_from.saturating_sub(num_rows);
+        let window_end = rows_from + num_rows * (PREFETCH_SCREENS + 1);
+        let mut dropped = 0;
+        m_guard.rows.retain(|&i, row| {
+            let keep = i >= window_start && i < window_end;
+            if !keep {
+                dropped += row.memory_usage();
+            }
+            keep
+        });
+        m_guard.memory_usage -= dropped;
+
+        // Only the rows not prefetched yet need to be read
+        let mut from = rows_from + num_rows;
+        while from < window_end && m_guard.rows.contains_key(&from) {
+            from += 1;
+        }
+        if from < window_end {
+            m_guard.requested = Some((from, window_end - from));
+            self.internal.changed.notify_all();
+        }
+    }
+
+    /// Approximate number of bytes used by the prefetched rows
+    pub fn memory_usage(&self) -> usize {
+        self.internal.state.lock().unwrap().memory_usage
+    }
+
+    /// Forget the prefetched rows, which are read again when scrolling on
+    pub fn clear(&self) {
+        let mut m_guard = self.internal.state.lock().unwrap();
+        m_guard.rows.clear();
+        m_guard.memory_usage = 0;
+    }
+
+    fn terminate(&self) {
+        self.internal.state.lock().unwrap().should_terminate = true;
+        self.internal.changed.notify_all();
+    }
+
+    #[cfg(test)]
+    pub fn wait_internal(&self) {
+        let m_guard = self.internal.state.lock().unwrap();
+        let _m = self
+            .internal
+            .changed
+            .wait_while(m_guard, |m| m.requested.is_some() || m.busy)
+            .unwrap();
+    }
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+struct PrefetcherShared {
+    state: Mutex<PrefetcherInternalState>,
+    /// Notified when a range is requested, when the worker is done reading one and on termination
+    changed: Condvar,
+}
+
+struct PrefetcherInternalState {
+    /// Prefetched rows by record index
+    rows: BTreeMap<u64, Row>,
+    /// Approximate number of bytes used by the rows, kept up to date as they change
+    memory_usage: usize,
+    /// Range of record indices (from, count) to read next
+    requested: Option<(u64, u64)>,
+    busy: bool,
+    should_terminate: bool,
+}
+
+impl PrefetcherShared {
+    fn init(mut reader: CsvLensReader) -> Arc<PrefetcherShared> {
+        let shared = Arc::new(PrefetcherShared {
+            state: Mutex::new(PrefetcherInternalState {
+                rows: BTreeMap::new(),
+                memory_usage: 0,
+                requested: None,
+                busy: false,
+                should_terminate: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let _m = shared.clone();
+        thread::spawn(move || {
+            loop {
+                let (from, count) = {
+                    let m = _m.state.lock().unwrap();
+                    // Sleep until the view scrolls to rows not prefetched yet
+                    let mut m = _m
+                        .changed
+                        .wait_while(m, |m| m.requested.is_none() && !m.should_terminate)
+                        .unwrap();
+                    if m.should_terminate {
+                        break;
+                    }
+                    m.busy = true;
+                    m.requested.take().unwrap()
+                };
+                let rows = reader.get_rows(from, count).map(|(rows, _)| rows);
+                let mut m = _m.state.lock().unwrap();
+                if let Ok(rows) = rows {
+                    for (i, row) in (from..).zip(rows) {
+                        m.memory_usage += row.memory_usage();
+                        if let Some(old) = m.rows.insert(i, row) {
+                            m.memory_usage -= old.memory_usage();
+                        }
+                    }
+                }
+                m.busy = false;
+                _m.changed.notify_all();
+            }
+        });
+
+        shared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv;
+
+    fn reader(filename: &str) -> CsvLensReader {
+        let config = Arc::new(csv::CsvConfig::new(
+            filename,
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let reader = CsvLensReader::new(config).unwrap();
+        reader.wait_internal();
+        reader
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let mut reader = reader("tests/data/simple.csv");
+        let prefetcher = Prefetcher::new(reader.try_clone().unwrap());
+        assert_eq!(prefetcher.get_rows(10, 10), None);
+
+        prefetcher.prefetch(0, 10);
+        prefetcher.wait_internal();
+        assert_eq!(prefetcher.get_rows(0, 10), None);
+        let expected = reader.get_rows(10, 30).unwrap().0;
+        assert_eq!(prefetcher.get_rows(10, 30), Some(expected));
+        assert_eq!(prefetcher.get_rows(35, 10), None);
+
+        // Rows behind the previous screen are dropped
+        prefetcher.prefetch(25, 10);
+        prefetcher.wait_internal();
+        assert_eq!(prefetcher.get_rows(14, 1), None);
+        let expected = reader.get_rows(15, 50).unwrap().0;
+        let usage: usize = expected.iter().map(Row::memory_usage).sum();
+        assert_eq!(prefetcher.get_rows(15, 50), Some(expected));
+        assert_eq!(prefetcher.memory_usage(), usage);
+
+        prefetcher.clear();
+        assert_eq!(prefetcher.get_rows(15, 1), None);
+        assert_eq!(prefetcher.memory_usage(), 0);
+    }
+
+    #[test]
+    fn test_prefetch_beyond_end() {
+        let reader = reader("tests/data/cities.csv");
+        let prefetcher = Prefetcher::new(reader.try_clone().unwrap());
+        let total = reader.get_total_line_numbers().unwrap() as u64;
+        prefetcher.prefetch(total - 5, 10);
+        prefetcher.wait_internal();
+        assert_eq!(prefetcher.get_rows(total, 1), None);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::command::{self, CommandAliases};
+use crate::config;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::sort::NullPlacement;
+
+use serde_json::Value;
+use std::path::Path;
+
+/// How to arrange the view of files matching a pattern, e.g. a routinely viewed export format
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ViewPreset {
+    /// Glob of file names (or paths if it has a `/`) the preset is for, e.g. `*_events.csv`
+    pub files: String,
+    pub hide: Vec<String>,
+    pub freeze: Option<u64>,
+    /// Column to sort by, and whether in descending order
+    pub sort: Option<(String, bool)>,
+    /// Sort by natural ordering, e.g. "file2" before "file10"
+    pub natural_sort: bool,
+    /// Where rows with an empty value to sort by go
+    pub null_placement: Option<NullPlacement>,
+    /// Values that stand for none, e.g. `NA`, which are sorted like empty ones and dimmed
+    pub null_values: Vec<String>,
+    pub widths: Vec<(String, u16)>,
+    /// Colors for values, as given to --color-rule, e.g. `level:ERROR=red`
+    pub color_rules: Vec<String>,
+    /// Commands to run once the file is loaded, e.g. `filter level ERROR`
+    pub on_open: Vec<String>,
+}
+
+/// Presets loaded from a TOML or JSON file like:
+///
+/// ```toml
+/// on_open = ["freeze 1"]
+///
+/// [[preset]]
+/// files = "*_events.csv"
+/// hide = ["debug_info"]
+/// freeze = 1
+/// sort = "-timestamp"
+/// natural_sort = false
+/// nulls = "last"
+/// null_values = ["NA", "-"]
+/// widths = { message = 60 }
+/// color_rules = ["level:ERROR=red"]
+/// on_open = ["filter level ERROR"]
+/// ```
+///
+/// The `on_open` commands at the top are for every file, before those of its preset.
+#[derive(Default)]
+pub struct ViewPresets {
+    presets: Vec<ViewPreset>,
+    on_open: Vec<String>,
+}
+
+impl ViewPresets {
+    /// `$CSVLENS_PRESETS`, or `csvlens/presets.toml` in the platform's config directory
+    pub fn default_path() -> Option<String> {
+        config::config_file("CSVLENS_PRESETS", "presets.toml")
+    }
+
+    /// Presets in the given file. A missing file has none.
+    pub fn load(path: &str) -> CsvlensResult<Self> {
+        if !Path::new(path).exists() {
+            return Ok(ViewPresets::default());
+        }
+        let value = config::load_value(path)?;
+        if value.get("preset").is_none() && value.get("on_open").is_none() {
+            return Err(CsvlensError::ConfigParsing(
+                path.to_string(),
+                "expected [[preset]] tables".into(),
+            ));
+        }
+        Self::from_value(path, &value)
+    }
+
+    /// Add the presets of a project config (`.csvlens.toml`), which come before these. Its
+    /// `on_open` commands for every file replace these, if it has them. They may only change
+    /// what is shown, e.g. not export, as the config comes with the files rather than the user,
+    /// which is checked on the commands of the aliases they stand for too.
+    pub fn with_project(mut self, path: &str, aliases: &CommandAliases) -> CsvlensResult<Self> {
+        let value = config::load_value(path)?;
+        let project = Self::from_value(path, &value)?;
+        let commands = project
+            .on_open
+            .iter()
+            .chain(project.presets.iter().flat_map(|p| &p.on_open));
+        for line in commands {
+            if !aliases.only_changes_view(line) {
+                return Err(CsvlensError::ConfigParsing(
+                    path.to_string(),
+                    format!(
+                        "on_open of a project config can only change the view ({}): {line}",
+                        command::VIEW_COMMANDS.join(", ")
+                    ),
+                ));
+            }
+        }
+        self.presets.splice(0..0, project.presets);
+        if value.get("on_open").is_some() {
+            self.on_open = project.on_open;
+        }
+        Ok(self)
+    }
+
+    fn from_value(path: &str, value: &Value) -> CsvlensResult<Self> {
+        let parse_error =
+            |message: &str| CsvlensError::ConfigParsing(path.to_string(), message.into());
+        let on_open = match value.get("on_open") {
+            Some(commands) => parse_commands(commands)
+                .ok_or_else(|| parse_error("expected a list of on_open commands"))?,
+            None => vec![],
+        };
+        let entries = match value.get("preset") {
+            Some(entries) => entries
+                .as_array()
+                .ok_or_else(|| parse_error("expected [[preset]] tables"))?
+                .as_slice(),
+            None => &[],
+        };
+        let mut presets = vec![];
+        for entry in entries {
+            let preset = parse_preset(entry).ok_or_else(|| {
+                parse_error(
+                    "expected files, and optionally hide, freeze, sort, nulls, null_values, widths, color_rules and on_open for each preset",
+                )
+            })?;
+            presets.push(preset);
+        }
+        Ok(ViewPresets { presets, on_open })
+    }
+
+    /// The first preset for the file, if any
+    pub fn find(&self, filename: &str) -> Option<&ViewPreset> {
+        let name = Path::new(filename)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(filename);
+        self.presets.iter().find(|p| {
+            let target = if p.files.contains('/') {
+                filename
+            } else {
+                name
+            };
+            glob_match(p.files.as_bytes(), target.as_bytes())
+        })
+    }
+
+    /// The commands to run once the file is loaded: those for every file, then those of its preset
+    pub fn on_open(&self, preset: Option<&ViewPreset>) -> Vec<String> {
+        let mut commands = self.on_open.clone();
+        commands.extend(preset.iter().flat_map(|p| p.on_open.iter().cloned()));
+        commands
+    }
+}
+
+fn parse_preset(entry: &Value) -> Option<ViewPreset> {
+    let mut preset = ViewPreset {
+        files: entry.get("files")?.as_str()?.to_string(),
+        ..Default::default()
+    };
+    if let Some(hide) = entry.get("hide") {
+        for name in hide.as_array()? {
+            preset.hide.push(name.as_str()?.to_string());
+        }
+    }
+    if let Some(freeze) = entry.get("freeze") {
+        preset.freeze = Some(freeze.as_u64()?);
+    }
+    if let Some(sort) = entry.get("sort") {
+        let sort = sort.as_str()?;
+        preset.sort = Some(match sort.strip_prefix('-') {
+            Some(column) => (column.to_string(), true),
+            None => (sort.to_string(), false),
+        });
+    }
+    if let Some(natural_sort) = entry.get("natural_sort") {
+        preset.natural_sort = natural_sort.as_bool()?;
+    }
+    if let Some(nulls) = entry.get("nulls") {
+        preset.null_placement = Some(NullPlacement::from_name(nulls.as_str()?)?);
+    }
+    if let Some(null_values) = entry.get("null_values") {
+        for value in null_values.as_array()? {
+            preset.null_values.push(value.as_str()?.to_string());
+        }
+    }
+    if let Some(widths) = entry.get("widths") {
+        for (name, width) in widths.as_object()? {
+            let width = u16::try_from(width.as_u64()?).ok()?;
+            preset.widths.push((name.clone(), width));
+        }
+    }
+    if let Some(color_rules) = entry.get("color_rules") {
+        for rule in color_rules.as_array()? {
+            preset.color_rules.push(rule.as_str()?.to_string());
+        }
+    }
+    if let Some(on_open) = entry.get("on_open") {
+        preset.on_open = parse_commands(on_open)?;
+    }
+    Some(preset)
+}
+
+/// A command, or a list of them
+fn parse_commands(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::String(command) => Some(vec![command.clone()]),
+        Value::Array(commands) => commands
+            .iter()
+            .map(|c| c.as_str().map(String::from))
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Whether the text matches the glob, where `*` matches any run of characters and `?` any one
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && glob_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let matches = |pattern: &str, text: &str| glob_match(pattern.as_bytes(), text.as_bytes());
+        assert!(matches("*_events.csv", "app_events.csv"));
+        assert!(matches("*_events.csv", "_events.csv"));
+        assert!(!matches("*_events.csv", "app_events.csv.bak"));
+        assert!(matches("report-????.csv", "report-2024.csv"));
+        assert!(!matches("report-????.csv", "report-24.csv"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn test_load_and_find() {
+        let presets = ViewPresets::load("tests/data/presets.toml").unwrap();
+        let preset = presets.find("exports/app_events.csv").unwrap();
+        assert_eq!(preset.files, "*_events.csv");
+        assert_eq!(preset.hide, vec!["debug_info".to_string()]);
+        assert_eq!(preset.freeze, Some(1));
+        assert_eq!(preset.sort, Some(("timestamp".to_string(), true)));
+        assert_eq!(preset.widths, vec![("message".to_string(), 12)]);
+        assert!(!preset.natural_sort);
+        assert_eq!(preset.null_placement, Some(NullPlacement::Last));
+        assert_eq!(preset.null_values, vec!["-".to_string()]);
+        assert_eq!(preset.on_open, vec!["filter WARN|ERROR".to_string()]);
+        assert_eq!(presets.on_open(Some(preset)), preset.on_open);
+
+        // Patterns with a slash match the whole path
+        let preset = presets.find("/data/cities.csv").unwrap();
+        assert_eq!(preset.files, "/data/*.csv");
+        assert!(preset.natural_sort);
+        assert_eq!(preset.null_placement, None);
+        assert!(presets.find("cities.csv").is_none());
+
+        let presets = ViewPresets::load("tests/data/missing_presets.toml").unwrap();
+        assert!(presets.find("app_events.csv").is_none());
+    }
+
+    #[test]
+    fn test_on_open_for_every_file() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"on_open = \"freeze 1\"\n\n[[preset]]\nfiles = \"*.csv\"\non_open = [\"sort -id\"]\n",
+        )
+        .unwrap();
+        let presets = ViewPresets::load(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(presets.on_open(None), vec!["freeze 1".to_string()]);
+        assert_eq!(
+            presets.on_open(presets.find("events.csv")),
+            vec!["freeze 1".to_string(), "sort -id".to_string()]
+        );
+
+        // Only commands for every file
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, b"on_open = [\"freeze 2\"]\n").unwrap();
+        let presets = ViewPresets::load(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(presets.on_open(None), vec!["freeze 2".to_string()]);
+    }
+
+    #[test]
+    fn test_with_project() {
+        let presets = ViewPresets::load("tests/data/presets.toml")
+            .unwrap()
+            .with_project(
+                "tests/data/project/.csvlens.toml",
+                &CommandAliases::default(),
+            )
+            .unwrap();
+        let preset = presets.find("daily_events.csv").unwrap();
+        assert_eq!(preset.hide, vec!["debug_info", "message"]);
+        assert_eq!(preset.freeze, None);
+        assert_eq!(
+            preset.color_rules,
+            vec!["level:ERROR=red", "missing:x=blue"]
+        );
+        assert_eq!(presets.on_open(Some(preset)), vec!["goto 3".to_string()]);
+
+        // The user's own presets still apply to other files
+        let preset = presets.find("/data/cities.csv").unwrap();
+        assert_eq!(preset.files, "/data/*.csv");
+        assert_eq!(presets.on_open(Some(preset)), vec!["goto 3".to_string()]);
+    }
+
+    #[test]
+    fn test_project_on_open_view_only() {
+        let project = |content: &str| {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join(config::PROJECT_FILE);
+            std::fs::write(&path, content).unwrap();
+            let path = path.to_str().unwrap();
+            let aliases = CommandAliases::default().with_project(path)?;
+            ViewPresets::default().with_project(path, &aliases)
+        };
+        assert!(project("on_open = [\"sort -id\", \"filter x\", \"50%\"]\n").is_ok());
+        assert!(
+            project("on_open = [\"errors\"]\n\n[aliases]\nerrors = \"filter ERROR\"\n").is_ok()
+        );
+        for content in [
+            "on_open = [\"export /tmp/x\"]\n",
+            "on_open = [\"w /tmp/x\"]\n",
+            "on_open = [\"dump-state /tmp/x\"]\n",
+            "[[preset]]\nfiles = \"*.csv\"\non_open = [\"freeze 1\", \"write-split by=a {}.csv\"]\n",
+            "on_open = [\"errors\"]\n\n[aliases]\nerrors = \"export /tmp/x\"\n",
+            // Aliases expand before commands are resolved, so one named like a shortened command
+            // can't pass for it
+            "on_open = [\"filt x\"]\n\n[aliases]\nfilt = \"export /tmp/x\"\n",
+        ] {
+            assert!(
+                matches!(project(content), Err(CsvlensError::ConfigParsing(_, _))),
+                "{content}"
+            );
+        }
+
+        // The user's own presets may run any command
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, b"on_open = [\"export /tmp/x\"]\n").unwrap();
+        let presets = ViewPresets::load(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(presets.on_open(None), vec!["export /tmp/x".to_string()]);
+
+        // Nor may the project run the user's aliases that don't only change the view
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(config::PROJECT_FILE);
+        std::fs::write(&path, "on_open = [\"save\"]\n").unwrap();
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, b"save = \"export /tmp/x\"\n").unwrap();
+        let aliases = CommandAliases::load(file.path().to_str().unwrap()).unwrap();
+        assert!(matches!(
+            ViewPresets::default().with_project(path.to_str().unwrap(), &aliases),
+            Err(CsvlensError::ConfigParsing(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let result = ViewPresets::load("tests/data/validation.toml");
+        assert!(matches!(result, Err(CsvlensError::ConfigParsing(_, _))));
+    }
+}
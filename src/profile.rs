@@ -0,0 +1,474 @@
+use crate::csv;
+use crate::csv::CsvlensRecordIterator;
+use crate::errors::CsvlensResult;
+use crate::io::input_len;
+use crate::metadata;
+use crate::sketch::{HyperLogLog, TDigest};
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Files smaller than this are scanned fully right away instead of being sampled first
+const SAMPLE_MIN_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Number of evenly spaced places in the file to sample rows from
+const NUM_SAMPLE_CHUNKS: u64 = 16;
+
+const ROWS_PER_SAMPLE_CHUNK: usize = 1000;
+
+/// How many distinct values a column has relative to its number of values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cardinality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Cardinality {
+    fn new(distinct: u64, count: u64) -> Self {
+        if count > 0 && distinct * 2 >= count {
+            Cardinality::High
+        } else if distinct <= 20 || distinct * 100 <= count {
+            Cardinality::Low
+        } else {
+            Cardinality::Medium
+        }
+    }
+
+    pub fn badge(&self) -> &'static str {
+        match self {
+            Cardinality::Low => "low",
+            Cardinality::Medium => "med",
+            Cardinality::High => "high",
+        }
+    }
+}
+
+/// Whether profiles are estimated from a sample or partial scan, or computed from all rows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    Approximate,
+    Exact,
+}
+
+impl Precision {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Precision::Approximate => "approx",
+            Precision::Exact => "exact",
+        }
+    }
+}
+
+/// Summary of the values of a single column
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnProfile {
+    /// Number of non-empty values
+    pub count: u64,
+    pub empty: u64,
+    /// Approximate number of distinct non-empty values
+    pub distinct: u64,
+    pub cardinality: Cardinality,
+    /// Only set if all non-empty values are numbers, or all are percentages
+    pub numeric: Option<NumericStats>,
+    /// Only set if all non-empty values are booleans, e.g. yes/no
+    pub boolean: Option<BooleanStats>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumericStats {
+    /// Whether the values are percentages, which the stats are in
+    pub percentage: bool,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Approximate 50th, 90th and 99th percentiles
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BooleanStats {
+    pub true_count: u64,
+    pub false_count: u64,
+}
+
+/// Profiles the columns of a file in the background. Large files are sampled first so that
+/// approximate profiles are available right away, while all rows are scanned for exact ones.
+pub struct Profiler {
+    internal: Arc<Mutex<ProfilerInternalState>>,
+}
+
+impl Profiler {
+    pub fn new(config: Arc<csv::CsvConfig>) -> Self {
+        let internal = ProfilerInternalState::init(config);
+        Profiler { internal }
+    }
+
+    /// Profiles by origin column index, with their precision
+    pub fn profiles(&self) -> (Vec<ColumnProfile>, Precision) {
+        let m_guard = self.internal.lock().unwrap();
+        if m_guard.done {
+            return (to_profiles(&m_guard.columns, 1.0), Precision::Exact);
+        }
+        let profiles = match &m_guard.sample {
+            Some(sample) => to_profiles(&sample.columns, sample.scale),
+            None => to_profiles(&m_guard.columns, 1.0),
+        };
+        (profiles, Precision::Approximate)
+    }
+
+    /// Approximate number of bytes used by the sketches of the sample and the full scan
+    pub fn memory_usage(&self) -> usize {
+        let m_guard = self.internal.lock().unwrap();
+        let sample_columns = m_guard.sample.iter().flat_map(|s| s.columns.iter());
+        m_guard
+            .columns
+            .iter()
+            .chain(sample_columns)
+            .map(ColumnAccumulator::memory_usage)
+            .sum()
+    }
+
+    fn terminate(&self) {
+        self.internal.lock().unwrap().should_terminate = true;
+    }
+
+    #[cfg(test)]
+    pub fn wait_internal(&self) {
+        loop {
+            if self.internal.lock().unwrap().done {
+                break;
+            }
+            thread::sleep(core::time::Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+/// Profiles from accumulated values, with counts multiplied by scale to extrapolate from a sample
+fn to_profiles(columns: &[ColumnAccumulator], scale: f64) -> Vec<ColumnProfile> {
+    let extrapolate = |n: u64| (n as f64 * scale).round() as u64;
+    columns
+        .iter()
+        .map(|c| {
+            // The estimate can be slightly off, but never more than the number of values
+            let distinct = c.sketch.estimate().min(c.count);
+            let cardinality = Cardinality::new(distinct, c.count);
+            // Only mostly unique columns gain distinct values proportionally to the number of rows
+            let distinct = match cardinality {
+                Cardinality::High => extrapolate(distinct),
+                _ => distinct,
+            };
+            let numeric = if c.count > 0 && c.numeric_count == c.count {
+                let quantile = |q: f64| c.digest.quantile(q).unwrap_or(f64::NAN);
+                Some(NumericStats {
+                    percentage: c.percentage_count == c.count,
+                    min: c.min,
+                    max: c.max,
+                    mean: c.sum / c.numeric_count as f64,
+                    p50: quantile(0.5),
+                    p90: quantile(0.9),
+                    p99: quantile(0.99),
+                })
+            } else {
+                None
+            };
+            let boolean =
+                (c.count > 0 && c.true_count + c.false_count == c.count).then(|| BooleanStats {
+                    true_count: extrapolate(c.true_count),
+                    false_count: extrapolate(c.false_count),
+                });
+            ColumnProfile {
+                count: extrapolate(c.count),
+                empty: extrapolate(c.empty),
+                distinct,
+                cardinality,
+                numeric,
+                boolean,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+struct ColumnAccumulator {
+    count: u64,
+    empty: u64,
+    sketch: HyperLogLog,
+    numeric_count: u64,
+    percentage_count: u64,
+    true_count: u64,
+    false_count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    digest: TDigest,
+}
+
+impl ColumnAccumulator {
+    fn new() -> Self {
+        ColumnAccumulator {
+            count: 0,
+            empty: 0,
+            sketch: HyperLogLog::new(),
+            numeric_count: 0,
+            percentage_count: 0,
+            true_count: 0,
+            false_count: 0,
+            sum: 0.0,
+            min: f64::MAX,
+            max: f64::MIN,
+            digest: TDigest::new(),
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        std::mem::size_of::<ColumnAccumulator>()
+            + self.sketch.memory_usage()
+            + self.digest.memory_usage()
+    }
+
+    fn add(&mut self, value: &str, config: &csv::CsvConfig) {
+        if value.is_empty() {
+            self.empty += 1;
+            return;
+        }
+        self.count += 1;
+        self.sketch.insert(value);
+        match metadata::parse_bool(value) {
+            Some(true) => self.true_count += 1,
+            Some(false) => self.false_count += 1,
+            None => {}
+        }
+        let number = config.parse_number(value).or_else(|| {
+            let percentage = config.parse_percentage(value);
+            if percentage.is_some() {
+                self.percentage_count += 1;
+            }
+            percentage
+        });
+        if let Some(n) = number
+            && n.is_finite()
+        {
+            self.numeric_count += 1;
+            self.sum += n;
+            self.min = self.min.min(n);
+            self.max = self.max.max(n);
+            self.digest.insert(n);
+        }
+    }
+
+    fn add_record(
+        columns: &mut Vec<ColumnAccumulator>,
+        record: &::csv::StringRecord,
+        config: &csv::CsvConfig,
+    ) {
+        let num_fields = record.len().min(config.max_fields().unwrap_or(usize::MAX));
+        if columns.len() < num_fields {
+            columns.resize(num_fields, ColumnAccumulator::new());
+        }
+        for (column, value) in columns.iter_mut().zip(record.iter()) {
+            column.add(value, config);
+        }
+    }
+}
+
+struct Sample {
+    columns: Vec<ColumnAccumulator>,
+    /// Estimated number of rows in the file per sampled row
+    scale: f64,
+}
+
+/// Read rows from evenly spaced places in the file. Returns None if the file is small enough to
+/// be scanned quickly.
+fn sample(config: &csv::CsvConfig, min_file_size: u64) -> CsvlensResult<Option<Sample>> {
+    // Record batches are in memory already, and scanned quickly
+    if config.is_streaming() || config.arrow_table().is_some() {
+        return Ok(None);
+    }
+    let file_size = input_len(config.filename())?;
+    if file_size < min_file_size {
+        return Ok(None);
+    }
+    let mut reader = config.new_reader()?;
+    if config.has_headers() {
+        reader.byte_headers()?;
+    }
+    let data_start = reader.position().byte();
+    let data_size = file_size.saturating_sub(data_start);
+    let chunk_offset = |i: u64| data_start + data_size * i / NUM_SAMPLE_CHUNKS;
+
+    let mut columns = vec![];
+    let mut sampled_bytes = 0;
+    let mut record = ::csv::StringRecord::new();
+    for i in 0..NUM_SAMPLE_CHUNKS {
+        if i > 0 {
+            let mut position = ::csv::Position::new();
+            position.set_byte(chunk_offset(i));
+            reader.seek(position)?;
+            // Skip the rest of the row the offset falls into
+            if !reader.read_record(&mut record).unwrap_or(false) {
+                break;
+            }
+        }
+        let chunk_start = reader.position().byte();
+        let mut num_rows = 0;
+        while num_rows < ROWS_PER_SAMPLE_CHUNK
+            && reader.position().byte() < chunk_offset(i + 1)
+            && reader.read_record(&mut record).unwrap_or(false)
+        {
+            ColumnAccumulator::add_record(&mut columns, &record, config);
+            num_rows += 1;
+        }
+        sampled_bytes += reader.position().byte().saturating_sub(chunk_start);
+    }
+    if sampled_bytes == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Sample {
+        columns,
+        scale: data_size as f64 / sampled_bytes as f64,
+    }))
+}
+
+struct ProfilerInternalState {
+    sample: Option<Sample>,
+    columns: Vec<ColumnAccumulator>,
+    done: bool,
+    should_terminate: bool,
+}
+
+impl ProfilerInternalState {
+    fn init(config: Arc<csv::CsvConfig>) -> Arc<Mutex<ProfilerInternalState>> {
+        let m_state = Arc::new(Mutex::new(ProfilerInternalState {
+            sample: None,
+            columns: vec![],
+            done: false,
+            should_terminate: false,
+        }));
+
+        let _m = m_state.clone();
+        thread::spawn(move || {
+            if let Ok(sample) = sample(&config, SAMPLE_MIN_FILE_SIZE) {
+                _m.lock().unwrap().sample = sample;
+            }
+            let records = CsvlensRecordIterator::new(config.clone()).unwrap();
+            for record in records.flatten() {
+                let mut m = _m.lock().unwrap();
+                if m.should_terminate {
+                    break;
+                }
+                ColumnAccumulator::add_record(&mut m.columns, &record, &config);
+            }
+            _m.lock().unwrap().done = true;
+        });
+
+        m_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(filename: &str) -> Arc<csv::CsvConfig> {
+        Arc::new(csv::CsvConfig::new(
+            filename,
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ))
+    }
+
+    #[test]
+    fn test_profiler() {
+        let profiler = Profiler::new(config("tests/data/validation.csv"));
+        profiler.wait_internal();
+        let (profiles, precision) = profiler.profiles();
+        assert_eq!(precision, Precision::Exact);
+        assert_eq!(profiles.len(), 4);
+        assert_eq!(profiles[0].distinct, 5);
+        assert_eq!(profiles[0].cardinality, Cardinality::High);
+        assert_eq!(
+            profiles[0].numeric,
+            Some(NumericStats {
+                percentage: false,
+                min: 1.0,
+                max: 5.0,
+                mean: 3.0,
+                p50: 3.0,
+                p90: 5.0,
+                p99: 5.0,
+            })
+        );
+        assert_eq!(profiles[1].empty, 1);
+        assert_eq!(profiles[1].count, 4);
+        assert_eq!(profiles[1].numeric, None);
+        assert_eq!(profiles[1].boolean, None);
+    }
+
+    #[test]
+    fn test_percentages_and_booleans() {
+        let profiler = Profiler::new(config("tests/data/kinds.csv"));
+        profiler.wait_internal();
+        let (profiles, _) = profiler.profiles();
+        let stats = profiles[1].numeric.as_ref().unwrap();
+        assert!(stats.percentage);
+        assert_eq!((stats.min, stats.max), (-2.5, 100.0));
+        assert_eq!(
+            profiles[2].boolean,
+            Some(BooleanStats {
+                true_count: 3,
+                false_count: 2,
+            })
+        );
+        assert_eq!(profiles[2].numeric, None);
+    }
+
+    #[test]
+    fn test_decimal_comma() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/decimal_comma.csv",
+            None,
+            csv::CsvBaseConfig::new(b';', false).with_decimal_comma(true),
+        ));
+        let profiler = Profiler::new(config);
+        profiler.wait_internal();
+        let (profiles, _) = profiler.profiles();
+        let stats = profiles[1].numeric.as_ref().unwrap();
+        assert_eq!((stats.min, stats.max), (4.5, 1234.5));
+    }
+
+    #[test]
+    fn test_sample() {
+        assert!(
+            sample(&config("tests/data/simple.csv"), SAMPLE_MIN_FILE_SIZE)
+                .unwrap()
+                .is_none()
+        );
+
+        // Rows are sampled from all over the file, and counts are extrapolated to all 5000 rows
+        let sample = sample(&config("tests/data/simple.csv"), 0)
+            .unwrap()
+            .unwrap();
+        let profiles = to_profiles(&sample.columns, sample.scale);
+        let count = profiles[0].count as f64;
+        assert!((count - 5000.0).abs() / 5000.0 < 0.01, "{count}");
+        assert!(sample.columns[0].count < 5000);
+        assert_eq!(profiles[0].cardinality, Cardinality::High);
+    }
+
+    #[test]
+    fn test_cardinality() {
+        assert_eq!(Cardinality::new(5, 1000), Cardinality::Low);
+        assert_eq!(Cardinality::new(100, 1000), Cardinality::Medium);
+        assert_eq!(Cardinality::new(800, 1000), Cardinality::High);
+        assert_eq!(Cardinality::new(0, 0), Cardinality::Low);
+    }
+}
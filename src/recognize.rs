@@ -0,0 +1,60 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+const HEX4: &str = "[0-9a-fA-F]{1,4}";
+
+/// Emails, IPv4/IPv6 addresses and UUIDs, which are styled when pattern highlighting is enabled
+static PATTERNS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    let email = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+    let uuid = r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b";
+    let octet = r"(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)";
+    let ipv4 = format!(r"\b{octet}(?:\.{octet}){{3}}\b");
+    // Full form, compressed form with :: in the middle, or a leading ::
+    let ipv6 = format!(
+        r"\b(?:{HEX4}:){{7}}{HEX4}\b|\b(?:{HEX4}:){{1,7}}(?::{HEX4}){{1,7}}\b|::{HEX4}(?::{HEX4}){{0,6}}\b"
+    );
+    Regex::new(&format!("{email}|{uuid}|{ipv4}|{ipv6}")).unwrap()
+});
+
+/// Byte ranges of recognized values in the given text
+pub fn find_patterns(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    PATTERNS_REGEX.find_iter(text).map(|m| (m.start(), m.end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(text: &str) -> Vec<&str> {
+        find_patterns(text).map(|(s, e)| &text[s..e]).collect()
+    }
+
+    #[test]
+    fn test_find_patterns() {
+        assert_eq!(
+            matches("login by alice.smith+audit@example.co.uk from 10.0.0.255"),
+            vec!["alice.smith+audit@example.co.uk", "10.0.0.255"]
+        );
+        assert_eq!(
+            matches("request 123e4567-e89b-12d3-a456-426614174000 failed"),
+            vec!["123e4567-e89b-12d3-a456-426614174000"]
+        );
+        assert_eq!(
+            matches("2001:db8::8a2e:370:7334 and 2001:0db8:0000:0000:0000:ff00:0042:8329"),
+            vec![
+                "2001:db8::8a2e:370:7334",
+                "2001:0db8:0000:0000:0000:ff00:0042:8329"
+            ]
+        );
+        assert_eq!(matches("::1"), vec!["::1"]);
+    }
+
+    #[test]
+    fn test_no_false_positives() {
+        assert!(matches("12:30:45").is_empty());
+        assert!(matches("256.1.1.1").is_empty());
+        assert!(matches("version 1.2.3").is_empty());
+        assert!(matches("00:1a:2b:3c:4d:5e").is_empty());
+        assert!(matches("user@localhost").is_empty());
+    }
+}
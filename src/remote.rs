@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+/// Bytes fetched per range request
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Chunks kept in memory, the least recently read dropped first
+const MAX_CHUNKS: usize = 16;
+
+/// Fetch of the bytes of an object from a start offset up to an end offset
+type FetchRange = Box<dyn Fn(u64, u64) -> io::Result<Vec<u8>> + Send + Sync>;
+
+/// Object read with range requests of CHUNK_SIZE bytes as parts of it are needed, so that only
+/// the rows looked at are downloaded. Only read operations are used: the object is never
+/// modified.
+pub struct RemoteObject {
+    size: u64,
+    fetch: FetchRange,
+    /// Chunks read recently by their index, the most recent last
+    chunks: Mutex<VecDeque<(u64, Arc<Vec<u8>>)>>,
+}
+
+impl RemoteObject {
+    pub fn new(size: u64, fetch: FetchRange) -> RemoteObject {
+        RemoteObject {
+            size,
+            fetch,
+            chunks: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// Reader of the object from its start, with its own position
+    pub fn reader(self: &Arc<Self>) -> ObjectReader {
+        ObjectReader {
+            object: self.clone(),
+            offset: 0,
+        }
+    }
+
+    fn chunk(&self, index: u64) -> io::Result<Arc<Vec<u8>>> {
+        {
+            let mut chunks = self.chunks.lock().unwrap();
+            if let Some(i) = chunks.iter().position(|(j, _)| *j == index) {
+                let entry = chunks.remove(i).unwrap();
+                let chunk = entry.1.clone();
+                chunks.push_back(entry);
+                return Ok(chunk);
+            }
+        }
+        // Fetched without holding the lock, so that readers of other chunks don't wait for it
+        let start = index * CHUNK_SIZE;
+        let end = (start + CHUNK_SIZE).min(self.size);
+        let chunk = Arc::new((self.fetch)(start, end)?);
+        let mut chunks = self.chunks.lock().unwrap();
+        if !chunks.iter().any(|(j, _)| *j == index) {
+            if chunks.len() >= MAX_CHUNKS {
+                chunks.pop_front();
+            }
+            chunks.push_back((index, chunk.clone()));
+        }
+        Ok(chunk)
+    }
+}
+
+/// Position in a [RemoteObject] read from
+pub struct ObjectReader {
+    object: Arc<RemoteObject>,
+    offset: u64,
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset >= self.object.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let chunk = self.object.chunk(self.offset / CHUNK_SIZE)?;
+        let chunk_pos = (self.offset % CHUNK_SIZE) as usize;
+        if chunk_pos >= chunk.len() {
+            // The object is shorter than when opened
+            return Ok(0);
+        }
+        let n = buf.len().min(chunk.len() - chunk_pos);
+        buf[..n].copy_from_slice(&chunk[chunk_pos..chunk_pos + n]);
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for ObjectReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.object.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.offset.checked_add_signed(offset),
+        };
+        self.offset = offset.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Object of the bytes, counting the range requests made
+    fn remote_object(data: Vec<u8>) -> (Arc<RemoteObject>, Arc<Mutex<Vec<u64>>>) {
+        let size = data.len() as u64;
+        let requests = Arc::new(Mutex::new(vec![]));
+        let _requests = requests.clone();
+        let fetch = move |start: u64, end: u64| {
+            _requests.lock().unwrap().push(start);
+            Ok(data[start as usize..end as usize].to_vec())
+        };
+        let object = Arc::new(RemoteObject::new(size, Box::new(fetch)));
+        (object, requests)
+    }
+
+    #[test]
+    fn test_read_ranges() {
+        let data: Vec<u8> = (0..CHUNK_SIZE * 3 + 10).map(|i| (i % 251) as u8).collect();
+        let (object, requests) = remote_object(data.clone());
+        assert_eq!(object.len(), data.len() as u64);
+        let mut reader = object.reader();
+
+        // Only the chunk of the part read is fetched
+        let mut buf = [0; 4];
+        reader.seek(SeekFrom::Start(CHUNK_SIZE * 2 + 1)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        let offset = (CHUNK_SIZE * 2 + 1) as usize;
+        assert_eq!(buf, data[offset..offset + 4]);
+        assert_eq!(*requests.lock().unwrap(), vec![CHUNK_SIZE * 2]);
+
+        // Reads across chunks, and of chunks read before by another reader, which aren't fetched
+        // again
+        let mut other = object.reader();
+        other.seek(SeekFrom::End(-12)).unwrap();
+        let mut rest = vec![];
+        other.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, data[data.len() - 12..]);
+        reader.seek(SeekFrom::Start(CHUNK_SIZE * 2)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![CHUNK_SIZE * 2, CHUNK_SIZE * 3]
+        );
+
+        let mut all = vec![];
+        object.reader().read_to_end(&mut all).unwrap();
+        assert_eq!(all, data);
+        assert!(
+            reader
+                .seek(SeekFrom::Current(-(CHUNK_SIZE as i64) * 3))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_least_recently_read_chunks_dropped() {
+        let data = vec![b'a'; (CHUNK_SIZE as usize) * (MAX_CHUNKS + 1)];
+        let (object, requests) = remote_object(data);
+        let mut reader = object.reader();
+        let mut buf = [0; 1];
+        let mut read_chunk = |index: u64| {
+            reader.seek(SeekFrom::Start(index * CHUNK_SIZE)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+        };
+        for index in 0..MAX_CHUNKS as u64 {
+            read_chunk(index);
+        }
+        // The first chunk read again becomes the most recent, so the second one is dropped
+        read_chunk(0);
+        read_chunk(MAX_CHUNKS as u64);
+        assert_eq!(requests.lock().unwrap().len(), MAX_CHUNKS + 1);
+        read_chunk(0);
+        assert_eq!(requests.lock().unwrap().len(), MAX_CHUNKS + 1);
+        read_chunk(1);
+        assert_eq!(requests.lock().unwrap().len(), MAX_CHUNKS + 2);
+        assert_eq!(object.chunks.lock().unwrap().len(), MAX_CHUNKS);
+    }
+}
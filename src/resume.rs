@@ -0,0 +1,173 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+
+/// Number of files to remember positions for. The least recently viewed ones are forgotten first.
+const MAX_ENTRIES: usize = 1000;
+
+/// Where a file was left off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewPosition {
+    /// Record number of the selected row
+    pub row: usize,
+    /// Number of columns scrolled past
+    pub col: u64,
+}
+
+/// Last viewed positions of files across sessions, like the history file of less. Kept as JSON
+/// with the most recently viewed file last.
+pub struct PositionHistory {
+    path: String,
+    entries: Vec<(String, ViewPosition)>,
+}
+
+impl PositionHistory {
+    /// `$CSVLENS_HISTFILE`, or `csvlens/positions.json` in the platform's state directory
+    pub fn default_path() -> Option<String> {
+        if let Ok(path) = std::env::var("CSVLENS_HISTFILE") {
+            return Some(path);
+        }
+        let state_dir = if cfg!(windows) {
+            std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/state")))
+        }?;
+        let path = state_dir.join("csvlens").join("positions.json");
+        path.to_str().map(|s| s.to_string())
+    }
+
+    /// Positions stored in the given file, which is created on save if missing
+    pub fn load(path: &str) -> CsvlensResult<Self> {
+        let mut history = PositionHistory {
+            path: path.to_string(),
+            entries: vec![],
+        };
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(history),
+            Err(e) => return Err(e.into()),
+        };
+        let parse_error =
+            |message: &str| CsvlensError::ConfigParsing(path.to_string(), message.into());
+        let value: Value =
+            serde_json::from_str(&content).map_err(|e| parse_error(&e.to_string()))?;
+        let files = value
+            .get("files")
+            .and_then(|f| f.as_array())
+            .ok_or_else(|| parse_error("expected a files array"))?;
+        for entry in files {
+            let (Some(file), Some(row), Some(col)) = (
+                entry.get("path").and_then(|v| v.as_str()),
+                entry.get("row").and_then(|v| v.as_u64()),
+                entry.get("col").and_then(|v| v.as_u64()),
+            ) else {
+                return Err(parse_error("expected path, row and col for each file"));
+            };
+            let position = ViewPosition {
+                row: row as usize,
+                col,
+            };
+            history.entries.push((file.to_string(), position));
+        }
+        Ok(history)
+    }
+
+    pub fn get(&self, file: &str) -> Option<ViewPosition> {
+        self.entries
+            .iter()
+            .find(|(f, _)| f == file)
+            .map(|(_, position)| *position)
+    }
+
+    pub fn set(&mut self, file: &str, position: ViewPosition) {
+        self.entries.retain(|(f, _)| f != file);
+        self.entries.push((file.to_string(), position));
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+    }
+
+    pub fn save(&self) -> CsvlensResult<()> {
+        if let Some(dir) = Path::new(&self.path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let files: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|(file, position)| {
+                json!({
+                    "path": file,
+                    "row": position.row,
+                    "col": position.col,
+                })
+            })
+            .collect();
+        let content = serde_json::to_string_pretty(&json!({ "files": files }))
+            .map_err(|e| CsvlensError::ConfigParsing(self.path.clone(), e.to_string()))?;
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+/// Key of a file in the history, so that it is found however it is opened
+pub fn file_key(filename: &str) -> Option<String> {
+    let path = std::fs::canonicalize(filename).ok()?;
+    path.to_str().map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/positions.json");
+        let path = path.to_str().unwrap();
+
+        let mut history = PositionHistory::load(path).unwrap();
+        assert_eq!(history.get("/a.csv"), None);
+        let position = ViewPosition { row: 1234, col: 2 };
+        history.set("/a.csv", position);
+        history.set("/b.csv", ViewPosition { row: 1, col: 0 });
+        history.set("/a.csv", ViewPosition { row: 99, col: 0 });
+        history.save().unwrap();
+
+        let history = PositionHistory::load(path).unwrap();
+        assert_eq!(
+            history.get("/a.csv"),
+            Some(ViewPosition { row: 99, col: 0 })
+        );
+        assert_eq!(
+            history
+                .entries
+                .iter()
+                .map(|(f, _)| f.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/b.csv", "/a.csv"]
+        );
+
+        std::fs::write(path, r#"{"files": [{"path": "/a.csv"}]}"#).unwrap();
+        assert!(matches!(
+            PositionHistory::load(path),
+            Err(CsvlensError::ConfigParsing(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let mut history = PositionHistory {
+            path: String::new(),
+            entries: vec![],
+        };
+        for i in 0..MAX_ENTRIES + 5 {
+            history.set(&format!("/{i}.csv"), ViewPosition { row: i, col: 0 });
+        }
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.get("/4.csv"), None);
+        assert_eq!(history.get("/5.csv"), Some(ViewPosition { row: 5, col: 0 }));
+    }
+}
@@ -1,6 +1,15 @@
-use crate::app::{App, WrapMode};
-use crate::delimiter::Delimiter;
-use crate::errors::CsvlensResult;
+use crate::app::{App, AppOptions, WrapMode};
+use crate::command::CommandAliases;
+use crate::console;
+use crate::delimiter::{self, Delimiter};
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::io;
+use crate::locale::{self, NumberFormat};
+use crate::presets::ViewPresets;
+use crate::resume::PositionHistory;
+use crate::slice::Slice;
+
+use regex::Regex;
 
 #[cfg(feature = "cli")]
 use clap::ArgGroup;
@@ -8,14 +17,10 @@ use clap::ArgGroup;
 use clap::Parser;
 #[cfg(feature = "cli")]
 use clap::ValueEnum;
-use crossterm::execute;
-use crossterm::terminal::{
-    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
-};
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use std::ffi::OsString;
-use std::io::LineWriter;
+use std::io::BufWriter;
 use std::panic;
 use std::thread::panicking;
 
@@ -32,6 +37,12 @@ pub enum ClapWrapMode {
 #[command(version)]
 #[command(group(ArgGroup::new("wrap_flags").conflicts_with("wrap")))]
 #[command(styles = clap_cargo::style::CLAP_STYLING)]
+// Options given again, e.g. on the command line after those of CSVLENS_OPTS, replace the others
+#[command(args_override_self = true)]
+#[command(after_help = concat!(
+    "Default options can be set in $CSVLENS_OPTS, e.g. CSVLENS_OPTS=\"--ignore-case --theme dark\". ",
+    "They come before the options given on the command line, which override them."
+))]
 struct Args {
     /// CSV filename
     filename: Option<String>,
@@ -48,10 +59,32 @@ struct Args {
     #[clap(short = 'c', long)]
     comma_separated: bool,
 
-    /// Do not interpret the first row as headers.
+    /// Character fields are quoted with, e.g. ' for files quoting with single quotes [default: "]
+    #[arg(long, value_name = "char")]
+    quote_char: Option<String>,
+
+    /// Character escaping quotes in quoted fields, e.g. \ for files writing \" rather than ""
+    #[arg(long, value_name = "char")]
+    escape_char: Option<String>,
+
+    /// Read quotes as any other character, for files whose fields have stray quotes in them
+    #[clap(long, conflicts_with_all = ["quote_char", "escape_char"])]
+    no_quoting: bool,
+
+    /// Encoding of the file, e.g. latin1, windows-1252, utf-16 or shift_jis, read as UTF-8 after
+    /// converting it. By default it is guessed from the start of the file. [default: auto]
+    #[arg(long, value_name = "name")]
+    encoding: Option<String>,
+
+    /// Do not interpret the first row as headers
     #[clap(long)]
     no_headers: bool,
 
+    /// Tell whether the first row is a header from whether it looks different from the rest, e.g.
+    /// text above numbers, instead of always reading it as one
+    #[clap(long, conflicts_with = "no_headers")]
+    detect_header: bool,
+
     /// Use this regex to select columns to display by default
     ///
     /// Example: "column1|column2" matches "column1", "column2", and also column names like
@@ -77,6 +110,29 @@ struct Args {
     #[arg(long, value_name = "regex")]
     find: Option<String>,
 
+    /// Print the number of records matching --filter (or --find), or of all records without one,
+    /// instead of viewing the file. Exits with 1 if there are none, and 2 on errors.
+    #[clap(long, conflicts_with = "exists")]
+    count_only: bool,
+
+    /// Print nothing and only exit with 0 if any record matches --filter (or --find), or 1 if none
+    /// does, instead of viewing the file. Exits with 2 on errors.
+    #[clap(long)]
+    exists: bool,
+
+    /// Run the commands in this file on start, one per line, e.g. to open a reproducible view or
+    /// to export it without interaction (ending with quit). Commands are the same as after : in
+    /// the view: goto <n>, find <regex>, filter <regex>, columns <regex>, hide <col>[, <col>...],
+    /// sort [-]<col>, freeze <n>, export <file> [options],
+    /// write-split by=<col> <file> [options], dump-state [file] and quit.
+    #[arg(long, value_name = "file")]
+    script: Option<String>,
+
+    /// Print the state the view opens in as JSON (file, filters, sort, columns and selection)
+    /// instead of viewing the file. `:dump-state <file>` saves it from the view later on.
+    #[clap(long, conflicts_with_all = ["count_only", "exists"])]
+    dump_state: bool,
+
     /// Searches ignore case. Ignored if any uppercase letters are present in the search string
     #[clap(short, long)]
     ignore_case: bool,
@@ -106,15 +162,205 @@ struct Args {
     #[arg(short = 'W', group = "wrap_flags")]
     pub wrap_words: bool,
 
+    /// Open only this byte range of the file, e.g. 1G..2G. Records starting within the range are
+    /// shown, so the range doesn't need to fall on record boundaries.
+    #[arg(long, value_name = "range", conflicts_with = "rows")]
+    bytes: Option<String>,
+
+    /// Open only the records numbered in this range, e.g. 1000000..2000000 (end exclusive)
+    #[arg(long, value_name = "range")]
+    rows: Option<String>,
+
+    /// View CSV or TSV content in the system clipboard instead of a file, e.g. a table copied from
+    /// a spreadsheet or a web page
+    #[clap(long, conflicts_with_all = ["filename", "bytes", "rows"])]
+    from_clipboard: bool,
+
+    /// Sheet of an Excel workbook to view, by name or number counting from 1. The first sheet is
+    /// shown by default.
+    #[arg(long, value_name = "name")]
+    sheet: Option<String>,
+
+    /// Table or view of a SQLite database to view. The first table is shown by default.
+    #[arg(long, value_name = "name")]
+    table: Option<String>,
+
+    /// SQL query whose rows to view from a SQLite database instead of a table, e.g.
+    /// "SELECT * FROM orders WHERE total > 100". The database isn't changed.
+    #[arg(long, value_name = "sql", conflicts_with = "table")]
+    query: Option<String>,
+
+    /// Leave out this many rows at the end of the file, e.g. totals or notes appended by exports,
+    /// or "auto" to leave out trailing rows with a different number of fields than the header
+    #[arg(long, value_name = "n|auto")]
+    skip_footer: Option<String>,
+
+    /// Read the first row after the header as a subheader, e.g. units or descriptions, pinned
+    /// beneath the header and left out of sorting, finding and column stats
+    #[clap(long)]
+    subheader: bool,
+
+    /// Group columns sharing the prefix of their names, e.g. `http_status` and `http_latency`,
+    /// under a row spanning each group. Press -G to collapse the group of the selected column.
+    #[clap(long)]
+    group_columns: bool,
+
+    /// Show tables separated by blank lines or new header rows in the file one at a time instead
+    /// of as one table. Press -T to switch between them.
+    #[clap(long)]
+    split_tables: bool,
+
+    /// Read numbers with a decimal comma, e.g. 1.234,56, for sorting, column stats and validation
+    #[clap(long)]
+    decimal_comma: bool,
+
+    /// Trim leading and trailing whitespace in cells, e.g. of padded exports, for display, finding,
+    /// filtering, sorting and copying
+    #[clap(long)]
+    trim: bool,
+
+    /// Show at most this many fields of each record, so that malformed files with huge records
+    /// stay responsive. Press -F to show all of them. [default: 10000]
+    #[arg(long, value_name = "n")]
+    max_fields: Option<usize>,
+
+    /// Don't ask how to read files whose delimiter or header row can't be told for sure, and go
+    /// with the best guess
+    #[clap(long)]
+    no_wizard: bool,
+
+    /// Announce the selected cell in the status bar, e.g. "row 15, column 'price', value 42.10",
+    /// and leave out borders, for screen readers. Press -A to toggle.
+    #[clap(long)]
+    screen_reader: bool,
+
+    /// Color theme: auto (light or dark following the terminal), dark, light, high-contrast, or
+    /// deuteranopia or protanopia for colors that stay apart without telling red from green
+    #[arg(long, value_name = "name")]
+    theme: Option<String>,
+
+    /// Override a semantic color of the theme, e.g. match=yellow or error=#ff00ff (repeatable).
+    /// Roles are match, current-match, error, pattern, marked and selection.
+    #[arg(long, value_name = "role=color")]
+    theme_color: Vec<String>,
+
+    /// Ask the terminal to show each frame at once with synchronized update escapes, which avoids
+    /// flicker when scrolling on terminals that support them. Others ignore the escapes.
+    #[clap(long)]
+    sync_output: bool,
+
+    /// Don't arrange the view by the preset for files named like this one, nor run on_open
+    /// commands. Presets are read from $CSVLENS_PRESETS, or csvlens/presets.toml in the user's
+    /// config directory, and from a .csvlens.toml in the directory of the file or above.
+    #[clap(long)]
+    no_presets: bool,
+
+    /// Start where the file was left off last time, and remember the position on exit. Positions
+    /// are kept in $CSVLENS_HISTFILE, or csvlens/positions.json in the user's state directory.
+    #[clap(long, overrides_with = "no_resume")]
+    resume: bool,
+
+    /// Start from the top, overriding an earlier --resume (e.g. in an alias)
+    #[clap(long, overrides_with = "resume")]
+    no_resume: bool,
+
     /// Auto-reload the file when it changes on disk
     #[clap(long)]
     pub auto_reload: bool,
 
+    /// Validate cells against the rules in this TOML or JSON file and highlight failing cells
+    ///
+    /// Rules are given per column name under a "columns" table, e.g. `[columns.age]` with
+    /// `required = true`, `pattern = '^\d+$'`, `min = 0` and `max = 120`. Press v to list the
+    /// failures.
+    #[arg(long, value_name = "spec")]
+    validate: Option<String>,
+
+    /// Show headers under display names from this TOML or JSON file, e.g. `c_017 = "Signup date"`.
+    /// Printed and copied output keeps the original names.
+    #[arg(long, value_name = "file")]
+    header_aliases: Option<String>,
+
+    /// Decode the values of a column for display, given as <column>=<decoder>. Decoders are
+    /// base64, hex and url. Can be repeated.
+    #[arg(long, value_name = "column=decoder")]
+    decode: Vec<String>,
+
+    /// Show the values of columns in display formats from this TOML or JSON file, e.g.
+    /// `ratio = "{:.2}%"`, `created = "date:%d %b %Y"`, `size = "bytes"` or
+    /// `elapsed = "duration:ms"` or `token = "abbreviate"`. Sorting, finding and copying use the
+    /// values as they are.
+    #[arg(long, value_name = "file")]
+    formats: Option<String>,
+
+    /// Color the values of a column matching a regex, given as <column>:<regex>=<color>, e.g.
+    /// 'status:ERROR=red'. Colors are names such as red or light-blue, #rrggbb or 0-255. Can be
+    /// repeated; the first matching rule of a column applies.
+    #[arg(long, value_name = "column:regex=color")]
+    color_rule: Vec<String>,
+
+    /// Command used to open URLs and file paths in cells with o, e.g. "firefox --new-tab". `{}`
+    /// is replaced with the cell value, otherwise it is appended. Defaults to the system opener.
+    #[arg(long, value_name = "command")]
+    open_command: Option<String>,
+
+    /// Highlight emails, IP addresses and UUIDs in cells. Press -P to toggle.
+    #[clap(long)]
+    highlight_patterns: bool,
+
+    /// Memory used by csvlens past which its caches are trimmed, e.g. 512M or 2G. A best-effort
+    /// trim, not a hard limit: rows read ahead and sorts kept to switch back to are dropped, while
+    /// find results, row positions, marks and tags are kept. Press -M to show the current memory
+    /// usage.
+    #[arg(long, value_name = "size")]
+    memory_limit: Option<String>,
+
+    /// Number of rows per batch read when sorting. Larger batches are faster but use more memory.
+    #[arg(long, value_name = "rows")]
+    sort_batch_size: Option<usize>,
+
+    /// Number of rows read to infer column types for sorting
+    #[arg(long, value_name = "rows")]
+    sort_infer_rows: Option<usize>,
+
+    /// Memory for the values read when sorting, e.g. 512M (default 1G). Past it, sorted runs of
+    /// values are written to temporary files and merged.
+    #[arg(long, value_name = "size")]
+    sort_memory: Option<String>,
+
+    /// Use less memory, e.g. in containers with little of it: finished sorts aren't kept to switch
+    /// back to, rows aren't read ahead when scrolling, and sorting reads 256 rows at a time and
+    /// spills to temporary files past 64M unless --sort-batch-size or --sort-memory are given
+    #[clap(long)]
+    low_memory: bool,
+
+    /// Sort a column as dates written with a strftime pattern, given as <column>=<pattern>, e.g.
+    /// 'due=%d/%m/%Y'. Common patterns like 12/03/2023 or Jan 5 2021 are detected without it.
+    /// Can be repeated.
+    #[arg(long, value_name = "column=pattern")]
+    date_format: Vec<String>,
+
+    /// Keep row tags in this CSV file, e.g. data.csv.tags.csv. Tags are loaded from it on start
+    /// and saved to it whenever they change.
+    #[arg(long, value_name = "file")]
+    tags: Option<String>,
+
+    /// Integrate with the terminal emulator: show the filename and current row in the window
+    /// title, report the working directory (OSC 7) and make URLs in cells clickable (OSC 8)
+    #[clap(long)]
+    terminal_integration: bool,
+
+    /// Print how long startup phases took (schema inference, width estimation, first render and
+    /// indexing) on exit
+    #[clap(long)]
+    timings: bool,
+
     /// Show stats for debugging
     #[clap(long)]
     debug: bool,
 
-    /// Disable streaming stdin (load entire input before displaying)
+    /// Disable streaming stdin, compressed files and downloads (load entire input before
+    /// displaying)
     #[clap(long)]
     pub no_streaming_stdin: bool,
 }
@@ -151,10 +397,29 @@ impl From<Args> for CsvlensOptions {
             delimiter: args.delimiter,
             tab_separated: args.tab_separated,
             comma_separated: args.comma_separated,
+            quote_char: args.quote_char,
+            escape_char: args.escape_char,
+            no_quoting: args.no_quoting,
+            encoding: args.encoding,
             no_headers: args.no_headers,
+            detect_header: args.detect_header,
+            skip_footer: args.skip_footer,
+            sheet: args.sheet,
+            table: args.table,
+            query: args.query,
+            subheader: args.subheader,
+            group_columns: args.group_columns,
+            split_tables: args.split_tables,
+            decimal_comma: args.decimal_comma,
+            trim: args.trim,
+            max_fields: args.max_fields,
             columns: args.columns,
             filter: args.filter,
             find: args.find,
+            count_only: args.count_only,
+            exists: args.exists,
+            dump_state: args.dump_state,
+            script: args.script,
             ignore_case: args.ignore_case,
             echo_column: args.echo_column,
             debug: args.debug,
@@ -164,6 +429,32 @@ impl From<Args> for CsvlensOptions {
             wrap_mode: Args::get_wrap_mode(args.wrap, args.wrap_chars, args.wrap_words),
             auto_reload: args.auto_reload,
             no_streaming_stdin: args.no_streaming_stdin,
+            validate: args.validate,
+            header_aliases: args.header_aliases,
+            decode: args.decode,
+            formats: args.formats,
+            color_rules: args.color_rule,
+            open_command: args.open_command,
+            highlight_patterns: args.highlight_patterns,
+            memory_limit: args.memory_limit,
+            sort_batch_size: args.sort_batch_size,
+            sort_infer_rows: args.sort_infer_rows,
+            sort_memory: args.sort_memory,
+            low_memory: args.low_memory,
+            date_formats: args.date_format,
+            timings: args.timings,
+            tags: args.tags,
+            terminal_integration: args.terminal_integration,
+            bytes: args.bytes,
+            rows: args.rows,
+            resume: args.resume && !args.no_resume,
+            no_presets: args.no_presets,
+            no_wizard: args.no_wizard,
+            screen_reader: args.screen_reader,
+            theme: args.theme,
+            theme_colors: args.theme_color,
+            sync_output: args.sync_output,
+            from_clipboard: args.from_clipboard,
         }
     }
 }
@@ -175,10 +466,29 @@ pub struct CsvlensOptions {
     pub delimiter: Option<String>,
     pub tab_separated: bool,
     pub comma_separated: bool,
+    pub quote_char: Option<String>,
+    pub escape_char: Option<String>,
+    pub no_quoting: bool,
+    pub encoding: Option<String>,
     pub no_headers: bool,
+    pub detect_header: bool,
+    pub skip_footer: Option<String>,
+    pub sheet: Option<String>,
+    pub table: Option<String>,
+    pub query: Option<String>,
+    pub subheader: bool,
+    pub group_columns: bool,
+    pub split_tables: bool,
+    pub decimal_comma: bool,
+    pub trim: bool,
+    pub max_fields: Option<usize>,
     pub columns: Option<String>,
     pub filter: Option<String>,
     pub find: Option<String>,
+    pub count_only: bool,
+    pub exists: bool,
+    pub dump_state: bool,
+    pub script: Option<String>,
     pub ignore_case: bool,
     pub echo_column: Option<String>,
     pub debug: bool,
@@ -188,33 +498,68 @@ pub struct CsvlensOptions {
     pub wrap_mode: Option<WrapMode>,
     pub auto_reload: bool,
     pub no_streaming_stdin: bool,
+    pub validate: Option<String>,
+    pub header_aliases: Option<String>,
+    pub decode: Vec<String>,
+    pub formats: Option<String>,
+    pub color_rules: Vec<String>,
+    pub open_command: Option<String>,
+    pub highlight_patterns: bool,
+    pub memory_limit: Option<String>,
+    pub sort_batch_size: Option<usize>,
+    pub sort_infer_rows: Option<usize>,
+    pub sort_memory: Option<String>,
+    pub low_memory: bool,
+    pub date_formats: Vec<String>,
+    pub timings: bool,
+    pub tags: Option<String>,
+    pub terminal_integration: bool,
+    pub bytes: Option<String>,
+    pub rows: Option<String>,
+    pub resume: bool,
+    pub no_presets: bool,
+    pub no_wizard: bool,
+    pub screen_reader: bool,
+    pub theme: Option<String>,
+    pub theme_colors: Vec<String>,
+    pub sync_output: bool,
+    pub from_clipboard: bool,
 }
 
 struct AppRunner {
     app: App,
+    terminal_integration: bool,
 }
 
+/// Bytes of terminal output buffered per frame
+const OUTPUT_BUFFER_SIZE: usize = 256 * 1024;
+
 impl AppRunner {
-    fn new(app: App) -> AppRunner {
+    fn new(app: App, terminal_integration: bool) -> AppRunner {
         let original_panic_hook = panic::take_hook();
 
         panic::set_hook(Box::new(move |info| {
             // Restore terminal states first so that the backtrace on panic can
             // be printed with proper line breaks
-            disable_raw_mode().unwrap();
-            execute!(std::io::stderr(), LeaveAlternateScreen).unwrap();
+            console::leave_screen(terminal_integration).unwrap();
             original_panic_hook(info);
         }));
 
-        AppRunner { app }
+        AppRunner {
+            app,
+            terminal_integration,
+        }
     }
 
     fn run(&mut self) -> CsvlensResult<Option<String>> {
-        enable_raw_mode()?;
-        let mut output = std::io::stderr();
-        execute!(output, EnterAlternateScreen)?;
-
-        let backend = CrosstermBackend::new(LineWriter::new(output));
+        console::enter_screen(self.terminal_integration)?;
+
+        // Frames are flushed once drawn. A buffer smaller than a frame would write it out in parts,
+        // which shows as flicker when most cells change, e.g. when scrolling.
+        let backend = CrosstermBackend::new(BufWriter::with_capacity(
+            OUTPUT_BUFFER_SIZE,
+            std::io::stderr(),
+        ));
         let mut terminal = Terminal::new(backend)?;
 
         self.app.main_loop(&mut terminal)
@@ -227,8 +572,7 @@ impl Drop for AppRunner {
         // panic hook. Avoid doing that twice since that would clear the printed
         // backtrace.
         if !panicking() {
-            disable_raw_mode().unwrap();
-            execute!(std::io::stderr(), LeaveAlternateScreen).unwrap();
+            console::leave_screen(self.terminal_integration).unwrap();
         }
     }
 }
@@ -236,7 +580,10 @@ impl Drop for AppRunner {
 /// Run csvlens with options provided in a `CsvlensOptions` struct.
 ///
 /// On success, the result contains an optional string that is the value of the selected cell if
-/// any. If csvlens exits without selecting a cell, the result is None.
+/// any. If csvlens exits without selecting a cell, the result is None. With `--count-only`, it
+/// contains the number of matching records instead, and if none match, the result is a
+/// [`CsvlensError::NoMatches`] error. Other errors of `--count-only`, `--exists` and
+/// `--dump-state` are wrapped in [`CsvlensError::Headless`].
 ///
 /// Example:
 ///
@@ -253,41 +600,180 @@ impl Drop for AppRunner {
 ///     Err(e) => eprintln!("Error: {:?}", e),
 /// }
 /// ```
-pub fn run_csvlens_with_options(options: CsvlensOptions) -> CsvlensResult<Option<String>> {
+pub fn run_csvlens_with_options(mut options: CsvlensOptions) -> CsvlensResult<Option<String>> {
+    locale::set_number_format(NumberFormat::from_env());
+    // Legacy Windows consoles would print the escape sequences these write
+    if !console::supports_escapes() {
+        options.terminal_integration = false;
+        options.sync_output = false;
+    }
+    if options.count_only || options.exists || options.dump_state {
+        // Scripts tell errors apart from queries matching nothing
+        return run_headless(options).map_err(|e| match e {
+            CsvlensError::NoMatches(_) => e,
+            e => CsvlensError::Headless(Box::new(e)),
+        });
+    }
+    let terminal_integration = options.terminal_integration;
+
+    let app = new_app(options)?;
+    if app.quit_requested() {
+        return Ok(None);
+    }
+
+    let mut app_runner = AppRunner::new(app, terminal_integration);
+    let result = app_runner.run();
+    let timings_report = app_runner.app.timings_report();
+    let save_position_result = app_runner.app.save_position();
+    // Restore the terminal before printing
+    drop(app_runner);
+    if let Some(report) = timings_report {
+        eprintln!("{report}");
+    }
+    // Not being able to remember the position shouldn't discard the selection
+    if let Err(e) = save_position_result {
+        eprintln!("Failed to save the position: {e}");
+    }
+    result
+}
+
+/// Answer --count-only, --exists or --dump-state without the interactive view
+fn run_headless(options: CsvlensOptions) -> CsvlensResult<Option<String>> {
+    // The view shows an invalid pattern as a message, which scripts wouldn't see
+    for pattern in [&options.filter, &options.find].into_iter().flatten() {
+        Regex::new(pattern).map_err(|_| CsvlensError::InvalidRegex(pattern.clone()))?;
+    }
+    let is_query = options.count_only || options.exists;
+    let exists = options.exists;
+
+    let mut app = new_app(options)?;
+    if app.quit_requested() {
+        return Ok(None);
+    }
+    if is_query {
+        return run_query(&app, exists);
+    }
+    app.wait_until_ready()?;
+    app.check_input(true)?;
+    Ok(Some(
+        serde_json::to_string_pretty(&app.view_state()).unwrap(),
+    ))
+}
+
+/// The app for the options, after running the --script if any
+pub(crate) fn new_app(options: CsvlensOptions) -> CsvlensResult<App> {
+    let is_query = options.count_only || options.exists;
+    let headless = is_query || options.dump_state;
     let show_stats = options.debug;
     let delimiter = Delimiter::from_arg(
         &options.delimiter,
         options.tab_separated,
         options.comma_separated,
     )?;
-
-    let app = App::new(
-        options.filename,
+    let quote_char = options
+        .quote_char
+        .map(|c| delimiter::parse_char("quote-char", &c))
+        .transpose()?;
+    let escape_char = options
+        .escape_char
+        .map(|c| delimiter::parse_char("escape-char", &c))
+        .transpose()?;
+
+    let mut app = App::new(AppOptions {
+        original_filename: options.filename,
         delimiter,
         show_stats,
-        options.echo_column,
-        options.ignore_case,
-        options.no_headers,
-        options.columns,
-        options.filter,
-        options.find,
-        options.freeze_cols_offset,
-        options.color_columns,
-        options.prompt,
-        options.wrap_mode,
-        options.auto_reload,
-        options.no_streaming_stdin,
-    )?;
+        echo_column: options.echo_column,
+        ignore_case: options.ignore_case,
+        no_headers: options.no_headers,
+        columns_regex: options.columns,
+        filter_regex: options.filter,
+        find_regex: options.find,
+        freeze_cols_offset: options.freeze_cols_offset,
+        color_columns: options.color_columns,
+        prompt: options.prompt,
+        wrap_mode: options.wrap_mode,
+        auto_reload: options.auto_reload,
+        no_streaming_stdin: options.no_streaming_stdin,
+        validation_spec: options.validate,
+        header_aliases: options.header_aliases,
+        decoders: options.decode,
+        color_rules: options.color_rules,
+        open_command: options.open_command,
+        highlight_patterns: options.highlight_patterns,
+        memory_limit: options.memory_limit,
+        sort_batch_size: options.sort_batch_size,
+        sort_infer_rows: options.sort_infer_rows,
+        sort_memory: options.sort_memory,
+        low_memory: options.low_memory,
+        timings: options.timings,
+        tags_file: options.tags,
+        terminal_integration: options.terminal_integration,
+        slice: Slice::from_args(options.bytes.as_deref(), options.rows.as_deref())?,
+        resume_file: options.resume.then(PositionHistory::default_path).flatten(),
+        clipboard_text: options
+            .from_clipboard
+            .then(io::clipboard_text)
+            .transpose()?,
+        detect_headers: options.detect_header,
+        skip_footer: options.skip_footer,
+        split_tables: options.split_tables,
+        decimal_comma: options.decimal_comma,
+        trim: options.trim,
+        quote_char,
+        escape_char,
+        no_quoting: options.no_quoting,
+        encoding: options.encoding,
+        max_fields: options.max_fields,
+        presets_file: (!options.no_presets && !is_query)
+            .then(ViewPresets::default_path)
+            .flatten(),
+        aliases_file: CommandAliases::default_path(),
+        wizard: !options.no_wizard && !headless && options.script.is_none(),
+        screen_reader: options.screen_reader,
+        theme: options.theme,
+        theme_colors: options.theme_colors,
+        sync_output: options.sync_output,
+        formats_file: options.formats,
+        subheader: options.subheader,
+        group_columns: options.group_columns,
+        date_formats: options.date_formats,
+        sheet: options.sheet,
+        table: options.table,
+        query: options.query,
+    })?;
+
+    // The main loop runs them otherwise, while the view shows
+    if headless || options.script.is_some() {
+        app.run_on_open()?;
+    }
+    if let Some(path) = &options.script {
+        app.run_script(path)?;
+    }
+    Ok(app)
+}
 
-    let mut app_runner = AppRunner::new(app);
-    app_runner.run()
+/// Answer --count-only or --exists without the interactive view
+fn run_query(app: &App, exists: bool) -> CsvlensResult<Option<String>> {
+    let count = app.count_matches(exists.then_some(1))?;
+    // A count of what was read before a truncation would look like an answer
+    app.check_input(true)?;
+    match (count, exists) {
+        (0, true) => Err(CsvlensError::NoMatches(String::new())),
+        (0, false) => Err(CsvlensError::NoMatches("0".to_string())),
+        (_, true) => Ok(None),
+        (n, false) => Ok(Some(n.to_string())),
+    }
 }
 
 /// Run csvlens with a list of arguments. The accepted arguments are the same as the command line
 /// arguments for the csvlens binary.
 ///
 /// On success, the result contains an optional string that is the value of the selected cell if
-/// any. If csvlens exits without selecting a cell, the result is None.
+/// any. If csvlens exits without selecting a cell, the result is None. With `--count-only`, it
+/// contains the number of matching records instead, and if none match, the result is a
+/// [`CsvlensError::NoMatches`] error. Other errors of `--count-only`, `--exists` and
+/// `--dump-state` are wrapped in [`CsvlensError::Headless`].
 ///
 /// Example:
 ///
@@ -314,6 +800,35 @@ where
     run_csvlens_with_options(args.into())
 }
 
+/// Environment variable with default options, like LESS for less
+#[cfg(feature = "cli")]
+const OPTS_VAR: &str = "CSVLENS_OPTS";
+
+/// Default options set in CSVLENS_OPTS, to give to [`run_csvlens`] before the command line ones
+/// so that those override them. Only the binary reads them, so that programs running csvlens
+/// aren't affected by the options of their users.
+#[cfg(feature = "cli")]
+pub fn default_args() -> CsvlensResult<Vec<OsString>> {
+    split_default_args(std::env::var_os(OPTS_VAR))
+}
+
+/// Arguments in the value of CSVLENS_OPTS, split like a shell does, e.g. `--theme 'high contrast'`
+#[cfg(feature = "cli")]
+fn split_default_args(opts: Option<OsString>) -> CsvlensResult<Vec<OsString>> {
+    let Some(opts) = opts else {
+        return Ok(vec![]);
+    };
+    let invalid = |message: &str| CsvlensError::InvalidDefaultOptions(message.to_string());
+    let opts = opts.to_str().ok_or_else(|| invalid("not valid UTF-8"))?;
+    let words = shlex::split(opts).ok_or_else(|| invalid("unmatched quote"))?;
+    Ok(words.into_iter().map(OsString::from).collect())
+}
+
+#[cfg(not(feature = "cli"))]
+pub fn default_args() -> CsvlensResult<Vec<OsString>> {
+    Ok(vec![])
+}
+
 #[cfg(not(feature = "cli"))]
 pub fn run_csvlens<I, T>(_args: I) -> CsvlensResult<Option<String>>
 where
@@ -323,3 +838,87 @@ where
     eprintln!("Error: CLI is not enabled. Compile with the 'cli' feature to use this binary.");
     std::process::exit(1);
 }
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_default_args() {
+        let args =
+            split_default_args(Some(r"--ignore-case --filter 'Salt Lake' -d\;".into())).unwrap();
+        assert_eq!(args, vec!["--ignore-case", "--filter", "Salt Lake", "-d;"]);
+        assert!(split_default_args(None).unwrap().is_empty());
+        assert!(matches!(
+            split_default_args(Some("--filter 'Salt".into())),
+            Err(CsvlensError::InvalidDefaultOptions(_))
+        ));
+
+        // Options on the command line override the defaults
+        let mut items = vec![OsString::from("csvlens")];
+        items.extend(
+            split_default_args(Some("-i --filter Salt --color-rule City:S=red".into())).unwrap(),
+        );
+        items.extend(
+            [
+                "--filter",
+                "Lake",
+                "--color-rule",
+                "State:UT=blue",
+                "cities.csv",
+            ]
+            .map(OsString::from),
+        );
+        let args = Args::parse_from(items);
+        assert!(args.ignore_case);
+        assert_eq!(args.filter.as_deref(), Some("Lake"));
+        assert_eq!(args.filename.as_deref(), Some("cities.csv"));
+        // Options that can be repeated add up
+        assert_eq!(args.color_rule, vec!["City:S=red", "State:UT=blue"]);
+    }
+
+    #[test]
+    fn test_headless_errors() {
+        let options = |filename: &str, filter: &str| CsvlensOptions {
+            filename: Some(filename.to_string()),
+            filter: Some(filter.to_string()),
+            count_only: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            run_csvlens_with_options(options("tests/data/cities.csv", "Nowhere")),
+            Err(CsvlensError::NoMatches(_))
+        ));
+        let result = run_csvlens_with_options(options("tests/data/missing.csv", "Salt"));
+        let Err(CsvlensError::Headless(e)) = result else {
+            panic!("{result:?}");
+        };
+        assert!(matches!(*e, CsvlensError::FileNotFound(_)));
+
+        // The rows before the end of a truncated file aren't counted as if they were all of it
+        let gzipped = std::fs::read("tests/data/cities.csv.gz").unwrap();
+        let mut truncated = tempfile::Builder::new()
+            .suffix(".csv.gz")
+            .tempfile()
+            .unwrap();
+        std::io::Write::write_all(&mut truncated, &gzipped[..gzipped.len() / 2]).unwrap();
+        let filename = truncated.path().to_str().unwrap();
+        for (count_only, exists, dump_state) in [
+            (true, false, false),
+            (false, true, false),
+            (false, false, true),
+        ] {
+            let result = run_csvlens_with_options(CsvlensOptions {
+                filename: Some(filename.to_string()),
+                count_only,
+                exists,
+                dump_state,
+                ..Default::default()
+            });
+            let Err(CsvlensError::Headless(e)) = result else {
+                panic!("{result:?}");
+            };
+            assert!(matches!(*e, CsvlensError::InputStopped(_)), "{e}");
+        }
+    }
+}
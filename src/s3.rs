@@ -0,0 +1,118 @@
+use std::fmt;
+
+#[cfg(feature = "s3")]
+use crate::errors::{CsvlensError, CsvlensResult};
+#[cfg(feature = "s3")]
+use crate::remote::RemoteObject;
+#[cfg(feature = "s3")]
+use aws_sdk_s3::{Client, error::DisplayErrorContext};
+#[cfg(feature = "s3")]
+use std::collections::HashMap;
+#[cfg(feature = "s3")]
+use std::io;
+#[cfg(feature = "s3")]
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Objects opened so far by their URL, shared by everything reading them so that a chunk read
+/// by one is there for the others
+#[cfg(feature = "s3")]
+static OBJECTS: LazyLock<Mutex<HashMap<String, Arc<RemoteObject>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Object given as `s3://bucket/key`
+#[derive(Clone, Debug, PartialEq)]
+pub struct S3Location {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Location {
+    /// Location of an `s3://` URL, or None for anything else (e.g. a local path)
+    pub fn parse(url: &str) -> Option<S3Location> {
+        let (bucket, key) = url.strip_prefix("s3://")?.split_once('/')?;
+        if bucket.is_empty() || key.is_empty() {
+            return None;
+        }
+        Some(S3Location {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for S3Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+/// The object at the location, looked up with credentials and region from the environment (the
+/// same as the AWS CLI) the first time, failing early if it can't be read
+#[cfg(feature = "s3")]
+pub fn open(location: &S3Location) -> CsvlensResult<Arc<RemoteObject>> {
+    let url = location.to_string();
+    if let Some(object) = OBJECTS.lock().unwrap().get(&url) {
+        return Ok(object.clone());
+    }
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let config = runtime.block_on(aws_config::load_from_env());
+    let client = Client::new(&config);
+    let head = runtime
+        .block_on(
+            client
+                .head_object()
+                .bucket(&location.bucket)
+                .key(&location.key)
+                .send(),
+        )
+        .map_err(|e| CsvlensError::S3(format!("{location}: {}", DisplayErrorContext(&e))))?;
+    // Version of the object when opened. Later requests fail if it is replaced meanwhile,
+    // instead of mixing parts of different versions.
+    let etag = head.e_tag().map(|s| s.to_string());
+    let size = head.content_length().unwrap_or(0).max(0) as u64;
+    let location = location.clone();
+    let fetch = move |start: u64, end: u64| {
+        runtime.block_on(async {
+            let output = client
+                .get_object()
+                .bucket(&location.bucket)
+                .key(&location.key)
+                .range(format!("bytes={}-{}", start, end - 1))
+                .set_if_match(etag.clone())
+                .send()
+                .await
+                .map_err(|e| {
+                    io::Error::other(format!("{location}: {}", DisplayErrorContext(&e)))
+                })?;
+            let data = output.body.collect().await.map_err(io::Error::other)?;
+            Ok(data.into_bytes().to_vec())
+        })
+    };
+    let object = Arc::new(RemoteObject::new(size, Box::new(fetch)));
+    OBJECTS.lock().unwrap().insert(url, object.clone());
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let location = S3Location::parse("s3://my-bucket/lake/2024/data.csv").unwrap();
+        assert_eq!(location.bucket, "my-bucket");
+        assert_eq!(location.key, "lake/2024/data.csv");
+        assert_eq!(location.to_string(), "s3://my-bucket/lake/2024/data.csv");
+
+        for url in [
+            "data.csv",
+            "s3://my-bucket",
+            "s3://my-bucket/",
+            "s3:///data.csv",
+        ] {
+            assert_eq!(S3Location::parse(url), None);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::profile::{ColumnProfile, Precision};
+use crate::theme::Theme;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::symbols::line;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Widget};
+
+/// Width of the sidebar including its borders
+pub const SIDEBAR_WIDTH: u16 = 34;
+
+pub struct SidebarEntry {
+    pub name: String,
+    pub profile: Option<ColumnProfile>,
+}
+
+/// Panel listing the columns of the file with a summary of their values, and statistics of the
+/// selected column
+pub struct SchemaSidebar<'a> {
+    entries: &'a [SidebarEntry],
+    precision: Precision,
+    selected: Option<usize>,
+    theme: &'a Theme,
+}
+
+impl<'a> SchemaSidebar<'a> {
+    pub fn new(
+        entries: &'a [SidebarEntry],
+        precision: Precision,
+        selected: Option<usize>,
+        theme: &'a Theme,
+    ) -> Self {
+        SchemaSidebar {
+            entries,
+            precision,
+            selected,
+            theme,
+        }
+    }
+
+    fn stats_lines(&self) -> Vec<(String, String)> {
+        let Some(entry) = self.selected.and_then(|i| self.entries.get(i)) else {
+            return vec![];
+        };
+        let Some(profile) = &entry.profile else {
+            return vec![];
+        };
+        let mut lines = vec![
+            ("Values".to_string(), format_count_short(profile.count)),
+            ("Empty".to_string(), format_count_short(profile.empty)),
+            (
+                "Distinct".to_string(),
+                format!("~{}", format_count_short(profile.distinct)),
+            ),
+        ];
+        if let Some(boolean) = &profile.boolean {
+            let total = (boolean.true_count + boolean.false_count).max(1) as f64;
+            let share = |n: u64| {
+                format!(
+                    "{} ({:.0}%)",
+                    format_count_short(n),
+                    n as f64 * 100.0 / total
+                )
+            };
+            lines.push(("True".to_string(), share(boolean.true_count)));
+            lines.push(("False".to_string(), share(boolean.false_count)));
+        }
+        if let Some(numeric) = &profile.numeric {
+            let suffix = if numeric.percentage { "%" } else { "" };
+            let format = |n: f64| format!("{}{suffix}", format_number(n));
+            lines.push(("Min".to_string(), format(numeric.min)));
+            lines.push(("Max".to_string(), format(numeric.max)));
+            lines.push(("Mean".to_string(), format(numeric.mean)));
+            lines.push(("p50".to_string(), format(numeric.p50)));
+            lines.push(("p90".to_string(), format(numeric.p90)));
+            lines.push(("p99".to_string(), format(numeric.p99)));
+        }
+        lines
+    }
+}
+
+/// Short form of a count, e.g. 1.2k
+pub fn format_count_short(n: u64) -> String {
+    if n < 1000 {
+        format!("{n}")
+    } else if n < 1_000_000 {
+        format!("{:.1}k", n as f64 / 1e3)
+    } else {
+        format!("{:.1}M", n as f64 / 1e6)
+    }
+}
+
+/// A number with at most 4 decimals and without trailing zeros
+pub fn format_number(n: f64) -> String {
+    let s = format!("{n:.4}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+impl Widget for SchemaSidebar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.border))
+            .title(format!(" Schema [{}] ", self.precision.label()))
+            .title_bottom(Line::from(" distinct ").right_aligned());
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        // Statistics of the selected column take the bottom of the sidebar, below a separator,
+        // leaving at least half of the height to the columns if there are many of them
+        let stats_lines = self.stats_lines();
+        let stats_height = if stats_lines.is_empty() {
+            0
+        } else {
+            let min_list_height = (self.entries.len() as u16).min(inner.height / 2);
+            (stats_lines.len() as u16 + 2).min(inner.height.saturating_sub(min_list_height))
+        };
+        let list_height = inner.height - stats_height;
+
+        // Right part is e.g. "~1.2k high"
+        let summary_width: u16 = 11;
+        let name_width = inner.width.saturating_sub(summary_width + 1);
+        for (i, entry) in self.entries.iter().take(list_height as usize).enumerate() {
+            let y = inner.y + i as u16;
+            let name_style = if self.selected == Some(i) {
+                Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+            } else {
+                Style::default()
+            };
+            buf.set_stringn(inner.x, y, &entry.name, name_width as usize, name_style);
+            let Some(profile) = &entry.profile else {
+                continue;
+            };
+            let x = inner.x + inner.width.saturating_sub(summary_width);
+            let distinct = format!("~{}", format_count_short(profile.distinct));
+            buf.set_stringn(
+                x,
+                y,
+                format!("{distinct:>6} "),
+                summary_width as usize,
+                Style::default().fg(self.theme.status),
+            );
+            buf.set_stringn(
+                x + 7,
+                y,
+                profile.cardinality.badge(),
+                4,
+                Style::default().add_modifier(Modifier::BOLD),
+            );
+        }
+
+        if stats_height == 0 {
+            return;
+        }
+        let y = inner.y + list_height;
+        let border_style = Style::default().fg(self.theme.border);
+        buf.set_string(area.x, y, line::VERTICAL_RIGHT, border_style);
+        buf.set_string(
+            inner.x,
+            y,
+            line::HORIZONTAL.repeat(inner.width as usize),
+            border_style,
+        );
+        buf.set_string(inner.x + inner.width, y, line::VERTICAL_LEFT, border_style);
+        if let Some(entry) = self.selected.and_then(|i| self.entries.get(i)) {
+            buf.set_stringn(
+                inner.x,
+                y + 1,
+                &entry.name,
+                inner.width as usize,
+                Style::default().add_modifier(Modifier::BOLD),
+            );
+        }
+        for (i, (label, value)) in stats_lines
+            .iter()
+            .take(stats_height.saturating_sub(2) as usize)
+            .enumerate()
+        {
+            let y = y + 2 + i as u16;
+            buf.set_stringn(
+                inner.x,
+                y,
+                label,
+                inner.width as usize,
+                Style::default().fg(self.theme.status),
+            );
+            buf.set_stringn(
+                inner.x + 10,
+                y,
+                value,
+                inner.width.saturating_sub(10) as usize,
+                Style::default(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count_short(999), "999");
+        assert_eq!(format_count_short(1234), "1.2k");
+        assert_eq!(format_count_short(25_600_000), "25.6M");
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(-0.125), "-0.125");
+        assert_eq!(format_number(2.0 / 3.0), "0.6667");
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Number of bits of the hash used to pick a register. 2^12 registers give a standard error of
+/// about 1.6%.
+const PRECISION: u32 = 12;
+const NUM_REGISTERS: usize = 1 << PRECISION;
+
+/// HyperLogLog sketch estimating the number of distinct values with a fixed amount of memory
+#[derive(Clone, Debug)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; NUM_REGISTERS],
+        }
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // Position of the first set bit in the remaining bits
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let num_zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        // Linear counting is more accurate for small cardinalities
+        if estimate <= 2.5 * m && num_zeros > 0 {
+            (m * (m / num_zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.registers.capacity()
+    }
+}
+
+/// Larger values keep more centroids, giving more accurate quantiles with more memory
+const COMPRESSION: f64 = 100.0;
+
+const BUFFER_SIZE: usize = 500;
+
+/// Merging t-digest estimating quantiles of a stream of numbers. Centroids are kept small near the
+/// tails so that extreme quantiles such as p99 stay accurate.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    /// (mean, weight) sorted by mean
+    centroids: Vec<(f64, f64)>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new() -> Self {
+        TDigest {
+            centroids: vec![],
+            buffer: vec![],
+            min: f64::MAX,
+            max: f64::MIN,
+        }
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.centroids = self.merged();
+            self.buffer.clear();
+        }
+    }
+
+    /// Centroids with the buffered values merged in
+    fn merged(&self) -> Vec<(f64, f64)> {
+        let mut all: Vec<(f64, f64)> = self.centroids.clone();
+        all.extend(self.buffer.iter().map(|&v| (v, 1.0)));
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total: f64 = all.iter().map(|c| c.1).sum();
+
+        let mut out: Vec<(f64, f64)> = vec![];
+        let mut weight_so_far = 0.0;
+        for (mean, weight) in all {
+            if let Some(last) = out.last_mut() {
+                let q = (weight_so_far - last.1 + (last.1 + weight) / 2.0) / total;
+                let limit = 4.0 * total * q * (1.0 - q) / COMPRESSION;
+                if last.1 + weight <= limit.max(1.0) {
+                    last.0 += (mean - last.0) * weight / (last.1 + weight);
+                    last.1 += weight;
+                    weight_so_far += weight;
+                    continue;
+                }
+            }
+            out.push((mean, weight));
+            weight_so_far += weight;
+        }
+        out
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.centroids.capacity() * std::mem::size_of::<(f64, f64)>()
+            + self.buffer.capacity() * std::mem::size_of::<f64>()
+    }
+
+    /// Estimated value below which the fraction q of values fall
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let centroids = self.merged();
+        if centroids.is_empty() {
+            return None;
+        }
+        let total: f64 = centroids.iter().map(|c| c.1).sum();
+        let target = q.clamp(0.0, 1.0) * total;
+
+        // Interpolate between the centers of neighbouring centroids, and towards min and max at
+        // the ends
+        let mut prev = (self.min, 0.0);
+        let mut cumulative = 0.0;
+        for &(mean, weight) in &centroids {
+            let center = cumulative + weight / 2.0;
+            if target < center {
+                let fraction = (target - prev.1) / (center - prev.1).max(f64::EPSILON);
+                return Some(prev.0 + (mean - prev.0) * fraction);
+            }
+            prev = (mean, center);
+            cumulative += weight;
+        }
+        let fraction = (target - prev.1) / (total - prev.1).max(f64::EPSILON);
+        Some(prev.0 + (self.max - prev.0) * fraction.min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperloglog_small() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.estimate(), 0);
+        for _ in 0..3 {
+            for v in ["a", "b", "c", "d", "e"] {
+                hll.insert(v);
+            }
+        }
+        assert_eq!(hll.estimate(), 5);
+    }
+
+    #[test]
+    fn test_hyperloglog_large() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..100_000 {
+            hll.insert(&format!("value-{}", i % 50_000));
+        }
+        let estimate = hll.estimate() as f64;
+        assert!((estimate - 50_000.0).abs() / 50_000.0 < 0.05, "{estimate}");
+    }
+
+    #[test]
+    fn test_tdigest_small() {
+        let mut digest = TDigest::new();
+        assert_eq!(digest.quantile(0.5), None);
+        for v in [4.0, 1.0, 3.0, 2.0] {
+            digest.insert(v);
+        }
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(0.5), Some(2.5));
+        assert_eq!(digest.quantile(1.0), Some(4.0));
+    }
+
+    #[test]
+    fn test_tdigest_large() {
+        let mut digest = TDigest::new();
+        // Insert in a scrambled order
+        for i in 0..100_000u64 {
+            digest.insert(((i * 7919) % 100_000) as f64);
+        }
+        for (q, expected) in [(0.5, 50_000.0), (0.9, 90_000.0), (0.99, 99_000.0)] {
+            let actual = digest.quantile(q).unwrap();
+            assert!((actual - expected).abs() < 500.0, "{q}: {actual}");
+        }
+        assert!(digest.centroids.len() < 1000);
+    }
+}
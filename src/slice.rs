@@ -0,0 +1,225 @@
+use crate::csv::CsvConfig;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::io::{input_len, open_input};
+use crate::ipc::ArrowTable;
+use crate::memory;
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use tempfile::NamedTempFile;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SliceUnit {
+    Bytes,
+    Rows,
+}
+
+/// Part of a file to open instead of all of it, given as a byte range or a range of record
+/// numbers. Ranges are half-open and either end can be left out, e.g. `1G..2G` or `1000..`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slice {
+    unit: SliceUnit,
+    start: u64,
+    end: Option<u64>,
+    spec: String,
+}
+
+impl Slice {
+    /// Slice from `--bytes` or `--rows`, if any
+    pub fn from_args(bytes: Option<&str>, rows: Option<&str>) -> CsvlensResult<Option<Slice>> {
+        match (bytes, rows) {
+            (Some(_), Some(_)) => Err(CsvlensError::InvalidRange(
+                "--bytes and --rows can't be used together".to_string(),
+            )),
+            (Some(spec), None) => Slice::parse(spec, SliceUnit::Bytes).map(Some),
+            (None, Some(spec)) => Slice::parse(spec, SliceUnit::Rows).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn parse(spec: &str, unit: SliceUnit) -> CsvlensResult<Slice> {
+        let invalid =
+            || CsvlensError::InvalidRange(format!("{spec} (expected e.g. 1G..2G or 1000..2000)"));
+        let (start, end) = spec.split_once("..").ok_or_else(invalid)?;
+        let parse_bound = |s: &str| -> CsvlensResult<Option<u64>> {
+            let s = s.trim();
+            if s.is_empty() {
+                return Ok(None);
+            }
+            let n = match unit {
+                // Sizes can't be zero, but an offset can
+                SliceUnit::Bytes if s == "0" => 0,
+                SliceUnit::Bytes => memory::parse_size(s).map_err(|_| invalid())?,
+                SliceUnit::Rows => s.parse::<u64>().map_err(|_| invalid())?,
+            };
+            Ok(Some(n))
+        };
+        let min_start = match unit {
+            SliceUnit::Bytes => 0,
+            // Record numbers start from 1 like in the view
+            SliceUnit::Rows => 1,
+        };
+        let start = parse_bound(start)?.unwrap_or(min_start);
+        let end = parse_bound(end)?;
+        if start < min_start || end.is_some_and(|e| e <= start) {
+            return Err(invalid());
+        }
+        Ok(Slice {
+            unit,
+            start,
+            end,
+            spec: spec.to_string(),
+        })
+    }
+
+    /// The rows of the slice of record batches, which only have a range of rows
+    pub fn slice_table(&self, table: &ArrowTable) -> CsvlensResult<ArrowTable> {
+        match self.unit {
+            SliceUnit::Bytes => Err(CsvlensError::InvalidRange(
+                "--bytes can't be used with Arrow files (use --rows)".to_string(),
+            )),
+            SliceUnit::Rows => Ok(table.slice(
+                self.start as usize - 1,
+                self.end.map(|end| end as usize - 1),
+            )),
+        }
+    }
+
+    /// Copy the header and the records of the slice to a temporary file. A byte range is widened
+    /// to whole records: records starting within it are included.
+    pub fn extract(&self, config: &CsvConfig) -> CsvlensResult<NamedTempFile> {
+        let mut reader = config.new_reader()?;
+        if config.has_headers() {
+            reader.byte_headers()?;
+        }
+        let data_start = reader.position().byte();
+        let file_size = input_len(config.filename())?;
+
+        let mut record = ::csv::ByteRecord::new();
+        let (start, end) = match self.unit {
+            SliceUnit::Bytes => {
+                // Start of the first record at or after the offset
+                let mut record_start = |offset: u64| -> CsvlensResult<u64> {
+                    if offset <= data_start {
+                        return Ok(data_start);
+                    }
+                    if offset >= file_size {
+                        return Ok(file_size);
+                    }
+                    if previous_byte(config.filename(), offset)? == b'\n' {
+                        return Ok(offset);
+                    }
+                    let mut position = ::csv::Position::new();
+                    position.set_byte(offset);
+                    reader.seek(position)?;
+                    // Skip the rest of the record the offset falls into
+                    reader.read_byte_record(&mut record)?;
+                    Ok(reader.position().byte())
+                };
+                let start = record_start(self.start)?;
+                let end = match self.end {
+                    Some(end) => record_start(end)?,
+                    None => file_size,
+                };
+                (start, end)
+            }
+            SliceUnit::Rows => {
+                let mut skip_records = |n: u64| -> CsvlensResult<u64> {
+                    for _ in 0..n {
+                        if !reader.read_byte_record(&mut record)? {
+                            break;
+                        }
+                    }
+                    Ok(reader.position().byte())
+                };
+                let start = skip_records(self.start - 1)?;
+                let end = match self.end {
+                    Some(end) => skip_records(end - self.start)?,
+                    None => file_size,
+                };
+                (start, end)
+            }
+        };
+
+        let mut slice_file = NamedTempFile::new()?;
+        let mut file = open_input(config.filename())?;
+        std::io::copy(&mut (&mut file).take(data_start), &mut slice_file)?;
+        file.seek(SeekFrom::Start(start))?;
+        std::io::copy(&mut file.take(end.saturating_sub(start)), &mut slice_file)?;
+        Ok(slice_file)
+    }
+}
+
+fn previous_byte(filename: &str, offset: u64) -> CsvlensResult<u8> {
+    let mut file = open_input(filename)?;
+    file.seek(SeekFrom::Start(offset - 1))?;
+    let mut byte = [0u8];
+    file.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+impl fmt::Display for Slice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            SliceUnit::Bytes => write!(f, "bytes {}", self.spec),
+            SliceUnit::Rows => write!(f, "rows {}", self.spec),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+
+    fn extract(bytes: Option<&str>, rows: Option<&str>) -> String {
+        let config = CsvConfig::new(
+            "tests/data/simple.csv",
+            None,
+            CsvBaseConfig::new(b',', false),
+        );
+        let slice = Slice::from_args(bytes, rows).unwrap().unwrap();
+        let file = slice.extract(&config).unwrap();
+        std::fs::read_to_string(file.path()).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let slice = Slice::from_args(Some("1K..2K"), None).unwrap().unwrap();
+        assert_eq!((slice.start, slice.end), (1024, Some(2048)));
+        assert_eq!(slice.to_string(), "bytes 1K..2K");
+        let slice = Slice::from_args(None, Some("..100")).unwrap().unwrap();
+        assert_eq!((slice.start, slice.end), (1, Some(100)));
+        let slice = Slice::from_args(Some("0.."), None).unwrap().unwrap();
+        assert_eq!((slice.start, slice.end), (0, None));
+        assert_eq!(Slice::from_args(None, None).unwrap(), None);
+
+        for (bytes, rows) in [
+            (Some("1G"), None),
+            (Some("2G..1G"), None),
+            (None, Some("0..10")),
+            (None, Some("a..b")),
+            (Some("1G.."), Some("1..")),
+        ] {
+            assert!(matches!(
+                Slice::from_args(bytes, rows),
+                Err(CsvlensError::InvalidRange(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_extract_rows() {
+        assert_eq!(extract(None, Some("3..5")), "a,b\nA3,B3\nA4,B4\n");
+        assert!(extract(None, Some("4999..")).ends_with("a,b\nA4999,B4999\nA5000,B5000\n"));
+    }
+
+    #[test]
+    fn test_extract_bytes() {
+        // "a,b\n" is followed by "A1,B1\n" at byte 4 and "A2,B2\n" at byte 10. Offsets within a
+        // record move to the start of the next one.
+        assert_eq!(extract(Some("4..16"), None), "a,b\nA1,B1\nA2,B2\n");
+        assert_eq!(extract(Some("5..17"), None), "a,b\nA2,B2\nA3,B3\n");
+        assert_eq!(extract(Some("..5"), None), "a,b\nA1,B1\n");
+    }
+}
@@ -1,24 +1,59 @@
 use crate::csv;
-use crate::errors::CsvlensResult;
+use crate::date;
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::expr::{Expression, Value};
+use crate::metadata;
 
 use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
 use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::sync::Arc;
+use std::sync::LazyLock;
 use std::sync::Mutex;
+use std::sync::atomic::{self, AtomicU64};
 use std::thread::{self};
 use std::time::Duration;
 use std::time::Instant;
 
-use arrow::array::{Array, ArrayIter};
+use ::csv::StringRecord;
+
+use arrow::array::{
+    Array, ArrayIter, ArrayRef, AsArray, BooleanArray, Float64Array, Int64Array, StringArray,
+    UInt32Array, UInt64Array, new_empty_array,
+};
 use arrow::compute::concat;
 use arrow::compute::kernels;
+use arrow::compute::kernels::sort::{SortColumn, SortOptions};
+use arrow::compute::partition;
+use arrow::compute::take;
+use arrow::datatypes::DataType;
 use arrow::datatypes::Fields;
 use arrow::datatypes::Schema;
 use arrow::datatypes::SchemaBuilder;
+use arrow::error::ArrowError;
+use arrow::row::{RowConverter, SortField};
+use regex::Regex;
+
+/// Column index in the messages of arrow's ParseError for values that don't fit the type of
+/// their column, which are worded differently for timestamps
+static PARSE_ERROR_COLUMN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^Error (?:while parsing value '.*' as type '[^']*' for column (\d+)",
+        r"|parsing column (\d+)) at line \d+",
+    ))
+    .unwrap()
+});
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum SorterStatus {
-    Running,
+    /// Reading the file, with the rows and bytes read so far. Some sorts read the file again if
+    /// the first read rules them out.
+    Running {
+        rows: usize,
+        bytes: u64,
+    },
     Finished,
     Error(String),
 }
@@ -29,10 +64,136 @@ pub enum SortOrder {
     Descending,
 }
 
+impl SortOrder {
+    pub fn reversed(&self) -> SortOrder {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+}
+
+/// Where rows with an empty value in the column sorted by first go
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NullPlacement {
+    /// Empty values are the smallest, i.e. first in ascending order and last in descending order
+    #[default]
+    Smallest,
+    First,
+    Last,
+}
+
+impl NullPlacement {
+    /// Placement named `smallest`, `first` or `last`
+    pub fn from_name(name: &str) -> Option<NullPlacement> {
+        match name {
+            "smallest" => Some(NullPlacement::Smallest),
+            "first" => Some(NullPlacement::First),
+            "last" => Some(NullPlacement::Last),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NullPlacement::Smallest => "smallest",
+            NullPlacement::First => "first",
+            NullPlacement::Last => "last",
+        }
+    }
+}
+
+/// Column index of a key that sorts by an expression rather than a column
+pub const NO_COLUMN: usize = usize::MAX;
+
+/// A column to sort by, after the ones before it when sorting by several
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortKey {
+    pub column_index: usize,
+    pub column_name: String,
+    pub order: SortOrder,
+    /// Computed from the columns of each row instead, e.g. `len(name)`
+    pub expression: Option<Expression>,
+}
+
+impl SortKey {
+    pub fn expression(expression: Expression, order: SortOrder) -> SortKey {
+        SortKey {
+            column_index: NO_COLUMN,
+            column_name: expression.text().to_string(),
+            order,
+            expression: Some(expression),
+        }
+    }
+}
+
+/// What is known about a column to sort by beforehand, e.g. from sidecar metadata or options
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnHint {
+    /// Used instead of the inferred type for auto sorting if given
+    pub data_type: Option<DataType>,
+    /// strftime pattern the values are dates in, instead of detecting one
+    pub date_format: Option<String>,
+}
+
+impl ColumnHint {
+    /// Type to read the column as with arrow. Dates with a pattern are parsed from text.
+    fn schema_type(&self) -> Option<DataType> {
+        if self.date_format.is_some() {
+            Some(DataType::Utf8)
+        } else {
+            self.data_type.clone()
+        }
+    }
+}
+
+/// Rows of a filter to sort instead of the whole file, with a description of the filter to tell
+/// when it's no longer the one shown
+#[derive(Clone, Debug)]
+pub struct FilteredRows {
+    pub filter: String,
+    /// Indices of the records, in file order
+    pub row_indices: Arc<Vec<usize>>,
+}
+
+impl FilteredRows {
+    fn contains(&self, row_index: usize) -> bool {
+        self.row_indices.binary_search(&row_index).is_ok()
+    }
+
+    /// Indices of the records among the first num_records
+    fn indices_before(&self, num_records: usize) -> Vec<usize> {
+        self.indices_between(0, num_records)
+    }
+
+    /// Indices of the records from start until end
+    fn indices_between(&self, start: usize, end: usize) -> Vec<usize> {
+        let from = self.row_indices.partition_point(|i| *i < start);
+        let to = self.row_indices.partition_point(|i| *i < end);
+        self.row_indices[from..to].to_vec()
+    }
+}
+
+impl PartialEq for FilteredRows {
+    fn eq(&self, other: &Self) -> bool {
+        self.filter == other.filter && Arc::ptr_eq(&self.row_indices, &other.row_indices)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SortType {
     Auto,
     Natural,
+    /// Like auto, with text compared regardless of case
+    CaseInsensitive,
+}
+
+/// Compares strings by their lowercase characters, so that e.g. "apple" comes before "Banana".
+/// Strings that differ only in case are equal.
+fn caseless_cmp(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
 }
 
 // Natural sorting comparison function
@@ -106,12 +267,21 @@ fn parse_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> u64 {
     num
 }
 
+/// Sorts the records of a file in the background. The sort is stable: rows with equal values
+/// stay in the order of the file, in descending order as well as ascending.
 #[derive(Debug)]
 pub struct Sorter {
+    /// Column sorted by first
     pub column_index: usize,
-    column_name: String,
+    keys: Vec<SortKey>,
     #[allow(dead_code)]
     sort_type: SortType,
+    null_placement: NullPlacement,
+    /// Bytes of the file to read, to tell the progress
+    total_bytes: Option<u64>,
+    filtered_rows: Option<FilteredRows>,
+    /// Whether the input was still streaming, so that only the rows received by then are sorted
+    partial: bool,
     internal: Arc<Mutex<SorterInternalState>>,
 }
 
@@ -121,16 +291,77 @@ impl Sorter {
         column_index: usize,
         column_name: String,
         sort_type: SortType,
+        column_hint: ColumnHint,
+        filtered_rows: Option<FilteredRows>,
     ) -> Self {
-        let internal = SorterInternalState::init(csv_config, column_index, sort_type);
-        Sorter {
+        let key = SortKey {
             column_index,
             column_name,
+            order: SortOrder::Ascending,
+            expression: None,
+        };
+        Sorter::with_keys(
+            csv_config,
+            vec![key],
+            sort_type,
+            vec![column_hint],
+            filtered_rows,
+        )
+    }
+
+    /// Sort by the first key, then by the next ones where rows are equal. Column hints are by
+    /// key, like for a single column. Only the filtered rows are sorted if given, and the other
+    /// rows have no order.
+    pub fn with_keys(
+        csv_config: Arc<csv::CsvConfig>,
+        keys: Vec<SortKey>,
+        sort_type: SortType,
+        column_hints: Vec<ColumnHint>,
+        filtered_rows: Option<FilteredRows>,
+    ) -> Self {
+        let total_bytes = csv_config.data_len().ok();
+        let partial = csv_config.is_streaming();
+        // Columns are sorted like expressions too if sorting by any expression
+        let expression_keys = if keys.iter().any(|k| k.expression.is_some()) {
+            keys.iter()
+                .map(|k| {
+                    let expression = k
+                        .expression
+                        .clone()
+                        .unwrap_or_else(|| Expression::column(k.column_index, &k.column_name));
+                    (expression, k.order)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let internal = SorterInternalState::init(
+            csv_config,
+            keys.iter().map(|k| (k.column_index, k.order)).collect(),
+            expression_keys,
+            sort_type,
+            column_hints,
+            filtered_rows.clone(),
+        );
+        Sorter {
+            column_index: keys[0].column_index,
+            keys,
             sort_type,
+            null_placement: NullPlacement::default(),
+            total_bytes,
+            filtered_rows,
+            partial,
             internal,
         }
     }
 
+    /// Show rows with an empty value where given. The sort itself is the same, only the rows are
+    /// looked up differently.
+    pub fn with_null_placement(mut self, null_placement: NullPlacement) -> Self {
+        self.null_placement = null_placement;
+        self
+    }
+
     pub fn get_sorted_indices(
         &self,
         rows_from: u64,
@@ -138,57 +369,122 @@ impl Sorter {
         order: SortOrder,
     ) -> Option<Vec<u64>> {
         let m_guard = self.internal.lock().unwrap();
-        if let Some(sort_result) = &m_guard.sort_result {
-            let mut out = vec![];
-            let index_range: Box<dyn Iterator<Item = u64>> = if order == SortOrder::Ascending {
-                let start = rows_from;
-                let end = start.saturating_add(num_rows);
-                Box::new(start..end)
-            } else {
-                let end = sort_result.num_rows() as u64 - rows_from;
-                let start = end.saturating_sub(num_rows);
-                Box::new((start..end).rev())
-            };
-            for i in index_range {
-                if let Some(record_index) = sort_result.record_indices.get(i as usize) {
-                    out.push(*record_index as u64)
-                }
-            }
-            return Some(out);
-        }
-        None
+        let sort_result = m_guard.sort_result.as_ref()?;
+        let rows_to = rows_from
+            .saturating_add(num_rows)
+            .min(sort_result.num_rows() as u64);
+        Some(
+            (rows_from..rows_to)
+                .map(|i| {
+                    let position = sort_result.position(i as usize, order, self.null_placement);
+                    sort_result.record_indices[position] as u64
+                })
+                .collect(),
+        )
     }
 
     pub fn get_record_order(&self, row_index: u64, order: SortOrder) -> Option<u64> {
         let m_guard = self.internal.lock().unwrap();
-        if let Some(sort_result) = &m_guard.sort_result
-            && let Some(mut record_order) =
-                sort_result.record_orders.get(row_index as usize).cloned()
-        {
-            if order == SortOrder::Descending {
-                record_order = sort_result.num_rows() - record_order - 1;
-            }
-            return Some(record_order as u64);
-        }
-        None
+        let sort_result = m_guard.sort_result.as_ref()?;
+        let position = sort_result
+            .record_orders
+            .get(row_index as usize)
+            .cloned()
+            .filter(|o| *o != NOT_SORTED)?;
+        Some(sort_result.view_position(position, order, self.null_placement) as u64)
     }
 
     pub fn status(&self) -> SorterStatus {
         (self.internal.lock().unwrap()).status.clone()
     }
 
+    pub fn filtered_rows(&self) -> Option<&FilteredRows> {
+        self.filtered_rows.as_ref()
+    }
+
+    pub fn total_bytes(&self) -> Option<u64> {
+        self.total_bytes
+    }
+
     pub fn column_name(&self) -> &str {
-        self.column_name.as_str()
+        self.keys[0].column_name.as_str()
+    }
+
+    pub fn keys(&self) -> &[SortKey] {
+        &self.keys
+    }
+
+    /// Whether the other sorter puts rows in the same order, so that its results can be reused
+    pub fn sorts_like(&self, other: &Sorter) -> bool {
+        // Sorts of streamed input differ by the rows received by then
+        if self.partial || other.partial {
+            return self.shares_result(other) && self.null_placement == other.null_placement;
+        }
+        self.sorts_by(&other.keys, other.sort_type, other.filtered_rows.as_ref())
+            && self.null_placement == other.null_placement
+    }
+
+    /// Whether this sorts the rows by the keys in the same way, wherever empty values are shown
+    pub fn sorts_by(
+        &self,
+        keys: &[SortKey],
+        sort_type: SortType,
+        filtered_rows: Option<&FilteredRows>,
+    ) -> bool {
+        self.keys == keys
+            && self.sort_type == sort_type
+            && self.filtered_rows.as_ref() == filtered_rows
+    }
+
+    /// A sorter with the same result, which is shared rather than sorted again, showing empty
+    /// values where given
+    fn shared(&self, null_placement: NullPlacement) -> Sorter {
+        Sorter {
+            column_index: self.column_index,
+            keys: self.keys.clone(),
+            sort_type: self.sort_type,
+            null_placement,
+            total_bytes: self.total_bytes,
+            filtered_rows: self.filtered_rows.clone(),
+            partial: self.partial,
+            internal: self.internal.clone(),
+        }
+    }
+
+    /// Whether the other sorter shares the result of this one
+    pub fn shares_result(&self, other: &Sorter) -> bool {
+        Arc::ptr_eq(&self.internal, &other.internal)
+    }
+
+    /// Whether only the rows of streamed input received so far are sorted
+    pub fn is_partial(&self) -> bool {
+        self.partial
     }
 
     pub fn sort_type(&self) -> SortType {
         self.sort_type
     }
 
+    pub fn null_placement(&self) -> NullPlacement {
+        self.null_placement
+    }
+
     pub fn elapsed(&self) -> Option<Duration> {
         (self.internal.lock().unwrap()).elapsed
     }
 
+    /// Approximate number of bytes used by the sort result
+    pub fn memory_usage(&self) -> usize {
+        (self.internal.lock().unwrap())
+            .sort_result
+            .as_ref()
+            .map_or(0, |r| {
+                (r.record_indices.capacity() + r.record_orders.capacity())
+                    * std::mem::size_of::<usize>()
+                    + r.ties.memory_usage()
+            })
+    }
+
     pub fn terminate(&self) {
         let mut m = self.internal.lock().unwrap();
         m.terminate();
@@ -211,16 +507,222 @@ impl Drop for Sorter {
     }
 }
 
-#[derive(Debug)]
+/// Number of finished sorts kept by a SortCache
+const SORT_CACHE_SIZE: usize = 4;
+
+/// Finished sorts kept to switch back to a sort done before without reading and sorting the file
+/// again, the most recently used first
+#[derive(Default)]
+pub struct SortCache {
+    sorters: VecDeque<Arc<Sorter>>,
+}
+
+impl SortCache {
+    /// Keep a finished sort as the most recently used, in place of any that sorts the same way
+    pub fn insert(&mut self, sorter: &Arc<Sorter>) {
+        if sorter.status() != SorterStatus::Finished {
+            return;
+        }
+        if self
+            .sorters
+            .front()
+            .is_some_and(|s| s.shares_result(sorter))
+        {
+            return;
+        }
+        self.sorters.retain(|s| {
+            !s.sorts_by(
+                &sorter.keys,
+                sorter.sort_type,
+                sorter.filtered_rows.as_ref(),
+            )
+        });
+        self.sorters.push_front(sorter.clone());
+        self.sorters.truncate(SORT_CACHE_SIZE);
+    }
+
+    /// A sort by the keys kept, if any, showing empty values where given
+    pub fn get(
+        &self,
+        keys: &[SortKey],
+        sort_type: SortType,
+        filtered_rows: Option<&FilteredRows>,
+        null_placement: NullPlacement,
+    ) -> Option<Sorter> {
+        self.sorters
+            .iter()
+            .find(|s| s.sorts_by(keys, sort_type, filtered_rows))
+            .map(|s| s.shared(null_placement))
+    }
+
+    /// Approximate number of bytes used by the sorts kept, except the one shared with the given
+    /// sorter if any, which is counted with it
+    pub fn memory_usage(&self, current: Option<&Sorter>) -> usize {
+        self.sorters
+            .iter()
+            .filter(|s| current.is_none_or(|c| !s.shares_result(c)))
+            .map(|s| s.memory_usage())
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.sorters.clear();
+    }
+}
+
+/// Order of the records left out of a sort of filtered rows
+const NOT_SORTED: usize = usize::MAX;
+
+#[derive(Debug, Default)]
 struct SortResult {
     record_indices: Vec<usize>,
     record_orders: Vec<usize>,
+    ties: Ties,
+    /// Number of records with an empty value in the column sorted by first, which are the first
+    /// ones if empty_first or else the last ones
+    num_empty: usize,
+    empty_first: bool,
 }
 
 impl SortResult {
+    /// Records sorted by a first column in the order given, with num_empty empty values sorted as
+    /// the smallest
+    fn from_indices(
+        record_indices: Vec<usize>,
+        ties: Ties,
+        num_empty: usize,
+        order: SortOrder,
+    ) -> Self {
+        let num_records = record_indices.iter().max().map_or(0, |i| i + 1);
+        let mut record_orders: Vec<usize> = vec![NOT_SORTED; num_records];
+        for (order, record_index) in record_indices.iter().enumerate() {
+            record_orders[*record_index] = order;
+        }
+        SortResult {
+            record_indices,
+            record_orders,
+            ties,
+            num_empty,
+            empty_first: order == SortOrder::Ascending,
+        }
+    }
+
+    /// The result sorted in the order given, i.e. reversed if descending, with equal values still
+    /// in the order of the file
+    fn in_order(self, order: SortOrder) -> Self {
+        match order {
+            SortOrder::Ascending => self,
+            SortOrder::Descending => {
+                let num_rows = self.num_rows();
+                let record_indices = (0..num_rows)
+                    .map(|i| self.record_indices[self.ties.mirror(num_rows - i - 1)])
+                    .collect();
+                SortResult::from_indices(
+                    record_indices,
+                    self.ties.reversed(num_rows),
+                    self.num_empty,
+                    SortOrder::Descending,
+                )
+            }
+        }
+    }
+
     fn num_rows(&self) -> usize {
         self.record_indices.len()
     }
+
+    /// How far the rows shown are rotated from the records in the order given, to move the empty
+    /// values from one end to the other
+    fn shift(&self, order: SortOrder, null_placement: NullPlacement) -> usize {
+        let empty_first = self.empty_first == (order == SortOrder::Ascending);
+        match (null_placement, empty_first) {
+            (NullPlacement::First, false) => self.num_rows() - self.num_empty,
+            (NullPlacement::Last, true) => self.num_empty,
+            _ => 0,
+        }
+    }
+
+    /// Position in record_indices of the row shown at a position
+    fn position(
+        &self,
+        view_position: usize,
+        order: SortOrder,
+        null_placement: NullPlacement,
+    ) -> usize {
+        let num_rows = self.num_rows();
+        let position = (view_position + self.shift(order, null_placement)) % num_rows;
+        match order {
+            SortOrder::Ascending => position,
+            SortOrder::Descending => self.ties.mirror(num_rows - position - 1),
+        }
+    }
+
+    /// Position at which the record at a position in record_indices is shown
+    fn view_position(
+        &self,
+        position: usize,
+        order: SortOrder,
+        null_placement: NullPlacement,
+    ) -> usize {
+        let num_rows = self.num_rows();
+        let position = match order {
+            SortOrder::Ascending => position,
+            SortOrder::Descending => num_rows - self.ties.mirror(position) - 1,
+        };
+        (position + num_rows - self.shift(order, null_placement)) % num_rows
+    }
+}
+
+/// Ranges [start, end) of positions in sorted records where the values sorted by are equal, in
+/// order. Their records are in the order of the file, which is kept when the records are read
+/// backwards for the descending order.
+#[derive(Debug, Default)]
+struct Ties(Vec<(usize, usize)>);
+
+impl Ties {
+    /// Ties of the ranges of equal values, which are of a single value if not tied
+    fn from_ranges(ranges: Vec<Range<usize>>) -> Self {
+        Ties(
+            ranges
+                .into_iter()
+                .filter(|range| range.len() > 1)
+                .map(|range| (range.start, range.end))
+                .collect(),
+        )
+    }
+
+    /// Add the value at a position, which is equal to the one before it
+    fn push(&mut self, position: usize) {
+        match self.0.last_mut() {
+            Some((_, end)) if *end == position => *end = position + 1,
+            _ => self.0.push((position - 1, position + 1)),
+        }
+    }
+
+    /// Position of the record at the same place in its tie when the tie is read backwards, so
+    /// that tied records read backwards stay in the order of the file
+    fn mirror(&self, position: usize) -> usize {
+        let next = self.0.partition_point(|(start, _)| *start <= position);
+        match next.checked_sub(1).map(|i| self.0[i]) {
+            Some((start, end)) if position < end => start + end - 1 - position,
+            _ => position,
+        }
+    }
+
+    /// The ties of the records read backwards
+    fn reversed(&self, num_rows: usize) -> Self {
+        Ties(
+            self.0
+                .iter()
+                .rev()
+                .map(|(start, end)| (num_rows - end, num_rows - start))
+                .collect(),
+        )
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.0.capacity() * size_of::<(usize, usize)>()
+    }
 }
 
 #[derive(Debug)]
@@ -236,12 +738,15 @@ struct SorterInternalState {
 impl SorterInternalState {
     pub fn init(
         config: Arc<csv::CsvConfig>,
-        column_index: usize,
+        keys: Vec<(usize, SortOrder)>,
+        expression_keys: Vec<(Expression, SortOrder)>,
         sort_type: SortType,
+        column_hints: Vec<ColumnHint>,
+        filtered_rows: Option<FilteredRows>,
     ) -> Arc<Mutex<SorterInternalState>> {
         let m_state = Arc::new(Mutex::new(SorterInternalState {
             sort_result: None,
-            status: SorterStatus::Running,
+            status: SorterStatus::Running { rows: 0, bytes: 0 },
             should_terminate: false,
             done: false,
             start: Instant::now(),
@@ -250,12 +755,86 @@ impl SorterInternalState {
 
         let _m = m_state.clone();
         thread::spawn(move || {
-            let sort_result = if sort_type == SortType::Natural {
+            let rows = filtered_rows.as_ref();
+            let text_cmp = match sort_type {
+                SortType::Auto => str::cmp,
+                SortType::Natural => natural_cmp,
+                SortType::CaseInsensitive => caseless_cmp,
+            };
+            let sort_result = match (keys.as_slice(), sort_type) {
+                _ if !expression_keys.is_empty() => {
+                    run_expression_sort(_m.clone(), config, &expression_keys, text_cmp, rows)
+                }
                 // Use natural sorting
-                run_natural_sort(_m.clone(), config, column_index)
-            } else {
+                ([(column_index, order)], SortType::Natural) => {
+                    run_sort_by(_m.clone(), config, *column_index, natural_cmp, rows)
+                        .map(|r| r.in_order(*order))
+                }
                 // Use auto sorting based on type (numeric for numbers and lexicographic for strings)
-                run_auto_sort(_m.clone(), config, column_index)
+                ([(column_index, order)], SortType::Auto | SortType::CaseInsensitive) => {
+                    run_auto_sort(
+                        _m.clone(),
+                        config,
+                        *column_index,
+                        column_hints.into_iter().next().unwrap_or_default(),
+                        sort_type == SortType::CaseInsensitive,
+                        rows,
+                    )
+                    .map(|r| r.in_order(*order))
+                }
+                (_, SortType::Natural) => {
+                    run_multi_sort_by(_m.clone(), config, &keys, natural_cmp, rows)
+                }
+                (_, SortType::Auto | SortType::CaseInsensitive) => {
+                    let ignore_case = sort_type == SortType::CaseInsensitive;
+                    let mut column_hints = column_hints;
+                    loop {
+                        let result = run_lexsort(
+                            _m.clone(),
+                            config.clone(),
+                            &keys,
+                            &column_hints,
+                            ignore_case,
+                            rows,
+                        );
+                        // A value doesn't fit the type inferred for its column, e.g. N/A in a
+                        // column of numbers, so that column is sorted as text instead. The
+                        // other columns keep their types.
+                        let Err(CsvlensError::Arrow(ArrowError::ParseError(message))) = &result
+                        else {
+                            break result;
+                        };
+                        let text_hint = |hint: &ColumnHint| ColumnHint {
+                            data_type: Some(DataType::Utf8),
+                            date_format: hint.date_format.clone(),
+                        };
+                        let failed_keys: Vec<usize> = match parse_error_column(message) {
+                            Some(column_index) => keys
+                                .iter()
+                                .enumerate()
+                                .filter(|(i, (c, _))| {
+                                    *c == column_index
+                                        && column_hints[*i].data_type != Some(DataType::Utf8)
+                                })
+                                .map(|(i, _)| i)
+                                .collect(),
+                            None => vec![],
+                        };
+                        if failed_keys.is_empty() {
+                            // The column isn't told, so all of them are sorted as text
+                            let text_hints: Vec<ColumnHint> =
+                                column_hints.iter().map(text_hint).collect();
+                            if text_hints == column_hints {
+                                break result;
+                            }
+                            column_hints = text_hints;
+                        } else {
+                            for i in failed_keys {
+                                column_hints[i] = text_hint(&column_hints[i]);
+                            }
+                        }
+                    }
+                }
             };
 
             let mut m = _m.lock().unwrap();
@@ -272,18 +851,24 @@ impl SorterInternalState {
         m_state
     }
 
-    fn infer_schema(filename: &str, delimiter: u8) -> CsvlensResult<Schema> {
-        let schema = arrow::csv::infer_schema_from_files(
-            &[filename.to_string()],
-            delimiter,
-            Some(1000),
-            true,
-        )?;
+    /// Schema of the file, with the types of columns given as (index, type) where known
+    fn infer_schema(
+        config: &csv::CsvConfig,
+        column_types: &[(usize, Option<DataType>)],
+    ) -> CsvlensResult<Schema> {
+        let schema = config.infer_arrow_schema()?;
 
         // Convert integer fields to float64 to be more permissive
         let mut updated_fields = vec![];
-        for field in schema.fields() {
-            if field.data_type().is_integer() {
+        for (index, field) in schema.fields().iter().enumerate() {
+            if let Some((_, Some(column_type))) = column_types.iter().find(|(i, _)| *i == index) {
+                let new_field = field.as_ref().clone().with_data_type(column_type.clone());
+                updated_fields.push(new_field);
+            } else if field.data_type() == &DataType::Null {
+                // Columns with only empty values have no order, and are sorted as empty strings
+                let new_field = field.as_ref().clone().with_data_type(DataType::Utf8);
+                updated_fields.push(new_field);
+            } else if field.data_type().is_integer() {
                 let new_field = field
                     .as_ref()
                     .clone()
@@ -298,18 +883,46 @@ impl SorterInternalState {
         Ok(SchemaBuilder::from(updated_fields).finish())
     }
 
+    /// Record how much of the file has been read, and tell whether to stop
+    fn update_progress(&mut self, rows: usize, bytes: u64) -> bool {
+        self.status = SorterStatus::Running { rows, bytes };
+        self.should_terminate
+    }
+
     fn terminate(&mut self) {
         self.should_terminate = true;
     }
 }
 
-fn run_natural_sort(
+/// Number of records read between updates of the progress of sorts reading records one by one,
+/// so that the state is locked once in a while, like once per batch with arrow
+const PROGRESS_ROWS: usize = 1024;
+
+/// Record how much of the file has been read every PROGRESS_ROWS records, and tell whether to stop
+fn report_progress(m: &Mutex<SorterInternalState>, rows: usize, bytes: u64) -> bool {
+    rows.is_multiple_of(PROGRESS_ROWS) && m.lock().unwrap().update_progress(rows, bytes)
+}
+
+/// Index of the column whose value arrow failed to parse, from the message of its ParseError
+fn parse_error_column(message: &str) -> Option<usize> {
+    PARSE_ERROR_COLUMN_REGEX
+        .captures(message)
+        .and_then(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .and_then(|column| column.as_str().parse().ok())
+}
+
+/// Sort values as read by the csv reader, e.g. trimmed, with the given comparison
+fn run_sort_by(
     m: Arc<Mutex<SorterInternalState>>,
     config: Arc<csv::CsvConfig>,
     column_index: usize,
+    cmp: fn(&str, &str) -> Ordering,
+    rows: Option<&FilteredRows>,
 ) -> CsvlensResult<SortResult> {
-    // Read all values and their indices
-    let mut values_with_indices: Vec<(String, usize)> = Vec::new();
+    let mut values = ExternalSort::new(config.sort_memory_limit(), |a: &String, b: &String| {
+        cmp(a, b)
+    });
+    let mut num_empty = 0;
     let mut reader = config.new_reader()?;
 
     // Skip header if present
@@ -317,131 +930,1390 @@ fn run_natural_sort(
         reader.headers()?;
     }
 
-    for (index, result) in reader.records().enumerate() {
-        if m.lock().unwrap().should_terminate {
-            return Ok(SortResult {
-                record_indices: vec![],
-                record_orders: vec![],
-            });
+    let mut record = StringRecord::new();
+    let mut index = 0;
+    while reader.read_record(&mut record)? {
+        if report_progress(&m, index + 1, reader.position().byte()) {
+            return Ok(SortResult::default());
         }
-
-        let record = result?;
-        if let Some(field) = record.get(column_index) {
-            values_with_indices.push((field.to_string(), index));
-        } else {
-            // Handle missing field
-            values_with_indices.push(("".to_string(), index));
+        if rows.is_none_or(|r| r.contains(index)) {
+            // A missing field is like an empty one
+            let value = config.field(&record, column_index);
+            if cmp(value, "").is_eq() {
+                num_empty += 1;
+            }
+            values.push(value.to_string(), index)?;
         }
+        index += 1;
     }
 
-    // Sort using natural comparison
-    values_with_indices.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+    let Some((record_indices, ties)) = values.finish(&m)? else {
+        return Ok(SortResult::default());
+    };
+    Ok(SortResult::from_indices(
+        record_indices,
+        ties,
+        num_empty,
+        SortOrder::Ascending,
+    ))
+}
 
-    // Construct result
-    let mut sorted_record_indices: Vec<usize> = Vec::with_capacity(values_with_indices.len());
-    let mut record_orders: Vec<usize> = vec![0; values_with_indices.len()];
+/// Sort values that arrow doesn't read as numbers, booleans or dates: percentages, yes/no,
+/// numbers with a decimal comma and dates like `12/03/2023` or `Jan 5 2021`, in the given pattern
+/// if any. Empty values come first like they do with arrow. None if the column has anything else.
+///
+/// What the values are is only known once all of them are read, so they are kept as they are
+/// until then, or read again if they take more than the memory limit of the sort.
+fn run_parsed_sort(
+    m: Arc<Mutex<SorterInternalState>>,
+    config: Arc<csv::CsvConfig>,
+    column_index: usize,
+    date_format: Option<&str>,
+    rows: Option<&FilteredRows>,
+) -> CsvlensResult<Option<SortResult>> {
+    let mut kinds = ValueKinds::new(date_format);
+    let mut kept: Option<Vec<(String, usize)>> = Some(vec![]);
+    let mut kept_size = 0;
+    let mut reader = config.new_reader()?;
+    let mut record = StringRecord::new();
+    let mut index = 0;
+    while reader.read_record(&mut record)? {
+        if report_progress(&m, index + 1, reader.position().byte()) {
+            return Ok(Some(SortResult::default()));
+        }
+        if rows.is_none_or(|r| r.contains(index)) {
+            let value = config.field(&record, column_index);
+            kinds.add(value, &config);
+            if kinds.is_text() {
+                return Ok(None);
+            }
+            if let Some(values) = &mut kept {
+                kept_size += size_of::<(String, usize)>() + value.len();
+                values.push((value.to_string(), index));
+                if kept_size > config.sort_memory_limit() {
+                    kept = None;
+                }
+            }
+        }
+        index += 1;
+    }
+    let Some(kind) = kinds.kind() else {
+        return Ok(None);
+    };
 
-    for (order, (_, original_index)) in values_with_indices.into_iter().enumerate() {
-        sorted_record_indices.push(original_index);
-        record_orders[original_index] = order;
+    let mut numbers = ExternalSort::new(config.sort_memory_limit(), cmp_numbers);
+    let mut num_empty = 0;
+    let mut push = |value: &str, index: usize| {
+        let number = kind.sort_key(value, &config);
+        if number.is_none() {
+            num_empty += 1;
+        }
+        numbers.push(number, index)
+    };
+    match kept {
+        Some(values) => {
+            for (value, index) in values {
+                push(&value, index)?;
+            }
+        }
+        None => {
+            let mut reader = config.new_reader()?;
+            let mut index = 0;
+            while reader.read_record(&mut record)? {
+                if report_progress(&m, index + 1, reader.position().byte()) {
+                    return Ok(Some(SortResult::default()));
+                }
+                if rows.is_none_or(|r| r.contains(index)) {
+                    push(config.field(&record, column_index), index)?;
+                }
+                index += 1;
+            }
+        }
     }
 
-    Ok(SortResult {
-        record_indices: sorted_record_indices,
-        record_orders,
-    })
+    let Some((record_indices, ties)) = numbers.finish(&m)? else {
+        return Ok(Some(SortResult::default()));
+    };
+    Ok(Some(SortResult::from_indices(
+        record_indices,
+        ties,
+        num_empty,
+        SortOrder::Ascending,
+    )))
 }
 
 fn run_auto_sort(
     m: Arc<Mutex<SorterInternalState>>,
     config: Arc<csv::CsvConfig>,
     column_index: usize,
+    column_hint: ColumnHint,
+    ignore_case: bool,
+    rows: Option<&FilteredRows>,
+) -> CsvlensResult<SortResult> {
+    let column_type = column_hint.schema_type();
+    let schema =
+        SorterInternalState::infer_schema(&config, &[(column_index, column_type.clone())])?;
+    // Arrow reads strings for percentages, yes/no and most dates, and only numbers with a
+    // decimal point
+    let data_type = schema
+        .fields()
+        .get(column_index)
+        .map(|f| f.data_type().clone());
+    let try_parsed = column_hint.date_format.is_some()
+        || match (&data_type, &column_type) {
+            (Some(DataType::Utf8), None) => true,
+            (Some(DataType::Float64), _) => config.decimal_comma(),
+            (Some(DataType::Boolean), Some(_)) => true,
+            _ => false,
+        };
+    if try_parsed
+        && let Some(sort_result) = run_parsed_sort(
+            m.clone(),
+            config.clone(),
+            column_index,
+            column_hint.date_format.as_deref(),
+            rows,
+        )?
+    {
+        return Ok(sort_result);
+    }
+    // Arrow reads strings as they are in the file, and compares them by bytes
+    if data_type == Some(DataType::Utf8) && (config.trim() || ignore_case) {
+        let cmp = if ignore_case { caseless_cmp } else { str::cmp };
+        return run_sort_by(m, config, column_index, cmp, rows);
+    }
+    match run_arrow_sort(m.clone(), config.clone(), schema, column_index, rows) {
+        // A value past the rows the type was inferred from doesn't fit it, e.g. N/A in a column of
+        // numbers. Numbers are then sorted with such values as if empty, anything else as text.
+        Err(CsvlensError::Arrow(ArrowError::ParseError(_))) => match data_type {
+            Some(data_type) if data_type.is_numeric() => {
+                run_lenient_number_sort(m, config, column_index, rows)
+            }
+            _ => {
+                let column_hint = ColumnHint {
+                    data_type: Some(DataType::Utf8),
+                    date_format: None,
+                };
+                run_auto_sort(m, config, column_index, column_hint, ignore_case, rows)
+            }
+        },
+        result => result,
+    }
+}
+
+/// Sort the values arrow reads with the schema
+fn run_arrow_sort(
+    m: Arc<Mutex<SorterInternalState>>,
+    config: Arc<csv::CsvConfig>,
+    schema: Schema,
+    column_index: usize,
+    rows: Option<&FilteredRows>,
 ) -> CsvlensResult<SortResult> {
-    let schema = SorterInternalState::infer_schema(config.filename(), config.delimiter())?;
-    let file = File::open(config.filename())?;
-    let arrow_csv_reader = arrow::csv::ReaderBuilder::new(Arc::new(schema))
-        .with_delimiter(config.delimiter())
-        .with_header(!config.no_headers())
-        .with_projection(vec![column_index])
-        .build(file)?;
+    // Only the sort column is parsed into arrays, the rest of each row is skipped
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let arrow_csv_reader =
+        config.new_arrow_reader(Arc::new(schema), vec![column_index], bytes_read.clone())?;
 
     let mut arrs: Vec<Arc<dyn Array>> = Vec::new();
+    let mut num_rows = 0;
+    // Past the memory limit, values are sorted in runs written to temporary files, which are
+    // merged at the end. The arrays hold the values of the records from first_record on.
+    let mut arrs_size = 0;
+    let mut first_record = 0;
+    let mut spilled_runs: Option<SpilledRuns> = None;
     for record_batch_result in arrow_csv_reader {
         let record_batch = record_batch_result?;
         let arr = record_batch.column(0);
         arrs.push(arr.clone());
-        if m.lock().unwrap().should_terminate {
-            return Ok(SortResult {
-                record_indices: vec![],
-                record_orders: vec![],
-            });
+        arrs_size += arr.get_array_memory_size();
+        num_rows += record_batch.num_rows();
+        if m.lock()
+            .unwrap()
+            .update_progress(num_rows, bytes_read.load(atomic::Ordering::Relaxed))
+        {
+            return Ok(SortResult::default());
+        }
+        if arrs_size > config.sort_memory_limit() {
+            let runs = match &mut spilled_runs {
+                Some(runs) => runs,
+                None => spilled_runs.insert(SpilledRuns::new(
+                    vec![SortField::new(arr.data_type().clone())],
+                    config.sort_memory_limit(),
+                )?),
+            };
+            let ref_arrs: Vec<&dyn Array> = arrs.iter().map(|a| a.as_ref()).collect();
+            let (values, record_indices) =
+                sorted_records(vec![concat(&ref_arrs)?], first_record, rows)?;
+            runs.push(&values, &record_indices)?;
+            arrs.clear();
+            arrs_size = 0;
+            first_record = num_rows;
+        }
+    }
+    if let Some(mut runs) = spilled_runs {
+        if !arrs.is_empty() {
+            let ref_arrs: Vec<&dyn Array> = arrs.iter().map(|a| a.as_ref()).collect();
+            let (values, record_indices) =
+                sorted_records(vec![concat(&ref_arrs)?], first_record, rows)?;
+            runs.push(&values, &record_indices)?;
         }
+        return runs.finish(&m, SortOrder::Ascending);
     }
     let ref_arrs = arrs
         .iter()
         .map(|arr| arr.as_ref())
         .collect::<Vec<&dyn Array>>();
-    let combined_arr = concat(&ref_arrs)?;
+    let mut combined_arr = concat(&ref_arrs)?;
+    let row_indices = rows.map(|r| r.indices_before(combined_arr.len()));
+    if let Some(row_indices) = &row_indices {
+        combined_arr = take_rows(&combined_arr, row_indices)?;
+    }
 
-    let sorted_indices = kernels::sort::sort_to_indices(combined_arr.as_ref(), None, None)?;
+    let num_empty = combined_arr.null_count();
+    let sorted_indices = stable_sort_to_indices(combined_arr.clone())?;
+    let ties = sorted_ties(&[combined_arr], &sorted_indices)?;
+    let sorted_record_indices = ArrayIter::new(&sorted_indices)
+        .flatten()
+        .map(|i| record_index(i as usize, row_indices.as_deref()))
+        .collect();
+    Ok(SortResult::from_indices(
+        sorted_record_indices,
+        ties,
+        num_empty,
+        SortOrder::Ascending,
+    ))
+}
 
-    let mut sorted_record_indices: Vec<usize> = vec![];
-    let mut record_orders: Vec<usize> = vec![0; sorted_indices.len()];
-    for (record_order, sorted_record_index) in ArrayIter::new(&sorted_indices).flatten().enumerate()
-    {
-        sorted_record_indices.push(sorted_record_index as usize);
-        record_orders[sorted_record_index as usize] = record_order;
+/// Sort numbers as read by the csv reader. Values that aren't numbers are sorted like empty ones.
+fn run_lenient_number_sort(
+    m: Arc<Mutex<SorterInternalState>>,
+    config: Arc<csv::CsvConfig>,
+    column_index: usize,
+    rows: Option<&FilteredRows>,
+) -> CsvlensResult<SortResult> {
+    let mut numbers = ExternalSort::new(config.sort_memory_limit(), cmp_numbers);
+    let mut num_empty = 0;
+    let mut reader = config.new_reader()?;
+    let mut record = StringRecord::new();
+    let mut index = 0;
+    while reader.read_record(&mut record)? {
+        if report_progress(&m, index + 1, reader.position().byte()) {
+            return Ok(SortResult::default());
+        }
+        if rows.is_none_or(|r| r.contains(index)) {
+            let number = config.parse_number(config.field(&record, column_index));
+            if number.is_none() {
+                num_empty += 1;
+            }
+            numbers.push(number, index)?;
+        }
+        index += 1;
     }
-    let sort_result = SortResult {
-        record_indices: sorted_record_indices,
-        record_orders,
+
+    let Some((record_indices, ties)) = numbers.finish(&m)? else {
+        return Ok(SortResult::default());
     };
-    Ok(sort_result)
+    Ok(SortResult::from_indices(
+        record_indices,
+        ties,
+        num_empty,
+        SortOrder::Ascending,
+    ))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Numbers in order, after empty values
+fn cmp_numbers(a: &Option<f64>, b: &Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(b),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
 
-    #[test]
-    fn test_natural_sort() {
-        let mut items = vec!["disk1", "disk10", "disk2", "disk11"];
-        items.sort_by(|a, b| natural_cmp(a, b));
-        assert_eq!(items, vec!["disk1", "disk2", "disk10", "disk11"]);
+/// Values to sort with the indices of their records, kept in memory while they take less than the
+/// memory limit of the sort. Past it, they are sorted in runs written to temporary files, which
+/// are merged at the end. Equal values are in the order of their records, i.e. of the file.
+struct ExternalSort<V, F> {
+    cmp: F,
+    memory_limit: usize,
+    values: Vec<(V, usize)>,
+    size: usize,
+    runs: Vec<File>,
+}
+
+impl<V: RunValue, F: Fn(&V, &V) -> Ordering> ExternalSort<V, F> {
+    fn new(memory_limit: usize, cmp: F) -> Self {
+        ExternalSort {
+            cmp,
+            memory_limit,
+            values: vec![],
+            size: 0,
+            runs: vec![],
+        }
     }
 
-    #[test]
-    fn test_natural_sort_mixed() {
-        let mut items = vec!["file1.txt", "file10.txt", "file2.txt", "file20.txt"];
-        items.sort_by(|a, b| natural_cmp(a, b));
-        assert_eq!(
-            items,
-            vec!["file1.txt", "file2.txt", "file10.txt", "file20.txt"]
-        );
+    fn push(&mut self, value: V, record_index: usize) -> CsvlensResult<()> {
+        self.size += size_of::<(V, usize)>() + value.heap_size();
+        self.values.push((value, record_index));
+        if self.size > self.memory_limit {
+            self.write_run()?;
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_natural_sort_with_text() {
-        let mut items = vec!["chapter1", "chapter10", "chapter2", "chapter20", "appendix"];
-        items.sort_by(|a, b| natural_cmp(a, b));
-        assert_eq!(
-            items,
-            vec!["appendix", "chapter1", "chapter2", "chapter10", "chapter20"]
-        );
+    fn sort(&mut self) {
+        // Stable, so that equal values stay in the order of their records
+        let cmp = &self.cmp;
+        self.values.sort_by(|(a, _), (b, _)| cmp(a, b));
     }
 
-    #[test]
-    fn test_simple() {
-        let config = Arc::new(csv::CsvConfig::new(
-            "tests/data/simple.csv",
-            None,
-            csv::CsvBaseConfig::new(b',', false),
-        ));
-        let s = Sorter::new(config, 0, "A1".to_string(), SortType::Auto);
+    fn write_run(&mut self) -> CsvlensResult<()> {
+        self.sort();
+        let mut writer = BufWriter::new(tempfile::tempfile()?);
+        for (value, record_index) in self.values.drain(..) {
+            writer.write_all(&(record_index as u64).to_le_bytes())?;
+            value.write_to(&mut writer)?;
+        }
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        self.runs.push(file);
+        self.size = 0;
+        Ok(())
+    }
+
+    /// The records in order with the ties of equal values, or None if the sort was terminated
+    /// while merging
+    fn finish(
+        mut self,
+        m: &Arc<Mutex<SorterInternalState>>,
+    ) -> CsvlensResult<Option<(Vec<usize>, Ties)>> {
+        if self.runs.is_empty() {
+            self.sort();
+            let mut ties = Ties::default();
+            for (position, pair) in self.values.windows(2).enumerate() {
+                if (self.cmp)(&pair[0].0, &pair[1].0).is_eq() {
+                    ties.push(position + 1);
+                }
+            }
+            let record_indices = self.values.into_iter().map(|(_, i)| i).collect();
+            return Ok(Some((record_indices, ties)));
+        }
+        if !self.values.is_empty() {
+            self.write_run()?;
+        }
+        let ExternalSort { cmp, runs, .. } = self;
+        let mut readers: Vec<BufReader<File>> = runs.into_iter().map(BufReader::new).collect();
+        let mut heap = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some((record_index, value)) = read_run_entry(reader)? {
+                heap.push(RunHead {
+                    value,
+                    record_index,
+                    run,
+                    cmp: &cmp,
+                });
+            }
+        }
+        let mut record_indices = vec![];
+        let mut ties = Ties::default();
+        let mut previous: Option<V> = None;
+        while let Some(RunHead {
+            value,
+            record_index,
+            run,
+            ..
+        }) = heap.pop()
+        {
+            if previous.is_some_and(|previous| cmp(&previous, &value).is_eq()) {
+                ties.push(record_indices.len());
+            }
+            previous = Some(value);
+            record_indices.push(record_index);
+            if record_indices.len() % (1 << 16) == 0 && m.lock().unwrap().should_terminate {
+                return Ok(None);
+            }
+            if let Some((record_index, value)) = read_run_entry(&mut readers[run])? {
+                heap.push(RunHead {
+                    value,
+                    record_index,
+                    run,
+                    cmp: &cmp,
+                });
+            }
+        }
+        Ok(Some((record_indices, ties)))
+    }
+}
+
+/// Next record index and value of a run, or None at its end
+fn read_run_entry<V: RunValue>(reader: &mut impl Read) -> io::Result<Option<(usize, V)>> {
+    let mut record_index = [0; 8];
+    match reader.read_exact(&mut record_index) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let value = V::read_from(reader)?;
+    Ok(Some((u64::from_le_bytes(record_index) as usize, value)))
+}
+
+/// The next value of a run being merged. The heap pops the greatest, so the smallest value is
+/// the greatest here, and of equal values the one of the first record.
+struct RunHead<'a, V, F> {
+    value: V,
+    record_index: usize,
+    run: usize,
+    cmp: &'a F,
+}
+
+impl<V, F: Fn(&V, &V) -> Ordering> Ord for RunHead<'_, V, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.cmp)(&other.value, &self.value).then(other.record_index.cmp(&self.record_index))
+    }
+}
+
+impl<V, F: Fn(&V, &V) -> Ordering> PartialOrd for RunHead<'_, V, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V, F: Fn(&V, &V) -> Ordering> PartialEq for RunHead<'_, V, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<V, F: Fn(&V, &V) -> Ordering> Eq for RunHead<'_, V, F> {}
+
+/// A value that can be written to a run of sorted values and read back
+trait RunValue: Sized {
+    /// Bytes the value takes in memory besides its own size, e.g. those of a string
+    fn heap_size(&self) -> usize;
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()>;
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self>;
+}
+
+impl RunValue for f64 {
+    fn heap_size(&self) -> usize {
+        0
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+}
+
+impl<T: RunValue> RunValue for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            Some(value) => {
+                writer.write_all(&[1])?;
+                value.write_to(writer)
+            }
+            None => writer.write_all(&[0]),
+        }
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        match tag[0] {
+            0 => Ok(None),
+            _ => T::read_from(reader).map(Some),
+        }
+    }
+}
+
+impl RunValue for String {
+    fn heap_size(&self) -> usize {
+        self.len()
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        write_bytes(writer, self.as_bytes())
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        String::from_utf8(read_bytes(reader)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl RunValue for Value {
+    fn heap_size(&self) -> usize {
+        match self {
+            Value::Text(text) => text.heap_size(),
+            _ => 0,
+        }
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            Value::Empty => writer.write_all(&[0]),
+            Value::Number(number) => {
+                writer.write_all(&[1])?;
+                number.write_to(writer)
+            }
+            Value::Text(text) => {
+                writer.write_all(&[2])?;
+                text.write_to(writer)
+            }
+        }
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        match tag[0] {
+            0 => Ok(Value::Empty),
+            1 => f64::read_from(reader).map(Value::Number),
+            _ => String::read_from(reader).map(Value::Text),
+        }
+    }
+}
+
+/// The values of several columns, each the key of a sort
+impl<T: RunValue> RunValue for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.iter()
+            .map(|value| size_of::<T>() + value.heap_size())
+            .sum()
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&(self.len() as u32).to_le_bytes())?;
+        self.iter().try_for_each(|value| value.write_to(writer))
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+        (0..u32::from_le_bytes(len))
+            .map(|_| T::read_from(reader))
+            .collect()
+    }
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A row in arrow's row format, which compares by bytes
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct RowBytes(Vec<u8>);
+
+impl RunValue for RowBytes {
+    fn heap_size(&self) -> usize {
+        self.0.len()
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        write_bytes(writer, &self.0)
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        read_bytes(reader).map(RowBytes)
+    }
+}
+
+/// Values of any type, or of several columns, in arrow's row format, to be sorted in runs like
+/// other values
+struct SpilledRuns {
+    converter: RowConverter,
+    rows: ExternalSort<RowBytes, fn(&RowBytes, &RowBytes) -> Ordering>,
+    num_empty: usize,
+}
+
+impl SpilledRuns {
+    fn new(fields: Vec<SortField>, memory_limit: usize) -> CsvlensResult<Self> {
+        Ok(SpilledRuns {
+            converter: RowConverter::new(fields)?,
+            rows: ExternalSort::new(memory_limit, RowBytes::cmp),
+            num_empty: 0,
+        })
+    }
+
+    /// Add the values of the records, with empty values counted in the first column
+    fn push(&mut self, columns: &[ArrayRef], record_indices: &[usize]) -> CsvlensResult<()> {
+        self.num_empty += columns.first().map_or(0, |c| c.null_count());
+        let converted = self.converter.convert_columns(columns)?;
+        for (row, record_index) in converted.iter().zip(record_indices) {
+            self.rows
+                .push(RowBytes(row.as_ref().to_vec()), *record_index)?;
+        }
+        Ok(())
+    }
+
+    fn finish(
+        self,
+        m: &Arc<Mutex<SorterInternalState>>,
+        order: SortOrder,
+    ) -> CsvlensResult<SortResult> {
+        let Some((record_indices, ties)) = self.rows.finish(m)? else {
+            return Ok(SortResult::default());
+        };
+        Ok(SortResult::from_indices(
+            record_indices,
+            ties,
+            self.num_empty,
+            order,
+        ))
+    }
+}
+
+/// The values of the records from first_record on that are sorted, i.e. those of the filtered
+/// rows if any, with the indices of their records
+fn sorted_records(
+    columns: Vec<ArrayRef>,
+    first_record: usize,
+    rows: Option<&FilteredRows>,
+) -> CsvlensResult<(Vec<ArrayRef>, Vec<usize>)> {
+    let end = first_record + columns.first().map_or(0, |c| c.len());
+    let Some(rows) = rows else {
+        return Ok((columns, (first_record..end).collect()));
+    };
+    let record_indices = rows.indices_between(first_record, end);
+    let positions: Vec<usize> = record_indices.iter().map(|i| i - first_record).collect();
+    let columns = columns
+        .iter()
+        .map(|values| take_rows(values, &positions))
+        .collect::<CsvlensResult<_>>()?;
+    Ok((columns, record_indices))
+}
+
+/// Like arrow's `sort_to_indices`, but equal values stay in the order they are in, which it
+/// doesn't guarantee
+fn stable_sort_to_indices(values: ArrayRef) -> CsvlensResult<UInt32Array> {
+    let num_rows = values.len();
+    let columns = [
+        SortColumn {
+            values,
+            options: None,
+        },
+        SortColumn {
+            values: Arc::new(UInt64Array::from_iter_values(0..num_rows as u64)),
+            options: None,
+        },
+    ];
+    Ok(kernels::sort::lexsort_to_indices(&columns, None)?)
+}
+
+/// Ties of the values, as sorted by the indices, that are equal in all the columns
+fn sorted_ties(columns: &[ArrayRef], sorted_indices: &UInt32Array) -> CsvlensResult<Ties> {
+    let sorted_columns = columns
+        .iter()
+        .map(|values| take(values.as_ref(), sorted_indices, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Ties::from_ranges(partition(&sorted_columns)?.ranges()))
+}
+
+/// Values of the rows at the indices only
+fn take_rows(values: &ArrayRef, row_indices: &[usize]) -> CsvlensResult<ArrayRef> {
+    let indices = UInt64Array::from_iter_values(row_indices.iter().map(|i| *i as u64));
+    Ok(take(values.as_ref(), &indices, None)?)
+}
+
+/// Index of the record at a position in the values sorted, which are those of the rows at the
+/// indices if only they were taken
+fn record_index(position: usize, row_indices: Option<&[usize]>) -> usize {
+    row_indices.map_or(position, |indices| indices[position])
+}
+
+/// Sort by several columns, comparing the values as read by the csv reader with the given
+/// comparison
+fn run_multi_sort_by(
+    m: Arc<Mutex<SorterInternalState>>,
+    config: Arc<csv::CsvConfig>,
+    keys: &[(usize, SortOrder)],
+    cmp: fn(&str, &str) -> Ordering,
+    filtered_rows: Option<&FilteredRows>,
+) -> CsvlensResult<SortResult> {
+    let mut rows = ExternalSort::new(
+        config.sort_memory_limit(),
+        |a: &Vec<String>, b: &Vec<String>| {
+            keys.iter()
+                .zip(a.iter().zip(b.iter()))
+                .map(|((_, order), (a, b))| match order {
+                    SortOrder::Ascending => cmp(a, b),
+                    SortOrder::Descending => cmp(b, a),
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        },
+    );
+    let mut num_empty = 0;
+    let mut reader = config.new_reader()?;
+    let mut record = StringRecord::new();
+    let mut index = 0;
+    while reader.read_record(&mut record)? {
+        if report_progress(&m, index + 1, reader.position().byte()) {
+            return Ok(SortResult::default());
+        }
+        if filtered_rows.is_none_or(|r| r.contains(index)) {
+            let values: Vec<String> = keys
+                .iter()
+                .map(|(column_index, _)| config.field(&record, *column_index).to_string())
+                .collect();
+            if cmp(&values[0], "").is_eq() {
+                num_empty += 1;
+            }
+            rows.push(values, index)?;
+        }
+        index += 1;
+    }
+
+    let Some((record_indices, ties)) = rows.finish(&m)? else {
+        return Ok(SortResult::default());
+    };
+    Ok(SortResult::from_indices(
+        record_indices,
+        ties,
+        num_empty,
+        keys[0].1,
+    ))
+}
+
+/// Sort by the values of expressions computed for each row, by the first expression and then by
+/// the next ones where rows are equal. Text values are compared with the given comparison.
+fn run_expression_sort(
+    m: Arc<Mutex<SorterInternalState>>,
+    config: Arc<csv::CsvConfig>,
+    keys: &[(Expression, SortOrder)],
+    cmp: fn(&str, &str) -> Ordering,
+    filtered_rows: Option<&FilteredRows>,
+) -> CsvlensResult<SortResult> {
+    let mut rows = ExternalSort::new(
+        config.sort_memory_limit(),
+        |a: &Vec<Value>, b: &Vec<Value>| {
+            keys.iter()
+                .zip(a.iter().zip(b.iter()))
+                .map(|((_, order), (a, b))| match order {
+                    SortOrder::Ascending => a.cmp_by(b, cmp),
+                    SortOrder::Descending => b.cmp_by(a, cmp),
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        },
+    );
+    let mut num_empty = 0;
+    let mut reader = config.new_reader()?;
+    let mut record = StringRecord::new();
+    let mut index = 0;
+    while reader.read_record(&mut record)? {
+        if report_progress(&m, index + 1, reader.position().byte()) {
+            return Ok(SortResult::default());
+        }
+        if filtered_rows.is_none_or(|r| r.contains(index)) {
+            let values: Vec<Value> = keys
+                .iter()
+                .map(|(expression, _)| expression.eval(&record, &config))
+                .collect();
+            if values[0].is_empty() {
+                num_empty += 1;
+            }
+            rows.push(values, index)?;
+        }
+        index += 1;
+    }
+
+    let Some((record_indices, ties)) = rows.finish(&m)? else {
+        return Ok(SortResult::default());
+    };
+    Ok(SortResult::from_indices(
+        record_indices,
+        ties,
+        num_empty,
+        keys[0].1,
+    ))
+}
+
+/// Sort by several columns with arrow's lexicographic sort. Columns arrow reads as strings are
+/// parsed like a single column would be, so that e.g. percentages sort by value.
+///
+/// Past the memory limit of the sort, the values are only looked at for what they are, and read
+/// again to be sorted in runs written to temporary files.
+fn run_lexsort(
+    m: Arc<Mutex<SorterInternalState>>,
+    config: Arc<csv::CsvConfig>,
+    keys: &[(usize, SortOrder)],
+    column_hints: &[ColumnHint],
+    ignore_case: bool,
+    rows: Option<&FilteredRows>,
+) -> CsvlensResult<SortResult> {
+    let declared_types: Vec<(usize, Option<DataType>)> = keys
+        .iter()
+        .zip(column_hints)
+        .map(|((column_index, _), hint)| (*column_index, hint.schema_type()))
+        .collect();
+    let schema = SorterInternalState::infer_schema(&config, &declared_types)?;
+    // Numbers with a decimal comma are read as strings to be parsed
+    let schema = if config.decimal_comma() {
+        let fields: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|f| match f.data_type() {
+                DataType::Float64 => f.as_ref().clone().with_data_type(DataType::Utf8),
+                _ => f.as_ref().clone(),
+            })
+            .collect();
+        Schema::new(fields)
+    } else {
+        schema
+    };
+
+    let mut projection: Vec<usize> = keys.iter().map(|(column_index, _)| *column_index).collect();
+    projection.sort_unstable();
+    projection.dedup();
+    // Where the column of each key is among those read
+    let positions: Vec<usize> = keys
+        .iter()
+        .map(|(column_index, _)| {
+            projection
+                .iter()
+                .position(|i| i == column_index)
+                .unwrap_or_default()
+        })
+        .collect();
+    let data_types: Vec<DataType> = projection
+        .iter()
+        .map(|i| {
+            schema
+                .fields()
+                .get(*i)
+                .map_or(DataType::Utf8, |f| f.data_type().clone())
+        })
+        .collect();
+    // Columns of strings are sorted by value if they all are numbers, booleans or dates
+    let is_parsed: Vec<bool> = positions
+        .iter()
+        .zip(column_hints)
+        .map(|(position, hint)| {
+            data_types[*position] == DataType::Utf8
+                && (hint.data_type.is_none() || hint.date_format.is_some())
+        })
+        .collect();
+    let mut kinds: Vec<ValueKinds> = column_hints
+        .iter()
+        .map(|hint| ValueKinds::new(hint.date_format.as_deref()))
+        .collect();
+    let schema = Arc::new(schema);
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let arrow_csv_reader =
+        config.new_arrow_reader(schema.clone(), projection.clone(), bytes_read.clone())?;
+    // The arrays hold the values of the records from first_record on
+    let mut arrs: Vec<Vec<ArrayRef>> = vec![vec![]; projection.len()];
+    let mut arrs_size = 0;
+    let mut first_record = 0;
+    let mut spilled = false;
+    let mut num_rows = 0;
+    // The values of the records read since first_record that are sorted, with the indices of
+    // their records, after adding them to the kinds of their keys
+    let mut take_records = |arrs: &mut Vec<Vec<ArrayRef>>, first_record: usize| {
+        let columns = arrs
+            .iter_mut()
+            .zip(&data_types)
+            .map(|(column_arrs, data_type)| {
+                let ref_arrs: Vec<&dyn Array> = column_arrs.iter().map(|a| a.as_ref()).collect();
+                let values = match ref_arrs.is_empty() {
+                    true => new_empty_array(data_type),
+                    false => concat(&ref_arrs)?,
+                };
+                column_arrs.clear();
+                Ok(values)
+            })
+            .collect::<CsvlensResult<Vec<_>>>()?;
+        let (columns, record_indices) = sorted_records(columns, first_record, rows)?;
+        for (i, kinds) in kinds.iter_mut().enumerate() {
+            if is_parsed[i] {
+                kinds.add_strings(&columns[positions[i]], &config);
+            }
+        }
+        CsvlensResult::Ok((columns, record_indices))
+    };
+    for record_batch_result in arrow_csv_reader {
+        let record_batch = record_batch_result?;
+        for (i, column_arrs) in arrs.iter_mut().enumerate() {
+            let arr = record_batch.column(i);
+            arrs_size += arr.get_array_memory_size();
+            column_arrs.push(arr.clone());
+        }
+        num_rows += record_batch.num_rows();
+        if m.lock()
+            .unwrap()
+            .update_progress(num_rows, bytes_read.load(atomic::Ordering::Relaxed))
+        {
+            return Ok(SortResult::default());
+        }
+        if arrs_size > config.sort_memory_limit() {
+            take_records(&mut arrs, first_record)?;
+            arrs_size = 0;
+            first_record = num_rows;
+            spilled = true;
+        }
+    }
+    let (columns, record_indices) = take_records(&mut arrs, first_record)?;
+    let kinds: Vec<Option<ValueKind>> = kinds.iter().map(ValueKinds::kind).collect();
+
+    // The values of each key as they are sorted
+    let sort_columns = |columns: &[ArrayRef]| -> Vec<SortColumn> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, (_, order))| {
+                let mut values = columns[positions[i]].clone();
+                if is_parsed[i] {
+                    values = parse_string_array(&values, &config, kinds[i]);
+                }
+                if ignore_case && values.data_type() == &DataType::Utf8 {
+                    let lowercase: StringArray = values
+                        .as_string::<i32>()
+                        .iter()
+                        .map(|v| v.map(str::to_lowercase))
+                        .collect();
+                    values = Arc::new(lowercase);
+                }
+                // Empty values come first in ascending order, like with a single column
+                let options = SortOptions {
+                    descending: *order == SortOrder::Descending,
+                    nulls_first: *order == SortOrder::Ascending,
+                };
+                SortColumn {
+                    values,
+                    options: Some(options),
+                }
+            })
+            .collect()
+    };
+
+    if spilled {
+        let arrow_csv_reader = config.new_arrow_reader(schema, projection, bytes_read.clone())?;
+        let mut spilled_runs: Option<SpilledRuns> = None;
+        let mut first_record = 0;
+        for record_batch_result in arrow_csv_reader {
+            let record_batch = record_batch_result?;
+            let end = first_record + record_batch.num_rows();
+            let (columns, record_indices) =
+                sorted_records(record_batch.columns().to_vec(), first_record, rows)?;
+            let columns = sort_columns(&columns);
+            let runs = match &mut spilled_runs {
+                Some(runs) => runs,
+                None => {
+                    let fields = columns
+                        .iter()
+                        .map(|c| {
+                            SortField::new_with_options(
+                                c.values.data_type().clone(),
+                                c.options.unwrap_or_default(),
+                            )
+                        })
+                        .collect();
+                    spilled_runs.insert(SpilledRuns::new(fields, config.sort_memory_limit())?)
+                }
+            };
+            let values: Vec<ArrayRef> = columns.into_iter().map(|c| c.values).collect();
+            runs.push(&values, &record_indices)?;
+            if m.lock()
+                .unwrap()
+                .update_progress(end, bytes_read.load(atomic::Ordering::Relaxed))
+            {
+                return Ok(SortResult::default());
+            }
+            first_record = end;
+        }
+        return match spilled_runs {
+            Some(runs) => runs.finish(&m, keys[0].1),
+            None => Ok(SortResult::default()),
+        };
+    }
+
+    let mut columns = sort_columns(&columns);
+    let values: Vec<ArrayRef> = columns.iter().map(|c| c.values.clone()).collect();
+    // Rows that are equal otherwise stay in the order of the file
+    columns.push(SortColumn {
+        values: Arc::new(UInt64Array::from_iter_values(
+            0..record_indices.len() as u64,
+        )),
+        options: None,
+    });
+
+    let num_empty = columns.first().map_or(0, |c| c.values.null_count());
+    let sorted_indices = kernels::sort::lexsort_to_indices(&columns, None)?;
+    let ties = sorted_ties(&values, &sorted_indices)?;
+    Ok(SortResult::from_indices(
+        sorted_indices
+            .values()
+            .iter()
+            .map(|i| record_indices[*i as usize])
+            .collect(),
+        ties,
+        num_empty,
+        keys[0].1,
+    ))
+}
+
+/// What the values of a column seen so far all are, of the kinds that are sorted by value
+/// although arrow reads them as strings: numbers, which can be percentages, booleans like yes/no,
+/// or dates in one of the patterns left. Empty values can be of any kind.
+struct ValueKinds<'a> {
+    number: bool,
+    boolean: bool,
+    date_formats: Vec<&'a str>,
+    any_value: bool,
+}
+
+/// A kind of ValueKinds
+#[derive(Clone, Copy)]
+enum ValueKind<'a> {
+    Number,
+    Boolean,
+    Date(&'a str),
+}
+
+impl<'a> ValueKinds<'a> {
+    /// Dates are only in the given pattern if any, and nothing else is sorted by value then
+    fn new(date_format: Option<&'a str>) -> Self {
+        ValueKinds {
+            number: date_format.is_none(),
+            boolean: date_format.is_none(),
+            date_formats: match date_format {
+                Some(format) => vec![format],
+                None => date::DATE_FORMATS.to_vec(),
+            },
+            any_value: false,
+        }
+    }
+
+    fn add(&mut self, value: &str, config: &csv::CsvConfig) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        self.any_value = true;
+        if self.number
+            && config
+                .parse_number(value)
+                .or_else(|| config.parse_percentage(value))
+                .is_none()
+        {
+            self.number = false;
+        }
+        if self.boolean && metadata::parse_bool(value).is_none() {
+            self.boolean = false;
+        }
+        self.date_formats
+            .retain(|format| date::parse_date(value, format).is_some());
+    }
+
+    fn add_strings(&mut self, values: &ArrayRef, config: &csv::CsvConfig) {
+        for value in values.as_string::<i32>().iter().flatten() {
+            if self.is_text() {
+                return;
+            }
+            self.add(value, config);
+        }
+    }
+
+    fn is_text(&self) -> bool {
+        !self.number && !self.boolean && self.date_formats.is_empty()
+    }
+
+    /// None if there are only empty values, or values of none of the kinds
+    fn kind(&self) -> Option<ValueKind<'a>> {
+        if !self.any_value {
+            None
+        } else if self.number {
+            Some(ValueKind::Number)
+        } else if self.boolean {
+            Some(ValueKind::Boolean)
+        } else {
+            self.date_formats
+                .first()
+                .map(|format| ValueKind::Date(format))
+        }
+    }
+}
+
+impl ValueKind<'_> {
+    /// The value as a number to sort by, with booleans as 0 and 1 and dates as milliseconds since
+    /// the epoch. None if it is empty.
+    fn sort_key(self, value: &str, config: &csv::CsvConfig) -> Option<f64> {
+        match self {
+            ValueKind::Number => config
+                .parse_number(value)
+                .or_else(|| config.parse_percentage(value)),
+            ValueKind::Boolean => metadata::parse_bool(value).map(|b| f64::from(u8::from(b))),
+            ValueKind::Date(format) => {
+                date::parse_date(value, format).map(|d| d.and_utc().timestamp_millis() as f64)
+            }
+        }
+    }
+}
+
+/// Strings as the numbers, booleans or dates they all are, if they are of a kind, like
+/// run_parsed_sort reads them. Otherwise the strings, trimmed if the config says so.
+fn parse_string_array(
+    values: &ArrayRef,
+    config: &csv::CsvConfig,
+    kind: Option<ValueKind>,
+) -> ArrayRef {
+    let strings = values.as_string::<i32>();
+    let non_empty = || {
+        strings
+            .iter()
+            .map(|v| v.map(str::trim).filter(|v| !v.is_empty()))
+    };
+    match kind {
+        Some(ValueKind::Number) => {
+            let numbers: Float64Array = non_empty()
+                .map(|v| {
+                    v.and_then(|v| {
+                        config
+                            .parse_number(v)
+                            .or_else(|| config.parse_percentage(v))
+                    })
+                })
+                .collect();
+            Arc::new(numbers)
+        }
+        Some(ValueKind::Boolean) => {
+            let booleans: BooleanArray = non_empty()
+                .map(|v| v.and_then(metadata::parse_bool))
+                .collect();
+            Arc::new(booleans)
+        }
+        Some(ValueKind::Date(format)) => {
+            let timestamps: Int64Array = non_empty()
+                .map(|v| {
+                    v.and_then(|v| date::parse_date(v, format))
+                        .map(|d| d.and_utc().timestamp_millis())
+                })
+                .collect();
+            Arc::new(timestamps)
+        }
+        None if config.trim() => {
+            let trimmed: StringArray = strings.iter().map(|v| v.map(str::trim)).collect();
+            Arc::new(trimmed)
+        }
+        None => values.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_sort() {
+        let mut items = vec!["disk1", "disk10", "disk2", "disk11"];
+        items.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(items, vec!["disk1", "disk2", "disk10", "disk11"]);
+    }
+
+    #[test]
+    fn test_natural_sort_mixed() {
+        let mut items = vec!["file1.txt", "file10.txt", "file2.txt", "file20.txt"];
+        items.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            items,
+            vec!["file1.txt", "file2.txt", "file10.txt", "file20.txt"]
+        );
+    }
+
+    #[test]
+    fn test_natural_sort_with_text() {
+        let mut items = vec!["chapter1", "chapter10", "chapter2", "chapter20", "appendix"];
+        items.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            items,
+            vec!["appendix", "chapter1", "chapter2", "chapter10", "chapter20"]
+        );
+    }
+
+    #[test]
+    fn test_simple() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/simple.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let s = Sorter::new(
+            config,
+            0,
+            "A1".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        let expected = vec![0, 9, 99, 999, 1000];
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn test_streamed_input() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"n\n3\n1\n2\n").unwrap();
+        let stream_active = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let config = Arc::new(csv::CsvConfig::new(
+            file.path().to_str().unwrap(),
+            Some(stream_active.clone()),
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let new_sorter = || {
+            Sorter::new(
+                config.clone(),
+                0,
+                "n".to_string(),
+                SortType::Auto,
+                ColumnHint::default(),
+                None,
+            )
+        };
+
+        // Only the rows received so far
+        let partial = new_sorter();
+        partial.wait_internal();
+        assert!(partial.is_partial());
+        assert!(partial.sorts_like(&partial));
+        assert_eq!(
+            partial.get_sorted_indices(0, 5, SortOrder::Ascending),
+            Some(vec![1, 2, 0])
+        );
+
+        std::io::Write::write_all(&mut file, b"0\n").unwrap();
+        stream_active.store(false, std::sync::atomic::Ordering::Relaxed);
+        let complete = new_sorter();
+        complete.wait_internal();
+        assert!(!complete.is_partial());
+        assert!(!complete.sorts_like(&partial));
+        assert_eq!(
+            complete.get_sorted_indices(0, 5, SortOrder::Ascending),
+            Some(vec![3, 1, 2, 0])
+        );
+    }
+
+    #[test]
+    fn test_batch_size() {
+        let sorted_indices = |base_config: csv::CsvBaseConfig| {
+            let config = Arc::new(csv::CsvConfig::new(
+                "tests/data/cities.csv",
+                None,
+                base_config,
+            ));
+            let s = Sorter::new(
+                config,
+                0,
+                "LatD".to_string(),
+                SortType::Auto,
+                ColumnHint::default(),
+                None,
+            );
+            s.wait_internal();
+            s.get_sorted_indices(0, 200, SortOrder::Ascending).unwrap()
+        };
+        let expected = sorted_indices(csv::CsvBaseConfig::new(b',', false));
+        let actual = sorted_indices(
+            csv::CsvBaseConfig::new(b',', false)
+                .with_arrow_batch_size(7)
+                .with_schema_infer_max_records(5),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_reader_options() {
+        // Type inference sees the header row as a value when there are no headers
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/cities.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', true),
+        ));
+        let s = Sorter::new(
+            config,
+            0,
+            "1".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
         s.wait_internal();
-        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
-        let expected = vec![0, 9, 99, 999, 1000];
-        assert_eq!(rows, expected);
+        assert_eq!(s.status(), SorterStatus::Finished);
+
+        // Rows with missing fields are sorted like any other
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/irregular.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let s = Sorter::new(
+            config,
+            1,
+            "COL2".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        assert_eq!(s.status(), SorterStatus::Finished);
+        let rows = s.get_sorted_indices(0, 2, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_ties_in_file_order() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/cities.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let values: Vec<String> = csv::CsvlensRecordIterator::new(config.clone())
+            .unwrap()
+            .map(|r| r.unwrap()[0].to_string())
+            .collect();
+        for sort_type in [SortType::Auto, SortType::Natural] {
+            let s = Sorter::new(
+                config.clone(),
+                0,
+                "LatD".to_string(),
+                sort_type,
+                ColumnHint::default(),
+                None,
+            );
+            s.wait_internal();
+            // Rows with the same LatD, like the 41s, stay in the order of the file both ways
+            for order in [SortOrder::Ascending, SortOrder::Descending] {
+                let rows = s.get_sorted_indices(0, 128, order).unwrap();
+                assert!(values[rows[0] as usize] != values[rows[127] as usize]);
+                for pair in rows.windows(2) {
+                    if values[pair[0] as usize] == values[pair[1] as usize] {
+                        assert!(pair[0] < pair[1], "{order:?} {pair:?}");
+                    }
+                }
+                for (position, row) in rows.iter().enumerate() {
+                    assert_eq!(s.get_record_order(*row, order), Some(position as u64));
+                }
+            }
+        }
     }
 
     #[test]
@@ -451,7 +2323,14 @@ mod tests {
             None,
             csv::CsvBaseConfig::new(b',', false),
         ));
-        let s = Sorter::new(config, 0, "A1".to_string(), SortType::Auto);
+        let s = Sorter::new(
+            config,
+            0,
+            "A1".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
         s.wait_internal();
         let rows = s.get_sorted_indices(0, 5, SortOrder::Descending).unwrap();
         let expected = vec![998, 997, 996, 995, 994];
@@ -465,11 +2344,951 @@ mod tests {
             None,
             csv::CsvBaseConfig::new(b',', false),
         ));
-        let s = Sorter::new(config, 1, "b".to_string(), SortType::Auto);
+        let s = Sorter::new(
+            config,
+            1,
+            "b".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        // A column with only empty values sorts like one of empty strings
+        assert_eq!(s.status(), SorterStatus::Finished);
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_null_values() {
+        let sorted_indices = |null_values: &[&str], keys: &[usize], sort_type| {
+            let base = csv::CsvBaseConfig::new(b',', false)
+                .with_null_values(null_values.iter().map(|v| v.to_string()).collect());
+            let config = Arc::new(csv::CsvConfig::new(
+                "tests/data/null_values.csv",
+                None,
+                base,
+            ));
+            let keys: Vec<SortKey> = keys
+                .iter()
+                .map(|column_index| SortKey {
+                    column_index: *column_index,
+                    column_name: column_index.to_string(),
+                    order: SortOrder::Ascending,
+                    expression: None,
+                })
+                .collect();
+            let column_hints = vec![ColumnHint::default(); keys.len()];
+            let s = Sorter::with_keys(config, keys, sort_type, column_hints, None)
+                .with_null_placement(NullPlacement::Last);
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        // Numbers by value, with the values standing for none like empty ones
+        assert_eq!(
+            sorted_indices(&["NA", "-"], &[1], SortType::Auto),
+            vec![2, 0, 1, 3, 4]
+        );
+        assert_eq!(
+            sorted_indices(&["NA", "-"], &[1, 0], SortType::Auto),
+            vec![2, 0, 1, 3, 4]
+        );
+        assert_eq!(
+            sorted_indices(&["NA", "-"], &[1], SortType::Natural),
+            vec![2, 0, 1, 3, 4]
+        );
+        // Text otherwise
+        assert_eq!(
+            sorted_indices(&[], &[1], SortType::Auto),
+            vec![3, 0, 2, 1, 4]
+        );
+    }
+
+    #[test]
+    fn test_decimal_comma() {
+        let sorted_indices = |decimal_comma: bool| {
+            let config = Arc::new(csv::CsvConfig::new(
+                "tests/data/decimal_comma.csv",
+                None,
+                csv::CsvBaseConfig::new(b';', false).with_decimal_comma(decimal_comma),
+            ));
+            let s = Sorter::new(
+                config,
+                1,
+                "price".to_string(),
+                SortType::Auto,
+                ColumnHint::default(),
+                None,
+            );
+            s.wait_internal();
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        assert_eq!(sorted_indices(true), vec![2, 4, 1, 3, 0]);
+        // Sorted as strings otherwise
+        assert_eq!(sorted_indices(false), vec![2, 0, 3, 4, 1]);
+    }
+
+    #[test]
+    fn test_percentages_and_booleans() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/kinds.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let s = Sorter::new(
+            config.clone(),
+            1,
+            "share".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![2, 4, 3, 0, 1]);
+
+        let s = Sorter::new(
+            config,
+            2,
+            "active".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![1, 3, 0, 2, 4]);
+    }
+
+    #[test]
+    fn test_trim() {
+        let sorted_indices = |trim: bool| {
+            let config = Arc::new(csv::CsvConfig::new(
+                "tests/data/padded.csv",
+                None,
+                csv::CsvBaseConfig::new(b',', false).with_trim(trim),
+            ));
+            let s = Sorter::new(
+                config,
+                0,
+                "code".to_string(),
+                SortType::Auto,
+                ColumnHint::default(),
+                None,
+            );
+            s.wait_internal();
+            s.get_sorted_indices(0, 3, SortOrder::Ascending).unwrap()
+        };
+        assert_eq!(sorted_indices(true), vec![1, 0, 2]);
+        // Leading spaces come first otherwise
+        assert_eq!(sorted_indices(false), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn test_quote_char() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/single_quoted.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false).with_quoting(b'\'', Some(b'\\'), true),
+        ));
+        let s = Sorter::new(
+            config,
+            2,
+            "score".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 3, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/mixed_case.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let sorted_indices = |sort_type: SortType| {
+            let s = Sorter::new(
+                config.clone(),
+                0,
+                "name".to_string(),
+                sort_type,
+                ColumnHint::default(),
+                None,
+            );
+            s.wait_internal();
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        // Uppercase comes first otherwise
+        assert_eq!(sorted_indices(SortType::Auto), vec![1, 4, 3, 0, 2]);
+        // Values differing only in case keep the order of the file
+        assert_eq!(
+            sorted_indices(SortType::CaseInsensitive),
+            vec![1, 3, 0, 4, 2]
+        );
+
+        let keys = vec![
+            SortKey {
+                column_index: 0,
+                column_name: "name".to_string(),
+                order: SortOrder::Ascending,
+                expression: None,
+            },
+            SortKey {
+                column_index: 1,
+                column_name: "team".to_string(),
+                order: SortOrder::Descending,
+                expression: None,
+            },
+        ];
+        let s = Sorter::with_keys(
+            config,
+            keys,
+            SortType::CaseInsensitive,
+            vec![ColumnHint::default(); 2],
+            None,
+        );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![1, 3, 4, 0, 2]);
+    }
+
+    #[test]
+    fn test_dates() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/dates.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let sorted_indices = |column_index: usize, date_format: Option<&str>| {
+            let hint = ColumnHint {
+                date_format: date_format.map(|f| f.to_string()),
+                ..Default::default()
+            };
+            let s = Sorter::new(
+                config.clone(),
+                column_index,
+                column_index.to_string(),
+                SortType::Auto,
+                hint,
+                None,
+            );
+            s.wait_internal();
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        // Month first, empty values first
+        assert_eq!(sorted_indices(1, None), vec![2, 3, 4, 0, 1]);
+        // Day first, since 13/03/2023 can't be month first
+        assert_eq!(sorted_indices(2, None), vec![4, 2, 1, 0, 3]);
+        assert_eq!(sorted_indices(3, None), vec![1, 0, 4, 3, 2]);
+        // Month first where both fit, unless told otherwise
+        assert_eq!(sorted_indices(4, None), vec![4, 2, 1, 0, 3]);
+        assert_eq!(sorted_indices(4, Some("%d/%m/%Y")), vec![4, 3, 0, 1, 2]);
+        // Sorted as text if the pattern doesn't fit
+        assert_eq!(sorted_indices(1, Some("%d/%m/%Y")), vec![2, 1, 3, 4, 0]);
+
+        // Same with several keys
+        let keys = vec![
+            SortKey {
+                column_index: 4,
+                column_name: "both".to_string(),
+                order: SortOrder::Ascending,
+                expression: None,
+            },
+            SortKey {
+                column_index: 3,
+                column_name: "text".to_string(),
+                order: SortOrder::Ascending,
+                expression: None,
+            },
+        ];
+        let hints = vec![
+            ColumnHint {
+                date_format: Some("%d/%m/%Y".to_string()),
+                ..Default::default()
+            },
+            ColumnHint::default(),
+        ];
+        let s = Sorter::with_keys(config.clone(), keys, SortType::Auto, hints, None);
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![4, 3, 0, 1, 2]);
+
+        let keys = vec![
+            SortKey {
+                column_index: 3,
+                column_name: "text".to_string(),
+                order: SortOrder::Descending,
+                expression: None,
+            },
+            SortKey {
+                column_index: 0,
+                column_name: "id".to_string(),
+                order: SortOrder::Ascending,
+                expression: None,
+            },
+        ];
+        let s = Sorter::with_keys(
+            config,
+            keys,
+            SortType::Auto,
+            vec![ColumnHint::default(); 2],
+            None,
+        );
         s.wait_internal();
+        let rows = s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![2, 3, 4, 0, 1]);
+    }
+
+    #[test]
+    fn test_multiple_keys() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/multi_sort.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let sorted_indices = |keys: &[(usize, SortOrder)], sort_type: SortType| {
+            let keys: Vec<SortKey> = keys
+                .iter()
+                .map(|(column_index, order)| SortKey {
+                    column_index: *column_index,
+                    column_name: column_index.to_string(),
+                    order: *order,
+                    expression: None,
+                })
+                .collect();
+            let column_types = vec![ColumnHint::default(); keys.len()];
+            let s = Sorter::with_keys(config.clone(), keys, sort_type, column_types, None);
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        use SortOrder::*;
+        // Ties keep the order of the file
+        assert_eq!(
+            sorted_indices(&[(0, Ascending), (1, Descending)], SortType::Auto),
+            vec![3, 1, 4, 0, 2]
+        );
+        // Numbers by value
+        assert_eq!(
+            sorted_indices(&[(1, Ascending), (0, Descending)], SortType::Auto),
+            vec![2, 1, 4, 0, 3]
+        );
+        // Percentages by value, empty values first
+        assert_eq!(
+            sorted_indices(&[(3, Ascending), (0, Ascending)], SortType::Auto),
+            vec![3, 4, 0, 1, 2]
+        );
+        assert_eq!(
+            sorted_indices(&[(0, Ascending), (2, Descending)], SortType::Natural),
+            vec![1, 3, 4, 2, 0]
+        );
+        // A single key in descending order
+        assert_eq!(
+            sorted_indices(&[(2, Descending)], SortType::Auto),
+            vec![2, 1, 0, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_expression_keys() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/multi_sort.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let headers: Vec<String> = ["team", "score", "name", "share"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let sorted_indices = |keys: Vec<SortKey>| {
+            let column_types = vec![ColumnHint::default(); keys.len()];
+            let s = Sorter::with_keys(config.clone(), keys, SortType::Auto, column_types, None);
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        let key = |text: &str, order: SortOrder| {
+            SortKey::expression(Expression::parse(text, &headers).unwrap(), order)
+        };
+        use SortOrder::*;
+        assert_eq!(
+            sorted_indices(vec![key("score % 3", Ascending)]),
+            vec![0, 3, 1, 2, 4]
+        );
+        assert_eq!(
+            sorted_indices(vec![key("len(share)", Descending)]),
+            vec![1, 2, 0, 4, 3]
+        );
+        // Along with a column, which is sorted like an expression too
+        let name = SortKey {
+            column_index: 2,
+            column_name: "name".to_string(),
+            order: Descending,
+            expression: None,
+        };
+        assert_eq!(
+            sorted_indices(vec![key("-score", Ascending), name]),
+            vec![0, 3, 2, 1, 4]
+        );
+    }
+
+    #[test]
+    fn test_null_placement() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/sparse.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let sorter = |keys: &[(usize, SortOrder)], sort_type, null_placement| {
+            let keys: Vec<SortKey> = keys
+                .iter()
+                .map(|(column_index, order)| SortKey {
+                    column_index: *column_index,
+                    column_name: column_index.to_string(),
+                    order: *order,
+                    expression: None,
+                })
+                .collect();
+            let column_types = vec![ColumnHint::default(); keys.len()];
+            let s = Sorter::with_keys(config.clone(), keys, sort_type, column_types, None)
+                .with_null_placement(null_placement);
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            s
+        };
+        let sorted_indices = |keys: &[(usize, SortOrder)], sort_type, null_placement, order| {
+            sorter(keys, sort_type, null_placement)
+                .get_sorted_indices(0, 6, order)
+                .unwrap()
+        };
+        use NullPlacement::*;
+        use SortOrder::*;
+
+        // Numbers, read by arrow
+        let score = [(1, Ascending)];
+        assert_eq!(
+            sorted_indices(&score, SortType::Auto, Smallest, Ascending),
+            vec![1, 3, 2, 5, 4, 0]
+        );
+        assert_eq!(
+            sorted_indices(&score, SortType::Auto, Smallest, Descending),
+            vec![0, 4, 2, 5, 1, 3]
+        );
+        assert_eq!(
+            sorted_indices(&score, SortType::Auto, First, Descending),
+            vec![1, 3, 0, 4, 2, 5]
+        );
+        assert_eq!(
+            sorted_indices(&score, SortType::Auto, Last, Ascending),
+            vec![2, 5, 4, 0, 1, 3]
+        );
+        assert_eq!(
+            sorted_indices(&score, SortType::Auto, Last, Descending),
+            vec![0, 4, 2, 5, 1, 3]
+        );
+
+        // Text, compared by arrow, naturally or regardless of case
+        let name = [(2, Ascending)];
+        for sort_type in [SortType::Auto, SortType::CaseInsensitive] {
+            assert_eq!(
+                sorted_indices(&name, sort_type, Last, Ascending),
+                vec![1, 3, 0, 5, 2, 4]
+            );
+        }
+        assert_eq!(
+            sorted_indices(&name, SortType::Natural, First, Descending),
+            vec![2, 4, 5, 0, 3, 1]
+        );
+
+        // Percentages, parsed
+        assert_eq!(
+            sorted_indices(&[(3, Ascending)], SortType::Auto, Last, Ascending),
+            vec![3, 0, 2, 1, 4, 5]
+        );
+
+        // Several keys, by the first one
+        let keys = [(1, Ascending), (2, Descending)];
+        for sort_type in [SortType::Auto, SortType::Natural] {
+            assert_eq!(
+                sorted_indices(&keys, sort_type, Last, Ascending),
+                vec![5, 2, 4, 0, 3, 1]
+            );
+        }
+        // A single key in descending order, with the empty values at the end of the sort
+        assert_eq!(
+            sorted_indices(&[(1, Descending)], SortType::Auto, First, Ascending),
+            vec![1, 3, 0, 4, 2, 5]
+        );
+
+        // Rows are found where they are shown
+        let s = sorter(&score, SortType::Auto, Last);
+        for order in [Ascending, Descending] {
+            let rows = s.get_sorted_indices(0, 6, order).unwrap();
+            for (position, row) in rows.iter().enumerate() {
+                assert_eq!(s.get_record_order(*row, order), Some(position as u64));
+            }
+        }
+        assert_eq!(s.get_sorted_indices(4, 10, Ascending).unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_spilled_runs() {
+        let sorted_indices = |path: &str,
+                              column_index: usize,
+                              sort_memory_limit: Option<usize>,
+                              filtered_rows: Option<FilteredRows>,
+                              null_placement: NullPlacement| {
+            let mut base_config = csv::CsvBaseConfig::new(b',', false).with_arrow_batch_size(2);
+            if let Some(limit) = sort_memory_limit {
+                base_config = base_config.with_sort_memory_limit(limit);
+            }
+            let config = Arc::new(csv::CsvConfig::new(path, None, base_config));
+            let s = Sorter::new(
+                config,
+                column_index,
+                column_index.to_string(),
+                SortType::Auto,
+                ColumnHint::default(),
+                filtered_rows,
+            )
+            .with_null_placement(null_placement);
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            [SortOrder::Ascending, SortOrder::Descending]
+                .map(|order| s.get_sorted_indices(0, 200, order).unwrap())
+        };
+        let every_third_row = FilteredRows {
+            filter: "every third row".to_string(),
+            row_indices: Arc::new((0..128).step_by(3).collect()),
+        };
+        // Numbers, text, and a column of a single value where only the file order counts, with
+        // every batch spilled to a run
+        for column_index in [0, 8, 3] {
+            for filtered_rows in [None, Some(every_third_row.clone())] {
+                let in_memory = sorted_indices(
+                    "tests/data/cities.csv",
+                    column_index,
+                    None,
+                    filtered_rows.clone(),
+                    NullPlacement::Smallest,
+                );
+                let spilled = sorted_indices(
+                    "tests/data/cities.csv",
+                    column_index,
+                    Some(1),
+                    filtered_rows,
+                    NullPlacement::Smallest,
+                );
+                assert_eq!(spilled, in_memory);
+            }
+        }
+        // Empty values are counted across runs
+        let [ascending, descending] = sorted_indices(
+            "tests/data/sparse.csv",
+            1,
+            Some(1),
+            None,
+            NullPlacement::Last,
+        );
+        assert_eq!(ascending, vec![2, 5, 4, 0, 1, 3]);
+        assert_eq!(descending, vec![0, 4, 2, 5, 1, 3]);
+    }
+
+    #[test]
+    fn test_spilled_runs_every_mode() {
+        // Sorted in memory, and with every value in a run of its own
+        let sorted_indices = |path: &str,
+                              base_config: fn() -> csv::CsvBaseConfig,
+                              keys: &[(&str, SortOrder)],
+                              sort_type: SortType,
+                              filtered_rows: Option<FilteredRows>| {
+            let headers: Vec<String> = csv::CsvConfig::new(path, None, base_config())
+                .new_reader()
+                .unwrap()
+                .headers()
+                .unwrap()
+                .iter()
+                .map(|h| h.trim().to_string())
+                .collect();
+            [None, Some(1)].map(|sort_memory_limit| {
+                let mut base_config = base_config();
+                if let Some(limit) = sort_memory_limit {
+                    base_config = base_config.with_sort_memory_limit(limit);
+                }
+                let config = Arc::new(csv::CsvConfig::new(path, None, base_config));
+                let keys: Vec<SortKey> = keys
+                    .iter()
+                    .map(|(key, order)| match headers.iter().position(|h| h == key) {
+                        Some(column_index) => SortKey {
+                            column_index,
+                            column_name: key.to_string(),
+                            order: *order,
+                            expression: None,
+                        },
+                        None => {
+                            SortKey::expression(Expression::parse(key, &headers).unwrap(), *order)
+                        }
+                    })
+                    .collect();
+                let column_hints = vec![ColumnHint::default(); keys.len()];
+                let s =
+                    Sorter::with_keys(config, keys, sort_type, column_hints, filtered_rows.clone());
+                s.wait_internal();
+                assert_eq!(s.status(), SorterStatus::Finished);
+                [SortOrder::Ascending, SortOrder::Descending]
+                    .map(|order| s.get_sorted_indices(0, 200, order).unwrap())
+            })
+        };
+        fn comma() -> csv::CsvBaseConfig {
+            csv::CsvBaseConfig::new(b',', false)
+        }
+        use SortOrder::*;
+        type Case<'a> = (
+            &'a str,
+            fn() -> csv::CsvBaseConfig,
+            Vec<(&'a str, SortOrder)>,
+            SortType,
+        );
+        let cases: Vec<Case> = vec![
+            // Natural
+            (
+                "tests/data/cities.csv",
+                comma,
+                vec![("City", Ascending)],
+                SortType::Natural,
+            ),
+            // Trimmed or ignoring case
+            (
+                "tests/data/padded.csv",
+                || comma().with_trim(true),
+                vec![("code", Ascending)],
+                SortType::Auto,
+            ),
+            (
+                "tests/data/mixed_case.csv",
+                comma,
+                vec![("name", Ascending)],
+                SortType::CaseInsensitive,
+            ),
+            // Numbers with values that aren't
+            (
+                "tests/data/mixed_types.csv",
+                || comma().with_schema_infer_max_records(2),
+                vec![("value", Ascending)],
+                SortType::Auto,
+            ),
+            // Percentages, yes/no, decimal commas and dates
+            (
+                "tests/data/kinds.csv",
+                comma,
+                vec![("share", Ascending)],
+                SortType::Auto,
+            ),
+            (
+                "tests/data/kinds.csv",
+                comma,
+                vec![("active", Ascending)],
+                SortType::Auto,
+            ),
+            (
+                "tests/data/decimal_comma.csv",
+                || csv::CsvBaseConfig::new(b';', false).with_decimal_comma(true),
+                vec![("price", Ascending)],
+                SortType::Auto,
+            ),
+            (
+                "tests/data/dates.csv",
+                comma,
+                vec![("us", Ascending)],
+                SortType::Auto,
+            ),
+            // Several columns, natural or with arrow
+            (
+                "tests/data/multi_sort.csv",
+                comma,
+                vec![("team", Ascending), ("name", Descending)],
+                SortType::Natural,
+            ),
+            (
+                "tests/data/multi_sort.csv",
+                comma,
+                vec![("share", Ascending), ("team", Ascending)],
+                SortType::Auto,
+            ),
+            (
+                "tests/data/cities.csv",
+                comma,
+                vec![("State", Descending), ("LatD", Ascending)],
+                SortType::CaseInsensitive,
+            ),
+            // Expressions
+            (
+                "tests/data/multi_sort.csv",
+                comma,
+                vec![("score % 3", Ascending), ("name", Descending)],
+                SortType::Auto,
+            ),
+        ];
+        let every_other_row = FilteredRows {
+            filter: "every other row".to_string(),
+            row_indices: Arc::new((0..128).step_by(2).collect()),
+        };
+        for (path, base_config, keys, sort_type) in cases {
+            for filtered_rows in [None, Some(every_other_row.clone())] {
+                let [in_memory, spilled] =
+                    sorted_indices(path, base_config, &keys, sort_type, filtered_rows);
+                assert_eq!(spilled, in_memory, "{path} by {keys:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_filtered_rows() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/multi_sort.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let filtered_rows = FilteredRows {
+            filter: "rows 0, 2 and 4".to_string(),
+            row_indices: Arc::new(vec![0, 2, 4]),
+        };
+        let sorter = |keys: &[(usize, SortOrder)], sort_type: SortType| {
+            let keys: Vec<SortKey> = keys
+                .iter()
+                .map(|(column_index, order)| SortKey {
+                    column_index: *column_index,
+                    column_name: column_index.to_string(),
+                    order: *order,
+                    expression: None,
+                })
+                .collect();
+            let column_types = vec![ColumnHint::default(); keys.len()];
+            let s = Sorter::with_keys(
+                config.clone(),
+                keys,
+                sort_type,
+                column_types,
+                Some(filtered_rows.clone()),
+            );
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            s
+        };
+        let sorted_indices = |keys: &[(usize, SortOrder)], sort_type: SortType| {
+            sorter(keys, sort_type)
+                .get_sorted_indices(0, 5, SortOrder::Ascending)
+                .unwrap()
+        };
+        use SortOrder::*;
+        // Numbers read by arrow
+        assert_eq!(
+            sorted_indices(&[(1, Ascending)], SortType::Auto),
+            vec![2, 4, 0]
+        );
+        // Percentages
+        assert_eq!(
+            sorted_indices(&[(3, Ascending)], SortType::Auto),
+            vec![4, 0, 2]
+        );
+        assert_eq!(
+            sorted_indices(&[(2, Ascending)], SortType::Natural),
+            vec![4, 0, 2]
+        );
+        assert_eq!(
+            sorted_indices(&[(0, Ascending), (1, Descending)], SortType::Auto),
+            vec![4, 0, 2]
+        );
+        assert_eq!(
+            sorted_indices(&[(0, Descending), (2, Descending)], SortType::Natural),
+            vec![2, 0, 4]
+        );
+
+        // Rows left out have no order
+        let s = sorter(&[(1, Ascending)], SortType::Auto);
+        assert_eq!(s.get_record_order(0, Ascending), Some(2));
+        assert_eq!(s.get_record_order(0, Descending), Some(0));
+        assert_eq!(s.get_record_order(1, Ascending), None);
+        assert_eq!(
+            s.get_sorted_indices(0, 5, Descending).unwrap(),
+            vec![0, 2, 4]
+        );
+    }
+
+    #[test]
+    fn test_sort_cache() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/cities.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let key = |column_index: usize| SortKey {
+            column_index,
+            column_name: column_index.to_string(),
+            order: SortOrder::Ascending,
+            expression: None,
+        };
+        let sorter = |column_index| {
+            let s = Arc::new(Sorter::with_keys(
+                config.clone(),
+                vec![key(column_index)],
+                SortType::Auto,
+                vec![ColumnHint::default()],
+                None,
+            ));
+            s.wait_internal();
+            s
+        };
+        let mut cache = SortCache::default();
+        let lat_d = sorter(0);
+        cache.insert(&lat_d);
+        for column_index in 0..SORT_CACHE_SIZE {
+            cache.insert(&sorter(column_index + 1));
+        }
+        // The least recently used sort is dropped first
+        assert!(
+            cache
+                .get(&[key(0)], SortType::Auto, None, NullPlacement::Smallest)
+                .is_none()
+        );
+        cache.insert(&lat_d);
+        let cached = cache
+            .get(&[key(0)], SortType::Auto, None, NullPlacement::Last)
+            .unwrap();
+        assert!(cached.shares_result(&lat_d));
+        assert_eq!(
+            cached.get_sorted_indices(0, 128, SortOrder::Descending),
+            lat_d.get_sorted_indices(0, 128, SortOrder::Descending)
+        );
+        assert!(
+            cache
+                .get(&[key(0)], SortType::Natural, None, NullPlacement::Smallest)
+                .is_none()
+        );
+        assert_eq!(
+            cache.memory_usage(Some(&lat_d)),
+            cache.memory_usage(None) - lat_d.memory_usage()
+        );
+    }
+
+    #[test]
+    fn test_mixed_types() {
+        // Types are inferred from the first 2 rows only, which the third row doesn't fit
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/mixed_types.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false).with_schema_infer_max_records(2),
+        ));
+        let sorted_indices = |keys: &[usize], null_placement| {
+            let keys: Vec<SortKey> = keys
+                .iter()
+                .map(|column_index| SortKey {
+                    column_index: *column_index,
+                    column_name: column_index.to_string(),
+                    order: SortOrder::Ascending,
+                    expression: None,
+                })
+                .collect();
+            let column_types = vec![ColumnHint::default(); keys.len()];
+            let s = Sorter::with_keys(config.clone(), keys, SortType::Auto, column_types, None)
+                .with_null_placement(null_placement);
+            s.wait_internal();
+            assert_eq!(s.status(), SorterStatus::Finished);
+            s.get_sorted_indices(0, 5, SortOrder::Ascending).unwrap()
+        };
+        // Numbers, with N/A like an empty value
+        assert_eq!(
+            sorted_indices(&[1], NullPlacement::Smallest),
+            vec![2, 4, 1, 3, 0]
+        );
+        assert_eq!(
+            sorted_indices(&[1], NullPlacement::Last),
+            vec![1, 3, 0, 2, 4]
+        );
+        // Dates, as text
+        assert_eq!(
+            sorted_indices(&[2], NullPlacement::Smallest),
+            vec![3, 1, 4, 0, 2]
+        );
+        // Several columns, as text
         assert_eq!(
-            s.status(),
-            SorterStatus::Error("Compute error: Sort not supported for data type Null".to_string())
+            sorted_indices(&[1, 0], NullPlacement::Smallest),
+            vec![4, 0, 1, 3, 2]
+        );
+    }
+
+    #[test]
+    fn test_mixed_types_with_other_keys() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"code,n\n1,10\n1,9\nx,100\n1,10.5\n").unwrap();
+        let config = Arc::new(csv::CsvConfig::new(
+            file.path().to_str().unwrap(),
+            None,
+            csv::CsvBaseConfig::new(b',', false).with_schema_infer_max_records(2),
+        ));
+        let keys: Vec<SortKey> = ["code", "n"]
+            .iter()
+            .enumerate()
+            .map(|(column_index, name)| SortKey {
+                column_index,
+                column_name: name.to_string(),
+                order: SortOrder::Ascending,
+                expression: None,
+            })
+            .collect();
+        let s = Sorter::with_keys(
+            config,
+            keys,
+            SortType::Auto,
+            vec![ColumnHint::default(); 2],
+            None,
+        );
+        s.wait_internal();
+        assert_eq!(s.status(), SorterStatus::Finished);
+        // Only the codes are sorted as text, and rows with the same code still by number
+        let rows = s.get_sorted_indices(0, 4, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![1, 0, 3, 2]);
+    }
+
+    #[test]
+    fn test_declared_column_type() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/metadata.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        // Codes look like numbers but should be sorted as text
+        let s = Sorter::new(
+            config.clone(),
+            2,
+            "code".to_string(),
+            SortType::Auto,
+            ColumnHint::default(),
+            None,
+        );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 4, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![3, 0, 2, 1]);
+
+        let s = Sorter::new(
+            config,
+            2,
+            "code".to_string(),
+            SortType::Auto,
+            ColumnHint {
+                data_type: Some(DataType::Utf8),
+                ..Default::default()
+            },
+            None,
         );
+        s.wait_internal();
+        let rows = s.get_sorted_indices(0, 4, SortOrder::Ascending).unwrap();
+        assert_eq!(rows, vec![0, 3, 1, 2]);
     }
 }
@@ -0,0 +1,230 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+/// First bytes of every SQLite database file
+const HEADER: &[u8] = b"SQLite format 3\x00";
+
+/// Size of the CSV handed over at a time while the rows are read
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks read ahead of the ones taken, so that a large table isn't held in memory
+const CHUNKS_AHEAD: usize = 16;
+
+/// Whether the file is a SQLite database
+pub fn is_database(filename: &str) -> bool {
+    let mut start = vec![];
+    File::open(filename).is_ok_and(|f| f.take(16).read_to_end(&mut start).is_ok())
+        && start == HEADER
+}
+
+/// Rows of the query, or else of the table, the first one if not given, read as CSV as they come
+/// from the database. Mistakes in the query are reported here, before any row is read.
+pub fn rows(filename: &str, table: Option<&str>, query: Option<&str>) -> CsvlensResult<Rows> {
+    let connection = open(filename)?;
+    let sql = match query {
+        Some(query) => query.to_string(),
+        None => {
+            let tables = table_names(&connection).map_err(|e| database_error(filename, e))?;
+            let table = match table {
+                None => tables.first().ok_or_else(|| {
+                    CsvlensError::Database(filename.to_string(), "no tables".to_string())
+                })?,
+                Some(name) => tables.iter().find(|t| *t == name).ok_or_else(|| {
+                    CsvlensError::TableNotFound(name.to_string(), tables.join(", "))
+                })?,
+            };
+            format!("SELECT * FROM \"{}\"", table.replace('"', "\"\""))
+        }
+    };
+    connection
+        .prepare(&sql)
+        .map_err(|e| database_error(filename, e))?;
+
+    let (sender, receiver) = mpsc::sync_channel(CHUNKS_AHEAD);
+    let filename = filename.to_string();
+    std::thread::spawn(move || {
+        if let Err(e) = send_rows(&filename, &connection, &sql, &sender) {
+            sender.send(Err(e.to_string())).ok();
+        }
+    });
+    Ok(Rows {
+        chunks: receiver,
+        chunk: Cursor::new(vec![]),
+    })
+}
+
+/// CSV of the rows of a query, read from the thread stepping through them
+pub struct Rows {
+    chunks: Receiver<Result<Vec<u8>, String>>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for Rows {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.chunk.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => self.chunk = Cursor::new(chunk),
+                Ok(Err(message)) => return Err(io::Error::other(message)),
+                // All of the rows have been read
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+/// The database opened without changing it
+fn open(filename: &str) -> CsvlensResult<Connection> {
+    Connection::open_with_flags(
+        filename,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| database_error(filename, e))
+}
+
+/// Names of the tables and views in the database, in the order they were created
+fn table_names(connection: &Connection) -> rusqlite::Result<Vec<String>> {
+    let mut statement = connection.prepare(
+        "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') \
+         AND name NOT LIKE 'sqlite_%' ORDER BY rowid",
+    )?;
+    statement
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()
+}
+
+/// Write the header and the rows as CSV in chunks to the receiver, until it is gone
+fn send_rows(
+    filename: &str,
+    connection: &Connection,
+    sql: &str,
+    sender: &SyncSender<Result<Vec<u8>, String>>,
+) -> CsvlensResult<()> {
+    let error = |e| database_error(filename, e);
+    let send = |writer: &mut csv::Writer<Vec<u8>>| -> CsvlensResult<bool> {
+        let full = std::mem::replace(writer, csv::Writer::from_writer(vec![]));
+        let chunk = full.into_inner().map_err(|e| e.into_error())?;
+        Ok(sender.send(Ok(chunk)).is_ok())
+    };
+    let mut statement = connection.prepare(sql).map_err(error)?;
+    let num_columns = statement.column_count();
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(statement.column_names())?;
+
+    let mut rows = statement.query([]).map_err(error)?;
+    while let Some(row) = rows.next().map_err(error)? {
+        for i in 0..num_columns {
+            writer.write_field(field(row.get_ref(i).map_err(error)?))?;
+        }
+        writer.write_record(None::<&[u8]>)?;
+        // Stop once nothing is reading the rows anymore
+        if writer.get_ref().len() >= CHUNK_SIZE && !send(&mut writer)? {
+            return Ok(());
+        }
+    }
+    send(&mut writer)?;
+    Ok(())
+}
+
+/// A value as written by the sqlite3 program, with NULL empty
+fn field(value: ValueRef) -> Vec<u8> {
+    match value {
+        ValueRef::Null => vec![],
+        ValueRef::Integer(i) => i.to_string().into_bytes(),
+        ValueRef::Real(f) => {
+            let mut text = f.to_string();
+            // Real numbers keep a decimal point even when whole, e.g. 120.0
+            if f.is_finite() && !text.contains(['.', 'e']) {
+                text.push_str(".0");
+            }
+            text.into_bytes()
+        }
+        ValueRef::Text(bytes) | ValueRef::Blob(bytes) => bytes.to_vec(),
+    }
+}
+
+fn database_error(filename: &str, e: rusqlite::Error) -> CsvlensError {
+    let message = match &e {
+        rusqlite::Error::SqliteFailure(_, Some(message))
+        | rusqlite::Error::SqlInputError { msg: message, .. } => message.clone(),
+        e => e.to_string(),
+    };
+    CsvlensError::Database(filename.to_string(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(table: Option<&str>, query: Option<&str>) -> Vec<String> {
+        let mut text = String::new();
+        rows("tests/data/shop.sqlite", table, query)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_is_database() {
+        assert!(is_database("tests/data/shop.sqlite"));
+        assert!(!is_database("tests/data/cities.csv"));
+        assert!(!is_database("tests/data/empty.csv"));
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(
+            table_names(&open("tests/data/shop.sqlite").unwrap()).unwrap(),
+            vec!["customers", "order items"]
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        // The first table by default, with nulls empty
+        assert_eq!(
+            lines(None, None),
+            vec![
+                "id,name,city",
+                "1,Ada,London",
+                "2,Grace,New York",
+                "3,Linus,"
+            ]
+        );
+        assert_eq!(
+            lines(Some("order items"), None)[..3],
+            [
+                "id,customer_id,item,price",
+                "1,1,Lamp,19.5",
+                "2,2,\"Desk, oak\",120.0"
+            ]
+        );
+        assert_eq!(
+            lines(
+                None,
+                Some(
+                    "SELECT name, count(*) AS orders FROM customers JOIN \"order items\" ON customers.id = customer_id GROUP BY name ORDER BY orders DESC, name"
+                )
+            ),
+            vec!["name,orders", "Ada,2", "Grace,1", "Linus,1"]
+        );
+        assert!(matches!(
+            rows("tests/data/shop.sqlite", Some("orders"), None),
+            Err(CsvlensError::TableNotFound(name, tables)) if name == "orders" && tables == "customers, order items"
+        ));
+        assert!(matches!(
+            rows("tests/data/shop.sqlite", None, Some("SELECT nope")),
+            Err(CsvlensError::Database(_, message)) if message == "no such column: nope"
+        ));
+    }
+}
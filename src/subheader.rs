@@ -0,0 +1,100 @@
+use crate::csv::CsvConfig;
+use crate::errors::CsvlensResult;
+
+/// The first row after the header, when it describes the columns rather than being a record,
+/// e.g. units or descriptions in spreadsheet exports
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subheader {
+    pub fields: Vec<String>,
+    /// Byte offsets in the file where the row starts and ends
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Subheader {
+    /// The first record of the file, if it has a header and any records
+    pub fn read(config: &CsvConfig) -> CsvlensResult<Option<Subheader>> {
+        if config.no_headers() {
+            return Ok(None);
+        }
+        let mut reader = config.with_skipped_range(None).new_reader()?;
+        reader.byte_headers()?;
+        let start = reader.position().byte();
+        let mut record = ::csv::StringRecord::new();
+        if !reader.read_record(&mut record)? {
+            return Ok(None);
+        }
+        let end = reader.position().byte();
+        let (data_start, _) = config.data_range();
+        Ok(Some(Subheader {
+            fields: record.iter().map(|f| f.to_string()).collect(),
+            start: data_start + start,
+            end: data_start + end,
+        }))
+    }
+}
+
+/// The options reading the file without its subheader, so that it's left out of everything
+/// reading the file, e.g. sorting and stats, and the subheader if there is one
+pub fn split_subheader(config: &CsvConfig) -> CsvlensResult<(CsvConfig, Option<Vec<String>>)> {
+    match Subheader::read(config)? {
+        Some(subheader) => Ok((
+            config.with_skipped_range(Some((subheader.start, subheader.end))),
+            Some(subheader.fields),
+        )),
+        None => Ok((config.with_skipped_range(None), None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::{CsvBaseConfig, CsvLensReader, CsvlensRecordIterator};
+    use std::sync::Arc;
+
+    fn config(filename: &str, no_headers: bool) -> CsvConfig {
+        CsvConfig::new(filename, None, CsvBaseConfig::new(b',', no_headers))
+    }
+
+    #[test]
+    fn test_read() {
+        let subheader = Subheader::read(&config("tests/data/subheader.csv", false))
+            .unwrap()
+            .unwrap();
+        assert_eq!(subheader.fields, vec!["", "°C", "s"]);
+        assert_eq!((subheader.start, subheader.end), (24, 31));
+
+        assert_eq!(
+            Subheader::read(&config("tests/data/subheader.csv", true)).unwrap(),
+            None
+        );
+        // Without any records
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"a,b\n").unwrap();
+        assert_eq!(
+            Subheader::read(&config(file.path().to_str().unwrap(), false)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_left_out() {
+        let (config, _) = split_subheader(&config("tests/data/subheader.csv", false)).unwrap();
+        let config = Arc::new(config);
+        let sensors: Vec<String> = CsvlensRecordIterator::new(config.clone())
+            .unwrap()
+            .map(|r| r.unwrap()[0].to_string())
+            .collect();
+        assert_eq!(sensors, vec!["b", "a", "c", "a"]);
+
+        // Read in any order, e.g. by seeking to indexed positions
+        let mut reader = CsvLensReader::new(config).unwrap();
+        reader.wait_internal();
+        let (rows, _) = reader.get_rows_for_indices(&[3, 0]).unwrap();
+        let rows: Vec<(usize, String)> = rows
+            .into_iter()
+            .map(|r| (r.record_num, r.fields[1].clone()))
+            .collect();
+        assert_eq!(rows, vec![(4, "18.5".to_string()), (1, "21.5".to_string())]);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::csv::CsvConfig;
+use crate::errors::CsvlensResult;
+
+/// One of several tables in a file, e.g. sheets of a workbook exported one after another
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    /// Title above the table, if any
+    pub name: Option<String>,
+    /// Byte offsets in the file where the table starts and ends
+    pub start: u64,
+    pub end: Option<u64>,
+    /// Number of rows, including a header
+    pub num_rows: usize,
+    pub first_row: Vec<String>,
+}
+
+struct Segment {
+    start: u64,
+    num_fields: usize,
+    num_rows: usize,
+    first_row: Vec<String>,
+}
+
+/// Split the file into tables at blank lines, and where the number of fields changes for more
+/// than a single row. A table of a single cell is taken as the title of the next one.
+pub fn split_tables(config: &CsvConfig) -> CsvlensResult<Vec<Table>> {
+    let (data_start, data_end) = config.data_range();
+    let mut reader = config
+        .reader_builder()
+        .has_headers(false)
+        .from_reader(config.open()?);
+
+    let mut segments: Vec<Segment> = vec![];
+    // Row with a different number of fields than the current table, which starts a new table if
+    // the row after it has the same number of fields
+    let mut candidate: Option<Segment> = None;
+    let mut record = ::csv::StringRecord::new();
+    while reader.read_record(&mut record)? {
+        let Some(position) = record.position() else {
+            continue;
+        };
+        let segment = Segment {
+            start: data_start + position.byte(),
+            num_fields: record.len(),
+            num_rows: 1,
+            first_row: record.iter().map(|s| s.to_string()).collect(),
+        };
+        // Blank lines before a record are read as part of it
+        let line_breaks: u64 = record
+            .iter()
+            .map(|field| field.matches('\n').count() as u64)
+            .sum();
+        let after_blank_line = reader.position().line() > position.line() + line_breaks + 1;
+
+        let Some(current) = segments.last_mut() else {
+            segments.push(segment);
+            continue;
+        };
+        if after_blank_line {
+            if candidate.take().is_some() {
+                current.num_rows += 1;
+            }
+            segments.push(segment);
+            continue;
+        }
+        if let Some(c) = candidate.take() {
+            if c.num_fields == segment.num_fields {
+                segments.push(Segment { num_rows: 2, ..c });
+                continue;
+            }
+            // Only an irregular row of the current table
+            current.num_rows += 1;
+        }
+        if segment.num_fields == current.num_fields {
+            current.num_rows += 1;
+        } else {
+            candidate = Some(segment);
+        }
+    }
+    if candidate.is_some()
+        && let Some(current) = segments.last_mut()
+    {
+        current.num_rows += 1;
+    }
+
+    let mut tables: Vec<Table> = vec![];
+    let mut name = None;
+    for (i, segment) in segments.iter().enumerate() {
+        let end = segments.get(i + 1).map(|s| s.start).or(data_end);
+        let is_last = i + 1 == segments.len();
+        if segment.num_rows == 1 && segment.num_fields == 1 && !is_last {
+            name = Some(segment.first_row[0].trim().to_string());
+            continue;
+        }
+        tables.push(Table {
+            name: name.take(),
+            start: segment.start,
+            end,
+            num_rows: segment.num_rows,
+            first_row: segment.first_row.clone(),
+        });
+    }
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvBaseConfig;
+
+    fn split(content: &str) -> Vec<(Option<String>, String, usize)> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, content.as_bytes()).unwrap();
+        let config = CsvConfig::new(
+            file.path().to_str().unwrap(),
+            None,
+            CsvBaseConfig::new(b',', false),
+        );
+        split_tables(&config)
+            .unwrap()
+            .into_iter()
+            .map(|t| {
+                let end = t.end.map_or(content.len(), |e| e as usize);
+                (
+                    t.name,
+                    content[t.start as usize..end].to_string(),
+                    t.num_rows,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_tables() {
+        let tables = split("Sales\na,b\n1,2\n3,4\n\nCosts\nx,y,z\n5,6,7\n");
+        assert_eq!(
+            tables,
+            vec![
+                (Some("Sales".into()), "a,b\n1,2\n3,4\n".into(), 3),
+                (Some("Costs".into()), "x,y,z\n5,6,7\n".into(), 2),
+            ]
+        );
+        // A new header row without a blank line before it
+        let tables = split("a,b\n1,2\nx,y,z\n5,6,7\n");
+        assert_eq!(
+            tables,
+            vec![
+                (None, "a,b\n1,2\n".into(), 2),
+                (None, "x,y,z\n5,6,7\n".into(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_table() {
+        // Irregular rows alone don't start a table
+        let tables = split("a,b\n1,2\n3\n4,5\n6,7,8\n");
+        assert_eq!(tables, vec![(None, "a,b\n1,2\n3\n4,5\n6,7,8\n".into(), 5)]);
+        assert_eq!(split("a\n"), vec![(None, "a\n".into(), 1)]);
+        assert_eq!(split(""), vec![]);
+    }
+}
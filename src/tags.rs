@@ -0,0 +1,187 @@
+use crate::csv::RowId;
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Tag of the rows checked off in review mode
+pub const DONE_TAG: &str = "done";
+
+/// Named labels attached to rows, optionally kept in a sidecar CSV file with one `row,tag` line
+/// per tagged row so that triage can continue over multiple sessions
+pub struct Tags {
+    path: Option<String>,
+    tags: BTreeMap<RowId, BTreeSet<String>>,
+}
+
+impl Tags {
+    pub fn new() -> Self {
+        Tags {
+            path: None,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    /// Tags stored in the given file, which is created on the first change if missing
+    pub fn load(path: &str) -> CsvlensResult<Self> {
+        let mut tags = Tags {
+            path: Some(path.to_string()),
+            tags: BTreeMap::new(),
+        };
+        if !std::path::Path::new(path).exists() {
+            return Ok(tags);
+        }
+        let parse_error = |message: String| CsvlensError::ConfigParsing(path.to_string(), message);
+        let mut reader = ::csv::Reader::from_path(path)?;
+        for record in reader.records() {
+            let record = record?;
+            let (Some(row), Some(tag)) = (record.get(0), record.get(1)) else {
+                return Err(parse_error("expected row,tag columns".into()));
+            };
+            let record_num = row
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| parse_error(format!("invalid row number: {row}")))?;
+            tags.tags
+                .entry(RowId::from_record_num(record_num))
+                .or_default()
+                .insert(tag.to_string());
+        }
+        Ok(tags)
+    }
+
+    /// Tag the given rows, or untag them if all of them already have the tag. Returns whether
+    /// the tag was added.
+    pub fn toggle(&mut self, row_ids: &[RowId], tag: &str) -> bool {
+        let all_tagged = row_ids.iter().all(|id| self.has_tag(*id, tag));
+        for id in row_ids {
+            if all_tagged {
+                if let Some(t) = self.tags.get_mut(id) {
+                    t.remove(tag);
+                    if t.is_empty() {
+                        self.tags.remove(id);
+                    }
+                }
+            } else {
+                self.add(*id, tag);
+            }
+        }
+        !all_tagged
+    }
+
+    pub fn add(&mut self, row_id: RowId, tag: &str) {
+        self.tags.entry(row_id).or_default().insert(tag.to_string());
+    }
+
+    pub fn has_tag(&self, row_id: RowId, tag: &str) -> bool {
+        self.tags.get(&row_id).is_some_and(|t| t.contains(tag))
+    }
+
+    /// Tags of a row in alphabetical order
+    pub fn tags_of(&self, row_id: RowId) -> Vec<&str> {
+        self.tags
+            .get(&row_id)
+            .map(|t| t.iter().map(|s| s.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Rows with the given tag in file order
+    pub fn rows_with(&self, tag: &str) -> Vec<RowId> {
+        self.tags
+            .iter()
+            .filter(|(_, t)| t.contains(tag))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Rows with any tag in file order
+    pub fn row_ids(&self) -> Vec<RowId> {
+        self.tags.keys().copied().collect()
+    }
+
+    /// Move the tags to other rows, e.g. once the file is read with another config. Tags of rows
+    /// that aren't mapped are dropped.
+    pub fn remap(&mut self, row_ids: &HashMap<RowId, RowId>) {
+        for (id, t) in std::mem::take(&mut self.tags) {
+            if let Some(new_id) = row_ids.get(&id) {
+                self.tags.entry(*new_id).or_default().extend(t);
+            }
+        }
+    }
+
+    /// Write the tags to the sidecar file, if any
+    pub fn save(&self) -> CsvlensResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut writer = ::csv::Writer::from_path(path)?;
+        writer.write_record(["row", "tag"])?;
+        for (id, tags) in &self.tags {
+            for tag in tags {
+                writer.write_record([id.to_string().as_str(), tag])?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut tags = Tags::new();
+        let rows = [RowId::from_index(1), RowId::from_index(3)];
+        assert!(tags.toggle(&rows, "suspect"));
+        assert!(tags.toggle(&rows[..1], "done"));
+        assert_eq!(tags.tags_of(rows[0]), vec!["done", "suspect"]);
+        assert_eq!(tags.rows_with("suspect"), rows.to_vec());
+
+        // Untag only once all rows have the tag
+        assert!(tags.toggle(&rows, "done"));
+        assert!(!tags.toggle(&rows, "done"));
+        assert!(!tags.toggle(&rows, "suspect"));
+        assert!(tags.rows_with("suspect").is_empty());
+        assert!(tags.tags_of(rows[1]).is_empty());
+    }
+
+    #[test]
+    fn test_remap() {
+        let mut tags = Tags::new();
+        tags.toggle(&[RowId::from_index(0), RowId::from_index(4)], "suspect");
+        let row_ids = HashMap::from([(RowId::from_index(4), RowId::from_index(3))]);
+        tags.remap(&row_ids);
+        assert_eq!(tags.rows_with("suspect"), vec![RowId::from_index(3)]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv.tags.csv");
+        let path = path.to_str().unwrap();
+
+        let mut tags = Tags::load(path).unwrap();
+        tags.toggle(&[RowId::from_index(0), RowId::from_index(9)], "suspect");
+        tags.toggle(&[RowId::from_index(0)], "done");
+        tags.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "row,tag\n1,done\n1,suspect\n10,suspect\n"
+        );
+
+        let tags = Tags::load(path).unwrap();
+        assert_eq!(tags.tags_of(RowId::from_index(0)), vec!["done", "suspect"]);
+        assert_eq!(
+            tags.rows_with("suspect"),
+            vec![RowId::from_index(0), RowId::from_index(9)]
+        );
+
+        std::fs::write(path, "row,tag\nx,done\n").unwrap();
+        assert!(matches!(
+            Tags::load(path),
+            Err(CsvlensError::ConfigParsing(_, _))
+        ));
+    }
+}
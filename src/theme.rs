@@ -1,4 +1,7 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
 use ratatui::style::Color;
+use std::str::FromStr;
 use terminal_colorsaurus::{QueryOptions, ThemeMode, theme_mode};
 
 pub struct Theme {
@@ -10,19 +13,54 @@ pub struct Theme {
     pub marked_background: Color,
     pub found: Color,
     pub found_selected_background: Color,
+    pub invalid: Color,
+    pub pattern: Color,
     pub status: Color,
     pub column_colors: [Color; 5],
 }
 
 impl Theme {
     pub fn default() -> Self {
-        match theme_mode(QueryOptions::default()) {
-            Ok(ThemeMode::Dark) => Theme::dark(),
-            Ok(ThemeMode::Light) => Theme::light(),
-            _ => Theme::dark(),
+        if is_dark_terminal() {
+            Theme::dark()
+        } else {
+            Theme::light()
         }
     }
 
+    /// Built-in theme by name: auto, dark, light, high-contrast, or deuteranopia or protanopia for
+    /// the colorblind safe one. All but dark and light follow the terminal's background.
+    pub fn named(name: &str) -> CsvlensResult<Self> {
+        let theme = match name {
+            "auto" => Theme::default(),
+            "dark" => Theme::dark(),
+            "light" => Theme::light(),
+            "high-contrast" => Theme::high_contrast(is_dark_terminal()),
+            "deuteranopia" | "protanopia" => Theme::colorblind(is_dark_terminal()),
+            _ => return Err(CsvlensError::InvalidTheme(name.to_string())),
+        };
+        Ok(theme)
+    }
+
+    /// Overrides a semantic color from a `<role>=<color>` specification, e.g. `match=yellow`.
+    /// Decorative colors like borders only come from the theme.
+    pub fn set_color(&mut self, spec: &str) -> CsvlensResult<()> {
+        let invalid = || CsvlensError::InvalidThemeColor(spec.to_string());
+        let (role, color) = spec.split_once('=').ok_or_else(invalid)?;
+        let color = Color::from_str(color.trim()).map_err(|_| invalid())?;
+        let target = match role.trim() {
+            "match" => &mut self.found,
+            "current-match" => &mut self.found_selected_background,
+            "error" => &mut self.invalid,
+            "pattern" => &mut self.pattern,
+            "marked" => &mut self.marked_background,
+            "selection" => &mut self.selected_background,
+            _ => return Err(invalid()),
+        };
+        *target = color;
+        Ok(())
+    }
+
     pub fn dark() -> Self {
         let gutter = Color::Rgb(131, 148, 150);
         Theme {
@@ -34,6 +72,8 @@ impl Theme {
             marked_background: Color::Rgb(40, 50, 80),
             found: Color::Rgb(200, 0, 0),
             found_selected_background: Color::LightYellow,
+            invalid: Color::Rgb(255, 85, 85),
+            pattern: Color::Rgb(135, 175, 215),
             status: gutter,
             column_colors: [
                 Color::Rgb(253, 151, 31),
@@ -56,6 +96,8 @@ impl Theme {
             marked_background: Color::Rgb(220, 235, 255),
             found: Color::Rgb(200, 0, 0),
             found_selected_background: Color::LightYellow,
+            invalid: Color::Rgb(190, 0, 0),
+            pattern: Color::Rgb(0, 95, 135),
             status: gutter,
             column_colors: [
                 Color::Rgb(207, 112, 0),
@@ -66,4 +108,127 @@ impl Theme {
             ],
         }
     }
+
+    /// Basic colors only, so that the terminal's own (e.g. high contrast) palette applies
+    pub fn high_contrast(dark: bool) -> Self {
+        if dark {
+            Theme {
+                row_number: Color::White,
+                border: Color::White,
+                selected_foreground: Color::Black,
+                selected_background: Color::White,
+                marked_foreground: Color::Black,
+                marked_background: Color::LightCyan,
+                found: Color::LightYellow,
+                found_selected_background: Color::Blue,
+                invalid: Color::LightRed,
+                pattern: Color::LightCyan,
+                status: Color::White,
+                column_colors: [
+                    Color::LightYellow,
+                    Color::LightCyan,
+                    Color::LightMagenta,
+                    Color::LightGreen,
+                    Color::White,
+                ],
+            }
+        } else {
+            Theme {
+                row_number: Color::Black,
+                border: Color::Black,
+                selected_foreground: Color::White,
+                selected_background: Color::Black,
+                marked_foreground: Color::White,
+                marked_background: Color::Blue,
+                found: Color::Blue,
+                found_selected_background: Color::LightYellow,
+                invalid: Color::Red,
+                pattern: Color::Magenta,
+                status: Color::Black,
+                column_colors: [
+                    Color::Blue,
+                    Color::Red,
+                    Color::Magenta,
+                    Color::Green,
+                    Color::Black,
+                ],
+            }
+        }
+    }
+
+    /// Colors from the Okabe-Ito palette, which stay apart with deuteranopia and protanopia.
+    /// Matches and errors differ in hue and brightness rather than by red and green.
+    pub fn colorblind(dark: bool) -> Self {
+        let orange = Color::Rgb(230, 159, 0);
+        let sky_blue = Color::Rgb(86, 180, 233);
+        let bluish_green = Color::Rgb(0, 158, 115);
+        let yellow = Color::Rgb(240, 228, 66);
+        let blue = Color::Rgb(0, 114, 178);
+        let vermillion = Color::Rgb(213, 94, 0);
+        let reddish_purple = Color::Rgb(204, 121, 167);
+        let theme = if dark { Theme::dark() } else { Theme::light() };
+        if dark {
+            Theme {
+                found: orange,
+                found_selected_background: blue,
+                invalid: reddish_purple,
+                pattern: sky_blue,
+                column_colors: [orange, sky_blue, bluish_green, yellow, reddish_purple],
+                ..theme
+            }
+        } else {
+            Theme {
+                found: blue,
+                found_selected_background: yellow,
+                invalid: vermillion,
+                pattern: bluish_green,
+                column_colors: [vermillion, blue, bluish_green, reddish_purple, orange],
+                ..theme
+            }
+        }
+    }
+}
+
+fn is_dark_terminal() -> bool {
+    !matches!(theme_mode(QueryOptions::default()), Ok(ThemeMode::Light))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named() {
+        assert_eq!(Theme::named("dark").unwrap().found, Color::Rgb(200, 0, 0));
+        assert_eq!(
+            Theme::named("high-contrast").unwrap().border,
+            Theme::high_contrast(is_dark_terminal()).border
+        );
+        assert_eq!(
+            Theme::named("protanopia").unwrap().found,
+            Theme::named("deuteranopia").unwrap().found
+        );
+        assert!(matches!(
+            Theme::named("solarized"),
+            Err(CsvlensError::InvalidTheme(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_color() {
+        let mut theme = Theme::dark();
+        theme.set_color("match=yellow").unwrap();
+        theme.set_color("error=#ff00ff").unwrap();
+        assert_eq!(theme.found, Color::Yellow);
+        assert_eq!(theme.invalid, Color::Rgb(255, 0, 255));
+        // Decorative colors are left alone
+        assert_eq!(theme.border, Theme::dark().border);
+
+        for spec in ["border=red", "match", "match=nocolor"] {
+            assert!(matches!(
+                theme.set_color(spec),
+                Err(CsvlensError::InvalidThemeColor(s)) if s == spec
+            ));
+        }
+    }
 }
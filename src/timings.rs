@@ -0,0 +1,99 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long the phases of startup take, printed on exit with --timings
+pub struct Timings {
+    start: Instant,
+    schema_inference: Option<Duration>,
+    width_estimation: Option<Duration>,
+    first_render: Option<Duration>,
+    indexing: Option<Duration>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Timings {
+            start: Instant::now(),
+            schema_inference: None,
+            width_estimation: None,
+            first_render: None,
+            indexing: None,
+        }
+    }
+
+    /// Record the time taken to sniff the delimiter and read the headers and metadata
+    pub fn schema_inference_done(&mut self, elapsed: Duration) {
+        self.schema_inference.get_or_insert(elapsed);
+    }
+
+    /// Record the end of the first render and the time its column widths took to compute. Later
+    /// renders are ignored.
+    pub fn first_render_done(&mut self, width_estimation: Option<Duration>) {
+        if self.first_render.is_none() {
+            self.first_render = Some(self.start.elapsed());
+            self.width_estimation = width_estimation;
+        }
+    }
+
+    pub fn indexing_done(&mut self, elapsed: Option<Duration>) {
+        self.indexing = elapsed;
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_duration = |d: Option<Duration>| match d {
+            Some(d) => format!("{:>10.3} ms", d.as_secs_f64() * 1000.0),
+            None => format!("{:>13}", "-"),
+        };
+        writeln!(f, "csvlens timings:")?;
+        for (name, duration) in [
+            ("schema inference", self.schema_inference),
+            ("width estimation", self.width_estimation),
+            ("first render", self.first_render),
+            ("indexing", self.indexing),
+        ] {
+            writeln!(f, "  {name:<18}{}", format_duration(duration))?;
+        }
+        write!(
+            f,
+            "  {:<18}{}",
+            "total",
+            format_duration(Some(self.start.elapsed()))
+        )?;
+        if self.indexing.is_none() {
+            write!(f, "\n  (indexing did not finish before exit)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings() {
+        let mut timings = Timings::new();
+        timings.schema_inference_done(Duration::from_micros(1500));
+        timings.first_render_done(Some(Duration::from_micros(250)));
+        timings.first_render_done(Some(Duration::from_secs(1)));
+        let report = timings.to_string();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "csvlens timings:");
+        assert_eq!(lines[1], "  schema inference       1.500 ms");
+        assert_eq!(lines[2], "  width estimation       0.250 ms");
+        assert!(lines[3].starts_with("  first render"));
+        assert_eq!(lines[4], "  indexing                      -");
+        assert!(lines[5].starts_with("  total"));
+        assert_eq!(lines[6], "  (indexing did not finish before exit)");
+
+        timings.indexing_done(Some(Duration::from_millis(20)));
+        let report = timings.to_string();
+        assert_eq!(
+            report.lines().nth(4),
+            Some("  indexing              20.000 ms")
+        );
+        assert_eq!(report.lines().count(), 6);
+    }
+}
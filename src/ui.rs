@@ -1,6 +1,14 @@
+use crate::color_rule::{self, ColorRule};
 use crate::common::InputMode;
-use crate::csv::Row;
+use crate::csv::{Row, RowId};
+use crate::export::ExportProgress;
 use crate::find;
+use crate::links::Link;
+use crate::locale::format_count;
+use crate::messages::{Status, row_position};
+use crate::osc::Hyperlink;
+use crate::outline::GroupLabel;
+use crate::recognize;
 use crate::sort;
 use crate::sort::SortOrder;
 use crate::sort::SortType;
@@ -22,6 +30,7 @@ use ratatui::widgets::Widget;
 use ratatui::widgets::{Block, Borders, StatefulWidget};
 use regex::Regex;
 use tui_input::Input;
+use unicode_width::UnicodeWidthStr;
 
 use std::cmp::{max, min};
 use std::collections::HashMap;
@@ -48,6 +57,7 @@ pub fn set_line_safe(
     }
 }
 
+/// A count with thousands separators, e.g. 5,000,000
 #[derive(Debug)]
 pub struct ColumnWidthOverrides {
     overrides: HashMap<usize, u16>,
@@ -98,21 +108,32 @@ impl<'a> CsvTable<'a> {
         area_width: u16,
         overrides: &ColumnWidthOverrides,
         sorter_state: &SorterState,
+        subheader: Option<&Vec<String>>,
     ) -> Vec<u16> {
         let mut column_widths = Vec::new();
 
         for h in self.header {
-            let column_name = self.get_effective_column_name(h.name.as_str(), sorter_state);
+            let column_name = self.get_effective_column_name(h, sorter_state);
             if let Some(w) = overrides.get(h.origin_index) {
                 column_widths.push(*w);
                 continue;
             } else {
-                column_widths.push(column_name.len() as u16);
+                column_widths.push(column_name.width() as u16);
             }
         }
 
         let overriden_indices = overrides.overriden_indices();
 
+        if let Some(subheader) = subheader {
+            for (i, h) in self.header.iter().enumerate() {
+                if overriden_indices.contains(&h.origin_index) {
+                    continue;
+                }
+                let value_len = subheader.get(h.origin_index).map_or(0, |v| v.width()) as u16;
+                column_widths[i] = max(column_widths[i], value_len);
+            }
+        }
+
         for row in self.rows {
             for (i, value) in row.fields.iter().enumerate() {
                 if i >= column_widths.len() {
@@ -123,7 +144,7 @@ impl<'a> CsvTable<'a> {
                 }
                 let v = column_widths.get_mut(i).unwrap();
                 value.split('\n').for_each(|x| {
-                    let value_len = x.len() as u16;
+                    let value_len = x.width() as u16;
                     if *v < value_len {
                         *v = value_len;
                     }
@@ -269,7 +290,14 @@ impl<'a> CsvTable<'a> {
                     .add_modifier(Modifier::UNDERLINED);
             }
             let span = Span::styled(row_num_formatted, style);
-            buf.set_span(0, y, &span, view_layout.row_number_layout.max_length);
+            // Beneath the line starting the group, if any
+            let y_row = match state.row_groups.as_ref().and_then(|g| g.get(i)) {
+                Some(Some(label)) if !label.collapsed => y + 1,
+                _ => y,
+            };
+            if y_row < area.bottom() {
+                buf.set_span(0, y_row, &span, view_layout.row_number_layout.max_length);
+            }
             y += view_layout.row_heights[i];
             if y >= area.bottom() {
                 break;
@@ -283,21 +311,31 @@ impl<'a> CsvTable<'a> {
         });
     }
 
-    fn render_header_borders(&self, buf: &mut Buffer, area: Rect, theme: &Theme) -> (u16, u16) {
-        let block = Block::default()
-            .borders(Borders::TOP | Borders::BOTTOM)
-            .border_style(Style::default().fg(theme.border));
-        let height = 3;
-        let area = Rect::new(0, 0, area.width, height);
-        block.render(area, buf);
-        // y pos of header text and next line
-        (height.saturating_sub(2), height)
+    fn render_header_borders(
+        &self,
+        buf: &mut Buffer,
+        area: Rect,
+        state: &CsvTableState,
+    ) -> (u16, u16) {
+        // The group row goes above the header and the subheader beneath it, within the borders
+        let num_group_rows = u16::from(state.column_groups.is_some());
+        let height = 3 + num_group_rows + u16::from(state.subheader.is_some());
+        // Screen readers would read out the lines, so leave blank lines in their place
+        if !state.screen_reader {
+            let block = Block::default()
+                .borders(Borders::TOP | Borders::BOTTOM)
+                .border_style(Style::default().fg(state.theme.border));
+            let area = Rect::new(0, 0, area.width, height);
+            block.render(area, buf);
+        }
+        // y pos of header text and first record
+        (1 + num_group_rows, height)
     }
 
     fn render_other_borders(&self, buf: &mut Buffer, area: Rect, state: &CsvTableState) {
         // TODO: maybe should be combined with render_header_borders() above
         // Render vertical separator
-        if state.borders_state.is_none() {
+        if state.borders_state.is_none() || state.screen_reader {
             return;
         }
 
@@ -396,21 +434,39 @@ impl<'a> CsvTable<'a> {
         }
     }
 
-    fn get_effective_column_name(&self, column_name: &str, sorter_state: &SorterState) -> String {
+    fn get_effective_column_name(&self, header: &Header, sorter_state: &SorterState) -> String {
+        let column_name = header.name.as_str();
         if let SorterState::Enabled(info) = sorter_state
             && info.status == sort::SorterStatus::Finished
-            && info.column_name == column_name
+            && let Some(position) = info.keys.iter().position(|k| k.0 == header.origin_index)
         {
-            let indicator = match info.order {
+            // Keys are in their own order within the sort order
+            let order = if info.order == SortOrder::Descending {
+                info.keys[position].1.reversed()
+            } else {
+                info.keys[position].1
+            };
+            let indicator = match order {
                 SortOrder::Ascending => "▴",
                 SortOrder::Descending => "▾",
             };
 
+            // Number the keys after the first when there are several
+            let position_indicator = if position > 0 {
+                (position + 1).to_string()
+            } else {
+                "".to_string()
+            };
+
             let sort_type_indicator = match info.sort_type {
                 SortType::Natural => "N",
+                SortType::CaseInsensitive => "i",
                 _ => "",
             };
-            return format!("{} [{}{}]", column_name, indicator, sort_type_indicator);
+            return format!(
+                "{} [{}{}{}]",
+                column_name, indicator, position_indicator, sort_type_indicator
+            );
         }
         column_name.to_string()
     }
@@ -426,7 +482,7 @@ impl<'a> CsvTable<'a> {
         y: u16,
         row_type: RowType,
         row: &'a [String],
-        row_index: Option<usize>,
+        row_id: Option<RowId>,
         view_layout: &ViewLayout,
         remaining_height: Option<u16>,
     ) -> u16 {
@@ -437,7 +493,7 @@ impl<'a> CsvTable<'a> {
         let mut col_ending_pos_x = 0;
         let mut num_cols_rendered: u64 = 0;
         let row_height = match row_type {
-            RowType::Header => 1,
+            RowType::Header | RowType::Subheader => 1,
             RowType::Record(i) => match remaining_height {
                 Some(h) => min(h, view_layout.row_heights[i]),
                 None => view_layout.row_heights[i],
@@ -456,12 +512,24 @@ impl<'a> CsvTable<'a> {
                 content_style = content_style
                     .fg(state.theme.column_colors[col_index % state.theme.column_colors.len()]);
             }
+            if let (RowType::Record(_), Some(header)) = (&row_type, self.header.get(col_index))
+                && let Some(color) =
+                    color_rule::find_color(&state.color_rules, header.origin_index, hname)
+            {
+                content_style = content_style.fg(color);
+            }
+            if let RowType::Subheader = row_type {
+                content_style = content_style.add_modifier(Modifier::ITALIC);
+            }
             if let RowType::Header = row_type {
                 content_style = content_style.add_modifier(Modifier::BOLD);
                 if let Some(selection) = &state.selection
                     && selection.column.is_selected(num_cols_rendered as usize)
                 {
                     content_style = content_style.add_modifier(Modifier::UNDERLINED);
+                    if state.header_focused {
+                        content_style = content_style.add_modifier(Modifier::REVERSED);
+                    }
                 }
             }
             let is_selected = if let Some(selection) = &state.selection {
@@ -480,8 +548,8 @@ impl<'a> CsvTable<'a> {
             }
 
             let is_marked = if matches!(row_type, RowType::Record(_)) {
-                match (row_index, &state.marked_rows) {
-                    (Some(idx), Some(marked_rows)) => marked_rows.contains(&(idx + 1)),
+                match (row_id, &state.marked_rows) {
+                    (Some(id), Some(marked_rows)) => marked_rows.contains(&id),
                     _ => false,
                 }
             } else {
@@ -496,13 +564,76 @@ impl<'a> CsvTable<'a> {
                 content_style = content_style.patch(marked_style);
             }
 
+            let in_block = match (&row_type, &state.block) {
+                (RowType::Record(i), Some(block)) => {
+                    block.contains(state.rows_offset + *i as u64, col_index as u64)
+                }
+                _ => false,
+            };
+            if in_block && !is_selected {
+                let block_style = Style::default()
+                    .fg(state.theme.selected_foreground)
+                    .bg(state.theme.selected_background);
+                filler_style = filler_style.patch(block_style);
+                content_style = content_style.patch(block_style);
+            }
+
+            // The second cursor in compare mode
+            let is_compared = matches!(row_type, RowType::Record(_))
+                && row_id.is_some()
+                && row_id == state.compare_row;
+            if is_compared && !is_selected {
+                let compared_style = Style::default()
+                    .fg(state.theme.selected_foreground)
+                    .bg(state.theme.selected_background)
+                    .add_modifier(Modifier::DIM);
+                filler_style = filler_style.patch(compared_style);
+                content_style = content_style.patch(compared_style);
+            }
+
+            if matches!(row_type, RowType::Record(_))
+                && let Some(link) = Link::detect(hname)
+            {
+                content_style = content_style.add_modifier(Modifier::UNDERLINED);
+                if let (Link::Url(url), Some(hyperlinks)) = (link, &mut state.hyperlinks) {
+                    hyperlinks.push(Hyperlink {
+                        url,
+                        area: Rect::new(x_offset_header, y, effective_width, row_height),
+                    });
+                }
+            }
+
+            if matches!(row_type, RowType::Record(_))
+                && state.null_values.iter().any(|v| v == hname)
+            {
+                content_style = content_style.add_modifier(Modifier::DIM);
+            }
+
+            if let (RowType::Record(_), Some(id), Some(invalid_cells), Some(header)) = (
+                &row_type,
+                row_id,
+                &state.invalid_cells,
+                self.header.get(col_index),
+            ) && invalid_cells.contains(&(id, header.origin_index))
+            {
+                content_style = content_style
+                    .fg(state.theme.invalid)
+                    .add_modifier(Modifier::UNDERLINED);
+            }
+
             let short_padding = match &state.selection {
                 Some(selection) => !matches!(selection.selection_type(), view::SelectionType::Row),
                 None => false,
             };
+            let right_aligned = matches!(row_type, RowType::Record(_))
+                && self
+                    .header
+                    .get(col_index)
+                    .is_some_and(|h| state.right_aligned_columns.contains(&h.origin_index));
             let filler_style = FillerStyle {
                 style: filler_style,
                 short_padding,
+                right_aligned,
             };
 
             let should_highlight_cell = |active: &FinderActiveState, content: &str| {
@@ -517,9 +648,42 @@ impl<'a> CsvTable<'a> {
                 if active.is_filter && matches!(row_type, RowType::Header) {
                     return false;
                 }
-                active.target.is_match(content)
+                // Rows filtered by not matching have nothing to highlight
+                if active.inverted {
+                    return false;
+                }
+                // The subheader isn't searched
+                if matches!(row_type, RowType::Subheader) {
+                    return false;
+                }
+                active.target.as_ref().is_some_and(|t| t.is_match(content))
             };
+            let preview = state
+                .regex_preview
+                .as_ref()
+                .filter(|_| matches!(row_type, RowType::Record(_)));
             match &state.finder_state {
+                // The pattern being typed takes over from the last find
+                _ if preview.is_some() => {
+                    let highlight_style = filler_style.style.fg(state.theme.found);
+                    let spans = CsvTable::get_preview_spans(
+                        preview,
+                        col_index,
+                        hname,
+                        content_style,
+                        highlight_style,
+                    );
+                    self.set_spans(
+                        buf,
+                        &spans,
+                        x_offset_header,
+                        y,
+                        effective_width,
+                        row_height,
+                        filler_style,
+                        state.is_word_wrap,
+                    );
+                }
                 // TODO: seems like doing a bit too much of heavy lifting of
                 // checking for matches (finder's work)
                 FinderState::FinderActive(active) if should_highlight_cell(active, hname) => {
@@ -527,8 +691,8 @@ impl<'a> CsvTable<'a> {
                     if let Some(found_record) = &active.found_record {
                         match found_record {
                             find::FoundEntry::Row(entry) => {
-                                if let Some(row_index) = row_index
-                                    && row_index == entry.row_index()
+                                if let Some(row_id) = row_id
+                                    && row_id.index() == entry.row_index()
                                     && entry.column_index() == col_index
                                 {
                                     highlight_style =
@@ -562,6 +726,27 @@ impl<'a> CsvTable<'a> {
                         state.is_word_wrap,
                     );
                 }
+                _ if state.highlight_patterns && matches!(row_type, RowType::Record(_)) => {
+                    let pattern_style = content_style
+                        .fg(state.theme.pattern)
+                        .add_modifier(Modifier::ITALIC);
+                    let spans = CsvTable::get_styled_spans(
+                        hname,
+                        recognize::find_patterns(hname),
+                        content_style,
+                        pattern_style,
+                    );
+                    self.set_spans(
+                        buf,
+                        &spans,
+                        x_offset_header,
+                        y,
+                        effective_width,
+                        row_height,
+                        filler_style,
+                        state.is_word_wrap,
+                    );
+                }
                 _ => {
                     let span = Span::styled((*hname).as_str(), content_style);
                     self.set_spans(
@@ -591,6 +776,130 @@ impl<'a> CsvTable<'a> {
         row_height
     }
 
+    /// Render the name of each group of columns over its columns, or over the columns next to
+    /// each other if they aren't all
+    fn render_column_groups(
+        &self,
+        buf: &mut Buffer,
+        state: &CsvTableState,
+        column_widths: &[u16],
+        area: Rect,
+        x: u16,
+        y: u16,
+    ) {
+        let Some(groups) = &state.column_groups else {
+            return;
+        };
+        // Group of the columns next to each other so far, with where they start and their width
+        let mut run: Option<(&(String, bool), u16, u16)> = None;
+        for (col_index, x_offset, width) in self.rendered_columns(state, column_widths, area, x) {
+            let group = groups
+                .get(self.header[col_index].origin_index)
+                .and_then(|g| g.as_ref());
+            match (&mut run, group) {
+                (Some((run_group, _, run_width)), Some(group)) if run_group.0 == group.0 => {
+                    *run_width += width;
+                }
+                _ => {
+                    if let Some(run) = run.take() {
+                        Self::render_column_group(buf, state, run, y);
+                    }
+                    run = group.map(|g| (g, x_offset, width));
+                }
+            }
+        }
+        if let Some(run) = run {
+            Self::render_column_group(buf, state, run, y);
+        }
+    }
+
+    /// Where the columns shown are drawn, as their index, x position and width
+    fn rendered_columns(
+        &self,
+        state: &CsvTableState,
+        column_widths: &[u16],
+        area: Rect,
+        x: u16,
+    ) -> Vec<(usize, u16, u16)> {
+        let mut columns = vec![];
+        let mut x_offset = x;
+        let mut remaining_width = area.width.saturating_sub(x);
+        for (col_index, &hlen) in column_widths.iter().enumerate() {
+            if !state
+                .cols_offset
+                .should_filtered_column_index_be_rendered(col_index as u64)
+            {
+                continue;
+            }
+            columns.push((col_index, x_offset, min(remaining_width, hlen)));
+            x_offset += hlen;
+            if remaining_width < hlen {
+                break;
+            }
+            remaining_width = remaining_width.saturating_sub(hlen);
+        }
+        columns
+    }
+
+    /// Mark the selected column in the borders above and below the rows, so that it's easy to
+    /// follow while scrolling
+    fn render_column_guide(
+        &self,
+        buf: &mut Buffer,
+        state: &CsvTableState,
+        column_widths: &[u16],
+        area: Rect,
+        x: u16,
+    ) {
+        if state.screen_reader {
+            return;
+        }
+        let Some(local_index) = state.selection.as_ref().and_then(|s| s.column.index()) else {
+            return;
+        };
+        let col_index = state.cols_offset.get_filtered_column_index(local_index) as usize;
+        let Some((_, x, width)) = self
+            .rendered_columns(state, column_widths, area, x)
+            .into_iter()
+            .find(|(i, _, _)| *i == col_index)
+        else {
+            return;
+        };
+        let guide = "━".repeat(width.saturating_sub(2) as usize);
+        let style = Style::default().fg(state.theme.selected_background);
+        buf.set_string(x, 0, &guide, style);
+        buf.set_string(x, area.bottom(), &guide, style);
+    }
+
+    fn render_column_group(
+        buf: &mut Buffer,
+        state: &CsvTableState,
+        ((name, collapsed), x, width): (&(String, bool), u16, u16),
+        y: u16,
+    ) {
+        let label = if *collapsed {
+            format!("▸ {name} ")
+        } else {
+            format!("{name} ")
+        };
+        // Leave a space before the next group
+        let max_width = width.saturating_sub(2);
+        let (x_line, _) = buf.set_stringn(
+            x,
+            y,
+            label,
+            max_width as usize,
+            Style::default().add_modifier(Modifier::BOLD),
+        );
+        let line_width = (x + max_width).saturating_sub(x_line);
+        buf.set_string(
+            x_line,
+            y,
+            "─".repeat(line_width as usize),
+            Style::default().fg(state.theme.border),
+        );
+    }
+
     fn is_position_selected(
         selection: &view::Selection,
         row_type: &RowType,
@@ -628,12 +937,42 @@ impl<'a> CsvTable<'a> {
         hname: &'a str,
         style: Style,
         highlight_style: Style,
+    ) -> Vec<Span<'a>> {
+        let matches = active
+            .target
+            .iter()
+            .flat_map(|t| t.find_iter(hname))
+            .map(|m| (m.start(), m.end()));
+        CsvTable::get_styled_spans(hname, matches, style, highlight_style)
+    }
+
+    fn get_preview_spans(
+        preview: Option<&RegexPreview>,
+        col_index: usize,
+        hname: &'a str,
+        style: Style,
+        highlight_style: Style,
+    ) -> Vec<Span<'a>> {
+        let matches = preview
+            .filter(|p| p.column_index.is_none_or(|i| i == col_index))
+            .and_then(|p| p.target.as_ref())
+            .into_iter()
+            .flat_map(|t| t.find_iter(hname))
+            .map(|m| (m.start(), m.end()));
+        CsvTable::get_styled_spans(hname, matches, style, highlight_style)
+    }
+
+    /// Split content into spans, styling the given byte ranges with highlight_style
+    fn get_styled_spans(
+        hname: &'a str,
+        ranges: impl Iterator<Item = (usize, usize)>,
+        style: Style,
+        highlight_style: Style,
     ) -> Vec<Span<'a>> {
         let mut spans = Vec::new();
         let mut last = 0;
 
-        for m in active.target.find_iter(hname) {
-            let (s, e) = (m.start(), m.end());
+        for (s, e) in ranges {
             if s == e {
                 continue;
             }
@@ -691,12 +1030,22 @@ impl<'a> CsvTable<'a> {
                     && let Some(last_span) = line.spans.pop()
                 {
                     let truncate_length = last_span.width().saturating_sub(SUFFIX_LEN as usize);
-                    let truncated_content: String =
-                        last_span.content.chars().take(truncate_length).collect();
+                    let truncated_content =
+                        wrap::truncate_to_width(&last_span.content, truncate_length).to_string();
                     let truncated_span = Span::styled(truncated_content, last_span.style);
                     line.spans.push(truncated_span);
                     line.spans.push(Span::styled(SUFFIX, last_span.style));
                 }
+                if filler_style.right_aligned && offset == 0 && line_wrapper.finished() {
+                    let left_padding_width =
+                        (effective_width as usize).saturating_sub(line.width());
+                    if left_padding_width > 0 {
+                        line.spans.insert(
+                            0,
+                            Span::styled(" ".repeat(left_padding_width), filler_style.style),
+                        );
+                    }
+                }
                 let padding_width = min(
                     (effective_width as usize + buffer_space).saturating_sub(line.width()),
                     width as usize,
@@ -732,6 +1081,7 @@ impl<'a> CsvTable<'a> {
         let style = Style::default().fg(state.theme.status);
         let mut prompt_text: Text;
         let mut content: String;
+        let mut error = None;
         state.cursor_xy = None;
         if let Some(msg) = &state.transient_message {
             prompt_text = Text::default();
@@ -739,6 +1089,9 @@ impl<'a> CsvTable<'a> {
         } else if let BufferState::Enabled(buffer_mode, input) = &state.buffer_content {
             prompt_text = Text::default();
             let get_prefix = |&input_mode| {
+                if input_mode == InputMode::Command {
+                    return ":".to_string();
+                }
                 let prefix = match input_mode {
                     InputMode::GotoLine => "Go to line",
                     InputMode::Find => "Find",
@@ -746,6 +1099,8 @@ impl<'a> CsvTable<'a> {
                     InputMode::FilterColumns => "Columns regex",
                     InputMode::Option => "Option",
                     InputMode::FreezeColumns => "Number of columns to freeze",
+                    InputMode::Tag => "Tag",
+                    InputMode::FilterTag => "Filter by tag",
                     _ => "",
                 };
                 if prefix.is_empty() {
@@ -756,10 +1111,11 @@ impl<'a> CsvTable<'a> {
             };
             let prefix = get_prefix(buffer_mode);
             content = format!("{prefix}{}", input.value());
+            error = state.regex_preview.as_ref().and_then(|p| p.error.as_ref());
             state.cursor_xy = Some((
                 area.x
-                    .saturating_add(prefix.len() as u16)
-                    .saturating_add(input.cursor() as u16),
+                    .saturating_add(prefix.width() as u16)
+                    .saturating_add(input.visual_cursor() as u16),
                 area.bottom().saturating_sub(1),
             ));
         } else {
@@ -770,7 +1126,7 @@ impl<'a> CsvTable<'a> {
                 Text::default()
             };
             // Filename
-            if state.prompt.is_some() {
+            if state.prompt.is_some() || state.screen_reader {
                 content = "".to_string();
             } else if let Some(f) = &state.filename {
                 content = f.to_string();
@@ -779,11 +1135,6 @@ impl<'a> CsvTable<'a> {
             }
 
             // Row / Col
-            let total_str = match state.total_line_number {
-                Some((total, false)) => format!("{}", total),
-                Some((total, true)) => format!("{}+", total),
-                _ => "?".to_owned(),
-            };
             let current_row;
             if let Some(selection) = &state.selection {
                 current_row = if let Some(i) = selection.row.index() {
@@ -795,18 +1146,62 @@ impl<'a> CsvTable<'a> {
                 current_row = self.rows.first()
             }
 
-            let row_num = match current_row {
-                Some(row) => row.record_num.to_string(),
-                _ => "-".to_owned(),
-            };
-            content += format!(
-                " [Row {}/{}, Col {}/{}]",
-                row_num,
-                total_str,
-                state.cols_offset.num_skip + 1,
-                state.total_cols,
-            )
-            .as_str();
+            let row_position = row_position(
+                current_row.map(|row| row.record_num),
+                state.total_line_number,
+                state.streaming,
+            );
+            state.terminal_title = Some(format!(
+                "csvlens: {} [Row {}]",
+                state.filename.as_deref().unwrap_or("stdin"),
+                row_position
+            ));
+            if state.screen_reader {
+                if state.prompt.is_some() {
+                    content.push(' ');
+                }
+                content += self.announcement(state, current_row).as_str();
+                // Screen readers follow the cursor, so keep it on the status line
+                state.cursor_xy = Some((area.x, area.bottom().saturating_sub(1)));
+            } else {
+                // The selected column, if any, or else the first one scrolled to
+                let selected_column = state
+                    .selection
+                    .as_ref()
+                    .and_then(|s| s.column.index())
+                    .map(|i| state.cols_offset.get_filtered_column_index(i) as usize)
+                    .and_then(|i| self.header.get(i).map(|h| (i, h)));
+                let position = match selected_column {
+                    Some((i, header)) => Status::Position {
+                        row: &row_position,
+                        column: i + 1,
+                        total_columns: state.total_cols,
+                        column_name: Some(&header.name),
+                    },
+                    None => Status::Position {
+                        row: &row_position,
+                        column: state.cols_offset.num_skip as usize + 1,
+                        total_columns: state.total_cols,
+                        column_name: None,
+                    },
+                };
+                content += format!(" {position}").as_str();
+            }
+
+            // Table of several in the file
+            if let Some((current, total)) = state.table_status {
+                content += format!(" {}", Status::Table { current, total }).as_str();
+            }
+
+            // Records with more fields than shown
+            if let Some(n) = state.fields_cap {
+                content += format!(" {}", Status::FieldsCap(n)).as_str();
+            }
+
+            // Stalled read
+            if let Some(elapsed) = state.io_wait {
+                content += format!(" {}", Status::IoWait(elapsed.as_secs())).as_str();
+            }
 
             // Finder
             if let FinderState::FinderActive(s) = &state.finder_state {
@@ -830,23 +1225,80 @@ impl<'a> CsvTable<'a> {
                 }
             }
 
+            // Validation
+            if let Some((count, done)) = state.validation_status {
+                content += format!(" {}", Status::Invalid { count, done }).as_str();
+            }
+
+            // Marks
+            if let Some((count, hidden)) = state.marks_status {
+                content += format!(" {}", Status::Marked { count, hidden }).as_str();
+            }
+
+            // Tags of the selected row
+            if let Some(tags) = &state.selected_row_tags {
+                content += format!(" {}", Status::Tags(tags)).as_str();
+            }
+
+            // Block of cells
+            if let Some(block_status) = &state.block_status {
+                content += format!(" [{block_status}]").as_str();
+            }
+
+            // Compare mode
+            if let Some(compare_status) = &state.compare_status {
+                content += format!(" [{compare_status}]").as_str();
+            }
+
+            // Groups of rows
+            if let Some(row_groups_status) = &state.row_groups_status {
+                content += format!(" [{row_groups_status}]").as_str();
+            }
+
+            // Choosing a column to sort by from the header
+            if state.header_focused {
+                content += format!(" {}", Status::SortHeader).as_str();
+            }
+
+            // Review mode
+            if let Some((done, total)) = state.review_progress {
+                content += format!(" {}", Status::Reviewed { done, total }).as_str();
+            }
+
             // Echo option
             if let Some(column_name) = &state.echo_column {
-                content += format!(" [Echo {column_name} ↵]").as_str();
+                content += format!(" {}", Status::Echo(column_name)).as_str();
             }
 
             // Ignore case option
             if state.ignore_case {
-                content += " [ignore-case]";
+                content += format!(" {}", Status::IgnoreCase).as_str();
             }
 
             // Last autoreload time
             if let Some(last_autoreload_at) = &state.last_autoreload_at {
-                content += format!(
-                    " [Last reload: {}s ago]",
-                    last_autoreload_at.elapsed().as_secs()
-                )
-                .as_str();
+                let seconds = last_autoreload_at.elapsed().as_secs();
+                content += format!(" {}", Status::LastReload(seconds)).as_str();
+            }
+
+            // File changed under us
+            if state.file_replaced {
+                content += format!(" {}", Status::FileReplaced).as_str();
+            }
+
+            // Download of the file
+            if let Some(bytes) = state.download_progress {
+                content += format!(" {}", Status::Downloading(bytes)).as_str();
+            }
+
+            // Export in the background
+            if let Some(progress) = &state.export_progress {
+                let exporting = Status::Exporting {
+                    rows_written: progress.rows_written,
+                    total: progress.total,
+                    path: &progress.path,
+                };
+                content += format!(" {exporting}").as_str();
             }
 
             // Debug
@@ -856,10 +1308,92 @@ impl<'a> CsvTable<'a> {
         }
         prompt_text = prompt_text.set_style(style);
         prompt_text.push_span(Span::from(content));
+        if let Some(error) = error {
+            prompt_text.push_span(Span::styled(
+                format!(" ({error})"),
+                Style::default().fg(state.theme.invalid),
+            ));
+        }
         let prompt_area = Rect::new(area.x, area.y + 1, area.width, area.height);
         prompt_text.render(prompt_area, buf);
     }
 
+    /// The selected cell in words for screen readers, e.g. `row 15, column 'price', value 42.10`.
+    /// The first column shown stands in for the cell if only a row is selected.
+    fn announcement(&self, state: &CsvTableState, row: Option<&Row>) -> String {
+        let Some(row) = row else {
+            return "no rows".to_string();
+        };
+        let nth_shown = state
+            .selection
+            .as_ref()
+            .and_then(|s| s.column.index())
+            .unwrap_or(0);
+        let column_index = (0..self.header.len())
+            .filter(|i| {
+                state
+                    .cols_offset
+                    .should_filtered_column_index_be_rendered(*i as u64)
+            })
+            .nth(nth_shown as usize);
+        let (Some(header), Some(value)) = (
+            column_index.and_then(|i| self.header.get(i)),
+            column_index.and_then(|i| row.fields.get(i)),
+        ) else {
+            return format!("row {}", row.record_num);
+        };
+        let value = if value.is_empty() { "empty" } else { value };
+        format!(
+            "row {}, column '{}', value {}",
+            row.record_num, header.name, value
+        )
+    }
+
+    /// Line starting a group of rows, with its value and number of rows
+    #[allow(clippy::too_many_arguments)]
+    fn render_group_label(
+        &self,
+        buf: &mut Buffer,
+        state: &CsvTableState,
+        area: Rect,
+        x: u16,
+        y: u16,
+        label: &GroupLabel,
+        row_index: usize,
+    ) {
+        let value = if label.value.is_empty() {
+            "(empty)"
+        } else {
+            label.value.as_str()
+        };
+        let text = format!(
+            "{} {value} · {} row{}",
+            if label.collapsed { "▸" } else { "▾" },
+            label.len,
+            if label.len == 1 { "" } else { "s" },
+        );
+        let mut style = Style::default()
+            .fg(state.theme.row_number)
+            .add_modifier(Modifier::BOLD);
+        // The row of a collapsed group is only the line
+        let is_selected = label.collapsed
+            && state
+                .selection
+                .as_ref()
+                .is_some_and(|s| s.row.is_selected(row_index));
+        if is_selected {
+            style = style
+                .fg(state.theme.selected_foreground)
+                .bg(state.theme.selected_background);
+        }
+        buf.set_span(
+            x,
+            y,
+            &Span::styled(text, style),
+            area.width.saturating_sub(x),
+        );
+    }
+
     fn get_view_layout(&self, area: Rect, state: &mut CsvTableState, rows: &[Row]) -> ViewLayout {
         let max_row_num = rows.iter().map(|x| x.record_num).max().unwrap_or(0);
         let max_row_num_length = format!("{max_row_num}").len() as u16;
@@ -867,19 +1401,32 @@ impl<'a> CsvTable<'a> {
             max_row_num_length + 2 * NUM_SPACES_AFTER_LINE_NUMBER + 1;
         let x_row_separator = max_row_num_length + NUM_SPACES_AFTER_LINE_NUMBER + 1;
 
+        let tic = std::time::Instant::now();
         let column_widths = self.get_column_widths(
             area.width.saturating_sub(row_num_section_width_with_spaces),
             &state.column_width_overrides,
             &state.sorter_state,
+            state.subheader.as_ref(),
         );
+        state.debug_stats.column_widths_elapsed = Some(tic.elapsed());
         let _tic = std::time::Instant::now();
-        let row_heights = self.get_row_heights(
+        let mut row_heights = self.get_row_heights(
             area.height,
             self.rows,
             &column_widths,
             state.enable_line_wrap,
             state.is_word_wrap,
         );
+        // A group starts with a line of its own, and is only that line when collapsed
+        if let Some(row_groups) = &state.row_groups {
+            for (height, label) in row_heights.iter_mut().zip(row_groups) {
+                match label {
+                    Some(label) if label.collapsed => *height = 1,
+                    Some(_) => *height += 1,
+                    None => {}
+                }
+            }
+        }
         state.num_cols_rendered = 0;
         state.col_ending_pos_x = 0;
 
@@ -926,7 +1473,7 @@ impl StatefulWidget for CsvTable<'_> {
         let layout = self.get_view_layout(area, state, self.rows);
         state.view_layout = Some(layout.clone());
 
-        let (y_header, y_first_record) = self.render_header_borders(buf, area, &state.theme);
+        let (y_header, y_first_record) = self.render_header_borders(buf, area, state);
 
         // row area: including row numbers and row content
         let rows_area = Rect::new(
@@ -942,6 +1489,9 @@ impl StatefulWidget for CsvTable<'_> {
         let row_num_section_width = layout.row_number_layout.width_with_spaces;
 
         state.reset_more_cols_to_show();
+        if let Some(hyperlinks) = &mut state.hyperlinks {
+            hyperlinks.clear();
+        }
         self.render_row(
             buf,
             state,
@@ -953,16 +1503,71 @@ impl StatefulWidget for CsvTable<'_> {
             &self
                 .header
                 .iter()
-                .map(|h| self.get_effective_column_name(h.name.as_str(), &state.sorter_state))
+                .map(|h| self.get_effective_column_name(h, &state.sorter_state))
                 .collect::<Vec<String>>(),
             None,
             &layout,
             None,
         );
+        if state.column_groups.is_some() {
+            self.render_column_groups(
+                buf,
+                state,
+                &layout.column_widths,
+                rows_area,
+                row_num_section_width,
+                y_header - 1,
+            );
+        }
+        if let Some(subheader) = &state.subheader {
+            let values: Vec<String> = self
+                .header
+                .iter()
+                .map(|h| subheader.get(h.origin_index).cloned().unwrap_or_default())
+                .collect();
+            self.render_row(
+                buf,
+                state,
+                &layout.column_widths,
+                rows_area,
+                row_num_section_width,
+                y_header + 1,
+                RowType::Subheader,
+                &values,
+                None,
+                &layout,
+                None,
+            );
+        }
 
         let mut remaining_height = rows_area.height;
         let mut y_offset = y_first_record;
+        let row_groups = state.row_groups.clone();
         for (i, row) in self.rows.iter().enumerate() {
+            let label = row_groups
+                .as_ref()
+                .and_then(|g| g.get(i))
+                .and_then(|l| l.as_ref());
+            if let Some(label) = label {
+                self.render_group_label(
+                    buf,
+                    state,
+                    rows_area,
+                    row_num_section_width,
+                    y_offset,
+                    label,
+                    i,
+                );
+                remaining_height = remaining_height.saturating_sub(1);
+                y_offset += 1;
+                if y_offset >= rows_area.bottom() {
+                    break;
+                }
+                if label.collapsed {
+                    continue;
+                }
+            }
+            let row_height = layout.row_heights[i] - u16::from(label.is_some());
             let rendered_height = self.render_row(
                 buf,
                 state,
@@ -972,9 +1577,9 @@ impl StatefulWidget for CsvTable<'_> {
                 y_offset,
                 RowType::Record(i),
                 &row.fields,
-                Some(row.record_num - 1),
+                Some(row.id()),
                 &layout,
-                Some(remaining_height),
+                Some(min(remaining_height, row_height)),
             );
             remaining_height = remaining_height.saturating_sub(rendered_height);
             y_offset += rendered_height;
@@ -992,12 +1597,21 @@ impl StatefulWidget for CsvTable<'_> {
         self.render_status(status_area, buf, state);
 
         self.render_other_borders(buf, rows_area, state);
+        self.render_column_guide(
+            buf,
+            state,
+            &layout.column_widths,
+            rows_area,
+            row_num_section_width,
+        );
     }
 }
 
 pub enum RowType {
     /// Header row
     Header,
+    /// Row beneath the header describing the columns, with --subheader
+    Subheader,
     /// Regular row. Contains the row index (not the record number) and the row itself.
     Record(usize),
 }
@@ -1007,6 +1621,8 @@ pub enum RowType {
 struct FillerStyle {
     style: Style,
     short_padding: bool,
+    /// Put the fillers before the content instead, e.g. for numeric columns
+    right_aligned: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -1048,6 +1664,16 @@ pub enum BufferState {
     Enabled(InputMode, Input),
 }
 
+/// What the pattern being typed for a find or filter matches, highlighted in the rows before it's
+/// submitted
+pub struct RegexPreview {
+    /// The last pattern typed that parses, if any
+    pub target: Option<Regex>,
+    pub column_index: Option<usize>,
+    /// Why the pattern as typed doesn't parse
+    pub error: Option<String>,
+}
+
 pub enum FinderState {
     FinderInactive,
     FinderActive(FinderActiveState),
@@ -1064,11 +1690,13 @@ pub struct FinderActiveState {
     find_complete: bool,
     total_found: u64,
     cursor: Option<find::FinderCursor>,
-    target: Regex,
+    target: Option<Regex>,
+    tag: Option<String>,
     column_index: Option<(usize, String)>,
     found_record: Option<find::FoundEntry>,
     selected_offset: Option<u64>,
     is_filter: bool,
+    inverted: bool,
     header_has_match: bool,
 }
 
@@ -1081,12 +1709,14 @@ impl FinderActiveState {
             total_found: total_count as u64,
             cursor: finder.cursor(),
             target: finder.target(),
+            tag: finder.tag().map(|t| t.to_string()),
             column_index: finder
                 .column_index()
                 .map(|i| (i, rows_view.get_column_name_from_local_index(i))),
             found_record: finder.current(),
             selected_offset: rows_view.selected_offset(),
             is_filter: rows_view.is_filter(),
+            inverted: finder.inverted(),
             header_has_match,
         }
     }
@@ -1109,30 +1739,41 @@ impl FinderActiveState {
             let cursor_str;
             if self.is_filter {
                 if let Some(i) = self.selected_offset {
-                    cursor_str = i.saturating_add(1).to_string();
+                    cursor_str = format_count(i.saturating_add(1) as usize);
                 } else {
                     cursor_str = "-".to_owned();
                 }
             } else if let Some(cursor) = &self.cursor {
                 cursor_str = match cursor.row {
-                    find::RowPos::Row(i) => (i
-                        .saturating_add(1)
-                        .saturating_add(if self.header_has_match { 1 } else { 0 }))
-                    .to_string(),
+                    find::RowPos::Row(i) => format_count(
+                        i.saturating_add(1)
+                            .saturating_add(if self.header_has_match { 1 } else { 0 }),
+                    ),
                     find::RowPos::Header => "1".to_string(),
                 };
             } else {
                 cursor_str = "-".to_owned();
             }
-            line = format!("{cursor_str}/{}{plus_marker}", self.total_found);
+            line = format!(
+                "{cursor_str}/{}{plus_marker}",
+                format_count(self.total_found as usize)
+            );
+        }
+        if let Some(tag) = &self.tag {
+            return format!("[Tag \"{tag}\": {line}]");
         }
-        let action = if self.is_filter { "Filter" } else { "Find" };
+        let action = match (self.is_filter, self.inverted) {
+            (true, true) => "Filter not",
+            (true, false) => "Filter",
+            (false, _) => "Find",
+        };
         let target_column = self
             .column_index
             .as_ref()
             .map(|(_, name)| format!(" in {}", name))
             .unwrap_or_default();
-        format!("[{action} \"{}\"{target_column}: {line}]", self.target)
+        let target = self.target.as_ref().map(|t| t.as_str()).unwrap_or_default();
+        format!("[{action} \"{target}\"{target_column}: {line}]")
     }
 }
 
@@ -1146,6 +1787,7 @@ impl FilterColumnsState {
         if let Some(columns_filter) = rows_view.columns_filter() {
             Self::Enabled(FilterColumnsInfo {
                 pattern: columns_filter.pattern(),
+                hidden: columns_filter.hidden().cloned(),
                 shown: columns_filter.num_filtered(),
                 total: columns_filter.num_original(),
                 disabled_because_no_match: columns_filter.disabled_because_no_match(),
@@ -1158,6 +1800,7 @@ impl FilterColumnsState {
 
 pub struct FilterColumnsInfo {
     pattern: Regex,
+    hidden: Option<Vec<String>>,
     shown: usize,
     total: usize,
     disabled_because_no_match: bool,
@@ -1166,7 +1809,10 @@ pub struct FilterColumnsInfo {
 impl FilterColumnsInfo {
     fn status_line(&self) -> String {
         let mut line;
-        line = format!("[Filter \"{}\": ", self.pattern);
+        line = match &self.hidden {
+            Some(names) => format!("[Hiding {}: ", names.join(", ")),
+            None => format!("[Filter \"{}\": ", self.pattern),
+        };
         if self.disabled_because_no_match {
             line += "no match, showing all columns]";
         } else {
@@ -1185,7 +1831,18 @@ impl SorterState {
     fn from_sorter(sorter: &sort::Sorter, sort_order: SortOrder) -> Self {
         Self::Enabled(SorterInfo {
             status: sorter.status(),
-            column_name: sorter.column_name().to_string(),
+            total_bytes: sorter.total_bytes(),
+            keys: sorter
+                .keys()
+                .iter()
+                .map(|k| (k.column_index, k.order))
+                .collect(),
+            column_name: sorter
+                .keys()
+                .iter()
+                .map(|k| k.column_name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
             order: sort_order,
             sort_type: sorter.sort_type(),
         })
@@ -1194,6 +1851,8 @@ impl SorterState {
 
 struct SorterInfo {
     status: sort::SorterStatus,
+    total_bytes: Option<u64>,
+    keys: Vec<(usize, SortOrder)>,
     column_name: String,
     order: SortOrder,
     sort_type: sort::SortType,
@@ -1204,10 +1863,18 @@ impl SorterInfo {
         let sort_type_str = match self.sort_type {
             sort::SortType::Natural => "natural",
             sort::SortType::Auto => "auto based on type",
+            sort::SortType::CaseInsensitive => "ignoring case",
         };
         let prefix = format!("[Sorting by {} ({})", self.column_name, sort_type_str);
         match &self.status {
-            sort::SorterStatus::Running => format!("{prefix}...]").to_string(),
+            sort::SorterStatus::Running { rows: 0, .. } => format!("{prefix}...]"),
+            sort::SorterStatus::Running { rows, bytes } => match self.total_bytes {
+                Some(total_bytes) if total_bytes > 0 => {
+                    let percent = min(bytes * 100 / total_bytes, 100);
+                    format!("{prefix}... {percent}%, {} rows]", format_count(*rows))
+                }
+                _ => format!("{prefix}... {} rows]", format_count(*rows)),
+            },
             sort::SorterStatus::Error(error_msg) => {
                 format!("{} failed: {}]", prefix, error_msg).to_string()
             }
@@ -1228,6 +1895,7 @@ pub struct DebugStats {
     finder_elapsed: Option<Duration>,
     sorter_elapsed: Option<Duration>,
     render_elapsed: Option<Duration>,
+    column_widths_elapsed: Option<Duration>,
 }
 
 impl DebugStats {
@@ -1238,6 +1906,7 @@ impl DebugStats {
             finder_elapsed: None,
             sorter_elapsed: None,
             render_elapsed: None,
+            column_widths_elapsed: None,
         }
     }
 
@@ -1261,6 +1930,11 @@ impl DebugStats {
         self.render_elapsed = elapsed;
     }
 
+    /// Time taken to compute the column widths in the last render
+    pub fn column_widths_elapsed(&self) -> Option<Duration> {
+        self.column_widths_elapsed
+    }
+
     pub fn status_line(&self) -> Option<String> {
         if !self.show_stats {
             return None;
@@ -1306,14 +1980,41 @@ pub struct CsvTableState {
     total_cols: usize,
     pub debug_stats: DebugStats,
     buffer_content: BufferState,
+    /// Matches of the find or filter pattern being typed
+    pub regex_preview: Option<RegexPreview>,
     pub finder_state: FinderState,
     pub filter_columns_state: FilterColumnsState,
     sorter_state: SorterState,
+    /// Values of the row pinned beneath the header by column, with --subheader
+    pub subheader: Option<Vec<String>>,
+    /// Group of each column and whether it's collapsed, with --group-columns
+    pub column_groups: Option<Vec<Option<(String, bool)>>>,
     borders_state: Option<BordersState>,
     // TODO: should probably be with BordersState
     col_ending_pos_x: u16,
     pub selection: Option<view::Selection>,
-    pub marked_rows: Option<HashSet<usize>>,
+    pub marked_rows: Option<HashSet<RowId>>,
+    pub invalid_cells: Option<HashSet<(RowId, usize)>>,
+    pub right_aligned_columns: HashSet<usize>,
+    pub validation_status: Option<(usize, bool)>,
+    pub marks_status: Option<(usize, usize)>,
+    pub selected_row_tags: Option<String>,
+    /// Row the selected one is compared with, and how they differ
+    pub compare_row: Option<RowId>,
+    pub compare_status: Option<String>,
+    /// Whether a column to sort by is chosen from the header, with s
+    pub header_focused: bool,
+    /// Labels of the groups started by the rows shown, if grouped with -O
+    pub row_groups: Option<Vec<Option<GroupLabel>>>,
+    pub row_groups_status: Option<String>,
+    /// Block of cells selected with V, and statistics of the numbers in it
+    pub block: Option<view::CellBlock>,
+    pub block_status: Option<String>,
+    pub review_progress: Option<(usize, usize)>,
+    /// Current table and the number of tables, with --split-tables
+    pub table_status: Option<(usize, usize)>,
+    pub fields_cap: Option<usize>,
+    pub io_wait: Option<Duration>,
     pub transient_message: Option<String>,
     pub echo_column: Option<String>,
     pub ignore_case: bool,
@@ -1324,8 +2025,25 @@ pub struct CsvTableState {
     pub cursor_xy: Option<(u16, u16)>,
     pub theme: Theme,
     pub color_columns: bool,
+    pub color_rules: Vec<ColorRule>,
+    /// Values that stand for none, e.g. `NA`, which are dimmed
+    pub null_values: Vec<String>,
+    pub highlight_patterns: bool,
+    /// Announce the selected cell in the status bar and leave out borders, with --screen-reader
+    pub screen_reader: bool,
+    /// URL cells drawn in the last frame, only collected with --terminal-integration
+    pub hyperlinks: Option<Vec<Hyperlink>>,
+    pub terminal_title: Option<String>,
     pub prompt: Option<String>,
     pub last_autoreload_at: Option<Instant>,
+    /// Set when the file shrank or was replaced on disk since it was read
+    pub file_replaced: bool,
+    /// How far the export in the background got, while it runs
+    pub export_progress: Option<ExportProgress>,
+    /// Bytes received of the URL being downloaded
+    pub download_progress: Option<u64>,
+    /// Whether rows are still coming in, e.g. piped to stdin
+    pub streaming: bool,
     pub debug: String,
 }
 
@@ -1348,13 +2066,32 @@ impl CsvTableState {
             total_cols,
             debug_stats: DebugStats::new(),
             buffer_content: BufferState::Disabled,
+            regex_preview: None,
             finder_state: FinderState::FinderInactive,
             filter_columns_state: FilterColumnsState::Disabled,
             sorter_state: SorterState::Disabled,
+            subheader: None,
+            column_groups: None,
             borders_state: None,
             col_ending_pos_x: 0,
             selection: None,
             marked_rows: None,
+            invalid_cells: None,
+            right_aligned_columns: HashSet::new(),
+            validation_status: None,
+            marks_status: None,
+            selected_row_tags: None,
+            compare_row: None,
+            compare_status: None,
+            header_focused: false,
+            row_groups: None,
+            row_groups_status: None,
+            block: None,
+            block_status: None,
+            review_progress: None,
+            table_status: None,
+            fields_cap: None,
+            io_wait: None,
             transient_message: None,
             echo_column: echo_column.clone(),
             ignore_case,
@@ -1365,8 +2102,18 @@ impl CsvTableState {
             cursor_xy: None,
             theme: Theme::default(),
             color_columns,
+            color_rules: vec![],
+            null_values: vec![],
+            highlight_patterns: false,
+            screen_reader: false,
+            hyperlinks: None,
+            terminal_title: None,
             prompt,
             last_autoreload_at: None,
+            file_replaced: false,
+            export_progress: None,
+            download_progress: None,
+            streaming: false,
             debug: "".into(),
         }
     }
@@ -1404,12 +2151,18 @@ impl CsvTableState {
         self.total_cols = n;
     }
 
+    /// Name of the file as shown in the status bar
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
     pub fn set_buffer(&mut self, mode: InputMode, input: Input) {
         self.buffer_content = BufferState::Enabled(mode, input);
     }
 
     pub fn reset_buffer(&mut self) {
         self.buffer_content = BufferState::Disabled;
+        self.regex_preview = None;
     }
 
     pub fn line_number_and_spaces_width(&self) -> u16 {
@@ -1436,7 +2189,9 @@ mod tests {
     #[test]
     fn test_sorter_info_status_line() {
         let info = SorterInfo {
-            status: SorterStatus::Running,
+            status: SorterStatus::Running { rows: 0, bytes: 0 },
+            total_bytes: Some(1000),
+            keys: vec![(0, SortOrder::Ascending)],
             column_name: "test_column".to_string(),
             order: SortOrder::Ascending,
             sort_type: SortType::Natural,
@@ -1446,7 +2201,9 @@ mod tests {
         assert!(status_line.contains("Sorting by test_column (natural)"));
 
         let info_lex = SorterInfo {
-            status: SorterStatus::Running,
+            status: SorterStatus::Running { rows: 0, bytes: 0 },
+            total_bytes: Some(1000),
+            keys: vec![(0, SortOrder::Ascending)],
             column_name: "test_column".to_string(),
             order: SortOrder::Ascending,
             sort_type: SortType::Auto,
@@ -1454,5 +2211,26 @@ mod tests {
 
         let status_line_lex = info_lex.status_line();
         assert!(status_line_lex.contains("Sorting by test_column (auto based on type)"));
+
+        // Progress of reading the file
+        let info_progress = SorterInfo {
+            status: SorterStatus::Running {
+                rows: 500,
+                bytes: 250,
+            },
+            ..info_lex
+        };
+        assert_eq!(
+            info_progress.status_line(),
+            "[Sorting by test_column (auto based on type)... 25%, 500 rows]"
+        );
+        let info_progress = SorterInfo {
+            total_bytes: None,
+            ..info_progress
+        };
+        assert_eq!(
+            info_progress.status_line(),
+            "[Sorting by test_column (auto based on type)... 500 rows]"
+        );
     }
 }
@@ -6,7 +6,11 @@ use crate::watch::FileWatcher;
 
 pub enum CsvlensEvent<I> {
     Input(I),
+    /// Text pasted at once, with bracketed paste
+    Paste(String),
     FileChanged,
+    /// The terminal was resized, which may have reflowed what it showed
+    Resize,
     Tick,
 }
 
@@ -32,6 +36,8 @@ impl CsvlensEvents {
                 Event::Key(event) if event.kind == KeyEventKind::Press => {
                     Ok(CsvlensEvent::Input(event))
                 }
+                Event::Paste(text) => Ok(CsvlensEvent::Paste(text)),
+                Event::Resize(_, _) => Ok(CsvlensEvent::Resize),
                 _ => Ok(CsvlensEvent::Tick),
             },
             Ok(false) => {
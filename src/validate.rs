@@ -0,0 +1,467 @@
+use crate::config;
+use crate::csv;
+use crate::csv::{CsvlensRecordIterator, RowId};
+use crate::errors::{CsvlensError, CsvlensResult};
+use crate::metadata::{FieldMetadata, FieldType, TableMetadata};
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Rules to check for the values of a single column
+#[derive(Debug)]
+pub struct ColumnRule {
+    column_name: String,
+    column_index: usize,
+    field_type: FieldType,
+    pattern: Option<Regex>,
+    enum_values: Option<Vec<String>>,
+    min: Option<f64>,
+    max: Option<f64>,
+    required: bool,
+}
+
+impl ColumnRule {
+    fn from_value(column_name: &str, column_index: usize, value: &Value) -> CsvlensResult<Self> {
+        let invalid = |msg: &str| {
+            CsvlensError::InvalidValidationSpec(format!("column \"{column_name}\": {msg}"))
+        };
+        let rule = value
+            .as_object()
+            .ok_or_else(|| invalid("rule should be a table"))?;
+        let pattern = match rule.get("pattern") {
+            Some(Value::String(s)) => {
+                Some(Regex::new(s).map_err(|_| invalid(format!("invalid regex {s}").as_str()))?)
+            }
+            Some(_) => return Err(invalid("pattern should be a string")),
+            None => None,
+        };
+        let get_number = |key: &str| match rule.get(key) {
+            Some(v) => v
+                .as_f64()
+                .map(Some)
+                .ok_or_else(|| invalid(format!("{key} should be a number").as_str())),
+            None => Ok(None),
+        };
+        let required = match rule.get("required") {
+            Some(v) => v
+                .as_bool()
+                .ok_or_else(|| invalid("required should be a boolean"))?,
+            None => false,
+        };
+        let field_type = match rule.get("type") {
+            Some(Value::String(s)) => match FieldType::from_name(s) {
+                FieldType::Any => return Err(invalid(format!("unknown type {s}").as_str())),
+                t => t,
+            },
+            Some(_) => return Err(invalid("type should be a string")),
+            None => FieldType::Any,
+        };
+        let enum_values = match rule.get("enum") {
+            Some(Value::Array(values)) => Some(
+                values
+                    .iter()
+                    .map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect(),
+            ),
+            Some(_) => return Err(invalid("enum should be an array")),
+            None => None,
+        };
+        Ok(ColumnRule {
+            column_name: column_name.to_string(),
+            column_index,
+            field_type,
+            pattern,
+            enum_values,
+            min: get_number("min")?,
+            max: get_number("max")?,
+            required,
+        })
+    }
+
+    /// Rule implied by the type and constraints of a column in sidecar metadata, if any
+    fn from_field(field: &FieldMetadata, column_index: usize) -> CsvlensResult<Option<Self>> {
+        let pattern = match &field.pattern {
+            Some(p) => Some(Regex::new(p).map_err(|_| {
+                CsvlensError::InvalidValidationSpec(format!(
+                    "column \"{}\": invalid regex {p}",
+                    field.name
+                ))
+            })?),
+            None => None,
+        };
+        let rule = ColumnRule {
+            column_name: field.name.clone(),
+            column_index,
+            field_type: field.field_type,
+            pattern,
+            enum_values: field.enum_values.clone(),
+            min: field.minimum,
+            max: field.maximum,
+            required: field.required,
+        };
+        Ok(if rule.is_noop() { None } else { Some(rule) })
+    }
+
+    fn is_noop(&self) -> bool {
+        matches!(self.field_type, FieldType::String | FieldType::Any)
+            && self.pattern.is_none()
+            && self.enum_values.is_none()
+            && self.min.is_none()
+            && self.max.is_none()
+            && !self.required
+    }
+
+    /// Check a single value against this rule. Returns a description of the failure if any.
+    /// Empty values are only checked against `required`.
+    fn check(&self, value: &str, config: &csv::CsvConfig) -> Option<String> {
+        if value.is_empty() {
+            if self.required {
+                return Some("required value is missing".to_string());
+            }
+            return None;
+        }
+        if !self.field_type.accepts(value, config.decimal_comma()) {
+            return Some(format!("not a valid {:?}", self.field_type).to_lowercase());
+        }
+        if let Some(pattern) = &self.pattern
+            && !pattern.is_match(value)
+        {
+            return Some(format!("does not match /{pattern}/"));
+        }
+        if let Some(enum_values) = &self.enum_values
+            && !enum_values.iter().any(|v| v == value)
+        {
+            return Some(format!("not one of {}", enum_values.join(", ")));
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Some(number) = config.parse_number(value) else {
+                return Some("not a number".to_string());
+            };
+            if let Some(min) = self.min
+                && number < min
+            {
+                return Some(format!("{number} is less than {min}"));
+            }
+            if let Some(max) = self.max
+                && number > max
+            {
+                return Some(format!("{number} is greater than {max}"));
+            }
+        }
+        None
+    }
+}
+
+/// A set of validation rules, read from a TOML or JSON file of the form:
+///
+/// ```toml
+/// [columns.age]
+/// required = true
+/// type = "integer"
+/// pattern = '^\d+$'
+/// enum = ["18", "21"]
+/// min = 0
+/// max = 120
+/// ```
+#[derive(Debug)]
+pub struct ValidationSpec {
+    rules: Vec<ColumnRule>,
+}
+
+impl ValidationSpec {
+    pub fn from_file(path: &str, headers: &[String]) -> CsvlensResult<Self> {
+        let value = config::load_value(path)?;
+        Self::from_value(&value, headers)
+    }
+
+    pub fn from_value(value: &Value, headers: &[String]) -> CsvlensResult<Self> {
+        let columns = value
+            .get("columns")
+            .and_then(|c| c.as_object())
+            .ok_or_else(|| {
+                CsvlensError::InvalidValidationSpec("missing [columns] table".to_string())
+            })?;
+        let mut rules = vec![];
+        for (column_name, rule) in columns {
+            let column_index = headers
+                .iter()
+                .position(|h| h == column_name)
+                .ok_or_else(|| CsvlensError::ColumnNameNotFound(column_name.clone()))?;
+            rules.push(ColumnRule::from_value(column_name, column_index, rule)?);
+        }
+        // Report failures of a record in column order
+        rules.sort_by_key(|r| r.column_index);
+        Ok(ValidationSpec { rules })
+    }
+
+    /// Rules from the types and constraints in sidecar metadata. Fields not present in the
+    /// headers are ignored.
+    pub fn from_metadata(metadata: &TableMetadata, headers: &[String]) -> CsvlensResult<Self> {
+        let mut rules = vec![];
+        for field in &metadata.fields {
+            if let Some(column_index) = headers.iter().position(|h| *h == field.name)
+                && let Some(rule) = ColumnRule::from_field(field, column_index)?
+            {
+                rules.push(rule);
+            }
+        }
+        rules.sort_by_key(|r| r.column_index);
+        Ok(ValidationSpec { rules })
+    }
+
+    /// Combine with another spec, whose rules replace the ones for the same columns
+    pub fn merge(mut self, other: ValidationSpec) -> Self {
+        self.rules
+            .retain(|r| !other.rules.iter().any(|o| o.column_index == r.column_index));
+        self.rules.extend(other.rules);
+        self.rules.sort_by_key(|r| r.column_index);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn check_record(
+        &self,
+        row_id: RowId,
+        record: &::csv::StringRecord,
+        config: &csv::CsvConfig,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        for rule in &self.rules {
+            let value = record.get(rule.column_index).unwrap_or("");
+            if let Some(message) = rule.check(value, config) {
+                errors.push(ValidationError {
+                    row_id,
+                    column_index: rule.column_index,
+                    column_name: rule.column_name.clone(),
+                    message,
+                });
+            }
+        }
+        errors
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub row_id: RowId,
+    /// Index of the column in the original data
+    pub column_index: usize,
+    pub column_name: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn description(&self) -> String {
+        format!(
+            "Row {}, {}: {}",
+            self.row_id, self.column_name, self.message
+        )
+    }
+}
+
+/// Checks all records against a `ValidationSpec` in the background
+pub struct Validator {
+    internal: Arc<Mutex<ValidatorInternalState>>,
+    spec: Arc<ValidationSpec>,
+}
+
+impl Validator {
+    pub fn new(config: Arc<csv::CsvConfig>, spec: Arc<ValidationSpec>) -> Self {
+        let internal = ValidatorInternalState::init(config, spec.clone());
+        Validator { internal, spec }
+    }
+
+    pub fn spec(&self) -> Arc<ValidationSpec> {
+        self.spec.clone()
+    }
+
+    pub fn count(&self) -> usize {
+        self.internal.lock().unwrap().errors.len()
+    }
+
+    pub fn done(&self) -> bool {
+        self.internal.lock().unwrap().done
+    }
+
+    /// Approximate number of bytes used by the errors found so far
+    pub fn memory_usage(&self) -> usize {
+        self.internal
+            .lock()
+            .unwrap()
+            .errors
+            .iter()
+            .map(|e| {
+                std::mem::size_of::<ValidationError>()
+                    + e.column_name.capacity()
+                    + e.message.capacity()
+            })
+            .sum()
+    }
+
+    pub fn errors(&self) -> Vec<ValidationError> {
+        self.internal.lock().unwrap().errors.clone()
+    }
+
+    /// The (row, column index) of failing cells among the given rows
+    pub fn invalid_cells(&self, row_ids: &[RowId]) -> HashSet<(RowId, usize)> {
+        let m_guard = self.internal.lock().unwrap();
+        let mut out = HashSet::new();
+        for row_id in row_ids {
+            // Errors are in row order since records are scanned sequentially
+            let start = m_guard.errors.partition_point(|e| e.row_id < *row_id);
+            for e in m_guard.errors[start..]
+                .iter()
+                .take_while(|e| e.row_id == *row_id)
+            {
+                out.insert((e.row_id, e.column_index));
+            }
+        }
+        out
+    }
+
+    fn terminate(&self) {
+        self.internal.lock().unwrap().should_terminate = true;
+    }
+
+    #[cfg(any(test, feature = "harness"))]
+    pub fn wait_internal(&self) {
+        loop {
+            if self.internal.lock().unwrap().done {
+                break;
+            }
+            thread::sleep(core::time::Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+struct ValidatorInternalState {
+    errors: Vec<ValidationError>,
+    done: bool,
+    should_terminate: bool,
+}
+
+impl ValidatorInternalState {
+    fn init(
+        config: Arc<csv::CsvConfig>,
+        spec: Arc<ValidationSpec>,
+    ) -> Arc<Mutex<ValidatorInternalState>> {
+        let m_state = Arc::new(Mutex::new(ValidatorInternalState {
+            errors: vec![],
+            done: false,
+            should_terminate: false,
+        }));
+
+        let _m = m_state.clone();
+        thread::spawn(move || {
+            let records = CsvlensRecordIterator::new(config.clone()).unwrap();
+            for (row_index, r) in records.enumerate() {
+                let errors = match r {
+                    Ok(record) => spec.check_record(RowId::from_index(row_index), &record, &config),
+                    Err(_) => vec![],
+                };
+                let mut m = _m.lock().unwrap();
+                if m.should_terminate {
+                    break;
+                }
+                m.errors.extend(errors);
+            }
+            _m.lock().unwrap().done = true;
+        });
+
+        m_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<String> {
+        ["id", "name", "email", "age"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_validator() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/validation.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let spec = ValidationSpec::from_file("tests/data/validation.toml", &headers()).unwrap();
+        let validator = Validator::new(config, Arc::new(spec));
+        validator.wait_internal();
+        let descriptions: Vec<String> =
+            validator.errors().iter().map(|e| e.description()).collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "Row 2, email: does not match /^[^@\\s]+@[^@\\s]+$/",
+                "Row 3, name: required value is missing",
+                "Row 3, age: -5 is less than 0",
+                "Row 4, age: not a number",
+                "Row 5, age: 150 is greater than 120",
+            ]
+        );
+        let row_id = csv::RowId::from_index(2);
+        let expected: HashSet<(csv::RowId, usize)> =
+            [(row_id, 1), (row_id, 3)].into_iter().collect();
+        assert_eq!(
+            validator.invalid_cells(&[csv::RowId::from_index(0), row_id]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_metadata_rules() {
+        let config = Arc::new(csv::CsvConfig::new(
+            "tests/data/metadata.csv",
+            None,
+            csv::CsvBaseConfig::new(b',', false),
+        ));
+        let headers: Vec<String> = ["id", "score", "code", "joined", "active"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let metadata = TableMetadata::discover("tests/data/metadata.csv")
+            .unwrap()
+            .unwrap();
+        let spec = ValidationSpec::from_metadata(&metadata, &headers).unwrap();
+        let validator = Validator::new(config, Arc::new(spec));
+        validator.wait_internal();
+        let descriptions: Vec<String> =
+            validator.errors().iter().map(|e| e.description()).collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "Row 3, score: not a valid number",
+                "Row 3, active: not a valid boolean",
+                "Row 4, score: -1 is less than 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let value = serde_json::json!({"columns": {"nope": {"required": true}}});
+        let result = ValidationSpec::from_value(&value, &headers());
+        assert!(matches!(result, Err(CsvlensError::ColumnNameNotFound(_))));
+    }
+}
@@ -1,15 +1,23 @@
 use crate::columns_filter::ColumnsFilter;
-use crate::csv::{CsvLensReader, Row};
+use crate::csv::{CsvLensReader, GetRowsStats, Row, RowId};
 use crate::errors::CsvlensResult;
 use crate::find;
 use crate::input::Control;
+use crate::loader::RowsLoader;
+use crate::outline::{GroupLabel, Outline};
+use crate::prefetch::Prefetcher;
 use crate::sort::{SortOrder, Sorter};
 
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// How long to wait for rows to be read before leaving the read pending and showing that the
+/// interface is waiting on I/O
+const IO_WAIT_TIMEOUT: Duration = Duration::from_millis(200);
+
 struct RowsFilter {
     indices: Vec<u64>,
     total: usize,
@@ -29,7 +37,7 @@ impl RowsFilter {
 }
 
 pub struct MarkToggleResult {
-    pub record_num: usize,
+    pub row_id: RowId,
     pub marked: bool,
 }
 
@@ -152,7 +160,7 @@ impl Selection {
         }
     }
 
-    fn set_selection_type(&mut self, selection_type: SelectionType) {
+    pub fn set_selection_type(&mut self, selection_type: SelectionType) {
         let target_row_index = self.row.last_selected().unwrap_or(0);
         let target_column_index = self.column.last_selected().unwrap_or(0);
 
@@ -187,7 +195,28 @@ impl Selection {
     }
 }
 
-#[derive(Debug)]
+/// Cells between two corners, as offsets of rows in the view and indices of columns kept by the
+/// columns filter
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellBlock {
+    pub rows: RangeInclusive<u64>,
+    pub columns: RangeInclusive<u64>,
+}
+
+impl CellBlock {
+    pub fn new(corner: (u64, u64), other_corner: (u64, u64)) -> Self {
+        CellBlock {
+            rows: min(corner.0, other_corner.0)..=max(corner.0, other_corner.0),
+            columns: min(corner.1, other_corner.1)..=max(corner.1, other_corner.1),
+        }
+    }
+
+    pub fn contains(&self, row_offset: u64, column_index: u64) -> bool {
+        self.rows.contains(&row_offset) && self.columns.contains(&column_index)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Header {
     pub name: String,
     pub origin_index: usize,
@@ -264,9 +293,21 @@ pub struct RowsView {
     columns_filter: Option<Arc<ColumnsFilter>>,
     sorter: Option<Arc<Sorter>>,
     sort_order: SortOrder,
+    /// Groups of the sorted rows, shown once found unless filtered
+    outline: Option<Outline>,
+    /// Whether the rows shown follow the outline yet, since its groups are found in the
+    /// background
+    outline_shown: bool,
     pub selection: Selection,
     perf_stats: Option<PerfStats>,
-    marked_rows: HashSet<usize>,
+    marked_rows: HashSet<RowId>,
+    prefetcher: Option<Prefetcher>,
+    loader: Option<RowsLoader>,
+    io_wait_timeout: Duration,
+    /// When the read of the rows to show started, if it is still pending
+    pending_since: Option<Instant>,
+    /// rows_from of the last rows read, to tell if scrolling down
+    last_rows_from: u64,
 }
 
 impl RowsView {
@@ -286,9 +327,21 @@ impl RowsView {
             columns_filter: None,
             sorter: None,
             sort_order: SortOrder::Ascending,
+            outline: None,
+            outline_shown: false,
             selection: Selection::default(num_rows),
             perf_stats: None,
             marked_rows: HashSet::new(),
+            prefetcher: None,
+            loader: None,
+            // Reads of tests on a busy machine shouldn't be taken as stalled
+            io_wait_timeout: if cfg!(test) {
+                Duration::from_secs(10)
+            } else {
+                IO_WAIT_TIMEOUT
+            },
+            pending_since: None,
+            last_rows_from: rows_from,
         };
         Ok(view)
     }
@@ -305,10 +358,25 @@ impl RowsView {
         &self.rows
     }
 
-    pub fn marked_rows(&self) -> &HashSet<usize> {
+    pub fn marked_rows(&self) -> &HashSet<RowId> {
         &self.marked_rows
     }
 
+    /// Marked rows in the order they appear in the view when not filtered, i.e. following the
+    /// current sort if any
+    pub fn marked_rows_in_view_order(&self) -> Vec<RowId> {
+        let mut row_ids: Vec<RowId> = self.marked_rows.iter().copied().collect();
+        match &self.sorter {
+            Some(sorter) => row_ids.sort_by_key(|id| {
+                sorter
+                    .get_record_order(id.index() as u64, self.sort_order)
+                    .unwrap_or(id.index() as u64)
+            }),
+            None => row_ids.sort_unstable(),
+        }
+        row_ids
+    }
+
     pub fn get_column_name_from_global_index(&self, column_index: usize) -> String {
         self.raw_headers()
             .get(column_index)
@@ -367,6 +435,11 @@ impl RowsView {
         None
     }
 
+    pub fn selected_row_id(&self) -> Option<RowId> {
+        let row_index = self.selection.row.index()?;
+        self.rows().get(row_index as usize).map(|row| row.id())
+    }
+
     pub fn get_headers_line(&self) -> String {
         self.headers()
             .iter()
@@ -375,16 +448,18 @@ impl RowsView {
             .join("\t")
     }
 
-    pub fn get_rows_values(&mut self, record_numbers: &[usize]) -> CsvlensResult<Vec<String>> {
-        if record_numbers.is_empty() {
+    pub fn get_rows_values(&mut self, row_ids: &[RowId]) -> CsvlensResult<Vec<String>> {
+        let rows = self.get_rows_fields(row_ids)?;
+        Ok(rows.into_iter().map(|fields| fields.join("\t")).collect())
+    }
+
+    /// Fields of the rows in the columns shown, in the given order
+    pub fn get_rows_fields(&mut self, row_ids: &[RowId]) -> CsvlensResult<Vec<Vec<String>>> {
+        if row_ids.is_empty() {
             return Ok(vec![]);
         }
 
-        // marked rows store 1-based record numbers; convert to 0-based indices for fetching
-        let indices: Vec<u64> = record_numbers
-            .iter()
-            .map(|&n| n.saturating_sub(1) as u64)
-            .collect();
+        let indices: Vec<u64> = row_ids.iter().map(|id| id.index() as u64).collect();
 
         let (mut rows, _) = self.reader.get_rows_for_indices(&indices)?;
 
@@ -392,13 +467,131 @@ impl RowsView {
             rows = Self::subset_columns(&rows, columns_filter.indices());
         }
 
-        Ok(rows.into_iter().map(|row| row.fields.join("\t")).collect())
+        Ok(rows.into_iter().map(|row| row.fields).collect())
+    }
+
+    /// A reader of its own to read the rows from elsewhere, e.g. to export them in the
+    /// background, with the indices of the columns shown if not all of them are
+    pub fn export_reader(&self) -> CsvlensResult<(CsvLensReader, Option<Vec<usize>>)> {
+        let columns = self
+            .columns_filter
+            .as_ref()
+            .map(|columns_filter| columns_filter.indices().to_vec());
+        Ok((self.reader.try_clone()?, columns))
+    }
+
+    /// Offsets in the file where the rows start, to find them again once the file is read with
+    /// another config. None for rows past the end.
+    pub fn row_offsets(&mut self, row_ids: &[RowId]) -> CsvlensResult<Vec<Option<u64>>> {
+        let indices: Vec<u64> = row_ids.iter().map(|id| id.index() as u64).collect();
+        self.reader.record_offsets(&indices)
+    }
+
+    /// The rows starting right at the offsets in the file, None where no record starts
+    pub fn rows_at_offsets(&mut self, offsets: &[u64]) -> CsvlensResult<Vec<Option<RowId>>> {
+        let indices = self.reader.record_indices_at_offsets(offsets)?;
+        Ok(indices
+            .into_iter()
+            .map(|index| index.map(|i| RowId::from_index(i as usize)))
+            .collect())
+    }
+
+    /// The first record starting at or after an offset in the file, if any
+    pub fn record_at_offset(&mut self, file_offset: u64) -> CsvlensResult<Option<RowId>> {
+        let index = self.reader.record_index_at_offset(file_offset)?;
+        Ok(index.map(|i| RowId::from_index(i as usize)))
     }
 
     pub fn num_rows(&self) -> u64 {
         self.num_rows
     }
 
+    pub fn indexing_elapsed(&self) -> Option<Duration> {
+        self.reader.indexing_elapsed()
+    }
+
+    /// Approximate number of bytes used by the index of row positions in the file
+    pub fn index_memory_usage(&self) -> usize {
+        self.reader.memory_usage()
+    }
+
+    /// Approximate number of bytes used by the rows loaded for display
+    pub fn rows_memory_usage(&self) -> usize {
+        self.rows.iter().map(Row::memory_usage).sum()
+    }
+
+    /// Approximate number of bytes used by the rows read ahead of the current screen
+    pub fn prefetch_memory_usage(&self) -> usize {
+        self.prefetcher.as_ref().map_or(0, |p| p.memory_usage())
+    }
+
+    /// Forget the rows read ahead, to free memory
+    pub fn clear_prefetch(&mut self) {
+        if let Some(prefetcher) = &self.prefetcher {
+            prefetcher.clear();
+        }
+    }
+
+    /// Read the next screens in the background when scrolling down
+    pub fn enable_prefetch(&mut self) -> CsvlensResult<()> {
+        self.prefetcher = Some(Prefetcher::new(self.reader.try_clone()?));
+        Ok(())
+    }
+
+    /// Read the rows to show on a background thread, leaving reads that stall pending
+    pub fn enable_background_reads(&mut self) -> CsvlensResult<()> {
+        self.loader = Some(RowsLoader::new(self.reader.try_clone()?));
+        self.pending_since = None;
+        Ok(())
+    }
+
+    /// Number of fields shown per record, if any record has more
+    pub fn fields_cap(&self) -> Option<usize> {
+        self.reader.fields_cap()
+    }
+
+    /// How long the pending read of the rows to show has been waited for, if any
+    pub fn io_wait_elapsed(&self) -> Option<Duration> {
+        self.pending_since.map(|t| t.elapsed())
+    }
+
+    /// Show the rows of the pending read if it has finished. Returns whether it did.
+    pub fn poll_pending_rows(&mut self) -> CsvlensResult<bool> {
+        let (Some(loader), Some(since)) = (&self.loader, self.pending_since) else {
+            return Ok(false);
+        };
+        let Some(result) = loader.poll() else {
+            return Ok(false);
+        };
+        self.pending_since = None;
+        let (rows, reader_stats) = result?;
+        self.set_rows(rows, reader_stats, since.elapsed());
+        Ok(true)
+    }
+
+    /// Give up on the pending read and go back to where the shown rows are from. Returns whether
+    /// there was a pending read.
+    pub fn abort_pending_rows(&mut self) -> bool {
+        let Some(loader) = &self.loader else {
+            return false;
+        };
+        if !loader.abort() {
+            return false;
+        }
+        self.pending_since = None;
+        self.rows_from = self.last_rows_from;
+        true
+    }
+
+    /// Make reads slow and wait only briefly for them, to simulate stalled I/O
+    #[cfg(test)]
+    pub fn set_io_delay(&mut self, delay: Duration) {
+        if let Some(loader) = &self.loader {
+            loader.set_delay(delay);
+        }
+        self.io_wait_timeout = IO_WAIT_TIMEOUT;
+    }
+
     pub fn set_num_rows(&mut self, num_rows: u64) -> CsvlensResult<()> {
         if num_rows == self.num_rows {
             return Ok(());
@@ -421,6 +614,13 @@ impl RowsView {
     ) -> CsvlensResult<()> {
         self.reader = reader;
         self.headers = Self::get_default_headers_from_reader(&self.reader);
+        // Prefetched rows are from the previous content of the file
+        if self.prefetcher.is_some() {
+            self.enable_prefetch()?;
+        }
+        if self.loader.is_some() {
+            self.enable_background_reads()?;
+        }
         if let Some(finder) = filter_finder {
             self.set_filter(finder)?;
         } else {
@@ -542,6 +742,73 @@ impl RowsView {
         Ok(())
     }
 
+    pub fn outline(&self) -> Option<&Outline> {
+        self.outline.as_ref()
+    }
+
+    pub fn set_outline(&mut self, outline: Option<Outline>) -> CsvlensResult<()> {
+        self.outline = outline;
+        self.outline_shown = false;
+        self.do_get_rows()
+    }
+
+    /// Show the rows following the outline once its groups are found
+    pub fn poll_outline(&mut self) -> CsvlensResult<()> {
+        if !self.outline_shown && self.outline.as_ref().is_some_and(|o| o.done()) {
+            self.outline_shown = true;
+            self.set_rows_from(self.rows_from.min(self.bottom_rows_from().unwrap_or(0)))?;
+            self.do_get_rows()?;
+        }
+        Ok(())
+    }
+
+    /// The outline the rows shown follow, if any
+    fn shown_outline(&self) -> Option<&Outline> {
+        self.outline
+            .as_ref()
+            .filter(|_| self.outline_shown && self.filter.is_none())
+    }
+
+    /// Labels of the groups started by the rows shown, if they follow an outline
+    pub fn group_labels(&self) -> Option<Vec<Option<GroupLabel>>> {
+        let outline = self.shown_outline()?;
+        Some(outline.labels(self.rows_from, self.rows.len() as u64))
+    }
+
+    /// Collapse the group of a row shown, or expand it. The value of the group and whether it is
+    /// now collapsed.
+    pub fn toggle_group(&mut self, row_index: u64) -> CsvlensResult<Option<(String, bool)>> {
+        if self.shown_outline().is_none() {
+            return Ok(None);
+        }
+        let visible_index = self.rows_from.saturating_add(row_index);
+        let toggled = self.outline.as_mut().and_then(|o| o.toggle(visible_index));
+        self.after_groups_toggled()?;
+        Ok(toggled)
+    }
+
+    /// Collapse all groups, or expand them all. Whether they are now collapsed.
+    pub fn toggle_all_groups(&mut self) -> CsvlensResult<Option<bool>> {
+        if self.shown_outline().is_none() {
+            return Ok(None);
+        }
+        let collapsed = self.outline.as_mut().map(|o| o.toggle_all());
+        self.selection.row.select_first();
+        self.rows_from = 0;
+        self.do_get_rows()?;
+        Ok(collapsed)
+    }
+
+    fn after_groups_toggled(&mut self) -> CsvlensResult<()> {
+        // Fewer rows might be left below
+        if let Some(n) = self.bottom_rows_from()
+            && self.rows_from > n
+        {
+            self.rows_from = n;
+        }
+        self.do_get_rows()
+    }
+
     pub fn rows_from(&self) -> u64 {
         self.rows_from
     }
@@ -596,6 +863,14 @@ impl RowsView {
         self.reader.get_total_line_numbers()
     }
 
+    /// Whether the file is indexed a screen of rows past the given record number, so that going to
+    /// it isn't cut short at what looks like the bottom yet
+    pub fn is_indexed_past(&self, record_num: usize) -> bool {
+        self.reader.get_total_line_numbers().is_some()
+            || self.reader.get_approx_line_numbers()
+                >= record_num.saturating_add(self.num_rows_rendered as usize)
+    }
+
     pub fn get_total_line_numbers_approx(&self) -> Option<usize> {
         Some(self.reader.get_approx_line_numbers())
     }
@@ -693,6 +968,9 @@ impl RowsView {
             .get_total_line_numbers()
             .or_else(|| Some(self.reader.get_approx_line_numbers()))
         {
+            if let Some(outline) = self.shown_outline() {
+                return Some(outline.num_visible() as usize);
+            }
             if let Some(filter) = &self.filter {
                 if let Some(max_index) = filter.max_index {
                     if max_index < max_line_number as u64 {
@@ -739,21 +1017,49 @@ impl RowsView {
 
     pub fn do_get_rows(&mut self) -> CsvlensResult<()> {
         let start = Instant::now();
-        let (mut rows, reader_stats) = if let Some(filter) = &self.filter {
-            let indices = &filter.indices;
-            self.reader.get_rows_for_indices(indices)?
-        } else if let Some(sorter) = &self.sorter {
-            if let Some(sorted_indices) =
-                sorter.get_sorted_indices(self.rows_from, self.num_rows, self.sort_order)
-            {
-                self.reader.get_rows_for_indices(&sorted_indices)?
-            } else {
-                self.reader.get_rows(self.rows_from, self.num_rows)?
+        let indices = if let Some(filter) = &self.filter {
+            filter.indices.clone()
+        } else if let (Some(outline), Some(sorter)) = (self.shown_outline(), &self.sorter) {
+            outline
+                .visible_positions(self.rows_from, self.num_rows)
+                .into_iter()
+                .filter_map(|position| {
+                    sorter
+                        .get_sorted_indices(position, 1, self.sort_order)
+                        .and_then(|indices| indices.first().copied())
+                })
+                .collect()
+        } else if let Some(sorted_indices) = self.sorter.as_ref().and_then(|sorter| {
+            sorter.get_sorted_indices(self.rows_from, self.num_rows, self.sort_order)
+        }) {
+            sorted_indices
+        } else if let Some(rows) = self.get_prefetched_rows() {
+            if let Some(loader) = &self.loader {
+                loader.abort();
             }
+            self.pending_since = None;
+            self.set_rows(rows, GetRowsStats::new(), start.elapsed());
+            return Ok(());
         } else {
-            self.reader.get_rows(self.rows_from, self.num_rows)?
+            (self.rows_from..self.rows_from + self.num_rows).collect()
         };
-        let elapsed = start.elapsed();
+        let result = match &self.loader {
+            Some(loader) => loader.load(indices, self.io_wait_timeout),
+            None => Some(self.reader.get_rows_for_indices(&indices)),
+        };
+        let Some(result) = result else {
+            // Keep showing the current rows until the read finishes or is aborted
+            self.pending_since.get_or_insert(start);
+            return Ok(());
+        };
+        self.pending_since = None;
+        let (rows, reader_stats) = result?;
+        self.set_rows(rows, reader_stats, start.elapsed());
+        Ok(())
+    }
+
+    fn set_rows(&mut self, mut rows: Vec<Row>, reader_stats: GetRowsStats, elapsed: Duration) {
+        self.last_rows_from = self.rows_from;
         if let Some(columns_filter) = &self.columns_filter {
             rows = Self::subset_columns(&rows, columns_filter.indices());
         }
@@ -764,23 +1070,31 @@ impl RowsView {
         });
         // current selected might be out of range, reset it
         // self.selection.row.set_bound(self.rows.len() as u64);
-        Ok(())
+    }
+
+    /// Rows already read ahead by the prefetcher, if any. Scrolling down reads further ahead.
+    fn get_prefetched_rows(&self) -> Option<Vec<Row>> {
+        let prefetcher = self.prefetcher.as_ref()?;
+        if self.rows_from > self.last_rows_from {
+            prefetcher.prefetch(self.rows_from, self.num_rows);
+        }
+        prefetcher.get_rows(self.rows_from, self.num_rows)
     }
 
     pub fn toggle_mark(&mut self, row_index: usize) -> Option<MarkToggleResult> {
-        let record_num = self.rows.get(row_index)?.record_num;
+        let row_id = self.rows.get(row_index)?.id();
 
-        if self.marked_rows.remove(&record_num) {
+        if self.marked_rows.remove(&row_id) {
             return Some(MarkToggleResult {
-                record_num,
+                row_id,
                 marked: false,
             });
         };
 
-        self.marked_rows.insert(record_num);
+        self.marked_rows.insert(row_id);
 
         Some(MarkToggleResult {
-            record_num,
+            row_id,
             marked: true,
         })
     }
@@ -789,7 +1103,11 @@ impl RowsView {
         self.marked_rows.clear();
     }
 
-    #[cfg(test)]
+    pub fn set_marked_rows(&mut self, marked_rows: HashSet<RowId>) {
+        self.marked_rows = marked_rows;
+    }
+
+    #[cfg(any(test, feature = "harness"))]
     pub fn wait_internal(&self) {
         self.reader.wait_internal()
     }
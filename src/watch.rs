@@ -1,6 +1,9 @@
 use std::sync::{Arc, Mutex};
 
 use crate::errors::CsvlensResult;
+use crate::gcs::GcsLocation;
+use crate::io::input_len;
+use crate::s3::S3Location;
 
 /// A file watcher that keeps track of the file state and can check for changes. A thin wrapper
 /// around a shared `Watcher` for easier usage.
@@ -65,11 +68,26 @@ impl Drop for Watcher {
 pub struct FileState {
     pub modified_time: std::time::SystemTime,
     pub size: u64,
+    /// Inode number on unix, to tell a file replaced under the same name (e.g. rotated logs)
+    pub inode: u64,
+}
+
+impl FileState {
+    /// Whether the file shrank or was replaced since `previous`, so that row offsets found in
+    /// the earlier file no longer point to the same rows.
+    pub fn invalidates(&self, previous: &FileState) -> bool {
+        self.size < previous.size || self.inode != previous.inode
+    }
 }
 
 impl From<std::fs::Metadata> for FileState {
     fn from(metadata: std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
         FileState {
+            inode,
             modified_time: metadata
                 .modified()
                 .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
@@ -78,6 +96,19 @@ impl From<std::fs::Metadata> for FileState {
     }
 }
 
+/// State of the file. S3 and GCS objects stay as they were opened, since reading a replaced one
+/// fails.
+fn file_state(filename: &str) -> std::io::Result<FileState> {
+    if S3Location::parse(filename).is_some() || GcsLocation::parse(filename).is_some() {
+        return Ok(FileState {
+            modified_time: std::time::SystemTime::UNIX_EPOCH,
+            size: input_len(filename)?,
+            inode: 0,
+        });
+    }
+    std::fs::metadata(filename).map(FileState::from)
+}
+
 struct WatcherInternal {
     should_terminate: bool,
     file_state: FileState,
@@ -85,11 +116,9 @@ struct WatcherInternal {
 
 impl WatcherInternal {
     pub fn init(filename: &str) -> CsvlensResult<Arc<Mutex<WatcherInternal>>> {
-        let file_state = std::fs::metadata(filename)?;
-
         let internal = WatcherInternal {
             should_terminate: false,
-            file_state: file_state.into(),
+            file_state: file_state(filename)?,
         };
 
         let m_internal = Arc::new(Mutex::new(internal));
@@ -102,10 +131,9 @@ impl WatcherInternal {
                     if m_internal.lock().unwrap().should_terminate {
                         break;
                     }
-                    match std::fs::metadata(&filename) {
-                        Ok(metadata) => {
+                    match file_state(&filename) {
+                        Ok(new_file_state) => {
                             let mut internal = m_internal.lock().unwrap();
-                            let new_file_state = FileState::from(metadata);
                             internal.file_state = new_file_state;
                         }
                         Err(_) => {
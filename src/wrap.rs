@@ -1,4 +1,5 @@
 use ratatui::text::{Line, Span};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub struct LineWrapper<'a> {
     spans: &'a [Span<'a>],
@@ -34,37 +35,40 @@ impl<'a> LineWrapper<'a> {
                 self.index += 1;
             }
             if let Some(span) = span {
-                let chars_count = span.content.chars().count();
-                let newline_pos = span.content.chars().position(|c| c == '\n');
-                if let Some((pos, true)) = newline_pos.map(|x| (x, x <= remaining_width)) {
-                    out_spans.push(Span::styled(
-                        span.content.chars().take(pos).collect::<String>(),
-                        span.style,
-                    ));
+                let span_width = span.content.width();
+                let newline_pos = span.content.find('\n');
+                if let Some(pos) =
+                    newline_pos.filter(|pos| span.content[..*pos].width() <= remaining_width)
+                {
+                    out_spans.push(Span::styled(span.content[..pos].to_string(), span.style));
                     self.pending = Some(Span::styled(
-                        span.content.chars().skip(pos + 1).collect::<String>(),
+                        span.content[pos + 1..].to_string(),
                         span.style,
                     ));
                     // Technically this might not be zero, but this is to force the loop to break -
                     // we must wrap now.
                     remaining_width = 0;
-                } else if chars_count <= remaining_width {
-                    remaining_width = remaining_width.saturating_sub(chars_count);
+                } else if span_width <= remaining_width {
+                    remaining_width = remaining_width.saturating_sub(span_width);
                     out_spans.push(span);
                 } else {
-                    let mut current: String = span.content.chars().take(remaining_width).collect();
-                    let pending: String;
-
-                    if self.word_wrap {
-                        if let Some(wrapped) = LineWrapper::wrap_by_whitespace(current.as_str()) {
-                            current = wrapped;
-                            pending = span.content.chars().skip(current.chars().count()).collect();
-                        } else {
-                            pending = span.content.chars().skip(remaining_width).collect();
-                        }
-                    } else {
-                        pending = span.content.chars().skip(remaining_width).collect();
+                    let mut current = truncate_to_width(&span.content, remaining_width);
+                    // A character wider than the whole line goes on a line of its own
+                    if current.is_empty() && remaining_width == self.max_width && self.max_width > 0
+                    {
+                        current = span
+                            .content
+                            .chars()
+                            .next()
+                            .map_or("", |c| &span.content[..c.len_utf8()]);
                     }
+                    let mut current = current.to_string();
+                    if self.word_wrap
+                        && let Some(wrapped) = LineWrapper::wrap_by_whitespace(current.as_str())
+                    {
+                        current = wrapped;
+                    }
+                    let pending = span.content[current.len()..].to_string();
                     out_spans.push(Span::styled(current, span.style));
                     self.pending = Some(Span::styled(pending, span.style));
                     remaining_width = 0;
@@ -99,6 +103,19 @@ impl<'a> LineWrapper<'a> {
     }
 }
 
+/// The start of the text that fits in the width, in terminal cells. Wide characters, e.g. CJK or
+/// emoji, take two cells.
+pub fn truncate_to_width(s: &str, max_width: usize) -> &str {
+    let mut width = 0;
+    for (i, c) in s.char_indices() {
+        width += c.width().unwrap_or(0);
+        if width > max_width {
+            return &s[..i];
+        }
+    }
+    s
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -316,4 +333,27 @@ mod tests {
         assert_eq!(wrapper.next(), Some(Line::from(vec![Span::raw("")])));
         assert_eq!(wrapper.finished(), false);
     }
+
+    #[test]
+    fn test_wide_chars() {
+        let s = Span::raw("東京タワー");
+        let spans = vec![s.clone()];
+        let mut wrapper = LineWrapper::new(&spans, 5, false);
+        assert_eq!(wrapper.next(), Some(Line::from(vec![Span::raw("東京")])));
+        assert_eq!(wrapper.next(), Some(Line::from(vec![Span::raw("タワ")])));
+        assert_eq!(wrapper.next(), Some(Line::from(vec![Span::raw("ー")])));
+        assert_eq!(wrapper.next(), None);
+
+        // Narrower than a character
+        let mut wrapper = LineWrapper::new(&spans, 1, false);
+        assert_eq!(wrapper.next(), Some(Line::from(vec![Span::raw("東")])));
+        assert_eq!(wrapper.next(), Some(Line::from(vec![Span::raw("京")])));
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("東京タワー", 5), "東京");
+        assert_eq!(truncate_to_width("Zürich", 3), "Zür");
+        assert_eq!(truncate_to_width("abc", 10), "abc");
+    }
 }
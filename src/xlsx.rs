@@ -0,0 +1,499 @@
+use crate::errors::{CsvlensError, CsvlensResult};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use chrono::{NaiveDate, TimeDelta};
+use quick_xml::Reader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesRef, BytesStart, Event};
+use tempfile::NamedTempFile;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+/// Whether the file is an Excel workbook, i.e. a zip archive named like one
+pub fn is_workbook(filename: &str) -> bool {
+    let name = filename.to_lowercase();
+    if !(name.ends_with(".xlsx") || name.ends_with(".xlsm")) {
+        return false;
+    }
+    let mut start = vec![];
+    File::open(filename).is_ok_and(|f| f.take(4).read_to_end(&mut start).is_ok())
+        && start == b"PK\x03\x04"
+}
+
+/// A CSV file with the cells of a sheet of the workbook, the first one if not given by name or
+/// number. Cells are written as shown in Excel for text, numbers and booleans, and dates as ISO
+/// 8601. Rows are as wide as the widest one, so that blank cells keep their place.
+pub fn to_csv(filename: &str, sheet: Option<&str>) -> CsvlensResult<NamedTempFile> {
+    let mut workbook = Workbook::open(filename)?;
+    let part = workbook.sheet_part(sheet)?;
+    let xml = workbook.read_part(&part)?.ok_or_else(|| {
+        CsvlensError::Workbook(filename.to_string(), format!("{part} is missing"))
+    })?;
+    let mut rows = workbook
+        .read_rows(&xml)
+        .map_err(|e| CsvlensError::Workbook(filename.to_string(), format!("{part}: {e}")))?;
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let file = NamedTempFile::new()?;
+    let mut writer = ::csv::Writer::from_path(file.path())?;
+    for row in rows.iter_mut() {
+        row.resize(width, String::new());
+        writer.write_record(&*row)?;
+    }
+    writer.flush()?;
+    Ok(file)
+}
+
+struct Workbook {
+    filename: String,
+    archive: ZipArchive<File>,
+    /// Names and parts of the sheets, in the order of their tabs
+    sheets: Vec<(String, String)>,
+    shared_strings: Vec<String>,
+    /// Whether the number format of each cell style is a date or time one
+    date_styles: Vec<bool>,
+    /// Whether dates count days from 1904 instead of 1900, as in workbooks from old Macs
+    date1904: bool,
+}
+
+impl Workbook {
+    fn open(filename: &str) -> CsvlensResult<Workbook> {
+        let archive = ZipArchive::new(File::open(filename)?)
+            .map_err(|e| CsvlensError::Workbook(filename.to_string(), e.to_string()))?;
+        let mut workbook = Workbook {
+            filename: filename.to_string(),
+            archive,
+            sheets: vec![],
+            shared_strings: vec![],
+            date_styles: vec![],
+            date1904: false,
+        };
+        let parse_error = |part: &str, e: quick_xml::Error| {
+            CsvlensError::Workbook(filename.to_string(), format!("{part}: {e}"))
+        };
+        let workbook_xml = workbook.read_part("xl/workbook.xml")?.ok_or_else(|| {
+            CsvlensError::Workbook(filename.to_string(), "not an Excel workbook".to_string())
+        })?;
+        let rels_xml = workbook
+            .read_part("xl/_rels/workbook.xml.rels")?
+            .unwrap_or_default();
+        let (sheets, date1904) = parse_workbook(&workbook_xml, &rels_xml)
+            .map_err(|e| parse_error("xl/workbook.xml", e))?;
+        workbook.sheets = sheets;
+        workbook.date1904 = date1904;
+        if let Some(xml) = workbook.read_part("xl/sharedStrings.xml")? {
+            workbook.shared_strings =
+                parse_shared_strings(&xml).map_err(|e| parse_error("xl/sharedStrings.xml", e))?;
+        }
+        if let Some(xml) = workbook.read_part("xl/styles.xml")? {
+            workbook.date_styles =
+                parse_date_styles(&xml).map_err(|e| parse_error("xl/styles.xml", e))?;
+        }
+        Ok(workbook)
+    }
+
+    /// The part of the sheet with the name, or else the number counting from 1
+    fn sheet_part(&self, sheet: Option<&str>) -> CsvlensResult<String> {
+        let found = match sheet {
+            None => self.sheets.first(),
+            Some(name) => self
+                .sheets
+                .iter()
+                .find(|(n, _)| n == name)
+                .or_else(|| {
+                    self.sheets
+                        .iter()
+                        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                })
+                .or_else(|| {
+                    name.parse::<usize>()
+                        .ok()
+                        .and_then(|i| self.sheets.get(i.checked_sub(1)?))
+                }),
+        };
+        match (found, sheet) {
+            (Some((_, part)), _) => Ok(part.clone()),
+            (None, Some(name)) => Err(CsvlensError::SheetNotFound(
+                name.to_string(),
+                self.sheets
+                    .iter()
+                    .map(|(n, _)| n.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )),
+            (None, None) => Err(CsvlensError::Workbook(
+                self.filename.clone(),
+                "no sheets".to_string(),
+            )),
+        }
+    }
+
+    /// Content of a file in the archive, if there is one
+    fn read_part(&mut self, part: &str) -> CsvlensResult<Option<String>> {
+        let error = |message: String| CsvlensError::Workbook(self.filename.clone(), message);
+        let mut file = match self.archive.by_name(part) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(error(format!("{part}: {e}"))),
+        };
+        let mut content = vec![];
+        file.read_to_end(&mut content)
+            .map_err(|e| error(format!("failed to extract {part}: {e}")))?;
+        String::from_utf8(content)
+            .map(Some)
+            .map_err(|_| error(format!("{part} isn't UTF-8")))
+    }
+
+    /// Values of the cells of a sheet, with the rows and cells left out for being blank put back
+    fn read_rows(&self, xml: &str) -> Result<Vec<Vec<String>>, quick_xml::Error> {
+        let mut reader = Reader::from_str(xml);
+        let mut rows: Vec<Vec<String>> = vec![];
+        // Column, type and style of the cell being read
+        let mut cell: Option<(Option<usize>, String, usize)> = None;
+        let mut value = String::new();
+        let mut in_value = false;
+        let mut in_phonetic = false;
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => match e.local_name().as_ref() {
+                    b"row" => {
+                        let number = attribute(&e, "r").and_then(|r| r.parse::<usize>().ok());
+                        if let Some(number) = number {
+                            rows.resize(number.saturating_sub(1).max(rows.len()), vec![]);
+                        }
+                        rows.push(vec![]);
+                    }
+                    b"c" => {
+                        cell = Some(cell_attributes(&e));
+                        value.clear();
+                    }
+                    b"v" | b"t" if !in_phonetic => in_value = true,
+                    b"rPh" => in_phonetic = true,
+                    _ => {}
+                },
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"v" | b"t" => in_value = false,
+                    b"rPh" => in_phonetic = false,
+                    b"c" => {
+                        if let (Some((column, kind, style)), Some(row)) =
+                            (cell.take(), rows.last_mut())
+                        {
+                            let column = column.unwrap_or(row.len());
+                            if row.len() <= column {
+                                row.resize(column + 1, String::new());
+                            }
+                            row[column] = self.cell_value(&kind, style, &value);
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Text(e) if in_value => value.push_str(&e.xml_content()?),
+                Event::GeneralRef(e) if in_value => push_reference(&mut value, &e)?,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(rows)
+    }
+
+    fn cell_value(&self, kind: &str, style: usize, value: &str) -> String {
+        match kind {
+            "s" => value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| self.shared_strings.get(i))
+                .cloned()
+                .unwrap_or_default(),
+            "b" => if value.trim() == "1" { "TRUE" } else { "FALSE" }.to_string(),
+            // Numbers, the only cells that can be dates
+            "n" | "" if self.date_styles.get(style).copied().unwrap_or(false) => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(|serial| format_date(serial, self.date1904))
+                .unwrap_or_else(|| value.to_string()),
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// Sheets named in the workbook with their parts, and whether dates count from 1904
+fn parse_workbook(
+    xml: &str,
+    rels_xml: &str,
+) -> Result<(Vec<(String, String)>, bool), quick_xml::Error> {
+    let mut targets: HashMap<String, String> = HashMap::new();
+    let mut reader = Reader::from_str(rels_xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if let (Some(id), Some(target)) = (attribute(&e, "Id"), attribute(&e, "Target")) {
+                    // Relative to the workbook unless absolute
+                    let part = match target.strip_prefix('/') {
+                        Some(part) => part.to_string(),
+                        None => format!("xl/{target}"),
+                    };
+                    targets.insert(id, part);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let mut sheets = vec![];
+    let mut date1904 = false;
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"sheet" => {
+                    let part = attribute(&e, "id").and_then(|id| targets.get(&id).cloned());
+                    if let (Some(name), Some(part)) = (attribute(&e, "name"), part) {
+                        sheets.push((name, part));
+                    }
+                }
+                b"workbookPr" => {
+                    date1904 = matches!(attribute(&e, "date1904").as_deref(), Some("1" | "true"))
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((sheets, date1904))
+}
+
+/// Text of the strings cells refer to by index. Phonetic readings of Japanese text are left out.
+fn parse_shared_strings(xml: &str) -> Result<Vec<String>, quick_xml::Error> {
+    let mut strings = vec![];
+    let mut current = String::new();
+    let mut in_text = false;
+    let mut in_phonetic = false;
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"si" => current.clear(),
+                b"t" if !in_phonetic => in_text = true,
+                b"rPh" => in_phonetic = true,
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"si" => strings.push(String::new()),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"si" => strings.push(std::mem::take(&mut current)),
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                _ => {}
+            },
+            Event::Text(e) if in_text => current.push_str(&e.xml_content()?),
+            Event::GeneralRef(e) if in_text => push_reference(&mut current, &e)?,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+/// Whether each cell style formats numbers as dates or times
+fn parse_date_styles(xml: &str) -> Result<Vec<bool>, quick_xml::Error> {
+    let mut custom_formats: HashMap<u32, String> = HashMap::new();
+    let mut style_formats: Vec<u32> = vec![];
+    let mut in_cell_styles = false;
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"numFmt" => {
+                    let id = attribute(&e, "numFmtId").and_then(|id| id.parse().ok());
+                    if let (Some(id), Some(code)) = (id, attribute(&e, "formatCode")) {
+                        custom_formats.insert(id, code);
+                    }
+                }
+                b"cellXfs" => in_cell_styles = true,
+                b"xf" if in_cell_styles => style_formats.push(
+                    attribute(&e, "numFmtId")
+                        .and_then(|id| id.parse().ok())
+                        .unwrap_or(0),
+                ),
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"cellXfs" => in_cell_styles = false,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(style_formats
+        .into_iter()
+        .map(|id| match custom_formats.get(&id) {
+            Some(code) => is_date_format(code),
+            // The built-in date and time formats
+            None => matches!(id, 14..=22 | 45..=47),
+        })
+        .collect())
+}
+
+/// Whether a number format code shows a date or time, i.e. has day, month, year, hour or second
+/// parts outside of quoted text, colors and conditions in brackets
+fn is_date_format(code: &str) -> bool {
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                chars.find(|&c| c == '"');
+            }
+            '[' => {
+                chars.find(|&c| c == ']');
+            }
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            c if "dmyhsDMYHS".contains(c) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// A date as a serial number of days, with the time of day as the fraction, in ISO 8601
+fn format_date(serial: f64, date1904: bool) -> Option<String> {
+    if !(0.0..2958466.0).contains(&serial) {
+        return None;
+    }
+    let epoch = if date1904 {
+        NaiveDate::from_ymd_opt(1904, 1, 1)?
+    } else if serial < 60.0 {
+        NaiveDate::from_ymd_opt(1899, 12, 31)?
+    } else {
+        // Excel counts a 29 February 1900 that didn't exist
+        NaiveDate::from_ymd_opt(1899, 12, 30)?
+    };
+    let seconds = (serial * 86400.0).round() as i64;
+    let date_time = epoch.and_hms_opt(0, 0, 0)? + TimeDelta::seconds(seconds);
+    Some(if seconds % 86400 == 0 {
+        date_time.format("%Y-%m-%d").to_string()
+    } else if serial < 1.0 {
+        date_time.format("%H:%M:%S").to_string()
+    } else {
+        date_time.format("%Y-%m-%d %H:%M:%S").to_string()
+    })
+}
+
+/// Column index, type and style index of a cell
+fn cell_attributes(e: &BytesStart) -> (Option<usize>, String, usize) {
+    let column = attribute(e, "r").and_then(|r| column_index(&r));
+    let kind = attribute(e, "t").unwrap_or_default();
+    let style = attribute(e, "s").and_then(|s| s.parse().ok()).unwrap_or(0);
+    (column, kind, style)
+}
+
+/// Index of the column of a cell reference, e.g. 27 for AB3
+fn column_index(reference: &str) -> Option<usize> {
+    let letters = reference
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>();
+    if letters.is_empty() {
+        return None;
+    }
+    letters
+        .chars()
+        .try_fold(0usize, |n, c| {
+            n.checked_mul(26)?
+                .checked_add((c.to_ascii_uppercase() as u8 - b'A') as usize + 1)
+        })
+        .map(|n| n - 1)
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+/// Add the character an entity or character reference in text stands for
+fn push_reference(text: &mut String, e: &BytesRef) -> Result<(), quick_xml::Error> {
+    if let Some(c) = e.resolve_char_ref()? {
+        text.push(c);
+    } else if let Some(s) = resolve_predefined_entity(&e.decode()?) {
+        text.push_str(s);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet_lines(sheet: Option<&str>) -> Vec<String> {
+        let file = to_csv("tests/data/sales.xlsx", sheet).unwrap();
+        std::fs::read_to_string(file.path())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_is_workbook() {
+        assert!(is_workbook("tests/data/sales.xlsx"));
+        assert!(!is_workbook("tests/data/cities.csv"));
+        assert!(!is_workbook("tests/data/cities.csv.gz"));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        // The first sheet by default
+        assert_eq!(sheet_lines(None), vec!["Total,Value", "sum,3751.5"]);
+        assert_eq!(
+            sheet_lines(Some("Sales")),
+            vec![
+                "region,date,amount,paid,note",
+                "North,2024-01-15,1250.5,TRUE,Big deal",
+                "South,2024-01-16 18:00:00,2501,FALSE,\"Said \"\"no\"\" — later\"",
+                ",,,,",
+                "East & West,,0.25,,#DIV/0!",
+            ]
+        );
+        assert_eq!(sheet_lines(Some("sales")), sheet_lines(Some("2")));
+        assert!(matches!(
+            to_csv("tests/data/sales.xlsx", Some("Costs")),
+            Err(CsvlensError::SheetNotFound(name, sheets)) if name == "Costs" && sheets == "Summary, Sales"
+        ));
+    }
+
+    #[test]
+    fn test_is_date_format() {
+        assert!(is_date_format("yyyy\\-mm\\-dd"));
+        assert!(is_date_format("[$-409]h:mm AM/PM"));
+        assert!(!is_date_format("0.00%"));
+        assert!(!is_date_format("#,##0 \"days\""));
+        assert!(!is_date_format("[Red]0.00"));
+        assert!(!is_date_format("General"));
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(45306.0, false), Some("2024-01-15".to_string()));
+        assert_eq!(format_date(0.5, false), Some("12:00:00".to_string()));
+        assert_eq!(format_date(1.0, false), Some("1900-01-01".to_string()));
+        assert_eq!(format_date(61.0, false), Some("1900-03-01".to_string()));
+        assert_eq!(format_date(0.0, true), Some("1904-01-01".to_string()));
+        assert_eq!(format_date(-1.0, false), None);
+    }
+
+    #[test]
+    fn test_column_index() {
+        assert_eq!(column_index("A1"), Some(0));
+        assert_eq!(column_index("Z9"), Some(25));
+        assert_eq!(column_index("AB3"), Some(27));
+        assert_eq!(column_index("12"), None);
+    }
+}